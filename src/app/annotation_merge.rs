@@ -0,0 +1,303 @@
+//! Merging another annotator's work on the same text. Their corrections file
+//! is compared sentence by sentence with ours, and a reviewer settles every
+//! sentence where the two disagree by keeping one side.
+
+use std::path::Path;
+
+use crate::app::interactive_parser::{InteractiveParser, SavedCorrections, SentenceMatch};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReviewStatus {
+    /// Both sides write the same facts.
+    Agreement,
+    /// Both sides reviewed the sentence, and their facts differ.
+    Conflict,
+    /// Only they reviewed the sentence, and their facts differ from our parse.
+    OnlyTheirs,
+}
+
+impl ReviewStatus {
+    pub fn name(self) -> &'static str {
+        match self {
+            ReviewStatus::Agreement => "agreement",
+            ReviewStatus::Conflict => "conflict",
+            ReviewStatus::OnlyTheirs => "only theirs",
+        }
+    }
+
+    fn color(self) -> egui::Color32 {
+        match self {
+            ReviewStatus::Agreement => egui::Color32::from_rgb(60, 160, 60),
+            ReviewStatus::Conflict => egui::Color32::from_rgb(200, 60, 60),
+            ReviewStatus::OnlyTheirs => egui::Color32::from_rgb(200, 170, 50),
+        }
+    }
+}
+
+/// One sentence the other annotator reviewed, next to our parse of it.
+#[derive(Debug, Clone)]
+pub struct SentenceReview {
+    pub sentence: String,
+    pub status: ReviewStatus,
+    pub our_facts: Vec<String>,
+    pub their_facts: Vec<String>,
+    pub their_matches: Vec<SentenceMatch>,
+    /// Whether they locked the sentence, rather than only correcting captures.
+    pub their_lock: bool,
+}
+
+/// Compares every sentence `theirs` reviewed with our curated matches of
+/// it, or our current parse where we reviewed nothing. Sorted by sentence.
+pub fn compare(ours: &InteractiveParser, theirs: SavedCorrections) -> Vec<SentenceReview> {
+    let their_sentences = theirs
+        .locked
+        .into_iter()
+        .map(|(sentence, locked)| (sentence, locked.matches, true))
+        .chain(
+            theirs
+                .corrected
+                .into_iter()
+                .map(|(sentence, matches)| (sentence, matches, false)),
+        );
+
+    let mut reviews: Vec<SentenceReview> = their_sentences
+        .map(|(sentence, their_matches, their_lock)| {
+            let curated = ours.curated(&sentence);
+            let our_facts = match curated {
+                Some(matches) => facts(matches),
+                None => facts(ours.matches.iter().filter(|m| m.sentence == sentence)),
+            };
+            let their_facts = facts(&their_matches);
+            let status = if our_facts == their_facts {
+                ReviewStatus::Agreement
+            } else if curated.is_some() {
+                ReviewStatus::Conflict
+            } else {
+                ReviewStatus::OnlyTheirs
+            };
+            SentenceReview {
+                sentence,
+                status,
+                our_facts,
+                their_facts,
+                their_matches,
+                their_lock,
+            }
+        })
+        .collect();
+    reviews.sort_by(|a, b| a.sentence.cmp(&b.sentence));
+    reviews
+}
+
+/// The fact lines the matches generate, without comments.
+fn facts<'a>(matches: impl IntoIterator<Item = &'a SentenceMatch>) -> Vec<String> {
+    matches
+        .into_iter()
+        .flat_map(|m| m.generated_output.lines())
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("//"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Window comparing another annotator's corrections with ours.
+pub struct AnnotationMerge {
+    pub open: bool,
+    path_text: String,
+    reviews: Vec<SentenceReview>,
+    status_message: String,
+}
+
+impl AnnotationMerge {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            path_text: String::new(),
+            reviews: Vec::new(),
+            status_message: String::new(),
+        }
+    }
+
+    /// Draws the merge window if it is open. Returns the reviews whose side
+    /// the reviewer took from the other annotator.
+    pub fn show(&mut self, ctx: &egui::Context, ours: &InteractiveParser) -> Vec<SentenceReview> {
+        let mut taken = Vec::new();
+        if !self.open {
+            return taken;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("👥 Merge Annotations")
+            .open(&mut open)
+            .default_width(640.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Their corrections:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.path_text)
+                            .hint_text("notes.txt.corrections.json")
+                            .desired_width(360.0),
+                    );
+                    if ui.button("Compare").clicked() {
+                        match SavedCorrections::read(Path::new(self.path_text.trim())) {
+                            Ok(theirs) => {
+                                self.reviews = compare(ours, theirs);
+                                self.status_message = self.summary();
+                            }
+                            Err(e) => {
+                                self.status_message = format!("Error reading corrections: {}", e);
+                            }
+                        }
+                    }
+                });
+                if !self.status_message.is_empty() {
+                    ui.label(&self.status_message);
+                }
+                if self.reviews.is_empty() {
+                    return;
+                }
+
+                let uncontested = self
+                    .reviews
+                    .iter()
+                    .filter(|review| review.status == ReviewStatus::OnlyTheirs)
+                    .count();
+                let take_uncontested = egui::Button::new(format!(
+                    "Take theirs where only they reviewed ({})",
+                    uncontested
+                ));
+                if ui.add_enabled(uncontested > 0, take_uncontested).clicked() {
+                    let (only_theirs, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut self.reviews)
+                        .into_iter()
+                        .partition(|review| review.status == ReviewStatus::OnlyTheirs);
+                    taken = only_theirs;
+                    self.reviews = rest;
+                }
+                ui.separator();
+
+                let mut kept = None;
+                let mut took = None;
+                egui::ScrollArea::vertical().max_height(420.0).show(ui, |ui| {
+                    for (idx, review) in self.reviews.iter().enumerate() {
+                        ui.push_id(idx, |ui| {
+                            ui.group(|ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label(
+                                        egui::RichText::new(review.status.name())
+                                            .strong()
+                                            .color(review.status.color()),
+                                    );
+                                    ui.label(&review.sentence);
+                                });
+                                if review.status == ReviewStatus::Agreement {
+                                    return;
+                                }
+                                egui::Grid::new("merge_sides").num_columns(2).show(ui, |ui| {
+                                    ui.label(egui::RichText::new("Mine").strong());
+                                    let theirs = if review.their_lock {
+                                        "Theirs 🔒"
+                                    } else {
+                                        "Theirs"
+                                    };
+                                    ui.label(egui::RichText::new(theirs).strong());
+                                    ui.end_row();
+                                    ui.monospace(review.our_facts.join("\n"));
+                                    ui.monospace(review.their_facts.join("\n"));
+                                    ui.end_row();
+                                });
+                                ui.horizontal(|ui| {
+                                    if ui.button("Keep mine").clicked() {
+                                        kept = Some(idx);
+                                    }
+                                    if ui.button("Take theirs").clicked() {
+                                        took = Some(idx);
+                                    }
+                                });
+                            });
+                        });
+                    }
+                });
+                if let Some(idx) = kept {
+                    self.reviews.remove(idx);
+                }
+                if let Some(idx) = took {
+                    taken.push(self.reviews.remove(idx));
+                }
+            });
+        self.open = open;
+        taken
+    }
+
+    /// How many sentences fall under each status.
+    fn summary(&self) -> String {
+        let count = |status| {
+            self.reviews
+                .iter()
+                .filter(|review| review.status == status)
+                .count()
+        };
+        format!(
+            "{} sentences: {} agreements, {} conflicts, {} only theirs",
+            self.reviews.len(),
+            count(ReviewStatus::Agreement),
+            count(ReviewStatus::Conflict),
+            count(ReviewStatus::OnlyTheirs)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::interactive_parser::LockedParse;
+
+    #[test]
+    fn test_sentences_are_sorted_into_agreements_and_conflicts() {
+        let sentence_match = |sentence: &str, output: &str| SentenceMatch {
+            sentence: sentence.to_string(),
+            words: Vec::new(),
+            pattern_name: "pattern".to_string(),
+            template: String::new(),
+            highlights: Vec::new(),
+            generated_output: output.to_string(),
+            confidence: 1.0,
+            alternatives: Vec::new(),
+        };
+        let locked = |sentence: &str, output: &str| {
+            (
+                sentence.to_string(),
+                LockedParse::new(sentence, vec![sentence_match(sentence, output)]),
+            )
+        };
+
+        let mut ours = InteractiveParser::new();
+        ours.locked.extend([
+            locked("bear is an animal.", "animal(bear)."),
+            locked("cats purr.", "purr(cat)."),
+        ]);
+        ours.matches.push(sentence_match("dogs bark.", "bark(dogs)."));
+
+        let mut theirs = SavedCorrections::default();
+        theirs.locked.extend([
+            locked("bear is an animal.", "// checked\nanimal(bear)."),
+            locked("cats purr.", "purrs(cat)."),
+        ]);
+        theirs.corrected.insert(
+            "dogs bark.".to_string(),
+            vec![sentence_match("dogs bark.", "bark(dog).")],
+        );
+
+        let statuses: Vec<(String, ReviewStatus, bool)> = compare(&ours, theirs)
+            .into_iter()
+            .map(|review| (review.sentence, review.status, review.their_lock))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("bear is an animal.".to_string(), ReviewStatus::Agreement, true),
+                ("cats purr.".to_string(), ReviewStatus::Conflict, true),
+                ("dogs bark.".to_string(), ReviewStatus::OnlyTheirs, false),
+            ]
+        );
+    }
+}
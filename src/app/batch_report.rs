@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::app::query_engine::QueryEngine;
+
+/// Results of one saved query in a report run.
+struct QueryReport {
+    query: String,
+    outcome: Result<Vec<String>, String>,
+}
+
+/// Previous results per query, stored next to the report as `<report>.state.json`.
+/// Ordered so the state file is stable between runs.
+type ReportState = BTreeMap<String, Vec<String>>;
+
+/// Runs the saved queries in `queries_path` against the engine and appends a summary
+/// to `output_path`. Lines with `:-` or `-->` are added as rules/patterns first, the
+/// rest are run as queries. Returns the summary that was written.
+pub fn run(
+    query_engine: &mut QueryEngine,
+    queries_path: &str,
+    output_path: &str,
+    batch_label: &str,
+) -> Result<String, String> {
+    let queries = fs::read_to_string(queries_path)
+        .map_err(|e| format!("Failed to read {}: {}", queries_path, e))?;
+
+    let mut reports = Vec::new();
+    for line in queries.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with("//") || line.starts_with('#') {
+            continue;
+        }
+
+        if line.contains(":-") {
+            query_engine.add_rule(line)?;
+        } else if line.contains("-->") {
+            query_engine.add_pattern(line)?;
+        } else {
+            reports.push(QueryReport {
+                query: line.to_string(),
+                outcome: query_engine.query(line),
+            });
+        }
+    }
+
+    let state_path = format!("{}.state.json", output_path);
+    let previous: ReportState = fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+
+    let summary = summarize(batch_label, &reports, &previous);
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(output_path)
+        .map_err(|e| format!("Failed to open {}: {}", output_path, e))?;
+    writeln!(file, "{}", summary).map_err(|e| format!("Failed to write {}: {}", output_path, e))?;
+
+    let state: ReportState = reports
+        .into_iter()
+        .filter_map(|report| report.outcome.ok().map(|results| (report.query, results)))
+        .collect();
+    let json = serde_json::to_string_pretty(&state).map_err(|e| e.to_string())?;
+    fs::write(Path::new(&state_path), json)
+        .map_err(|e| format!("Failed to write {}: {}", state_path, e))?;
+
+    Ok(summary)
+}
+
+fn summarize(batch_label: &str, reports: &[QueryReport], previous: &ReportState) -> String {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut lines = vec![format!(
+        "=== Batch report: {} (unix time {}) ===",
+        batch_label, timestamp
+    )];
+
+    for report in reports {
+        match &report.outcome {
+            Ok(results) => {
+                let seen_before: HashSet<&String> = previous
+                    .get(&report.query)
+                    .map(|old| old.iter().collect())
+                    .unwrap_or_default();
+                let new_results: Vec<&String> = results
+                    .iter()
+                    .filter(|r| !seen_before.contains(r))
+                    .collect();
+
+                lines.push(format!(
+                    "{}  {} results ({} new)",
+                    report.query,
+                    results.len(),
+                    new_results.len()
+                ));
+                for result in new_results {
+                    lines.push(format!("  + {}", result));
+                }
+            }
+            Err(e) => lines.push(format!("{}  error: {}", report.query, e)),
+        }
+    }
+
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// A one-line count of what a summary reports: how many queries ran, how
+/// many of them failed, and how many results are new.
+pub fn outcome_counts(summary: &str) -> String {
+    let (mut queries, mut errors, mut new_results) = (0, 0, 0);
+    for line in summary.lines().skip(1).filter(|line| !line.is_empty()) {
+        if line.starts_with("  + ") {
+            new_results += 1;
+        } else {
+            queries += 1;
+            if line.contains("  error: ") {
+                errors += 1;
+            }
+        }
+    }
+    format!("{} queries ({} failed), {} new results", queries, errors, new_results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_lists_only_results_new_since_last_run() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("animal(bear).\nanimal(cat).\n");
+
+        let reports = vec![
+            QueryReport {
+                query: "animal(X).".to_string(),
+                outcome: engine.query("animal(X)."),
+            },
+            QueryReport {
+                query: "plant(X).".to_string(),
+                outcome: engine.query("plant(X)."),
+            },
+        ];
+        let mut previous = ReportState::new();
+        previous.insert("animal(X).".to_string(), vec!["X = bear".to_string()]);
+
+        let summary = summarize("docs", &reports, &previous);
+        assert!(summary.starts_with("=== Batch report: docs"));
+        assert!(summary.contains("animal(X).  2 results (1 new)\n  + X = cat"));
+        assert!(!summary.contains("+ X = bear"));
+        assert!(summary.contains("plant(X).  0 results (0 new)"));
+        assert_eq!(outcome_counts(&summary), "2 queries (0 failed), 1 new results");
+    }
+}
@@ -0,0 +1,179 @@
+//! Opt-in watching of the system clipboard. While it is on, the clipboard is
+//! polled, and text copied anywhere is offered in a toast for parsing, so
+//! facts can be captured while reading a document in another program.
+
+use std::time::{Duration, Instant};
+
+/// How often the clipboard is read while watching.
+const POLL_INTERVAL: Duration = Duration::from_millis(750);
+
+/// Characters of the copied text the toast previews.
+const PREVIEW_CHARS: usize = 80;
+
+pub struct ClipboardWatcher {
+    pub enabled: bool,
+    clipboard: Option<arboard::Clipboard>,
+    last_poll: Option<Instant>,
+    /// The clipboard text when last read, so only new copies are offered.
+    last_seen: Option<String>,
+    /// Copied text waiting in the toast.
+    offered: Option<String>,
+    error: Option<String>,
+}
+
+impl ClipboardWatcher {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            clipboard: None,
+            last_poll: None,
+            last_seen: None,
+            offered: None,
+            error: None,
+        }
+    }
+
+    /// Reads the clipboard when it is due, and keeps the app repainting
+    /// while watching so copies are noticed while the window is idle.
+    fn poll(&mut self, ctx: &egui::Context) {
+        if !self.enabled {
+            self.clipboard = None;
+            self.last_poll = None;
+            self.last_seen = None;
+            self.offered = None;
+            return;
+        }
+        ctx.request_repaint_after(POLL_INTERVAL);
+        if self.last_poll.is_some_and(|polled| polled.elapsed() < POLL_INTERVAL) {
+            return;
+        }
+
+        if self.clipboard.is_none() {
+            match arboard::Clipboard::new() {
+                Ok(clipboard) => {
+                    self.clipboard = Some(clipboard);
+                    self.error = None;
+                }
+                Err(e) => {
+                    self.error = Some(format!("Cannot watch the clipboard: {}", e));
+                    self.enabled = false;
+                    return;
+                }
+            }
+        }
+        let first_poll = self.last_poll.is_none();
+        self.last_poll = Some(Instant::now());
+        // Images and empty clipboards read as errors, and offer nothing
+        let text = self
+            .clipboard
+            .as_mut()
+            .and_then(|clipboard| clipboard.get_text().ok());
+        if first_poll {
+            // Whatever was copied before watching started is not offered
+            self.last_seen = text;
+        } else {
+            self.observe(text);
+        }
+    }
+
+    /// Offers `text` read from the clipboard if it was copied since the last
+    /// read and has something to parse.
+    fn observe(&mut self, text: Option<String>) {
+        if text == self.last_seen {
+            return;
+        }
+        self.last_seen = text.clone();
+        if let Some(text) = text.filter(|text| !text.trim().is_empty()) {
+            self.offered = Some(text);
+        }
+    }
+
+    /// Polls the clipboard and draws the toast offering the last copied
+    /// text. Returns the text when the user chose to parse it.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<String> {
+        self.poll(ctx);
+        if self.offered.is_none() && self.error.is_none() {
+            return None;
+        }
+
+        let mut parse = false;
+        let mut dismiss = false;
+        egui::Area::new(egui::Id::new("clipboard_toast"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -12.0])
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(360.0);
+                    match (&self.error, &self.offered) {
+                        (Some(error), _) => {
+                            ui.colored_label(egui::Color32::from_rgb(200, 60, 60), error);
+                        }
+                        (None, Some(text)) => {
+                            ui.label(egui::RichText::new("📋 Copied text").strong());
+                            ui.label(egui::RichText::new(preview(text)).italics());
+                        }
+                        (None, None) => {}
+                    }
+                    ui.horizontal(|ui| {
+                        if self.offered.is_some() && ui.button("Parse clipboard").clicked() {
+                            parse = true;
+                        }
+                        if ui.button("Dismiss").clicked() {
+                            dismiss = true;
+                        }
+                    });
+                });
+            });
+
+        if dismiss {
+            self.offered = None;
+            self.error = None;
+        }
+        if parse {
+            self.offered.take()
+        } else {
+            None
+        }
+    }
+}
+
+/// The start of the first line of `text`, cut short with an ellipsis.
+fn preview(text: &str) -> String {
+    let mut lines = text.trim().lines();
+    let line = lines.next().unwrap_or_default();
+    let mut preview: String = line.chars().take(PREVIEW_CHARS).collect();
+    if line.chars().count() > PREVIEW_CHARS || lines.next().is_some() {
+        preview.push('…');
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_new_copies_with_text_are_offered() {
+        let mut watcher = ClipboardWatcher::new();
+        watcher.last_seen = Some("already copied".to_string());
+
+        watcher.observe(Some("already copied".to_string()));
+        assert_eq!(watcher.offered, None);
+
+        watcher.observe(Some("  \n".to_string()));
+        assert_eq!(watcher.offered, None);
+
+        watcher.observe(Some("Bears eat fish.".to_string()));
+        assert_eq!(watcher.offered.as_deref(), Some("Bears eat fish."));
+
+        // Dismissed text stays dismissed until something else is copied
+        watcher.offered = None;
+        watcher.observe(Some("Bears eat fish.".to_string()));
+        assert_eq!(watcher.offered, None);
+
+        assert_eq!(preview("Bears eat fish.\nCats purr."), "Bears eat fish.…");
+        // Non-ASCII text is cut by characters, not bytes
+        assert_eq!(preview("Ведмеді їдять рибу."), "Ведмеді їдять рибу.");
+        let long = "é".repeat(PREVIEW_CHARS + 1);
+        assert_eq!(preview(&long), format!("{}…", "é".repeat(PREVIEW_CHARS)));
+    }
+}
@@ -0,0 +1,120 @@
+//! Commands that drive the app without the mouse: typed into the command
+//! palette (Ctrl+P), run from a file with `--script <file>`, or sent by a test
+//! harness through `PrologApp::run_command`.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppCommand {
+    /// Replaces the input text and parses it.
+    LoadText(String),
+    /// Opens an input file, as the 📂 Open button does.
+    Open(String),
+    /// Parses the current input again.
+    Parse,
+    /// Runs a query; the result is the query results text.
+    Query(String),
+    /// Locks the sentence of a match, counting from 1 in the Parsing panel.
+    Accept(usize),
+    /// Unlocks the sentence of a match.
+    Unlock(usize),
+    /// The result is the parsed Prolog output.
+    Output,
+}
+
+impl AppCommand {
+    /// Reads one line such as `query animal(X).` or `accept 2`. In `load`
+    /// text, `\n` stands for a line break.
+    pub fn parse(line: &str) -> Result<AppCommand, String> {
+        let line = line.trim();
+        let (name, argument) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(name, argument)| (name, argument.trim()));
+        let match_number = || match argument.parse::<usize>() {
+            Ok(number) if number > 0 => Ok(number),
+            _ => Err(format!("{} expects a match number from 1, got '{}'", name, argument)),
+        };
+
+        match name {
+            "load" => Ok(AppCommand::LoadText(argument.replace("\\n", "\n"))),
+            "open" | "query" if argument.is_empty() => {
+                Err(format!("{} expects an argument", name))
+            }
+            "open" => Ok(AppCommand::Open(argument.to_string())),
+            "parse" => Ok(AppCommand::Parse),
+            "query" => Ok(AppCommand::Query(argument.to_string())),
+            "accept" => match_number().map(AppCommand::Accept),
+            "unlock" => match_number().map(AppCommand::Unlock),
+            "output" => Ok(AppCommand::Output),
+            _ => Err(format!("Unknown command: {}", name)),
+        }
+    }
+}
+
+/// Parses one command per line, skipping blank lines and `#` comments.
+pub fn parse_script(script: &str) -> Result<Vec<AppCommand>, String> {
+    script
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(idx, line)| AppCommand::parse(line).map_err(|e| format!("Line {}: {}", idx + 1, e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{
+        PrologApp,
+        database::{Database, fixtures::pattern},
+        query_engine::QueryEngine,
+    };
+
+    #[test]
+    fn test_scripts_parse_into_commands() {
+        let script = "# smoke test\n\
+                      load Bear is an animal.\\nCat has fur.\n\
+                      accept 1\n\
+                      query animal(X).\n\
+                      output\n";
+        assert_eq!(
+            parse_script(script),
+            Ok(vec![
+                AppCommand::LoadText("Bear is an animal.\nCat has fur.".to_string()),
+                AppCommand::Accept(1),
+                AppCommand::Query("animal(X).".to_string()),
+                AppCommand::Output,
+            ])
+        );
+
+        assert!(AppCommand::parse("accept 0").is_err());
+        assert!(AppCommand::parse("query").is_err());
+        assert_eq!(
+            parse_script("parse\nfly away"),
+            Err("Line 2: Unknown command: fly".to_string())
+        );
+    }
+
+    #[test]
+    fn test_scripts_run_against_a_database() {
+        let pattern = pattern("<Noun> is a <Noun>", "is_a($1, $2).");
+        let database = Database {
+            patterns: vec![pattern],
+            ..Database::default()
+        };
+        let mut app = PrologApp::with_database(database, QueryEngine::new());
+
+        let script = "load Tom is a cat.\\nRex is a dog.\n\
+                      parse\n\
+                      accept 2\n\
+                      query is_a(X, dog)\n\
+                      output\n";
+        let outputs = app.run_script(script).unwrap();
+        assert_eq!(outputs[0], "Parsed 2 matches");
+        assert_eq!(outputs[1], "Parsed 2 matches");
+        assert_eq!(outputs[2], "Locked: rex is a dog.");
+        assert!(outputs[3].contains("rex"), "{}", outputs[3]);
+        assert!(!outputs[3].contains("tom"), "{}", outputs[3]);
+        assert!(outputs[4].contains("is_a(tom, cat)."), "{}", outputs[4]);
+
+        assert!(app.run_script("accept 3").is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! Which words of the input the parse covered, for the coverage overlay on
+//! the input panel.
+
+use std::ops::Range;
+
+use crate::app::{
+    database::Locale,
+    parser::{
+        pattern_matcher::{PatternMatch, PatternToken},
+        sentence_body,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WordCoverage {
+    /// Matched by a literal or a single-word capture.
+    Matched,
+    /// Part of a greedy `token+` capture.
+    Greedy,
+    /// Consumed by a `*` wildcard.
+    Wildcard,
+    /// Not part of any match.
+    Unmatched,
+}
+
+impl WordCoverage {
+    pub const ALL: [WordCoverage; 4] = [
+        WordCoverage::Matched,
+        WordCoverage::Greedy,
+        WordCoverage::Wildcard,
+        WordCoverage::Unmatched,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WordCoverage::Matched => "matched",
+            WordCoverage::Greedy => "greedy capture",
+            WordCoverage::Wildcard => "wildcard",
+            WordCoverage::Unmatched => "unmatched",
+        }
+    }
+
+    /// Background the overlay draws behind the word.
+    pub fn color(self) -> egui::Color32 {
+        match self {
+            WordCoverage::Matched => egui::Color32::from_rgba_unmultiplied(60, 160, 60, 90),
+            WordCoverage::Greedy => egui::Color32::from_rgba_unmultiplied(150, 100, 200, 90),
+            WordCoverage::Wildcard => egui::Color32::from_rgba_unmultiplied(200, 170, 50, 90),
+            WordCoverage::Unmatched => egui::Color32::from_rgba_unmultiplied(200, 60, 60, 90),
+        }
+    }
+}
+
+/// The coverage of each word of one parsed sentence.
+#[derive(Debug, Clone)]
+pub struct SentenceCoverage {
+    pub words: Vec<String>,
+    pub coverage: Vec<WordCoverage>,
+}
+
+impl SentenceCoverage {
+    /// Every word unmatched.
+    pub fn new(words: &[String]) -> Self {
+        Self {
+            words: words.to_vec(),
+            coverage: vec![WordCoverage::Unmatched; words.len()],
+        }
+    }
+
+    /// This coverage carried over to `words`, where word `i` of the covered
+    /// sentence is `words[positions[i]]`. Words left out of the covered
+    /// sentence, such as a stripped negation, count as matched.
+    pub fn mapped_onto(self, words: &[String], positions: &[usize]) -> SentenceCoverage {
+        let mut coverage = vec![WordCoverage::Matched; words.len()];
+        for (&position, word_coverage) in positions.iter().zip(self.coverage) {
+            coverage[position] = word_coverage;
+        }
+        SentenceCoverage {
+            words: words.to_vec(),
+            coverage,
+        }
+    }
+
+    /// Marks the words of `pattern_match`. `positions[i]` is where word `i`
+    /// of the sentence the match was made against sits in this sentence;
+    /// they differ when a conjunction was expanded.
+    pub fn mark(
+        &mut self,
+        pattern_match: &PatternMatch,
+        pattern_tokens: &[PatternToken],
+        positions: &[usize],
+    ) {
+        let mut set = |idx: usize, coverage| {
+            if let Some(&position) = positions.get(idx) {
+                self.coverage[position] = coverage;
+            }
+        };
+
+        for idx in pattern_match.start_idx..pattern_match.end_idx {
+            set(idx, WordCoverage::Matched);
+        }
+        for &idx in &pattern_match.skipped {
+            set(idx, WordCoverage::Wildcard);
+        }
+        for span in &pattern_match.spans {
+            if matches!(pattern_tokens[span.token], PatternToken::Greedy(_)) {
+                for idx in span.start..span.end {
+                    set(idx, WordCoverage::Greedy);
+                }
+            }
+        }
+    }
+}
+
+/// The byte range in `text` of every word that lines up with a parsed
+/// sentence word, with that word's coverage. Words the parser never saw,
+/// such as those of comment and directive lines, are left out.
+pub fn input_coverage(
+    text: &str,
+    sentences: &[SentenceCoverage],
+    locale: Locale,
+) -> Vec<(Range<usize>, WordCoverage)> {
+    let mut parsed = sentences
+        .iter()
+        .flat_map(|sentence| sentence.words.iter().zip(&sentence.coverage))
+        .peekable();
+
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+    // Bytes of the current word already lined up, for names joined from
+    // several input words ("new_york")
+    let mut consumed = 0;
+    for token in text.split_whitespace() {
+        let start = offset + text[offset..].find(token).unwrap_or(0);
+        offset = start + token.len();
+
+        let Some(&(word, &coverage)) = parsed.peek() else {
+            break;
+        };
+        let rest = &word[consumed..];
+        // Sentences are lowercased, and their quotes and final punctuation dropped.
+        let token = locale.lowercase(token);
+        if rest == token || rest == sentence_body(&token) {
+            ranges.push((start..offset, coverage));
+            parsed.next();
+            consumed = 0;
+        } else if rest.starts_with(&format!("{}_", token)) {
+            ranges.push((start..offset, coverage));
+            consumed += token.len() + 1;
+        }
+    }
+    ranges
+}
+
+/// Lays out `text` with the coverage of each word as its background.
+pub fn coverage_layout(
+    text: &str,
+    ranges: &[(Range<usize>, WordCoverage)],
+    font_id: egui::FontId,
+    color: egui::Color32,
+    wrap_width: f32,
+) -> egui::text::LayoutJob {
+    let format = |background| egui::TextFormat {
+        font_id: font_id.clone(),
+        color,
+        background,
+        ..Default::default()
+    };
+
+    let mut job = egui::text::LayoutJob::default();
+    let mut offset = 0;
+    for (range, coverage) in ranges {
+        job.append(&text[offset..range.start], 0.0, format(egui::Color32::TRANSPARENT));
+        job.append(&text[range.clone()], 0.0, format(coverage.color()));
+        offset = range.end;
+    }
+    job.append(&text[offset..], 0.0, format(egui::Color32::TRANSPARENT));
+    job.wrap.max_width = wrap_width;
+    job
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_words_line_up_with_parsed_sentences() {
+        let words = |sentence: &str| -> Vec<String> {
+            sentence.split_whitespace().map(str::to_string).collect()
+        };
+        let mut first = SentenceCoverage::new(&words("bear is an animal"));
+        first.coverage[0] = WordCoverage::Matched;
+        first.coverage[3] = WordCoverage::Greedy;
+        let second = SentenceCoverage::new(&words("cats purr in new_york"));
+
+        let text = "% a comment\nBear is an animal.\n> likes(a, b).\nCats purr in New York.";
+        let ranges = input_coverage(text, &[first, second], Locale::English);
+        let covered: Vec<(&str, WordCoverage)> = ranges
+            .iter()
+            .map(|(range, coverage)| (&text[range.clone()], *coverage))
+            .collect();
+        assert_eq!(
+            covered,
+            vec![
+                ("Bear", WordCoverage::Matched),
+                ("is", WordCoverage::Unmatched),
+                ("an", WordCoverage::Unmatched),
+                ("animal.", WordCoverage::Greedy),
+                ("Cats", WordCoverage::Unmatched),
+                ("purr", WordCoverage::Unmatched),
+                ("in", WordCoverage::Unmatched),
+                ("New", WordCoverage::Unmatched),
+                ("York.", WordCoverage::Unmatched),
+            ]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Writes a crash report when the app panics and offers to restore from it on
+//! the next start. The window has no console on Windows, so without this a
+//! panic closes the app without a trace.
+
+use std::{
+    backtrace::Backtrace,
+    panic::PanicHookInfo,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, TryLockError},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::{data_dir, database::SharedDatabase};
+
+const CRASH_REPORT_PATH: &str = "crash_report.json";
+const RECOVERED_DATABASE_PATH: &str = "crash_recovered_database.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// Panic message with the source location.
+    pub message: String,
+    pub backtrace: String,
+    pub input_text: String,
+    pub input_path: Option<PathBuf>,
+    /// Where the in-memory database, unsaved edits included, was written.
+    /// `None` when it could not be read at the time of the crash.
+    pub database_path: Option<PathBuf>,
+    /// Whether that copy was read past a poisoned lock: an earlier panic hit
+    /// in the middle of an edit, so the copy may hold it half done.
+    #[serde(default)]
+    pub database_poisoned: bool,
+}
+
+/// What the panic hook writes out; kept up to date by the app.
+struct CrashState {
+    input_text: String,
+    input_path: Option<PathBuf>,
+    database: Option<Arc<SharedDatabase>>,
+}
+
+static CRASH_STATE: Mutex<CrashState> = Mutex::new(CrashState {
+    input_text: String::new(),
+    input_path: None,
+    database: None,
+});
+
+/// Writes a crash report on every panic, then runs the previous hook.
+pub fn install_panic_hook() {
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Err(e) = write_report(info) {
+            eprintln!("Could not write crash report: {}", e);
+        }
+        previous_hook(info);
+    }));
+}
+
+/// Remembers the input being worked on, for the next crash report.
+pub fn record_input(text: &str, path: Option<&Path>) {
+    if let Ok(mut state) = CRASH_STATE.lock() {
+        state.input_text = text.to_string();
+        state.input_path = path.map(Path::to_path_buf);
+    }
+}
+
+/// Has crash reports include a copy of `database`.
+pub fn watch_database(database: Arc<SharedDatabase>) {
+    if let Ok(mut state) = CRASH_STATE.lock() {
+        state.database = Some(database);
+    }
+}
+
+/// The report left by a crash in a previous run, if any.
+pub fn load_crash_report() -> Option<CrashReport> {
+    load_report(&data_dir::path(""))
+}
+
+/// Deletes the report and its database copy once they have been dealt with.
+pub fn discard_crash_report() {
+    discard_report(&data_dir::path(""));
+}
+
+fn load_report(dir: &Path) -> Option<CrashReport> {
+    let data = std::fs::read_to_string(dir.join(CRASH_REPORT_PATH)).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn discard_report(dir: &Path) {
+    let _ = std::fs::remove_file(dir.join(CRASH_REPORT_PATH));
+    let _ = std::fs::remove_file(dir.join(RECOVERED_DATABASE_PATH));
+}
+
+fn write_report(info: &PanicHookInfo) -> Result<(), Box<dyn std::error::Error>> {
+    // The hook runs before unwinding, so the panicking thread may still hold
+    // these locks; blocking on them would hang instead of exiting.
+    let state = match CRASH_STATE.try_lock() {
+        Ok(state) => state,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return Err("crash state is locked".into()),
+    };
+    save_report(&data_dir::path(""), info.to_string(), &state)
+}
+
+/// Writes the report of a panic with `message` into `dir`, with a copy of
+/// the watched database next to it.
+fn save_report(
+    dir: &Path,
+    message: String,
+    state: &CrashState,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let recovered = state.database.as_ref().and_then(|database| {
+        let (database, poisoned) = match database.try_read() {
+            Ok(database) => (database, false),
+            Err(TryLockError::Poisoned(e)) => (e.into_inner(), true),
+            Err(TryLockError::WouldBlock) => return None,
+        };
+        let recovered_path = dir.join(RECOVERED_DATABASE_PATH);
+        database.save(&recovered_path).ok()?;
+        Some((recovered_path, poisoned))
+    });
+    let (database_path, database_poisoned) = match recovered {
+        Some((path, poisoned)) => (Some(path), poisoned),
+        None => (None, false),
+    };
+
+    let report = CrashReport {
+        message,
+        backtrace: Backtrace::force_capture().to_string(),
+        input_text: state.input_text.clone(),
+        input_path: state.input_path.clone(),
+        database_path,
+        database_poisoned,
+    };
+    std::fs::write(dir.join(CRASH_REPORT_PATH), serde_json::to_string_pretty(&report)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::database::Database;
+
+    #[test]
+    fn test_reports_round_trip_and_flag_a_poisoned_database() {
+        let dir = std::env::temp_dir().join(format!("simple-prolog-crash-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let database = Arc::new(SharedDatabase::new(Database::default()));
+        let state = CrashState {
+            input_text: "Bear is an animal.".to_string(),
+            input_path: None,
+            database: Some(Arc::clone(&database)),
+        };
+
+        save_report(&dir, "boom".to_string(), &state).unwrap();
+        let report = load_report(&dir).unwrap();
+        assert_eq!(report.message, "boom");
+        assert_eq!(report.input_text, "Bear is an animal.");
+        assert_eq!(report.database_path, Some(dir.join(RECOVERED_DATABASE_PATH)));
+        assert!(!report.database_poisoned);
+
+        // A panic in the middle of an edit poisons the lock
+        let writer = Arc::clone(&database);
+        let _ = std::thread::spawn(move || {
+            let _write_database = writer.write();
+            panic!("edit failed");
+        })
+        .join();
+        save_report(&dir, "boom".to_string(), &state).unwrap();
+        assert!(load_report(&dir).unwrap().database_poisoned);
+
+        discard_report(&dir);
+        assert!(load_report(&dir).is_none());
+        assert!(!dir.join(RECOVERED_DATABASE_PATH).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
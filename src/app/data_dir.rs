@@ -0,0 +1,119 @@
+//! Where the app keeps the files it writes: the word database, the query
+//! config, snapshots, crash reports and default exports. They live in the
+//! platform's app data directory, so they no longer end up wherever the
+//! binary was launched from. `--portable` keeps them in the working
+//! directory instead.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const APP_DIR_NAME: &str = "simple-prolog";
+
+/// Files carried over from the working directory the first time the data
+/// directory is used, so an existing setup keeps its database and config.
+const CARRIED_OVER: [&str; 6] = [
+    "prolog_database.bin",
+    "prolog_database.json",
+    "query_config.txt",
+    "report_queries.txt",
+    "snapshots.json",
+    "word_frequencies.tsv",
+];
+
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Picks the data directory for this run. Called once at startup, before
+/// anything is loaded; falls back to the working directory if the app data
+/// directory cannot be created.
+pub fn init(portable: bool) {
+    let dir = if portable {
+        PathBuf::new()
+    } else {
+        match app_data_dir() {
+            Some(dir) => match carry_over(Path::new(""), &dir) {
+                Ok(_) => dir,
+                Err(e) => {
+                    eprintln!("Note: Could not use {}: {}", dir.display(), e);
+                    PathBuf::new()
+                }
+            },
+            None => PathBuf::new(),
+        }
+    };
+    let _ = DATA_DIR.set(dir);
+}
+
+/// Where the app keeps `file`. The working directory when [`init`] was not
+/// called, as in the command-line tools.
+pub fn path(file: &str) -> PathBuf {
+    DATA_DIR.get().map_or_else(PathBuf::new, Clone::clone).join(file)
+}
+
+/// The data directory, for display.
+pub fn describe() -> String {
+    match DATA_DIR.get() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.display().to_string(),
+        _ => "the working directory (portable)".to_string(),
+    }
+}
+
+/// The platform's directory for app data: `%APPDATA%` on Windows,
+/// `~/Library/Application Support` on macOS and `$XDG_DATA_HOME` or
+/// `~/.local/share` elsewhere.
+fn app_data_dir() -> Option<PathBuf> {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    };
+    let base = if cfg!(target_os = "windows") {
+        env_dir("APPDATA")?
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME")?.join("Library").join("Application Support")
+    } else {
+        env_dir("XDG_DATA_HOME").or_else(|| Some(env_dir("HOME")?.join(".local").join("share")))?
+    };
+    Some(base.join(APP_DIR_NAME))
+}
+
+/// Creates `dir` and copies into it the files of [`CARRIED_OVER`] that are in
+/// `from` but not yet in `dir`. Returns how many were copied.
+fn carry_over(from: &Path, dir: &Path) -> std::io::Result<usize> {
+    std::fs::create_dir_all(dir)?;
+    let mut copied = 0;
+    for file in CARRIED_OVER {
+        let source = from.join(file);
+        let target = dir.join(file);
+        if source.is_file() && !target.exists() {
+            std::fs::copy(&source, &target)?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_existing_files_are_carried_over_once() {
+        let root = std::env::temp_dir().join(format!("simple-prolog-data-{}", std::process::id()));
+        let working = root.join("working");
+        let data = root.join("data");
+        std::fs::create_dir_all(&working).unwrap();
+        std::fs::write(working.join("query_config.txt"), "old").unwrap();
+        std::fs::write(working.join("notes.txt"), "not ours").unwrap();
+
+        assert_eq!(carry_over(&working, &data).unwrap(), 1);
+        assert_eq!(std::fs::read_to_string(data.join("query_config.txt")).unwrap(), "old");
+        assert!(!data.join("notes.txt").exists());
+
+        // What the data directory already has is never overwritten
+        std::fs::write(data.join("query_config.txt"), "edited").unwrap();
+        assert_eq!(carry_over(&working, &data).unwrap(), 0);
+        assert_eq!(std::fs::read_to_string(data.join("query_config.txt")).unwrap(), "edited");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Contracted words and what they stand for, used to expand them before
+/// pattern matching.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct Contractions {
+    /// Whole words, e.g. `can't` -> `can not`, `it's` -> `it is`.
+    #[serde(default)]
+    pub words: BTreeMap<String, String>,
+    /// Endings, e.g. `n't` -> `not`, `'re` -> `are`, for words not listed
+    /// whole. `'s` is expanded only where it cannot be a possessive.
+    #[serde(default)]
+    pub endings: BTreeMap<String, String>,
+}
+
+impl Contractions {
+    /// The common English contractions, used for databases that have no
+    /// table yet.
+    pub fn english() -> Self {
+        let words = [
+            ("can't", "can not"),
+            ("cannot", "can not"),
+            ("won't", "will not"),
+            ("shan't", "shall not"),
+            ("ain't", "is not"),
+            ("i'm", "i am"),
+            ("let's", "let us"),
+            ("it's", "it is"),
+            ("he's", "he is"),
+            ("she's", "she is"),
+            ("that's", "that is"),
+            ("there's", "there is"),
+            ("here's", "here is"),
+            ("what's", "what is"),
+            ("who's", "who is"),
+            ("where's", "where is"),
+        ];
+        let endings = [
+            ("n't", "not"),
+            ("'re", "are"),
+            ("'ve", "have"),
+            ("'ll", "will"),
+            ("'d", "would"),
+            ("'s", "is"),
+        ];
+        let table = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(short, long)| (short.to_string(), long.to_string()))
+                .collect()
+        };
+        Contractions {
+            words: table(&words),
+            endings: table(&endings),
+        }
+    }
+}
@@ -1,8 +1,19 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
-use crate::app::database::{sentences::PrologPattern, words::WordEntry};
+use crate::app::{
+    database::{
+        contractions::Contractions,
+        locale::Locale,
+        sentences::PrologPattern,
+        words::{Tense, WordEntry, WordFeatures, WordType},
+    },
+    morphology::MorphologyExceptions,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Database {
@@ -10,6 +21,18 @@ pub struct Database {
     pub words: Vec<WordEntry>,
     #[serde(default)]
     pub patterns: Vec<PrologPattern>,
+    #[serde(default)]
+    pub locale: Locale,
+    /// Irregular plurals and verbs for the morphology rules.
+    #[serde(default = "MorphologyExceptions::english")]
+    pub morphology: MorphologyExceptions,
+    /// Contracted words expanded before pattern matching.
+    #[serde(default = "Contractions::english")]
+    pub contractions: Contractions,
+    /// Named pattern fragments, such as `noun_phrase` for
+    /// `[<Determiner>] [<Adjective>] <Noun>`, that patterns use as `@noun_phrase`.
+    #[serde(default)]
+    pub subpatterns: BTreeMap<String, String>,
 
     #[serde(skip)]
     pub form_index: HashMap<String, String>,
@@ -26,16 +49,32 @@ impl Database {
 
             let mut db: Database = if extension == Some("bin") {
                 let data = std::fs::read(path)?;
-                bincode::deserialize(&data)?
+                // Older layouts, newest first; bincode has no field defaults
+                bincode::deserialize(&data)
+                    .or_else(|_| read_layout::<UncomposedDatabase>(&data))
+                    .or_else(|_| read_layout::<UnnumberedDatabase>(&data))
+                    .or_else(|_| read_layout::<UnfeaturedDatabase>(&data))
+                    .or_else(|_| read_layout::<UncontractedDatabase>(&data))
+                    .or_else(|_| read_layout::<UntensedDatabase>(&data))
+                    .or_else(|_| read_layout::<FactOnlyDatabase>(&data))
+                    .or_else(|_| read_layout::<UntaggedDatabase>(&data))
+                    .or_else(|_| read_layout::<UnexampledDatabase>(&data))
+                    .or_else(|_| read_layout::<LegacyDatabase>(&data))?
             } else {
                 let data = std::fs::read_to_string(path)?;
                 serde_json::from_str(&data)?
             };
 
+            db.fill_verb_tenses();
+            db.fill_noun_numbers();
             db.rebuild_index();
             Ok(db)
         } else {
-            let db = Database::default();
+            let db = Database {
+                morphology: MorphologyExceptions::english(),
+                contractions: Contractions::english(),
+                ..Database::default()
+            };
             db.save(path)?;
             Ok(db)
         }
@@ -56,27 +95,408 @@ impl Database {
         Ok(())
     }
 
+    /// Folds a word the way `form_index` keys are folded.
+    pub fn fold_case(&self, word: &str) -> String {
+        self.locale.fold_case(word)
+    }
+
     pub fn rebuild_index(&mut self) {
         self.form_index.clear();
         self.form_value.clear();
 
         for entry in &self.words {
-            self.form_index
-                .insert(entry.lemma.clone(), entry.lemma.clone());
+            let mut keys: Vec<String> = std::iter::once(&entry.lemma)
+                .chain(&entry.forms)
+                .map(|form| self.locale.fold_case(form))
+                .collect();
+            keys.sort();
+            keys.dedup();
 
-            if self.form_value.contains_key(&entry.lemma) {
-                self.form_value
-                    .get_mut(&entry.lemma)
-                    .unwrap()
-                    .push(entry.clone());
-            } else {
-                self.form_value
-                    .insert(entry.lemma.clone(), vec![entry.clone()]);
+            for key in keys {
+                self.form_value.entry(key).or_default().push(entry.clone());
             }
+        }
 
-            for form in &entry.forms {
-                self.form_index.insert(form.clone(), entry.lemma.clone());
-            }
+        // Stable sort, so equally frequent readings keep their database order.
+        for (form, entries) in &mut self.form_value {
+            entries.sort_by_key(|entry| std::cmp::Reverse(entry.frequency));
+            self.form_index
+                .insert(form.clone(), entries[0].lemma.clone());
+        }
+    }
+}
+
+/// A database read from `data` in an older binary layout `T`.
+fn read_layout<T: DeserializeOwned + Into<Database>>(data: &[u8]) -> bincode::Result<Database> {
+    bincode::deserialize::<T>(data).map(Into::into)
+}
+
+/// Binary layout written before databases could define sub-patterns.
+#[derive(Deserialize)]
+struct UncomposedDatabase {
+    words: Vec<WordEntry>,
+    patterns: Vec<PrologPattern>,
+    locale: Locale,
+    morphology: MorphologyExceptions,
+    contractions: Contractions,
+}
+
+impl From<UncomposedDatabase> for Database {
+    fn from(old: UncomposedDatabase) -> Self {
+        Database {
+            words: old.words,
+            patterns: old.patterns,
+            locale: old.locale,
+            morphology: old.morphology,
+            contractions: old.contractions,
+            ..Database::default()
+        }
+    }
+}
+
+/// Binary layout written before nouns carried the number of their forms.
+#[derive(Deserialize)]
+struct UnnumberedDatabase {
+    words: Vec<UnnumberedWordEntry>,
+    patterns: Vec<PrologPattern>,
+    locale: Locale,
+    morphology: MorphologyExceptions,
+    contractions: Contractions,
+}
+
+#[derive(Deserialize)]
+struct UnnumberedWordEntry {
+    lemma: String,
+    word_type: WordType,
+    forms: Vec<String>,
+    frequency: u64,
+    tenses: BTreeMap<String, Tense>,
+    features: WordFeatures,
+}
+
+impl From<UnnumberedWordEntry> for WordEntry {
+    fn from(old: UnnumberedWordEntry) -> Self {
+        WordEntry {
+            lemma: old.lemma,
+            word_type: old.word_type,
+            forms: old.forms,
+            frequency: old.frequency,
+            tenses: old.tenses,
+            numbers: BTreeMap::new(),
+            features: old.features,
+        }
+    }
+}
+
+impl From<UnnumberedDatabase> for Database {
+    fn from(old: UnnumberedDatabase) -> Self {
+        Database {
+            words: old.words.into_iter().map(Into::into).collect(),
+            patterns: old.patterns,
+            locale: old.locale,
+            morphology: old.morphology,
+            contractions: old.contractions,
+            ..Database::default()
+        }
+    }
+}
+
+/// Binary layout written before words carried gender, animacy and number.
+#[derive(Deserialize)]
+struct UnfeaturedDatabase {
+    words: Vec<UnfeaturedWordEntry>,
+    patterns: Vec<PrologPattern>,
+    locale: Locale,
+    morphology: MorphologyExceptions,
+    contractions: Contractions,
+}
+
+#[derive(Deserialize)]
+struct UnfeaturedWordEntry {
+    lemma: String,
+    word_type: WordType,
+    forms: Vec<String>,
+    frequency: u64,
+    tenses: BTreeMap<String, Tense>,
+}
+
+impl From<UnfeaturedWordEntry> for WordEntry {
+    fn from(old: UnfeaturedWordEntry) -> Self {
+        WordEntry {
+            lemma: old.lemma,
+            word_type: old.word_type,
+            forms: old.forms,
+            frequency: old.frequency,
+            tenses: old.tenses,
+            numbers: BTreeMap::new(),
+            features: WordFeatures::default(),
+        }
+    }
+}
+
+impl From<UnfeaturedDatabase> for Database {
+    fn from(old: UnfeaturedDatabase) -> Self {
+        Database {
+            words: old.words.into_iter().map(Into::into).collect(),
+            patterns: old.patterns,
+            locale: old.locale,
+            morphology: old.morphology,
+            contractions: old.contractions,
+            ..Database::default()
+        }
+    }
+}
+
+/// Binary layout written before databases carried a contraction table.
+#[derive(Deserialize)]
+struct UncontractedDatabase {
+    words: Vec<UnfeaturedWordEntry>,
+    patterns: Vec<PrologPattern>,
+    locale: Locale,
+    morphology: MorphologyExceptions,
+}
+
+impl From<UncontractedDatabase> for Database {
+    fn from(old: UncontractedDatabase) -> Self {
+        Database {
+            words: old.words.into_iter().map(Into::into).collect(),
+            patterns: old.patterns,
+            locale: old.locale,
+            morphology: old.morphology,
+            contractions: Contractions::english(),
+            ..Database::default()
+        }
+    }
+}
+
+/// Binary layout written before verbs carried the tense of their forms.
+#[derive(Deserialize)]
+struct UntensedDatabase {
+    words: Vec<UntensedWordEntry>,
+    patterns: Vec<PrologPattern>,
+    locale: Locale,
+    morphology: MorphologyExceptions,
+}
+
+#[derive(Deserialize)]
+struct UntensedWordEntry {
+    lemma: String,
+    word_type: WordType,
+    forms: Vec<String>,
+    frequency: u64,
+}
+
+impl From<UntensedWordEntry> for WordEntry {
+    fn from(old: UntensedWordEntry) -> Self {
+        WordEntry {
+            lemma: old.lemma,
+            word_type: old.word_type,
+            forms: old.forms,
+            frequency: old.frequency,
+            tenses: BTreeMap::new(),
+            numbers: BTreeMap::new(),
+            features: WordFeatures::default(),
+        }
+    }
+}
+
+impl From<UntensedDatabase> for Database {
+    fn from(old: UntensedDatabase) -> Self {
+        Database {
+            words: old.words.into_iter().map(Into::into).collect(),
+            patterns: old.patterns,
+            locale: old.locale,
+            morphology: old.morphology,
+            contractions: Contractions::english(),
+            ..Database::default()
+        }
+    }
+}
+
+/// Binary layout written before patterns could write universal rules.
+#[derive(Deserialize)]
+struct FactOnlyDatabase {
+    words: Vec<UntensedWordEntry>,
+    patterns: Vec<FactOnlyPrologPattern>,
+    locale: Locale,
+    morphology: MorphologyExceptions,
+}
+
+#[derive(Deserialize)]
+struct FactOnlyPrologPattern {
+    name: String,
+    pattern: String,
+    template: String,
+    priority: i32,
+    enabled: bool,
+    examples: Vec<String>,
+    counter_examples: Vec<String>,
+    tags: Vec<String>,
+}
+
+impl From<FactOnlyPrologPattern> for PrologPattern {
+    fn from(old: FactOnlyPrologPattern) -> Self {
+        PrologPattern {
+            name: old.name,
+            pattern: old.pattern,
+            template: old.template,
+            priority: old.priority,
+            enabled: old.enabled,
+            examples: old.examples,
+            counter_examples: old.counter_examples,
+            tags: old.tags,
+            universal_rules: false,
+        }
+    }
+}
+
+impl From<FactOnlyDatabase> for Database {
+    fn from(old: FactOnlyDatabase) -> Self {
+        Database {
+            words: old.words.into_iter().map(Into::into).collect(),
+            patterns: old.patterns.into_iter().map(Into::into).collect(),
+            locale: old.locale,
+            morphology: old.morphology,
+            contractions: Contractions::english(),
+            ..Database::default()
+        }
+    }
+}
+
+/// Binary layout written before patterns carried tags.
+#[derive(Deserialize)]
+struct UntaggedDatabase {
+    words: Vec<UntensedWordEntry>,
+    patterns: Vec<UntaggedPrologPattern>,
+    locale: Locale,
+    morphology: MorphologyExceptions,
+}
+
+#[derive(Deserialize)]
+struct UntaggedPrologPattern {
+    name: String,
+    pattern: String,
+    template: String,
+    priority: i32,
+    enabled: bool,
+    examples: Vec<String>,
+    counter_examples: Vec<String>,
+}
+
+impl From<UntaggedPrologPattern> for PrologPattern {
+    fn from(old: UntaggedPrologPattern) -> Self {
+        PrologPattern {
+            name: old.name,
+            pattern: old.pattern,
+            template: old.template,
+            priority: old.priority,
+            enabled: old.enabled,
+            examples: old.examples,
+            counter_examples: old.counter_examples,
+            tags: Vec::new(),
+            universal_rules: false,
+        }
+    }
+}
+
+impl From<UntaggedDatabase> for Database {
+    fn from(old: UntaggedDatabase) -> Self {
+        Database {
+            words: old.words.into_iter().map(Into::into).collect(),
+            patterns: old.patterns.into_iter().map(Into::into).collect(),
+            locale: old.locale,
+            morphology: old.morphology,
+            contractions: Contractions::english(),
+            ..Database::default()
+        }
+    }
+}
+
+/// Binary layout written before patterns carried example sentences.
+#[derive(Deserialize)]
+struct UnexampledDatabase {
+    words: Vec<UntensedWordEntry>,
+    patterns: Vec<LegacyPrologPattern>,
+    locale: Locale,
+    morphology: MorphologyExceptions,
+}
+
+impl From<UnexampledDatabase> for Database {
+    fn from(old: UnexampledDatabase) -> Self {
+        Database {
+            words: old.words.into_iter().map(Into::into).collect(),
+            patterns: old.patterns.into_iter().map(Into::into).collect(),
+            locale: old.locale,
+            morphology: old.morphology,
+            contractions: Contractions::english(),
+            ..Database::default()
+        }
+    }
+}
+
+/// Binary layout written before databases carried a locale, word frequencies
+/// and morphology exceptions. bincode has no field defaults, so older `.bin` files are read
+/// through this instead.
+#[derive(Deserialize)]
+struct LegacyDatabase {
+    words: Vec<LegacyWordEntry>,
+    patterns: Vec<LegacyPrologPattern>,
+}
+
+#[derive(Deserialize)]
+struct LegacyWordEntry {
+    lemma: String,
+    word_type: WordType,
+    forms: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct LegacyPrologPattern {
+    name: String,
+    pattern: String,
+    template: String,
+    priority: i32,
+    enabled: bool,
+}
+
+impl From<LegacyPrologPattern> for PrologPattern {
+    fn from(legacy: LegacyPrologPattern) -> Self {
+        PrologPattern {
+            name: legacy.name,
+            pattern: legacy.pattern,
+            template: legacy.template,
+            priority: legacy.priority,
+            enabled: legacy.enabled,
+            examples: Vec::new(),
+            counter_examples: Vec::new(),
+            tags: Vec::new(),
+            universal_rules: false,
+        }
+    }
+}
+
+impl From<LegacyDatabase> for Database {
+    fn from(legacy: LegacyDatabase) -> Self {
+        let words = legacy
+            .words
+            .into_iter()
+            .map(|entry| WordEntry {
+                lemma: entry.lemma,
+                word_type: entry.word_type,
+                forms: entry.forms,
+                frequency: 0,
+                tenses: BTreeMap::new(),
+                numbers: BTreeMap::new(),
+                features: WordFeatures::default(),
+            })
+            .collect();
+
+        Database {
+            words,
+            patterns: legacy.patterns.into_iter().map(Into::into).collect(),
+            morphology: MorphologyExceptions::english(),
+            contractions: Contractions::english(),
+            ..Database::default()
         }
     }
 }
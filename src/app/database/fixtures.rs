@@ -0,0 +1,33 @@
+//! Words and patterns for tests to put in a [`Database`](super::Database).
+
+use super::{PrologPattern, WordEntry, WordType};
+
+/// An enabled pattern named after its template's first predicate, with the
+/// default priority and no examples or tags.
+pub fn pattern(src: &str, template: &str) -> PrologPattern {
+    let name = template.split('(').next().unwrap_or_default();
+    PrologPattern {
+        name: name.trim().to_string(),
+        pattern: src.to_string(),
+        template: template.to_string(),
+        priority: 50,
+        enabled: true,
+        examples: Vec::new(),
+        counter_examples: Vec::new(),
+        tags: Vec::new(),
+        universal_rules: false,
+    }
+}
+
+/// A word with the given inflected forms and nothing else known about it.
+pub fn word(lemma: &str, word_type: WordType, forms: &[&str]) -> WordEntry {
+    WordEntry {
+        lemma: lemma.to_string(),
+        word_type,
+        forms: forms.iter().map(|form| form.to_string()).collect(),
+        frequency: 0,
+        tenses: Default::default(),
+        numbers: Default::default(),
+        features: Default::default(),
+    }
+}
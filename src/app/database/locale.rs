@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// Language of a database's vocabulary. Decides how words are lowercased and
+/// compared, so e.g. Turkish `İ`/`I` and German `ß` fold the way speakers expect.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+    Turkish,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Locale::English, Locale::German, Locale::Turkish];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Locale::English => "English",
+            Locale::German => "German",
+            Locale::Turkish => "Turkish",
+        }
+    }
+
+    /// Lowercases text for display and tokenizing.
+    pub fn lowercase(self, text: &str) -> String {
+        match self {
+            // Dotted İ lowers to plain i and dotless I to ı, instead of the
+            // default "i̇" and "i".
+            Locale::Turkish => text
+                .chars()
+                .map(|c| match c {
+                    'İ' => "i".to_string(),
+                    'I' => "ı".to_string(),
+                    c => c.to_lowercase().collect(),
+                })
+                .collect(),
+            Locale::English | Locale::German => text.to_lowercase(),
+        }
+    }
+
+    /// Folds text for case-insensitive comparison and index keys. Goes further
+    /// than `lowercase`: German ß and ẞ fold to "ss", so "straße" equals "STRASSE".
+    pub fn fold_case(self, text: &str) -> String {
+        let lower = self.lowercase(text);
+        match self {
+            Locale::German => lower.replace('ß', "ss"),
+            Locale::English | Locale::Turkish => lower,
+        }
+    }
+
+    pub fn eq_ignore_case(self, a: &str, b: &str) -> bool {
+        a == b || self.fold_case(a) == self.fold_case(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folding_follows_the_locale() {
+        assert_eq!(Locale::Turkish.lowercase("İSTANBUL"), "istanbul");
+        assert_eq!(Locale::Turkish.lowercase("KIZ"), "kız");
+        assert_eq!(Locale::English.lowercase("KIZ"), "kiz");
+        assert!(!Locale::Turkish.eq_ignore_case("kiz", "KIZ"));
+
+        assert!(Locale::German.eq_ignore_case("straße", "STRASSE"));
+        assert!(Locale::German.eq_ignore_case("STRAẞE", "strasse"));
+        assert!(!Locale::English.eq_ignore_case("straße", "strasse"));
+    }
+}
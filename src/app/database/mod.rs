@@ -1,7 +1,15 @@
+mod contractions;
 mod database;
+#[cfg(test)]
+pub mod fixtures;
+mod locale;
 mod sentences;
+mod shared;
 mod words;
 
+pub use contractions::Contractions;
 pub use database::Database;
-pub use sentences::PrologPattern;
-pub use words::{WordEntry, WordType};
+pub use locale::Locale;
+pub use sentences::{PrologPattern, is_rule_template};
+pub use shared::SharedDatabase;
+pub use words::{Animacy, Gender, Number, Tense, WordEntry, WordFeatures, WordType};
@@ -1,3 +1,5 @@
+use std::{borrow::Cow, collections::BTreeSet};
+
 use serde::{Deserialize, Serialize};
 
 use crate::app::database::Database;
@@ -9,14 +11,197 @@ pub struct PrologPattern {
     pub template: String,
     pub priority: i32,
     pub enabled: bool,
+    /// Sentences the pattern must match. Re-run on every edit, which is
+    /// refused if one of them stops matching.
+    #[serde(default)]
+    pub examples: Vec<String>,
+    /// Sentences the pattern must not match.
+    #[serde(default)]
+    pub counter_examples: Vec<String>,
+    /// Groups the pattern belongs to, such as `biology` or `legal`. A document
+    /// can leave whole groups out without disabling them for everyone.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Writes rules about any member of the class the first capture names
+    /// instead of facts: "All mammals are animals" with `$2($1)` becomes
+    /// `animal(X) :- mammal(X).`
+    #[serde(default)]
+    pub universal_rules: bool,
+}
+
+impl PrologPattern {
+    /// Whether the template writes rules: its `{$n}` placeholders stand for
+    /// captures that are clauses of their own, parsed with the other patterns
+    /// into the goals of the rule's head and body.
+    pub fn is_rule_template(&self) -> bool {
+        is_rule_template(&self.template)
+    }
+
+    /// Describes every example `matches` rejects and every counter-example it
+    /// accepts. Empty when the pattern behaves as its examples say.
+    pub fn example_regressions(&self, matches: impl Fn(&str) -> bool) -> Vec<String> {
+        let missed = self
+            .examples
+            .iter()
+            .filter(|example| !matches(example))
+            .map(|example| format!("no longer matches \"{}\"", example));
+        let accepted = self
+            .counter_examples
+            .iter()
+            .filter(|counter| matches(counter))
+            .map(|counter| format!("now matches counter-example \"{}\"", counter));
+        missed.chain(accepted).collect()
+    }
+}
+
+/// Whether `template` has `{$n}` clause placeholders; see
+/// [`PrologPattern::is_rule_template`].
+pub fn is_rule_template(template: &str) -> bool {
+    template.contains("{$")
 }
 
 impl Database {
-    pub fn get_sorted_patterns(&self) -> Vec<&PrologPattern> {
-        let mut patterns: Vec<&PrologPattern> =
-            self.patterns.iter().filter(|p| p.enabled).collect();
+    /// `pattern` with each `@name` element replaced by the sub-pattern of that
+    /// name, itself expanded. Captures inside a sub-pattern take `$n` slots
+    /// like the tokens they stand for. Undefined names, and references back
+    /// to a sub-pattern being expanded, are left as written and match
+    /// nothing.
+    pub fn expand_subpatterns(&self, pattern: &str) -> String {
+        self.expand_within(pattern, &mut Vec::new())
+    }
+
+    fn expand_within<'a>(&'a self, pattern: &str, expanding: &mut Vec<&'a str>) -> String {
+        let elements: Vec<String> = pattern
+            .split_whitespace()
+            .map(|element| {
+                let Some((name, definition)) = element
+                    .strip_prefix('@')
+                    .and_then(|name| self.subpatterns.get_key_value(name))
+                else {
+                    return element.to_string();
+                };
+                if expanding.contains(&name.as_str()) {
+                    return element.to_string();
+                }
+                expanding.push(name);
+                let expanded = self.expand_within(definition, expanding);
+                expanding.pop();
+                expanded
+            })
+            .collect();
+        elements.join(" ")
+    }
+
+    /// `pattern` with its sub-patterns expanded, borrowed when it uses none.
+    pub fn with_subpatterns<'a>(&self, pattern: &'a PrologPattern) -> Cow<'a, PrologPattern> {
+        if !pattern.pattern.split_whitespace().any(|element| element.starts_with('@')) {
+            return Cow::Borrowed(pattern);
+        }
+        Cow::Owned(PrologPattern {
+            pattern: self.expand_subpatterns(&pattern.pattern),
+            ..pattern.clone()
+        })
+    }
+
+    /// Enabled patterns, highest priority first, leaving out any pattern
+    /// with a tag in `disabled_tags`.
+    pub fn get_sorted_patterns(&self, disabled_tags: &BTreeSet<String>) -> Vec<&PrologPattern> {
+        let mut patterns: Vec<&PrologPattern> = self
+            .patterns
+            .iter()
+            .filter(|p| p.enabled && !p.tags.iter().any(|tag| disabled_tags.contains(tag)))
+            .collect();
 
         patterns.sort_by(|a, b| b.priority.cmp(&a.priority));
         patterns
     }
+
+    /// Every tag used by a pattern, sorted.
+    pub fn pattern_tags(&self) -> BTreeSet<&str> {
+        self.patterns
+            .iter()
+            .flat_map(|p| p.tags.iter().map(String::as_str))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::database::fixtures::pattern;
+
+    #[test]
+    fn test_examples_and_counter_examples_report_regressions() {
+        let pattern = PrologPattern {
+            examples: vec!["john likes pizza".to_string(), "mary likes tea".to_string()],
+            counter_examples: vec!["john hates pizza".to_string()],
+            ..pattern("<Noun> likes <Noun>", "likes($1, $2).")
+        };
+
+        assert!(pattern
+            .example_regressions(|sentence| sentence.contains("likes"))
+            .is_empty());
+        assert_eq!(
+            pattern.example_regressions(|sentence| sentence.starts_with("john")),
+            vec![
+                "no longer matches \"mary likes tea\"",
+                "now matches counter-example \"john hates pizza\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disabled_tags_leave_their_patterns_out() {
+        let tagged = |name: &str, priority, tags: &[&str]| PrologPattern {
+            name: name.to_string(),
+            priority,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..pattern("", "")
+        };
+        let database = Database {
+            patterns: vec![
+                tagged("is_a", 10, &[]),
+                tagged("eats", 30, &["biology"]),
+                tagged("owns", 20, &["legal", "biology"]),
+            ],
+            ..Database::default()
+        };
+
+        assert_eq!(
+            database.pattern_tags().into_iter().collect::<Vec<_>>(),
+            vec!["biology", "legal"]
+        );
+        let names = |disabled: &[&str]| -> Vec<String> {
+            let disabled = disabled.iter().map(|tag| tag.to_string()).collect();
+            database
+                .get_sorted_patterns(&disabled)
+                .iter()
+                .map(|p| p.name.clone())
+                .collect()
+        };
+        assert_eq!(names(&[]), vec!["eats", "owns", "is_a"]);
+        assert_eq!(names(&["legal"]), vec!["eats", "is_a"]);
+        assert_eq!(names(&["biology"]), vec!["is_a"]);
+    }
+
+    #[test]
+    fn test_subpatterns_expand_recursively_and_stop_at_cycles() {
+        let database = Database {
+            subpatterns: [
+                ("noun_phrase", "[<Determiner>] @modified"),
+                ("modified", "[<Adjective>] <Noun>"),
+                ("loop", "again @loop"),
+            ]
+            .into_iter()
+            .map(|(name, definition)| (name.to_string(), definition.to_string()))
+            .collect(),
+            ..Database::default()
+        };
+
+        assert_eq!(
+            database.expand_subpatterns("@noun_phrase likes @noun_phrase"),
+            "[<Determiner>] [<Adjective>] <Noun> likes [<Determiner>] [<Adjective>] <Noun>"
+        );
+        assert_eq!(database.expand_subpatterns("@loop and @missing"), "again @loop and @missing");
+    }
 }
@@ -0,0 +1,95 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{Arc, LockResult, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockResult},
+};
+
+use crate::app::database::Database;
+
+/// The database shared by the editor and the parser. A parse works on a
+/// [`snapshot`](Self::snapshot), an `Arc` of the current version, so taking
+/// one never copies the database. A write copies it only while a snapshot
+/// of that version is still held, and later snapshots see the copy.
+#[derive(Debug, Default)]
+pub struct SharedDatabase {
+    current: RwLock<Arc<Database>>,
+}
+
+/// Write access to a [`SharedDatabase`]. The database is copied on the
+/// first change if a snapshot still shares it.
+pub struct DatabaseWriteGuard<'a> {
+    current: RwLockWriteGuard<'a, Arc<Database>>,
+}
+
+impl SharedDatabase {
+    pub fn new(database: Database) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(database)),
+        }
+    }
+
+    pub fn read(&self) -> LockResult<RwLockReadGuard<'_, Arc<Database>>> {
+        self.current.read()
+    }
+
+    /// Like [`read`](Self::read), but fails instead of waiting for a write.
+    pub fn try_read(&self) -> TryLockResult<RwLockReadGuard<'_, Arc<Database>>> {
+        self.current.try_read()
+    }
+
+    pub fn write(&self) -> LockResult<DatabaseWriteGuard<'_>> {
+        self.current
+            .write()
+            .map(|current| DatabaseWriteGuard { current })
+            .map_err(|e| PoisonError::new(DatabaseWriteGuard { current: e.into_inner() }))
+    }
+
+    /// The current version, or `None` while a write is in progress.
+    pub fn snapshot(&self) -> Option<Arc<Database>> {
+        self.current.try_read().ok().map(|current| Arc::clone(&current))
+    }
+}
+
+impl DatabaseWriteGuard<'_> {
+    /// Swaps in a whole new database, without copying the old one first.
+    pub fn replace(&mut self, database: Database) {
+        *self.current = Arc::new(database);
+    }
+}
+
+impl Deref for DatabaseWriteGuard<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.current
+    }
+}
+
+impl DerefMut for DatabaseWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Database {
+        Arc::make_mut(&mut self.current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshots_keep_their_version_while_writes_copy() {
+        let shared = SharedDatabase::new(Database::default());
+        let before = shared.snapshot().unwrap();
+
+        let mut write_database = shared.write().unwrap();
+        write_database.subpatterns.insert("np".to_string(), "<Noun>".to_string());
+        // A parse in the meantime keeps the version it started with
+        assert!(shared.snapshot().is_none());
+        assert!(before.subpatterns.is_empty());
+        drop(write_database);
+
+        let after = shared.snapshot().unwrap();
+        assert_eq!(after.subpatterns.len(), 1);
+        assert!(before.subpatterns.is_empty());
+        // No write since, so the snapshots share one copy
+        assert!(Arc::ptr_eq(&after, &shared.snapshot().unwrap()));
+    }
+}
@@ -1,8 +1,11 @@
-use std::fmt;
+use std::{collections::BTreeMap, fmt};
 
 use serde::{Deserialize, Serialize};
 
-use crate::app::database::Database;
+use crate::app::{
+    database::{Database, Locale},
+    morphology,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum WordType {
@@ -33,16 +36,243 @@ impl fmt::Display for WordType {
     }
 }
 
+/// The time a verb form speaks of.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Tense {
+    Past,
+    Present,
+    Future,
+}
+
+impl Tense {
+    /// The atom facts are tagged with.
+    pub fn name(self) -> &'static str {
+        match self {
+            Tense::Past => "past",
+            Tense::Present => "present",
+            Tense::Future => "future",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Gender {
+    Male,
+    Female,
+    Neuter,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Animacy {
+    Animate,
+    Inanimate,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Number {
+    Singular,
+    Plural,
+}
+
+/// What a noun refers to, as far as pronouns care: "she" prefers a
+/// female-marked entity and "it" an inanimate one. Each is optional, and an
+/// unset feature agrees with any pronoun.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct WordFeatures {
+    #[serde(default)]
+    pub gender: Option<Gender>,
+    #[serde(default)]
+    pub animacy: Option<Animacy>,
+    #[serde(default)]
+    pub number: Option<Number>,
+}
+
+impl WordFeatures {
+    /// Whether no feature set on both contradicts the other.
+    pub fn allows(&self, other: &WordFeatures) -> bool {
+        fn compatible<T: PartialEq>(a: Option<T>, b: Option<T>) -> bool {
+            a.zip(b).is_none_or(|(a, b)| a == b)
+        }
+        compatible(self.gender, other.gender)
+            && compatible(self.animacy, other.animacy)
+            && compatible(self.number, other.number)
+    }
+
+    /// Whether a gender or animacy set on both is the same, so the two are
+    /// known to agree rather than just not to clash.
+    pub fn agrees_with(&self, other: &WordFeatures) -> bool {
+        fn same<T: PartialEq>(a: Option<T>, b: Option<T>) -> bool {
+            a.is_some() && a == b
+        }
+        same(self.gender, other.gender) || same(self.animacy, other.animacy)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WordEntry {
     pub lemma: String,
     pub word_type: WordType,
     pub forms: Vec<String>,
+    /// How often this reading was seen, imported from a frequency list or
+    /// learned from parsed text. Ranks entries sharing a form.
+    #[serde(default)]
+    pub frequency: u64,
+    /// The tense of each form of a verb, lemma included; empty for other
+    /// word types. Forms the morphology rules cannot place are left out.
+    #[serde(default)]
+    pub tenses: BTreeMap<String, Tense>,
+    /// The number of each form of a noun, lemma included; empty for other
+    /// word types.
+    #[serde(default)]
+    pub numbers: BTreeMap<String, Number>,
+    /// Gender, animacy and number, used to resolve pronouns to a noun.
+    #[serde(default)]
+    pub features: WordFeatures,
+}
+
+impl WordEntry {
+    /// Whether `folded` is this entry's lemma or one of its forms, both folded
+    /// with `locale`.
+    fn has_form(&self, folded: &str, locale: Locale) -> bool {
+        locale.fold_case(&self.lemma) == folded
+            || self.forms.iter().any(|form| locale.fold_case(form) == folded)
+    }
 }
 
 impl Database {
+    /// Every entry `word` can be a form of, most frequent reading first. An
+    /// inflection missing from the database falls back to the entries of its
+    /// lemma, so "foxes" finds "fox" even when only the lemma is listed.
     pub fn get_word_entries(&self, word: &str) -> Option<&Vec<WordEntry>> {
-        let key = self.form_index.get(word)?;
-        self.form_value.get(key)
+        let folded = self.fold_case(word);
+        self.form_value.get(&folded).or_else(|| {
+            morphology::lemma_candidates(&folded, &self.morphology)
+                .iter()
+                .find_map(|lemma| self.form_value.get(lemma))
+        })
+    }
+
+    /// The lemma `word` is a form of: that of its most frequent reading
+    /// among `types`, or else the one `form_index` ranks first. `None` when
+    /// the word is not in the database.
+    pub fn lemma_of(&self, word: &str, types: &[WordType]) -> Option<&str> {
+        self.get_word_entries(word)
+            .and_then(|entries| entries.iter().find(|entry| types.contains(&entry.word_type)))
+            .map(|entry| entry.lemma.as_str())
+            .or_else(|| self.form_index.get(&self.fold_case(word)).map(String::as_str))
+    }
+
+    /// The tense `word` has as a form of a verb, `None` when it is not one.
+    /// Inflections missing from the database get theirs from the morphology
+    /// rules.
+    pub fn tense_of(&self, word: &str) -> Option<Tense> {
+        let folded = self.fold_case(word);
+        self.get_word_entries(word)?
+            .iter()
+            .filter(|entry| entry.word_type == WordType::Verb)
+            .find_map(|entry| {
+                entry
+                    .tenses
+                    .iter()
+                    .find(|(form, _)| self.fold_case(form) == folded)
+                    .map(|(_, tense)| *tense)
+                    .or_else(|| {
+                        morphology::verb_form_tense(&entry.lemma, &folded, &self.morphology)
+                    })
+            })
+    }
+
+    /// The number `word` has as a form of a noun, `None` when it is not one.
+    /// Inflections missing from the database get theirs from the morphology
+    /// rules.
+    pub fn number_of(&self, word: &str) -> Option<Number> {
+        let folded = self.fold_case(word);
+        self.get_word_entries(word)?
+            .iter()
+            .filter(|entry| entry.word_type == WordType::Noun)
+            .find_map(|entry| {
+                entry
+                    .numbers
+                    .iter()
+                    .find(|(form, _)| self.fold_case(form) == folded)
+                    .map(|(_, number)| *number)
+                    .or_else(|| {
+                        morphology::noun_form_number(&entry.lemma, &folded, &self.morphology)
+                    })
+            })
+    }
+
+    /// Works out the tenses of the verbs that have none, such as those of
+    /// databases saved before tenses were stored or words added by hand.
+    /// Call `rebuild_index` afterwards so lookups see them.
+    pub fn fill_verb_tenses(&mut self) {
+        for entry in &mut self.words {
+            if entry.word_type == WordType::Verb && entry.tenses.is_empty() {
+                entry.tenses =
+                    morphology::verb_tenses(&entry.lemma, &entry.forms, &self.morphology);
+            }
+        }
+    }
+
+    /// Marks which forms of the nouns that have no numbers are plural, as
+    /// `fill_verb_tenses` does for verbs. Call `rebuild_index` afterwards so
+    /// lookups see them.
+    pub fn fill_noun_numbers(&mut self) {
+        for entry in &mut self.words {
+            if entry.word_type == WordType::Noun && entry.numbers.is_empty() {
+                entry.numbers = morphology::noun_numbers(&entry.lemma, &entry.forms);
+            }
+        }
+    }
+
+    /// Counts one observed reading of `word` as `word_type`. Returns false if
+    /// no entry has that form and type. Call `rebuild_index` afterwards to
+    /// re-rank lookups.
+    pub fn record_reading(&mut self, word: &str, word_type: &WordType) -> bool {
+        let folded = self.fold_case(word);
+        let locale = self.locale;
+        match self
+            .words
+            .iter_mut()
+            .find(|entry| entry.word_type == *word_type && entry.has_form(&folded, locale))
+        {
+            Some(entry) => {
+                entry.frequency += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Imports frequencies from tab-separated lines, either `word<TAB>count`
+    /// or `word<TAB>Type<TAB>count`. A line without a type applies to every
+    /// entry with that lemma. Returns how many entries were updated.
+    pub fn import_frequencies(&mut self, text: &str) -> usize {
+        let mut updated = 0;
+
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+            let (word, word_type, count) = match fields.as_slice() {
+                [word, count] => (*word, None, *count),
+                [word, word_type, count] => (*word, Some(*word_type), *count),
+                _ => continue,
+            };
+            let Ok(count) = count.parse::<u64>() else {
+                continue;
+            };
+
+            let folded = self.fold_case(word);
+            let locale = self.locale;
+            for entry in &mut self.words {
+                let type_matches = word_type.is_none_or(|t| entry.word_type.to_string() == t);
+                if type_matches && locale.fold_case(&entry.lemma) == folded {
+                    entry.frequency = count;
+                    updated += 1;
+                }
+            }
+        }
+
+        self.rebuild_index();
+        updated
     }
 }
@@ -1,14 +1,29 @@
-use crate::app::database::{Database, PrologPattern, WordEntry, WordType};
+use crate::app::{
+    database::{Database, Locale, PrologPattern, SharedDatabase, WordEntry, WordType},
+    data_dir,
+    interactive_parser::UnknownWord,
+    morphology, notifications,
+    parser::pattern_matcher::suggest_template,
+};
 use std::sync::{
-    Arc, RwLock,
+    Arc,
     mpsc::{Receiver, Sender, channel},
 };
 
 const DATABASE_JSON_PATH: &str = "prolog_database.json";
 const DATABASE_BIN_PATH: &str = "prolog_database.bin";
+const FREQUENCY_LIST_PATH: &str = "word_frequencies.tsv";
 
 enum OperationResult {
-    SaveComplete(Result<(), String>),
+    /// How many words were saved, or why the save failed.
+    SaveComplete(Result<usize, String>),
+}
+
+/// A pattern waiting to be written, held back until its examples have been
+/// re-run against it. `index` is the pattern being edited, `None` for a new one.
+pub struct PendingPattern {
+    pub index: Option<usize>,
+    pub pattern: PrologPattern,
 }
 
 pub struct DatabaseEditor {
@@ -20,6 +35,13 @@ pub struct DatabaseEditor {
     new_pattern_pattern: String,
     new_pattern_template: String,
     new_pattern_priority: String,
+    new_pattern_examples: String,
+    new_pattern_counter_examples: String,
+    new_pattern_tags: String,
+    new_pattern_universal_rules: bool,
+
+    new_subpattern_name: String,
+    new_subpattern_definition: String,
 
     status_message: String,
 
@@ -41,6 +63,14 @@ pub struct DatabaseEditor {
     edit_pattern_pattern: String,
     edit_pattern_template: String,
     edit_pattern_priority: String,
+    edit_pattern_examples: String,
+    edit_pattern_counter_examples: String,
+    edit_pattern_tags: String,
+    edit_pattern_universal_rules: bool,
+
+    /// Examples that broke on the last save attempt, which was refused.
+    pattern_regressions: Vec<String>,
+    pending_pattern: Option<PendingPattern>,
 
     operation_sender: Option<Sender<OperationResult>>,
     operation_receiver: Option<Receiver<OperationResult>>,
@@ -73,6 +103,12 @@ impl DatabaseEditor {
             new_pattern_pattern: String::new(),
             new_pattern_template: String::new(),
             new_pattern_priority: String::new(),
+            new_pattern_examples: String::new(),
+            new_pattern_counter_examples: String::new(),
+            new_pattern_tags: String::new(),
+            new_pattern_universal_rules: false,
+            new_subpattern_name: String::new(),
+            new_subpattern_definition: String::new(),
             status_message: String::new(),
             pattern_page: 0,
             patterns_per_page: 10,
@@ -84,6 +120,12 @@ impl DatabaseEditor {
             edit_pattern_pattern: String::new(),
             edit_pattern_template: String::new(),
             edit_pattern_priority: String::new(),
+            edit_pattern_examples: String::new(),
+            edit_pattern_counter_examples: String::new(),
+            edit_pattern_tags: String::new(),
+            edit_pattern_universal_rules: false,
+            pattern_regressions: Vec::new(),
+            pending_pattern: None,
             operation_sender: Some(sender),
             operation_receiver: Some(receiver),
             is_saving: false,
@@ -93,9 +135,18 @@ impl DatabaseEditor {
         }
     }
 
-    pub fn show(&mut self, ctx: &egui::Context, database: &Arc<RwLock<Database>>) {
+    /// Returns a pattern the user asked to save; the caller re-runs its
+    /// examples and hands it back to `store_pattern`. `unknown_words` is the
+    /// parser's review queue, emptied as its words are added or dismissed.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        database: &Arc<SharedDatabase>,
+        unknown_words: &mut Vec<UnknownWord>,
+    ) -> Option<PendingPattern> {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Database Editor");
+            self.show_locale_selector(ui, database);
             ui.separator();
 
             let available_height = ui.available_height() - 120.0;
@@ -109,6 +160,7 @@ impl DatabaseEditor {
                 ui.separator();
 
                 self.show_word_list(ui, database);
+                self.show_unknown_words(ui, database, unknown_words);
 
                 ui.add_space(10.0);
                 ui.separator();
@@ -153,12 +205,13 @@ impl DatabaseEditor {
                 ui.separator();
 
                 self.show_pattern_list(ui, database);
+                self.show_subpatterns(ui, database);
 
                 ui.add_space(10.0);
                 ui.separator();
                 ui.label("Add New Pattern:");
 
-                self.show_pattern_form(ui, database);
+                self.show_pattern_form(ui);
             });
 
             ui.add_space(20.0);
@@ -172,13 +225,16 @@ impl DatabaseEditor {
             if let Some(receiver) = &self.operation_receiver {
                 if let Ok(result) = receiver.try_recv() {
                     match result {
-                        OperationResult::SaveComplete(Ok(())) => {
+                        OperationResult::SaveComplete(Ok(words)) => {
                             self.status_message = "✅ Database saved (JSON + Binary)!".to_string();
                             self.is_saving = false;
+                            let summary = format!("{} words saved as JSON and binary", words);
+                            notifications::job_finished(ctx, "Database save", Ok(&summary));
                         }
                         OperationResult::SaveComplete(Err(e)) => {
                             self.status_message = format!("❌ Error saving: {}", e);
                             self.is_saving = false;
+                            notifications::job_finished(ctx, "Database save", Err(&e));
                         }
                     }
                     ctx.request_repaint();
@@ -204,11 +260,11 @@ impl DatabaseEditor {
                     let db = Arc::clone(database);
                     std::thread::spawn(move || {
                         if let Ok(db_guard) = db.read() {
-                            let json_result = db_guard.save(DATABASE_JSON_PATH);
-                            let bin_result = db_guard.save(DATABASE_BIN_PATH);
+                            let json_result = db_guard.save(data_dir::path(DATABASE_JSON_PATH));
+                            let bin_result = db_guard.save(data_dir::path(DATABASE_BIN_PATH));
 
                             let result = match (json_result, bin_result) {
-                                (Ok(_), Ok(_)) => Ok(()),
+                                (Ok(_), Ok(_)) => Ok(db_guard.words.len()),
                                 (Err(e), _) | (_, Err(e)) => Err(e.to_string()),
                             };
 
@@ -222,11 +278,102 @@ impl DatabaseEditor {
 
                     ctx.request_repaint();
                 }
+
+                let frequency_path = data_dir::path(FREQUENCY_LIST_PATH);
+                if ui
+                    .button("📈 Import Frequencies")
+                    .on_hover_text(format!(
+                        "Read word<TAB>count or word<TAB>Type<TAB>count lines from {}",
+                        frequency_path.display()
+                    ))
+                    .clicked()
+                {
+                    let imported = match std::fs::read_to_string(&frequency_path) {
+                        Ok(text) => match database.write() {
+                            Ok(mut write_database) => Ok(format!(
+                                "Updated frequencies of {} words",
+                                write_database.import_frequencies(&text)
+                            )),
+                            Err(_) => Err("Failed to lock database".to_string()),
+                        },
+                        Err(e) => {
+                            Err(format!("Error reading {}: {}", frequency_path.display(), e))
+                        }
+                    };
+                    notifications::job_finished(
+                        ctx,
+                        "Frequency import",
+                        imported.as_deref().map_err(String::as_str),
+                    );
+                    self.status_message = match imported {
+                        Ok(summary) => format!("✅ {}", summary),
+                        Err(e) => format!("❌ {}", e),
+                    };
+                    self.cached_search.clear();
+                }
             });
         });
+
+        self.pending_pattern.take()
     }
 
-    fn show_word_list(&mut self, ui: &mut egui::Ui, database: &Arc<RwLock<Database>>) {
+    /// Writes a pattern whose examples were re-run, or refuses to when any
+    /// of them regressed.
+    pub fn store_pattern(
+        &mut self,
+        pending: PendingPattern,
+        regressions: Vec<String>,
+        database: &Arc<SharedDatabase>,
+    ) {
+        if !regressions.is_empty() {
+            self.status_message = format!(
+                "⛔ Pattern not saved: {} example(s) regressed",
+                regressions.len()
+            );
+            self.pattern_regressions = regressions;
+            return;
+        }
+        self.pattern_regressions.clear();
+
+        let Ok(mut write_database) = database.write() else {
+            self.status_message = "❌ Failed to lock database".to_string();
+            return;
+        };
+
+        match pending.index {
+            Some(idx) => {
+                if let Some(p) = write_database.patterns.get_mut(idx) {
+                    *p = pending.pattern;
+                    self.status_message = "✅ Pattern updated".to_string();
+                }
+                self.edit_pattern_index = None;
+                self.edit_pattern_name.clear();
+                self.edit_pattern_pattern.clear();
+                self.edit_pattern_template.clear();
+                self.edit_pattern_priority.clear();
+                self.edit_pattern_examples.clear();
+                self.edit_pattern_counter_examples.clear();
+                self.edit_pattern_tags.clear();
+                self.edit_pattern_universal_rules = false;
+            }
+            None => {
+                self.status_message = format!("✅ Added pattern: {}", pending.pattern.name);
+                write_database.patterns.push(pending.pattern);
+                self.new_pattern_name.clear();
+                self.new_pattern_pattern.clear();
+                self.new_pattern_template.clear();
+                self.new_pattern_priority.clear();
+                self.new_pattern_examples.clear();
+                self.new_pattern_counter_examples.clear();
+                self.new_pattern_tags.clear();
+                self.new_pattern_universal_rules = false;
+            }
+        }
+        self.cached_pattern_search.clear();
+    }
+
+
+    fn show_word_list(&mut self, ui: &mut egui::Ui, database: &Arc<SharedDatabase>) {
         if let Ok(read_database) = database.read() {
             ui.horizontal(|ui| {
                 ui.label(format!("Total words: {}", read_database.words.len()));
@@ -416,7 +563,169 @@ impl DatabaseEditor {
             ui.label("Error: Could not access database");
         }
     }
-    fn show_word_form(&mut self, ui: &mut egui::Ui, database: &Arc<RwLock<Database>>) {
+    fn show_locale_selector(&mut self, ui: &mut egui::Ui, database: &Arc<SharedDatabase>) {
+        let Ok(current) = database.read().map(|db| db.locale) else {
+            return;
+        };
+
+        let mut selected = current;
+        ui.horizontal(|ui| {
+            ui.label("Locale:");
+            egui::ComboBox::from_id_source("database_locale")
+                .selected_text(selected.name())
+                .show_ui(ui, |ui| {
+                    for locale in Locale::ALL {
+                        ui.selectable_value(&mut selected, locale, locale.name());
+                    }
+                });
+            ui.label(
+                egui::RichText::new("Controls lowercasing and case-insensitive word lookup")
+                    .weak(),
+            );
+        });
+
+        if selected != current {
+            if let Ok(mut write_database) = database.write() {
+                write_database.locale = selected;
+                write_database.rebuild_index();
+                self.status_message = format!("✅ Locale set to {}", selected.name());
+            }
+        }
+    }
+
+    /// The words the parser did not know, each added in one click as the type
+    /// the fallback read it as, with its generated forms, or loaded into the
+    /// Add New Word form to pick another type.
+    fn show_unknown_words(
+        &mut self,
+        ui: &mut egui::Ui,
+        database: &Arc<SharedDatabase>,
+        unknown_words: &mut Vec<UnknownWord>,
+    ) {
+        // Words added some other way since they were queued
+        if let Ok(read_database) = database.read() {
+            unknown_words
+                .retain(|unknown| read_database.get_word_entries(&unknown.word).is_none());
+        }
+        if unknown_words.is_empty() {
+            return;
+        }
+
+        let mut added = None;
+        let mut edited = None;
+        let mut dismissed = None;
+        ui.collapsing(format!("❓ {} unknown words", unknown_words.len()), |ui| {
+            for (idx, unknown) in unknown_words.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(&unknown.word).on_hover_text(&unknown.sentence);
+                    if ui
+                        .small_button(format!("➕ Add as {}", unknown.word_type))
+                        .clicked()
+                    {
+                        added = Some(idx);
+                    }
+                    if ui
+                        .small_button("✏")
+                        .on_hover_text("Fill in the Add New Word form")
+                        .clicked()
+                    {
+                        edited = Some(idx);
+                    }
+                    if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                        dismissed = Some(idx);
+                    }
+                });
+            }
+        });
+
+        if let Some(idx) = added {
+            let unknown = unknown_words.remove(idx);
+            if let Ok(mut write_database) = database.write() {
+                let entry = unknown_word_entry(&unknown, &write_database);
+                write_database.words.push(entry);
+                write_database.fill_verb_tenses();
+                write_database.fill_noun_numbers();
+                write_database.rebuild_index();
+                self.status_message = format!("✅ Added word: {}", unknown.word);
+                self.cached_search.clear();
+            }
+        } else if let Some(idx) = edited {
+            let unknown = unknown_words.remove(idx);
+            self.new_word_lemma = unknown.word;
+            self.new_word_type = unknown.word_type;
+            self.new_word_forms.clear();
+        } else if let Some(idx) = dismissed {
+            unknown_words.remove(idx);
+        }
+    }
+
+    /// The named fragments patterns can use as `@name`, each removable, and a
+    /// form to define one or replace the definition of an existing name.
+    fn show_subpatterns(&mut self, ui: &mut egui::Ui, database: &Arc<SharedDatabase>) {
+        let Ok(read_database) = database.read() else {
+            return;
+        };
+        let subpatterns = read_database.subpatterns.clone();
+        drop(read_database);
+
+        let mut removed = None;
+        let mut defined = None;
+        ui.collapsing(format!("🧩 {} sub-patterns", subpatterns.len()), |ui| {
+            for (name, definition) in &subpatterns {
+                ui.horizontal(|ui| {
+                    ui.monospace(format!("@{} := {}", name, definition));
+                    if ui.small_button("✏").on_hover_text("Edit").clicked() {
+                        self.new_subpattern_name = name.clone();
+                        self.new_subpattern_definition = definition.clone();
+                    }
+                    if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                        removed = Some(name.clone());
+                    }
+                });
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("@");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.new_subpattern_name)
+                        .hint_text("noun_phrase")
+                        .desired_width(120.0),
+                );
+                ui.label(":=");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.new_subpattern_definition)
+                        .hint_text("[<Determiner>] [<Adjective>] <Noun>")
+                        .desired_width(ui.available_width() - 80.0),
+                );
+                let name = self.new_subpattern_name.trim().trim_start_matches('@');
+                let valid = !name.is_empty()
+                    && !name.contains(char::is_whitespace)
+                    && !self.new_subpattern_definition.trim().is_empty();
+                if ui.add_enabled(valid, egui::Button::new("➕ Define")).clicked() {
+                    defined = Some((
+                        name.to_string(),
+                        self.new_subpattern_definition.trim().to_string(),
+                    ));
+                }
+            });
+        });
+
+        if let Some(name) = removed
+            && let Ok(mut write_database) = database.write()
+        {
+            write_database.subpatterns.remove(&name);
+            self.status_message = format!("✅ Removed sub-pattern: @{}", name);
+        } else if let Some((name, definition)) = defined
+            && let Ok(mut write_database) = database.write()
+        {
+            write_database.subpatterns.insert(name.clone(), definition);
+            self.status_message = format!("✅ Defined sub-pattern: @{}", name);
+            self.new_subpattern_name.clear();
+            self.new_subpattern_definition.clear();
+        }
+    }
+
+    fn show_word_form(&mut self, ui: &mut egui::Ui, database: &Arc<SharedDatabase>) {
         ui.horizontal(|ui| {
             ui.label("Lemma:");
             ui.add(
@@ -456,6 +765,20 @@ impl DatabaseEditor {
 
         ui.horizontal(|ui| {
             ui.label("Forms (comma-separated):");
+            if ui
+                .button("✨ Generate")
+                .on_hover_text("Fill in the plural or verb forms of the lemma")
+                .clicked()
+                && let Ok(read_database) = database.read()
+            {
+                let lemma = read_database.locale.lowercase(self.new_word_lemma.trim());
+                self.new_word_forms = morphology::generate_forms(
+                    &lemma,
+                    &self.new_word_type,
+                    &read_database.morphology,
+                )
+                .join(", ");
+            }
             ui.add(
                 egui::TextEdit::singleline(&mut self.new_word_forms)
                     .desired_width(ui.available_width()),
@@ -486,10 +809,16 @@ impl DatabaseEditor {
                     lemma: lemma.clone(),
                     word_type: self.new_word_type.clone(),
                     forms,
+                    frequency: 0,
+                    tenses: Default::default(),
+                    numbers: Default::default(),
+                    features: Default::default(),
                 };
 
                 if let Ok(mut write_database) = database.write() {
                     write_database.words.push(entry);
+                    write_database.fill_verb_tenses();
+                    write_database.fill_noun_numbers();
                     write_database.rebuild_index();
                     self.status_message = format!("✅ Added word: {}", lemma);
                     self.cached_search.clear();
@@ -502,7 +831,7 @@ impl DatabaseEditor {
         });
     }
 
-    fn show_pattern_list(&mut self, ui: &mut egui::Ui, database: &Arc<RwLock<Database>>) {
+    fn show_pattern_list(&mut self, ui: &mut egui::Ui, database: &Arc<SharedDatabase>) {
         let Ok(read_database) = database.read() else {
             ui.label("Error: Could not access database");
             return;
@@ -592,9 +921,9 @@ impl DatabaseEditor {
 
         let mut to_remove = Vec::new();
         let mut to_toggle = Vec::new();
-        let mut save_edit: Option<(usize, String, String, String, i32)> = None;
+        let mut save_edit: Option<PendingPattern> = None;
         let mut cancel_edit = false;
-        let mut start_edit: Option<(usize, String, String, String, i32)> = None;
+        let mut start_edit: Option<(usize, PrologPattern)> = None;
 
         egui::ScrollArea::vertical()
             .id_source("pattern_list_scroll")
@@ -635,6 +964,11 @@ impl DatabaseEditor {
 
                                     ui.horizontal(|ui| {
                                         ui.label("Template:");
+                                        show_template_suggestion(
+                                            ui,
+                                            &self.edit_pattern_pattern,
+                                            &mut self.edit_pattern_template,
+                                        );
                                         ui.add(
                                             egui::TextEdit::singleline(
                                                 &mut self.edit_pattern_template,
@@ -643,6 +977,48 @@ impl DatabaseEditor {
                                         );
                                     });
 
+                                    ui.horizontal(|ui| {
+                                        ui.label("Examples:");
+                                        ui.add(
+                                            egui::TextEdit::multiline(
+                                                &mut self.edit_pattern_examples,
+                                            )
+                                            .hint_text("One sentence per line")
+                                            .desired_rows(2)
+                                            .desired_width(ui.available_width()),
+                                        );
+                                    });
+
+                                    ui.horizontal(|ui| {
+                                        ui.label("Counter-examples:");
+                                        ui.add(
+                                            egui::TextEdit::multiline(
+                                                &mut self.edit_pattern_counter_examples,
+                                            )
+                                            .hint_text("Sentences it must not match")
+                                            .desired_rows(2)
+                                            .desired_width(ui.available_width()),
+                                        );
+                                    });
+
+                                    ui.horizontal(|ui| {
+                                        ui.label("Tags:");
+                                        ui.add(
+                                            egui::TextEdit::singleline(
+                                                &mut self.edit_pattern_tags,
+                                            )
+                                            .hint_text("biology, legal")
+                                            .desired_width(ui.available_width()),
+                                        );
+                                    });
+
+                                    show_universal_rules_toggle(
+                                        ui,
+                                        &mut self.edit_pattern_universal_rules,
+                                    );
+
+                                    show_regressions(ui, &self.pattern_regressions);
+
                                     ui.horizontal(|ui| {
                                         ui.label("Priority:");
                                         ui.add(
@@ -664,13 +1040,29 @@ impl DatabaseEditor {
                                                         .edit_pattern_priority
                                                         .parse()
                                                         .unwrap_or(50);
-                                                    save_edit = Some((
-                                                        idx,
-                                                        self.edit_pattern_name.clone(),
-                                                        self.edit_pattern_pattern.clone(),
-                                                        self.edit_pattern_template.clone(),
-                                                        priority,
-                                                    ));
+                                                    save_edit = Some(PendingPattern {
+                                                        index: Some(idx),
+                                                        pattern: PrologPattern {
+                                                            name: self.edit_pattern_name.clone(),
+                                                            pattern: self
+                                                                .edit_pattern_pattern
+                                                                .clone(),
+                                                            template: self
+                                                                .edit_pattern_template
+                                                                .clone(),
+                                                            priority,
+                                                            enabled: pattern.enabled,
+                                                            examples: example_lines(
+                                                                &self.edit_pattern_examples,
+                                                            ),
+                                                            counter_examples: example_lines(
+                                                                &self.edit_pattern_counter_examples,
+                                                            ),
+                                                            tags: tag_list(&self.edit_pattern_tags),
+                                                            universal_rules: self
+                                                                .edit_pattern_universal_rules,
+                                                        },
+                                                    });
                                                 }
                                             },
                                         );
@@ -712,13 +1104,7 @@ impl DatabaseEditor {
                                                 }
 
                                                 if ui.small_button("✏").clicked() {
-                                                    start_edit = Some((
-                                                        idx,
-                                                        pattern.name.clone(),
-                                                        pattern.pattern.clone(),
-                                                        pattern.template.clone(),
-                                                        pattern.priority,
-                                                    ));
+                                                    start_edit = Some((idx, pattern.clone()));
                                                 }
 
                                                 if ui
@@ -760,6 +1146,39 @@ impl DatabaseEditor {
                                                 .size(11.0),
                                         );
                                     });
+
+                                    if !pattern.tags.is_empty() {
+                                        ui.label(
+                                            egui::RichText::new(format!(
+                                                "  Tags: {}",
+                                                pattern.tags.join(", ")
+                                            ))
+                                            .color(egui::Color32::from_rgb(100, 100, 100))
+                                            .size(11.0),
+                                        );
+                                    }
+
+                                    if pattern.universal_rules {
+                                        ui.label(
+                                            egui::RichText::new("  Writes universal rules")
+                                                .color(egui::Color32::from_rgb(100, 100, 100))
+                                                .size(11.0),
+                                        );
+                                    }
+
+                                    if !pattern.examples.is_empty()
+                                        || !pattern.counter_examples.is_empty()
+                                    {
+                                        ui.label(
+                                            egui::RichText::new(format!(
+                                                "  {} example(s), {} counter-example(s)",
+                                                pattern.examples.len(),
+                                                pattern.counter_examples.len()
+                                            ))
+                                            .color(egui::Color32::from_rgb(100, 100, 100))
+                                            .size(11.0),
+                                        );
+                                    }
                                 }
                             });
 
@@ -776,31 +1195,28 @@ impl DatabaseEditor {
             self.edit_pattern_pattern.clear();
             self.edit_pattern_template.clear();
             self.edit_pattern_priority.clear();
+            self.edit_pattern_examples.clear();
+            self.edit_pattern_counter_examples.clear();
+            self.edit_pattern_tags.clear();
+            self.edit_pattern_universal_rules = false;
+            self.pattern_regressions.clear();
         }
 
-        if let Some((idx, name, pattern, template, priority)) = start_edit {
+        if let Some((idx, pattern)) = start_edit {
             self.edit_pattern_index = Some(idx);
-            self.edit_pattern_name = name;
-            self.edit_pattern_pattern = pattern;
-            self.edit_pattern_template = template;
-            self.edit_pattern_priority = priority.to_string();
+            self.edit_pattern_name = pattern.name;
+            self.edit_pattern_pattern = pattern.pattern;
+            self.edit_pattern_template = pattern.template;
+            self.edit_pattern_priority = pattern.priority.to_string();
+            self.edit_pattern_examples = pattern.examples.join("\n");
+            self.edit_pattern_counter_examples = pattern.counter_examples.join("\n");
+            self.edit_pattern_tags = pattern.tags.join(", ");
+            self.edit_pattern_universal_rules = pattern.universal_rules;
+            self.pattern_regressions.clear();
         }
 
-        if let Some((idx, name, pattern, template, priority)) = save_edit {
-            if let Ok(mut write_database) = database.write() {
-                if let Some(p) = write_database.patterns.get_mut(idx) {
-                    p.name = name;
-                    p.pattern = pattern;
-                    p.template = template;
-                    p.priority = priority;
-                    self.status_message = "✅ Pattern updated".to_string();
-                }
-            }
-            self.edit_pattern_index = None;
-            self.edit_pattern_name.clear();
-            self.edit_pattern_pattern.clear();
-            self.edit_pattern_template.clear();
-            self.edit_pattern_priority.clear();
+        if save_edit.is_some() {
+            self.pending_pattern = save_edit;
         }
 
         if !to_toggle.is_empty() || !to_remove.is_empty() {
@@ -820,7 +1236,7 @@ impl DatabaseEditor {
         }
     }
 
-    fn show_pattern_form(&mut self, ui: &mut egui::Ui, database: &Arc<RwLock<Database>>) {
+    fn show_pattern_form(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.label("Name:");
             ui.add(
@@ -830,7 +1246,7 @@ impl DatabaseEditor {
         });
 
         ui.horizontal(|ui| {
-            ui.label("Regex Pattern:");
+            ui.label("Pattern:");
             ui.add(
                 egui::TextEdit::singleline(&mut self.new_pattern_pattern)
                     .desired_width(ui.available_width()),
@@ -839,8 +1255,10 @@ impl DatabaseEditor {
 
         ui.horizontal(|ui| {
             ui.label("Template:");
+            show_template_suggestion(ui, &self.new_pattern_pattern, &mut self.new_pattern_template);
             ui.add(
                 egui::TextEdit::singleline(&mut self.new_pattern_template)
+                    .hint_text(suggest_template(&self.new_pattern_pattern))
                     .desired_width(ui.available_width()),
             );
         });
@@ -853,8 +1271,43 @@ impl DatabaseEditor {
             );
         });
 
+        ui.horizontal(|ui| {
+            ui.label("Examples:");
+            ui.add(
+                egui::TextEdit::multiline(&mut self.new_pattern_examples)
+                    .hint_text("One sentence per line")
+                    .desired_rows(2)
+                    .desired_width(ui.available_width()),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Counter-examples:");
+            ui.add(
+                egui::TextEdit::multiline(&mut self.new_pattern_counter_examples)
+                    .hint_text("Sentences it must not match")
+                    .desired_rows(2)
+                    .desired_width(ui.available_width()),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Tags:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_pattern_tags)
+                    .hint_text("biology, legal")
+                    .desired_width(ui.available_width()),
+            );
+        });
+
+        show_universal_rules_toggle(ui, &mut self.new_pattern_universal_rules);
+
+        if self.edit_pattern_index.is_none() {
+            show_regressions(ui, &self.pattern_regressions);
+        }
+
         ui.label(
-            egui::RichText::new("Tip: Use $1, $2, etc. in template for capture groups")
+            egui::RichText::new("Tip: Use $1, $2, etc. in template for capture groups, or ✨ for a starter template. Captures are written as lemmas; $1.raw keeps the words as written, and $1:lemma, $1:singular, $1:lower or $1:snake transform a capture. {$1} :- {$2} writes a rule from captured clauses. Possessives read as a separate 's word, which a pattern can spell out. <Date> and <Time> capture expressions like 1990 or 5pm as date(1990) or time(17, 0). (is|are|was) matches any one of its words, \"such as\" the whole phrase, @name a sub-pattern defined above, word:$ or *:$ captures a literal or wildcard too, <!Verb> or <Noun&!Pronoun> any word that cannot be the excluded types, and <re:^[0-9]{4}$> any word the regex matches.")
                 .italics()
                 .color(egui::Color32::from_rgb(100, 100, 100))
                 .size(11.0),
@@ -878,25 +1331,82 @@ impl DatabaseEditor {
 
                 let priority: i32 = self.new_pattern_priority.parse().unwrap_or(50);
 
-                let pattern = PrologPattern {
-                    name: self.new_pattern_name.clone(),
-                    pattern: self.new_pattern_pattern.clone(),
-                    template: self.new_pattern_template.clone(),
-                    priority,
-                    enabled: true,
-                };
-
-                if let Ok(mut write_database) = database.write() {
-                    write_database.patterns.push(pattern);
-                    self.status_message = format!("✅ Added pattern: {}", self.new_pattern_name);
-                }
-
-                self.new_pattern_name.clear();
-                self.new_pattern_pattern.clear();
-                self.new_pattern_template.clear();
-                self.new_pattern_priority.clear();
+                self.pending_pattern = Some(PendingPattern {
+                    index: None,
+                    pattern: PrologPattern {
+                        name: self.new_pattern_name.clone(),
+                        pattern: self.new_pattern_pattern.clone(),
+                        template: self.new_pattern_template.clone(),
+                        priority,
+                        enabled: true,
+                        examples: example_lines(&self.new_pattern_examples),
+                        counter_examples: example_lines(&self.new_pattern_counter_examples),
+                        tags: tag_list(&self.new_pattern_tags),
+                        universal_rules: self.new_pattern_universal_rules,
+                    },
+                });
                 self.is_adding_pattern = false;
             }
         });
     }
 }
+
+/// Button that fills `template` with one suggested from `pattern`.
+fn show_template_suggestion(ui: &mut egui::Ui, pattern: &str, template: &mut String) {
+    let suggestion = suggest_template(pattern);
+    let button = ui
+        .add_enabled(!pattern.trim().is_empty(), egui::Button::new("✨").small())
+        .on_hover_text(format!("Use the suggested template: {}", suggestion));
+    if button.clicked() {
+        *template = suggestion;
+    }
+}
+
+/// Checkbox that switches a pattern between writing facts and universal rules.
+fn show_universal_rules_toggle(ui: &mut egui::Ui, universal_rules: &mut bool) {
+    ui.checkbox(universal_rules, "Universal: write rules instead of facts")
+        .on_hover_text(
+            "The first capture names a class, and each fact holds for any member X of it: \
+             \"All mammals are animals\" with $2($1) becomes animal(X) :- mammal(X).",
+        );
+}
+
+fn show_regressions(ui: &mut egui::Ui, regressions: &[String]) {
+    for regression in regressions {
+        ui.colored_label(egui::Color32::from_rgb(220, 80, 80), format!("✗ {}", regression));
+    }
+}
+
+/// Comma-separated tags, lowercased, without blanks or repeats.
+fn tag_list(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(|tag| tag.trim().to_lowercase()) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// One example sentence per non-empty line.
+fn example_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The entry an unknown word is added as: its type as the fallback read it,
+/// with the forms generated for that type.
+fn unknown_word_entry(unknown: &UnknownWord, database: &Database) -> WordEntry {
+    WordEntry {
+        lemma: unknown.word.clone(),
+        word_type: unknown.word_type.clone(),
+        forms: morphology::generate_forms(&unknown.word, &unknown.word_type, &database.morphology),
+        frequency: 0,
+        tenses: Default::default(),
+        numbers: Default::default(),
+        features: Default::default(),
+    }
+}
@@ -0,0 +1,181 @@
+use crate::app::query_engine::QueryEngine;
+
+/// Side pane listing every fact that mentions one entity constant.
+pub struct EntityInspector {
+    entity: Option<String>,
+}
+
+impl EntityInspector {
+    pub fn new() -> Self {
+        Self { entity: None }
+    }
+
+    pub fn open(&mut self, entity: String) {
+        self.entity = Some(entity);
+    }
+
+    /// Draws the inspector pane if an entity is open. Returns the source
+    /// sentence the user clicked, if any.
+    pub fn show(&mut self, ctx: &egui::Context, query_engine: &QueryEngine) -> Option<String> {
+        let entity = self.entity.clone()?;
+        let mut clicked_source = None;
+        let mut next_entity = None;
+        let mut close = false;
+
+        egui::SidePanel::right("entity_inspector")
+            .default_width(320.0)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(format!("🔍 {}", entity));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("✖").clicked() {
+                            close = true;
+                        }
+                    });
+                });
+                ui.separator();
+
+                let groups = query_engine.facts_mentioning(&entity);
+                if groups.is_empty() {
+                    ui.label(
+                        egui::RichText::new("No facts mention this entity.")
+                            .italics()
+                            .color(egui::Color32::from_rgb(100, 100, 100)),
+                    );
+                    return;
+                }
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (predicate, facts) in groups {
+                        egui::CollapsingHeader::new(format!("{} ({})", predicate, facts.len()))
+                            .id_source(("entity_group", predicate))
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                for (fact, occurrences) in facts {
+                                    ui.label(
+                                        egui::RichText::new(fact.stable_id())
+                                            .monospace()
+                                            .size(10.0)
+                                            .color(egui::Color32::from_rgb(120, 120, 120)),
+                                    );
+                                    if let Some(other) = clickable_terms(ui, &format!("{}.", fact))
+                                    {
+                                        next_entity = Some(other);
+                                    }
+                                    for source in &occurrences.sources {
+                                        if ui
+                                            .link(
+                                                egui::RichText::new(format!("↳ {}", source))
+                                                    .size(11.0),
+                                            )
+                                            .on_hover_text("Show this sentence in the parser")
+                                            .clicked()
+                                        {
+                                            clicked_source = Some(source.clone());
+                                        }
+                                    }
+                                    ui.add_space(4.0);
+                                }
+                            });
+                    }
+                });
+            });
+
+        if close {
+            self.entity = None;
+        } else if let Some(other) = next_entity {
+            self.entity = Some(other);
+        }
+
+        clicked_source
+    }
+}
+
+/// Renders Prolog text with each atom as a link. Returns the atom that was clicked.
+pub fn clickable_terms(ui: &mut egui::Ui, text: &str) -> Option<String> {
+    let mut clicked = None;
+
+    for line in text.lines() {
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+
+            for (token, is_atom) in tokenize(line) {
+                if is_atom {
+                    if ui
+                        .link(egui::RichText::new(token).monospace())
+                        .on_hover_text("Inspect entity")
+                        .clicked()
+                    {
+                        clicked = Some(token.to_string());
+                    }
+                } else {
+                    ui.monospace(token);
+                }
+            }
+        });
+    }
+
+    clicked
+}
+
+/// The atom under character position `char_idx` of `text`, if there is one.
+pub fn entity_at(text: &str, char_idx: usize) -> Option<String> {
+    let byte_idx = text
+        .char_indices()
+        .nth(char_idx)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+
+    let mut offset = 0;
+    for (token, is_atom) in tokenize(text) {
+        let end = offset + token.len();
+        if is_atom && offset <= byte_idx && byte_idx <= end {
+            return Some(token.to_string());
+        }
+        offset = end;
+    }
+    None
+}
+
+/// Splits text into identifier and non-identifier runs, flagging the
+/// identifiers that are atoms (lowercase first letter).
+fn tokenize(text: &str) -> Vec<(&str, bool)> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut tokens = Vec::new();
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        let prev_is_ident = text[start..i].chars().next_back().is_some_and(is_ident);
+        if i > start && prev_is_ident != is_ident(c) {
+            tokens.push(&text[start..i]);
+            start = i;
+        }
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+
+    tokens
+        .into_iter()
+        .map(|token| {
+            let is_atom = token.chars().next().is_some_and(|c| c.is_lowercase());
+            (token, is_atom)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entity_at_finds_atoms_but_not_variables() {
+        let text = "X = bear, Y = f(cat)";
+        assert_eq!(entity_at(text, 0), None);
+        assert_eq!(entity_at(text, 5), Some("bear".to_string()));
+        assert_eq!(entity_at(text, 8), Some("bear".to_string()));
+        assert_eq!(entity_at(text, 16), Some("cat".to_string()));
+        assert_eq!(entity_at(text, 10), None);
+    }
+}
@@ -0,0 +1,571 @@
+use std::sync::Arc;
+
+use crate::app::database::{PrologPattern, SharedDatabase, WordEntry};
+use crate::app::morphology::{Inflection, MorphologyExceptions};
+use crate::app::parser::pattern_matcher::{
+    PatternToken, allows_type, apply_template, is_capturing, parse_pattern, token_label,
+};
+use crate::app::parser::temporal::TemporalKind;
+use crate::app::random::SplitMix64;
+
+/// User-tunable settings for one token of the selected pattern.
+struct SlotSettings {
+    /// Case-insensitive substring a vocabulary word must contain.
+    filter: String,
+    /// Explicit candidates as `word=weight, word, ...`. Overrides the vocabulary when set.
+    weights: String,
+    /// Chance of emitting an optional token.
+    include_probability: f64,
+    /// Upper bound on words produced by a greedy token.
+    max_repeat: usize,
+    /// Inflection applied to vocabulary words in the sentence. Captures keep
+    /// the lemma, so the facts stay in base form.
+    inflection: Option<Inflection>,
+}
+
+impl SlotSettings {
+    fn new() -> Self {
+        Self {
+            filter: String::new(),
+            weights: String::new(),
+            include_probability: 0.5,
+            max_repeat: 2,
+            inflection: None,
+        }
+    }
+}
+
+/// Candidate words for a slot, with cumulative weights for sampling.
+struct WordPool {
+    words: Vec<String>,
+    cumulative: Vec<f64>,
+}
+
+impl WordPool {
+    fn pick(&self, rng: &mut SplitMix64) -> &str {
+        let total = self.cumulative.last().copied().unwrap_or(0.0);
+        let target = rng.next_f64() * total;
+        let idx = self.cumulative.partition_point(|&c| c <= target);
+        &self.words[idx.min(self.words.len() - 1)]
+    }
+}
+
+struct GeneratedSentence {
+    sentence: String,
+    facts: Vec<String>,
+}
+
+pub struct Generator {
+    selected_pattern: Option<usize>,
+    tokens: Vec<PatternToken>,
+    slots: Vec<SlotSettings>,
+    count: usize,
+    use_seed: bool,
+    seed: u64,
+    output: String,
+    status_message: String,
+}
+
+impl Generator {
+    pub fn new() -> Self {
+        Self {
+            selected_pattern: None,
+            tokens: Vec::new(),
+            slots: Vec::new(),
+            count: 10,
+            use_seed: false,
+            seed: 1,
+            output: String::new(),
+            status_message: String::new(),
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, database: &Arc<SharedDatabase>) {
+        let db = database.read().unwrap();
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Sentence Generator");
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Pattern:");
+                let selected_text = self
+                    .selected_pattern
+                    .and_then(|idx| db.patterns.get(idx))
+                    .map(|p| format!("{} ({})", p.name, p.pattern))
+                    .unwrap_or_else(|| "Select a pattern...".to_string());
+
+                let mut new_selection = None;
+                egui::ComboBox::from_id_source("generator_pattern")
+                    .selected_text(selected_text)
+                    .width(400.0)
+                    .show_ui(ui, |ui| {
+                        for (idx, pattern) in db.patterns.iter().enumerate() {
+                            if !pattern.enabled {
+                                continue;
+                            }
+                            let label = format!("{} ({})", pattern.name, pattern.pattern);
+                            if ui
+                                .selectable_label(self.selected_pattern == Some(idx), label)
+                                .clicked()
+                            {
+                                new_selection = Some(idx);
+                            }
+                        }
+                    });
+
+                if let Some(idx) = new_selection
+                    && self.selected_pattern != Some(idx)
+                {
+                    self.select_pattern(idx, &db.with_subpatterns(&db.patterns[idx]));
+                }
+            });
+
+            let Some(pattern) = self.selected_pattern.and_then(|idx| db.patterns.get(idx)) else {
+                return;
+            };
+
+            ui.label(
+                egui::RichText::new(format!("Template: {}", pattern.template.replace('\n', " | ")))
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+            );
+            ui.add_space(5.0);
+
+            ui.group(|ui| {
+                ui.label(egui::RichText::new("Components").strong());
+                ui.label(
+                    egui::RichText::new(
+                        "Filter keeps vocabulary words containing the text. Weights (word=3, other=1) replace the vocabulary for that slot.",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+                ui.add_space(5.0);
+
+                egui::Grid::new("generator_slots")
+                    .num_columns(4)
+                    .spacing([10.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label(egui::RichText::new("Token").strong());
+                        ui.label(egui::RichText::new("Filter").strong());
+                        ui.label(egui::RichText::new("Weights").strong());
+                        ui.label(egui::RichText::new("Options").strong());
+                        ui.end_row();
+
+                        for (idx, (token, slot)) in self.tokens.iter().zip(self.slots.iter_mut()).enumerate() {
+                            ui.monospace(token_label(token));
+                            if matches!(token, PatternToken::Literal(_)) {
+                                ui.label("");
+                                ui.label("");
+                                ui.label("");
+                                ui.end_row();
+                                continue;
+                            }
+
+                            ui.add(egui::TextEdit::singleline(&mut slot.filter).desired_width(120.0));
+                            ui.add(egui::TextEdit::singleline(&mut slot.weights).desired_width(200.0));
+                            ui.horizontal(|ui| {
+                                match token {
+                                    PatternToken::Optional(_) => {
+                                        ui.label("Include:");
+                                        ui.add(egui::Slider::new(&mut slot.include_probability, 0.0..=1.0));
+                                    }
+                                    PatternToken::Greedy(_) => {
+                                        ui.label("Max words:");
+                                        ui.add(egui::DragValue::new(&mut slot.max_repeat).range(1..=10));
+                                    }
+                                    _ => {}
+                                }
+
+                                egui::ComboBox::from_id_source(("generator_inflection", idx))
+                                    .selected_text(slot.inflection.map_or("base form", |i| i.name()))
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(&mut slot.inflection, None, "base form");
+                                        for inflection in Inflection::ALL {
+                                            ui.selectable_value(&mut slot.inflection, Some(inflection), inflection.name());
+                                        }
+                                    });
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                ui.label("Count:");
+                ui.add(egui::DragValue::new(&mut self.count).range(1..=10_000));
+                ui.checkbox(&mut self.use_seed, "Fixed seed:");
+                ui.add_enabled(self.use_seed, egui::DragValue::new(&mut self.seed));
+
+                if ui.button("🎲 Generate").clicked() {
+                    let seed = if self.use_seed {
+                        self.seed
+                    } else {
+                        std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|d| d.as_nanos() as u64)
+                            .unwrap_or(0)
+                    };
+                    let mut rng = SplitMix64(seed);
+
+                    match generate(pattern, &self.tokens, &self.slots, &db.words, &db.morphology, self.count, &mut rng) {
+                        Ok(sentences) => {
+                            self.output = format_output(&sentences);
+                            self.status_message = format!("✓ Generated {} sentences", sentences.len());
+                        }
+                        Err(e) => self.status_message = format!("Error: {}", e),
+                    }
+                }
+
+                if ui.button("📋 Copy").clicked() {
+                    ui.ctx().copy_text(self.output.clone());
+                }
+            });
+
+            if !self.status_message.is_empty() {
+                ui.label(&self.status_message);
+            }
+
+            ui.separator();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.output)
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(20),
+                );
+            });
+        });
+    }
+
+    fn select_pattern(&mut self, idx: usize, pattern: &PrologPattern) {
+        self.selected_pattern = Some(idx);
+        self.tokens = parse_pattern(&pattern.pattern);
+        self.slots = self.tokens.iter().map(|_| SlotSettings::new()).collect();
+        self.output.clear();
+        self.status_message.clear();
+    }
+}
+
+/// Builds the candidate pool for a slot, or `None` for tokens that don't draw words.
+fn build_pool(
+    token: &PatternToken,
+    slot: &SlotSettings,
+    words: &[WordEntry],
+) -> Result<Option<WordPool>, String> {
+    let types = match token {
+        PatternToken::Literal(_) => return Ok(None),
+        PatternToken::TypeMatch(types, excluded, _) => Some((types, excluded)),
+        PatternToken::Alternatives(_)
+        | PatternToken::Temporal(_)
+        | PatternToken::Regex(_)
+        | PatternToken::Wildcard => None,
+        PatternToken::Optional(inner)
+        | PatternToken::Greedy(inner)
+        | PatternToken::Captured(inner) => {
+            return build_pool(inner, slot, words);
+        }
+    };
+
+    let mut pool = WordPool {
+        words: Vec::new(),
+        cumulative: Vec::new(),
+    };
+    let mut total = 0.0;
+
+    if !slot.weights.trim().is_empty() {
+        for entry in slot
+            .weights
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+        {
+            let (word, weight) = match entry.split_once('=') {
+                Some((word, weight)) => {
+                    let weight: f64 = weight
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid weight in '{}'", entry))?;
+                    (word.trim(), weight)
+                }
+                None => (entry, 1.0),
+            };
+            if weight <= 0.0 {
+                continue;
+            }
+            total += weight;
+            pool.words.push(word.to_string());
+            pool.cumulative.push(total);
+        }
+    } else if let PatternToken::Alternatives(alternatives) = token {
+        let filter = slot.filter.trim().to_lowercase();
+        for word in alternatives.iter().filter(|word| word.contains(&filter)) {
+            total += 1.0;
+            pool.words.push(word.clone());
+            pool.cumulative.push(total);
+        }
+    } else if let PatternToken::Temporal(kind) = token {
+        let filter = slot.filter.trim().to_lowercase();
+        for example in kind.examples().iter().filter(|example| example.contains(&filter)) {
+            total += 1.0;
+            pool.words.push(example.to_string());
+            pool.cumulative.push(total);
+        }
+    } else {
+        let filter = slot.filter.trim().to_lowercase();
+        for entry in words {
+            if entry.lemma.contains(char::is_whitespace)
+                || types.is_some_and(|(types, excluded)| {
+                    !allows_type(types, excluded, &entry.word_type)
+                })
+                || matches!(token, PatternToken::Regex(regex) if !regex.is_match(&entry.lemma))
+                || (!filter.is_empty() && !entry.lemma.to_lowercase().contains(&filter))
+            {
+                continue;
+            }
+            total += 1.0;
+            pool.words.push(entry.lemma.clone());
+            pool.cumulative.push(total);
+        }
+    }
+
+    if pool.words.is_empty() {
+        return Err(format!("No candidate words for {}", token_label(token)));
+    }
+
+    Ok(Some(pool))
+}
+
+/// The word a literal token writes, marked as a capture or not.
+fn literal_word(token: &PatternToken) -> Option<&String> {
+    match token {
+        PatternToken::Literal(word) => Some(word),
+        PatternToken::Captured(inner) => literal_word(inner),
+        _ => None,
+    }
+}
+
+/// Generates `count` sentences for a pattern, filling each slot from its pool and
+/// applying the pattern's template to the captures the matcher would produce.
+fn generate(
+    pattern: &PrologPattern,
+    tokens: &[PatternToken],
+    slots: &[SlotSettings],
+    words: &[WordEntry],
+    exceptions: &MorphologyExceptions,
+    count: usize,
+    rng: &mut SplitMix64,
+) -> Result<Vec<GeneratedSentence>, String> {
+    let pools = tokens
+        .iter()
+        .zip(slots)
+        .map(|(token, slot)| build_pool(token, slot, words))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut sentences = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut sentence_words: Vec<String> = Vec::new();
+        let mut captures = Vec::new();
+
+        for ((token, slot), pool) in tokens.iter().zip(slots).zip(&pools) {
+            let inflect = |word: &str| match slot.inflection {
+                Some(inflection) => inflection.apply(word, exceptions),
+                None => word.to_string(),
+            };
+
+            match token {
+                PatternToken::Literal(word) => sentence_words.push(word.clone()),
+                PatternToken::Alternatives(_) => {
+                    let word = pool
+                        .as_ref()
+                        .map(|p| p.pick(rng).to_string())
+                        .unwrap_or_default();
+                    sentence_words.push(word);
+                }
+                PatternToken::TypeMatch(..) | PatternToken::Wildcard => {
+                    let word = pool
+                        .as_ref()
+                        .map(|p| p.pick(rng).to_string())
+                        .unwrap_or_default();
+                    if matches!(token, PatternToken::TypeMatch(..)) {
+                        captures.push(word.to_lowercase());
+                    }
+                    sentence_words.push(inflect(&word));
+                }
+                PatternToken::Temporal(kind) => {
+                    let word = pool
+                        .as_ref()
+                        .map(|p| p.pick(rng).to_string())
+                        .unwrap_or_default();
+                    captures.push(temporal_capture(*kind, std::slice::from_ref(&word)));
+                    sentence_words.push(word);
+                }
+                PatternToken::Regex(_) => {
+                    let word = pool
+                        .as_ref()
+                        .map(|p| p.pick(rng).to_string())
+                        .unwrap_or_default();
+                    captures.push(word.to_lowercase());
+                    sentence_words.push(word);
+                }
+                PatternToken::Captured(inner) => {
+                    let word = match (literal_word(inner), pool) {
+                        (Some(word), _) => word.clone(),
+                        (_, Some(pool)) => pool.pick(rng).to_string(),
+                        _ => String::new(),
+                    };
+                    captures.push(word.to_lowercase().replace(' ', "_"));
+                    sentence_words.push(word);
+                }
+                PatternToken::Optional(inner) => {
+                    let included = rng.next_f64() < slot.include_probability;
+                    let word = match (literal_word(inner), pool) {
+                        _ if !included => None,
+                        (Some(word), _) => Some(word.clone()),
+                        (_, Some(pool)) => Some(pool.pick(rng).to_string()),
+                        _ => None,
+                    };
+                    let Some(word) = word else {
+                        // A capture left out keeps its `$n`, empty
+                        if is_capturing(token) {
+                            captures.push(String::new());
+                        }
+                        continue;
+                    };
+                    match inner.as_ref() {
+                        PatternToken::TypeMatch(..) => {
+                            captures.push(word.to_lowercase());
+                            sentence_words.push(inflect(&word));
+                        }
+                        PatternToken::Temporal(kind) => {
+                            captures.push(temporal_capture(*kind, std::slice::from_ref(&word)));
+                            sentence_words.push(word);
+                        }
+                        PatternToken::Captured(_) | PatternToken::Regex(_) => {
+                            captures.push(word.to_lowercase().replace(' ', "_"));
+                            sentence_words.push(word);
+                        }
+                        _ => sentence_words.push(word),
+                    }
+                }
+                PatternToken::Greedy(inner) => {
+                    let repeat = 1 + rng.below(slot.max_repeat.max(1));
+                    let mut run = Vec::with_capacity(repeat);
+                    for _ in 0..repeat {
+                        match (literal_word(inner), pool) {
+                            (Some(word), _) => run.push(word.clone()),
+                            (_, Some(pool)) => run.push(pool.pick(rng).to_string()),
+                            _ => {}
+                        }
+                    }
+                    captures.push(match inner.as_ref() {
+                        PatternToken::Temporal(kind) => temporal_capture(*kind, &run),
+                        _ => run.join(" ").to_lowercase().replace(' ', "_"),
+                    });
+                    // A compound inflects at its last word: "ice creams".
+                    if let Some(last) = run.last_mut()
+                        && !matches!(
+                            inner.as_ref(),
+                            PatternToken::Literal(_)
+                                | PatternToken::Alternatives(_)
+                                | PatternToken::Temporal(_)
+                                | PatternToken::Regex(_)
+                        )
+                    {
+                        *last = inflect(last);
+                    }
+                    sentence_words.extend(run);
+                }
+            }
+        }
+
+        let mut sentence = sentence_words.join(" ");
+        if let Some(first) = sentence.get(..1) {
+            sentence = first.to_uppercase() + &sentence[1..];
+        }
+        sentence.push('.');
+
+        sentences.push(GeneratedSentence {
+            sentence,
+            facts: apply_template(&captures, &pattern.template),
+        });
+    }
+
+    Ok(sentences)
+}
+
+/// The capture a `<Date>` or `<Time>` token makes of `words`, as the parser
+/// writes it.
+fn temporal_capture(kind: TemporalKind, words: &[String]) -> String {
+    kind.normalize(words).unwrap_or_else(|| words.join("_").to_lowercase())
+}
+
+/// Formats generated sentences the way the parser does, so the output can be
+/// pasted straight into the query executor.
+fn format_output(sentences: &[GeneratedSentence]) -> String {
+    let mut output = String::new();
+    for generated in sentences {
+        output.push_str(&format!("// FROM: {}\n", generated.sentence));
+        for fact in &generated.facts {
+            output.push_str(fact);
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::database::{
+        WordType,
+        fixtures::{pattern, word},
+    };
+
+    #[test]
+    fn test_generates_facts_from_filtered_and_weighted_slots() {
+        let words = vec![
+            word("cat", WordType::Noun, &["cat"]),
+            word("car", WordType::Noun, &["car"]),
+            word("dog", WordType::Noun, &["dog"]),
+            word("run", WordType::Verb, &["run"]),
+        ];
+        let pattern = pattern("<Noun> is a <Noun>", "is_a($1, $2).");
+        let tokens = parse_pattern(&pattern.pattern);
+        let mut slots: Vec<SlotSettings> = tokens.iter().map(|_| SlotSettings::new()).collect();
+        slots[0].filter = "ca".to_string();
+        slots[3].weights = "animal=1, thing=0".to_string();
+
+        let mut rng = SplitMix64(42);
+        let exceptions = MorphologyExceptions::english();
+        let sentences =
+            generate(&pattern, &tokens, &slots, &words, &exceptions, 20, &mut rng).unwrap();
+        assert_eq!(sentences.len(), 20);
+        for generated in &sentences {
+            assert_eq!(generated.facts.len(), 1);
+            let fact = &generated.facts[0];
+            assert!(
+                fact == "is_a(cat, animal)." || fact == "is_a(car, animal).",
+                "{}",
+                fact
+            );
+            assert!(generated.sentence.ends_with(" is a animal."));
+        }
+
+        // Inflected slots change the sentence but not the facts.
+        slots[0].inflection = Some(Inflection::Plural);
+        let generated = &generate(&pattern, &tokens, &slots, &words, &exceptions, 1, &mut rng)
+            .unwrap()[0];
+        assert!(generated.sentence.starts_with("Cats ") || generated.sentence.starts_with("Cars "));
+        assert!(generated.facts[0].starts_with("is_a(ca"));
+
+        slots[0].filter = "zebra".to_string();
+        assert!(generate(&pattern, &tokens, &slots, &words, &exceptions, 1, &mut rng).is_err());
+    }
+}
@@ -1,15 +1,32 @@
-use crate::app::database::WordType;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
 
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+
+use crate::app::{
+    coverage::SentenceCoverage,
+    database::{Locale, WordType},
+    parser::{
+        parser::Directive, pattern_matcher::apply_template, pronoun_resolver::PronounResolution,
+        question::Question,
+    },
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenHighlight {
     pub word: String,
     pub word_index: usize,
+    /// End (exclusive) of the sentence words this capture covers, so a greedy
+    /// capture spans `word_index..word_end` whatever its text says.
+    pub word_end: usize,
     pub capture_index: usize,
     pub token_type: TokenType,
     //pub is_editable: bool,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TokenType {
     Noun,
     Verb,
@@ -18,38 +35,138 @@ pub enum TokenType {
     Greedy,
 }
 
-#[derive(Debug, Clone)]
+impl TokenType {
+    /// The word type a `<Type>` capture was read as; `None` for greedy captures.
+    pub fn word_type(&self) -> Option<WordType> {
+        match self {
+            TokenType::Noun => Some(WordType::Noun),
+            TokenType::Verb => Some(WordType::Verb),
+            TokenType::Adjective => Some(WordType::Adjective),
+            TokenType::Other(word_type) => Some(word_type.clone()),
+            TokenType::Greedy => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SentenceMatch {
+    /// The input sentence this match was parsed from; locks are keyed by it.
+    pub sentence: String,
     pub words: Vec<String>,
     pub pattern_name: String,
     pub template: String,
     pub highlights: Vec<TokenHighlight>,
     pub generated_output: String,
+    /// How sure the parser is of this match, from 0 to 1. Hand-reviewed
+    /// matches are fully trusted.
+    #[serde(default = "SentenceMatch::full_confidence")]
+    pub confidence: f32,
+    /// Other patterns that match the same words, offered in the Parsing
+    /// panel as alternative interpretations. Found again on every parse.
+    #[serde(skip)]
+    pub alternatives: Vec<SentenceMatch>,
 }
 
 impl SentenceMatch {
+    fn full_confidence() -> f32 {
+        1.0
+    }
+
     pub fn regenerate_output(&mut self) {
-        let mut captures: Vec<String> = vec![String::new(); self.highlights.len()];
+        // Slots of optional captures that were left out stay empty
+        let slots = self.highlights.iter().map(|h| h.capture_index).max().unwrap_or(0);
+        let mut captures: Vec<String> = vec![String::new(); slots];
 
         for highlight in &self.highlights {
-            if highlight.capture_index > 0 && highlight.capture_index <= captures.len() {
+            if highlight.capture_index > 0 {
                 captures[highlight.capture_index - 1] = highlight.word.clone();
             }
         }
 
-        self.generated_output = apply_template_simple(&captures, &self.template);
+        self.generated_output = apply_template(&captures, &self.template).join("\n");
+    }
+
+    /// This match and its alternatives, except the one made with
+    /// `pattern_name`: the alternatives of that one.
+    pub fn alternatives_to(&self, pattern_name: &str) -> Vec<SentenceMatch> {
+        std::iter::once(self)
+            .chain(&self.alternatives)
+            .filter(|interpretation| interpretation.pattern_name != pattern_name)
+            .map(|interpretation| SentenceMatch {
+                alternatives: Vec::new(),
+                ..interpretation.clone()
+            })
+            .collect()
     }
 }
 
-fn apply_template_simple(captures: &[String], template: &str) -> String {
-    let mut result = template.to_string();
+/// A capture being retyped in the Parsing panel.
+#[derive(Debug, Clone)]
+pub struct CaptureEdit {
+    pub match_idx: usize,
+    pub word_idx: usize,
+    pub text: String,
+    /// Why the last attempt to apply `text` was refused.
+    pub error: Option<String>,
+}
+
+/// Turns a typed capture value into the form parsed captures take:
+/// lowercased, with words joined by `_`. Numbers are kept as typed. Anything
+/// that would not be a plain atom or number in the generated facts is refused.
+pub fn normalize_capture_value(text: &str, locale: Locale) -> Result<String, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("The value cannot be empty".to_string());
+    }
+    if text.parse::<f64>().is_ok() {
+        return Ok(text.to_string());
+    }
 
-    for (i, word) in captures.iter().enumerate() {
-        let placeholder = format!("${}", i + 1);
-        result = result.replace(&placeholder, word);
+    let value = locale
+        .lowercase(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_");
+    if !value.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(format!(
+            "'{}' may only contain letters, digits and spaces",
+            text
+        ));
     }
+    if value.starts_with(|c: char| c.is_numeric() || c == '_') {
+        return Err(format!("'{}' must start with a letter", text));
+    }
+    Ok(value)
+}
+
+/// An accepted parse of one sentence, reused verbatim on every re-parse.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedParse {
+    pub output: String,
+    pub matches: Vec<SentenceMatch>,
+}
+
+impl LockedParse {
+    /// The parse `matches` make of `sentence`, written out as locked.
+    pub fn new(sentence: &str, matches: Vec<SentenceMatch>) -> Self {
+        let mut output = format!("// FROM: {}\n// LOCKED\n", sentence);
+        for sentence_match in &matches {
+            output.push_str(&sentence_match.generated_output);
+            output.push('\n');
+        }
+        Self { output, matches }
+    }
+}
 
-    result
+/// A word the parser did not know and read through the word-type fallback,
+/// waiting in the Database Editor to be added.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownWord {
+    pub word: String,
+    /// The type the fallback read it as, offered as the type to add it with.
+    pub word_type: WordType,
+    /// The first sentence it was met in.
+    pub sentence: String,
 }
 
 #[derive(Default)]
@@ -58,6 +175,69 @@ pub struct InteractiveParser {
     pub dragging_highlight: Option<(usize, usize)>,
     pub temp_selected_word: Option<String>,
     pub selection_start_pos: Option<usize>,
+    /// Locked parses by sentence. Kept across `clear()` so re-parsing never
+    /// changes them.
+    pub locked: HashMap<String, LockedParse>,
+    /// Matches of unlocked sentences whose captures were reassigned by hand,
+    /// by sentence. Kept across `clear()` and re-applied while the sentence
+    /// still parses with the same patterns.
+    pub corrected: HashMap<String, Vec<SentenceMatch>>,
+    /// The input's `:-` and `>` directive lines, in order.
+    pub directives: Vec<Directive>,
+    /// The input's questions, answered once the whole input is parsed.
+    pub questions: Vec<Question>,
+    /// Capture opened for typing by double-clicking it.
+    pub editing_capture: Option<CaptureEdit>,
+    /// Matches less confident than this stay in the panel but emit no facts.
+    /// Kept across `clear()` for the rest of the session.
+    pub min_confidence: f32,
+    /// Whether the Parsing panel lists the least confident matches first.
+    /// Kept across `clear()` for the rest of the session.
+    pub least_confident_first: bool,
+    /// Pattern tags left out when parsing the open document. Saved with its
+    /// corrections, so each document keeps its own pattern sets.
+    pub disabled_tags: BTreeSet<String>,
+    /// How the last parse covered the words of each sentence, in input order.
+    pub coverage: Vec<SentenceCoverage>,
+    /// The pronouns replaced by their antecedents in the last parse, by
+    /// sentence.
+    pub pronoun_resolutions: HashMap<String, Vec<PronounResolution>>,
+    /// Antecedents picked by hand, by sentence and pronoun position. Kept
+    /// across `clear()` and saved with the corrections.
+    pub antecedent_overrides: HashMap<String, BTreeMap<usize, String>>,
+    /// Unknown words met while parsing, oldest first. Kept across `clear()`
+    /// until they are added or dismissed in the Database Editor.
+    pub unknown_words: Vec<UnknownWord>,
+}
+
+/// The locks, capture corrections and pattern sets of one input file, saved
+/// next to it so a curation session survives restarts.
+#[derive(Default, Serialize, Deserialize)]
+pub struct SavedCorrections {
+    #[serde(default)]
+    pub locked: HashMap<String, LockedParse>,
+    #[serde(default)]
+    pub corrected: HashMap<String, Vec<SentenceMatch>>,
+    #[serde(default)]
+    pub disabled_tags: BTreeSet<String>,
+    #[serde(default)]
+    pub antecedent_overrides: HashMap<String, BTreeMap<usize, String>>,
+}
+
+impl SavedCorrections {
+    /// Reads a corrections file, such as the sidecar of an input file.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    }
+}
+
+/// Sidecar file holding the corrections of `input_path`: `notes.txt` keeps
+/// them in `notes.txt.corrections.json`.
+pub fn corrections_path(input_path: &Path) -> PathBuf {
+    let mut file_name = input_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".corrections.json");
+    input_path.with_file_name(file_name)
 }
 
 impl InteractiveParser {
@@ -67,13 +247,435 @@ impl InteractiveParser {
             dragging_highlight: None,
             temp_selected_word: None,
             selection_start_pos: None,
+            locked: HashMap::new(),
+            corrected: HashMap::new(),
+            directives: Vec::new(),
+            questions: Vec::new(),
+            editing_capture: None,
+            min_confidence: 0.0,
+            least_confident_first: false,
+            disabled_tags: BTreeSet::new(),
+            coverage: Vec::new(),
+            pronoun_resolutions: HashMap::new(),
+            antecedent_overrides: HashMap::new(),
+            unknown_words: Vec::new(),
         }
     }
 
+    /// The pronoun resolution behind word `word_idx` of `sentence_match`, if
+    /// that word is an antecedent substituted for a pronoun.
+    pub fn resolution_at(
+        &self,
+        sentence_match: &SentenceMatch,
+        word_idx: usize,
+    ) -> Option<&PronounResolution> {
+        let word = sentence_match.words.get(word_idx)?;
+        let occurrence = sentence_match.words[..word_idx]
+            .iter()
+            .filter(|earlier| *earlier == word)
+            .count();
+        self.pronoun_resolutions
+            .get(&sentence_match.sentence)?
+            .iter()
+            .find(|resolution| {
+                resolution.antecedent == *word && resolution.occurrence == occurrence
+            })
+    }
+
+    /// Makes the pronoun at `word_index` of `sentence` stand for
+    /// `antecedent` from the next parse on.
+    pub fn override_antecedent(&mut self, sentence: &str, word_index: usize, antecedent: String) {
+        self.antecedent_overrides
+            .entry(sentence.to_string())
+            .or_default()
+            .insert(word_index, antecedent);
+    }
+
+    /// Queues `word` for review, unless it already waits there.
+    pub fn queue_unknown_word(&mut self, word: String, word_type: WordType, sentence: &str) {
+        if self.unknown_words.iter().all(|unknown| unknown.word != word) {
+            self.unknown_words.push(UnknownWord {
+                word,
+                word_type,
+                sentence: sentence.to_string(),
+            });
+        }
+    }
+
+    pub fn is_locked(&self, sentence: &str) -> bool {
+        self.locked.contains_key(sentence)
+    }
+
+    /// Whether the facts of `sentence_match` are left out of the output.
+    pub fn is_hidden(&self, sentence_match: &SentenceMatch) -> bool {
+        sentence_match.confidence < self.min_confidence
+    }
+
+    /// The positions of the matches in the order the Parsing panel lists
+    /// them: input order, or least confident first, ties in input order.
+    pub fn review_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.matches.len()).collect();
+        if self.least_confident_first {
+            order.sort_by(|&a, &b| {
+                self.matches[a]
+                    .confidence
+                    .total_cmp(&self.matches[b].confidence)
+            });
+        }
+        order
+    }
+
+    /// Marks the current matches of `sentence` as reviewed by hand.
+    fn trust(&mut self, sentence: &str) {
+        for sentence_match in self.matches.iter_mut().filter(|m| m.sentence == sentence) {
+            sentence_match.confidence = SentenceMatch::full_confidence();
+        }
+    }
+
+    /// Freezes the current matches of `sentence`, including any reassigned
+    /// captures, as its parse.
+    pub fn lock(&mut self, sentence: &str) {
+        self.trust(sentence);
+        let matches: Vec<SentenceMatch> = self
+            .matches
+            .iter()
+            .filter(|m| m.sentence == sentence)
+            .cloned()
+            .collect();
+        if matches.is_empty() {
+            return;
+        }
+
+        self.corrected.remove(sentence);
+        self.locked
+            .insert(sentence.to_string(), LockedParse::new(sentence, matches));
+    }
+
+    /// The matches of `sentence` that were reviewed by hand, locked or
+    /// corrected, if any were.
+    pub fn curated(&self, sentence: &str) -> Option<&[SentenceMatch]> {
+        match self.locked.get(sentence) {
+            Some(locked) => Some(&locked.matches),
+            None => self.corrected.get(sentence).map(Vec::as_slice),
+        }
+    }
+
+    /// Makes `matches`, curated by someone else, the parse of `sentence`:
+    /// locked if they locked it, a correction otherwise.
+    pub fn adopt(&mut self, sentence: &str, matches: Vec<SentenceMatch>, locked: bool) {
+        self.locked.remove(sentence);
+        self.corrected.remove(sentence);
+        if locked {
+            self.locked
+                .insert(sentence.to_string(), LockedParse::new(sentence, matches));
+        } else {
+            self.corrected.insert(sentence.to_string(), matches);
+        }
+    }
+
+    /// Remembers the current matches of `sentence` after one of its captures
+    /// was reassigned.
+    pub fn record_correction(&mut self, sentence: &str) {
+        self.trust(sentence);
+        let matches: Vec<SentenceMatch> = self
+            .matches
+            .iter()
+            .filter(|m| m.sentence == sentence)
+            .cloned()
+            .collect();
+        if !matches.is_empty() {
+            self.corrected.insert(sentence.to_string(), matches);
+        }
+    }
+
+    /// The corrected matches of `sentence`, if it was corrected and `fresh`,
+    /// its new parse, still uses the same patterns over the same words. A
+    /// chosen alternative counts as long as the new parse still offers it.
+    pub fn correction_for(&self, sentence: &str, fresh: &[SentenceMatch]) -> Option<&[SentenceMatch]> {
+        let corrected = self.corrected.get(sentence)?;
+        let same_parse = corrected.len() == fresh.len()
+            && corrected.iter().zip(fresh).all(|(old, new)| {
+                old.words == new.words
+                    && std::iter::once(new)
+                        .chain(&new.alternatives)
+                        .any(|interpretation| interpretation.pattern_name == old.pattern_name)
+            });
+        same_parse.then_some(corrected.as_slice())
+    }
+
+    /// Restores the locks and corrections saved for `input_path`. A missing
+    /// sidecar file just means nothing was saved yet.
+    pub fn load_corrections(&mut self, input_path: &Path) -> Result<(), String> {
+        let path = corrections_path(input_path);
+        if !path.exists() {
+            self.locked.clear();
+            self.corrected.clear();
+            self.disabled_tags.clear();
+            self.antecedent_overrides.clear();
+            return Ok(());
+        }
+
+        let saved = SavedCorrections::read(&path)?;
+        self.locked = saved.locked;
+        self.corrected = saved.corrected;
+        self.disabled_tags = saved.disabled_tags;
+        self.antecedent_overrides = saved.antecedent_overrides;
+        Ok(())
+    }
+
+    pub fn save_corrections(&self, input_path: &Path) -> Result<(), String> {
+        let saved = SavedCorrections {
+            locked: self.locked.clone(),
+            corrected: self.corrected.clone(),
+            disabled_tags: self.disabled_tags.clone(),
+            antecedent_overrides: self.antecedent_overrides.clone(),
+        };
+        let json = serde_json::to_string_pretty(&saved).map_err(|e| e.to_string())?;
+        std::fs::write(corrections_path(input_path), json).map_err(|e| e.to_string())
+    }
+
+    /// Replaces the value of the capture at `word_idx` of match `match_idx`
+    /// with `value`, normalized, and records the correction. Returns the
+    /// sentence that was corrected.
+    pub fn set_capture(
+        &mut self,
+        match_idx: usize,
+        word_idx: usize,
+        value: &str,
+        locale: Locale,
+    ) -> Result<String, String> {
+        let value = normalize_capture_value(value, locale)?;
+        let sentence_match = self
+            .matches
+            .get_mut(match_idx)
+            .ok_or("The match no longer exists")?;
+        let highlight = sentence_match
+            .highlights
+            .iter_mut()
+            .find(|h| h.word_index == word_idx)
+            .ok_or("The capture no longer exists")?;
+
+        highlight.word = value;
+        sentence_match.regenerate_output();
+        let sentence = sentence_match.sentence.clone();
+        self.record_correction(&sentence);
+        Ok(sentence)
+    }
+
+    /// Makes alternative `alternative_idx` of match `match_idx` its
+    /// interpretation, the match it replaces becoming an alternative, and
+    /// records the correction. Returns the sentence that was corrected.
+    pub fn choose_alternative(
+        &mut self,
+        match_idx: usize,
+        alternative_idx: usize,
+    ) -> Result<String, String> {
+        let sentence_match = self
+            .matches
+            .get_mut(match_idx)
+            .ok_or("The match no longer exists")?;
+        let mut chosen = sentence_match
+            .alternatives
+            .get(alternative_idx)
+            .cloned()
+            .ok_or("The alternative no longer exists")?;
+        chosen.sentence = sentence_match.sentence.clone();
+        chosen.alternatives = sentence_match.alternatives_to(&chosen.pattern_name);
+        *sentence_match = chosen;
+
+        let sentence = sentence_match.sentence.clone();
+        self.record_correction(&sentence);
+        Ok(sentence)
+    }
+
+    pub fn unlock(&mut self, sentence: &str) {
+        self.locked.remove(sentence);
+    }
+
     pub fn clear(&mut self) {
         self.matches.clear();
         self.dragging_highlight = None;
         self.temp_selected_word = None;
         self.selection_start_pos = None;
+        self.directives.clear();
+        self.questions.clear();
+        self.editing_capture = None;
+        self.coverage.clear();
+        self.pronoun_resolutions.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fully trusted match of `sentence` with one noun capture per
+    /// `(word_index, word)`, its output generated from `template`.
+    fn sentence_match(sentence: &str, template: &str, captures: &[(usize, &str)]) -> SentenceMatch {
+        let highlights = captures
+            .iter()
+            .enumerate()
+            .map(|(slot, &(word_index, word))| TokenHighlight {
+                word: word.to_string(),
+                word_index,
+                word_end: word_index + 1,
+                capture_index: slot + 1,
+                token_type: TokenType::Noun,
+            })
+            .collect();
+        let mut sentence_match = SentenceMatch {
+            sentence: sentence.to_string(),
+            words: sentence
+                .trim_end_matches('.')
+                .split_whitespace()
+                .map(String::from)
+                .collect(),
+            pattern_name: template.split('(').next().unwrap_or_default().to_string(),
+            template: template.to_string(),
+            highlights,
+            generated_output: String::new(),
+            confidence: 1.0,
+            alternatives: Vec::new(),
+        };
+        sentence_match.regenerate_output();
+        sentence_match
+    }
+
+    #[test]
+    fn test_locking_keeps_edited_captures() {
+        let mut sentence_match = sentence_match(
+            "bear is an animal.",
+            "is_a($1, $2).",
+            &[(0, "bear"), (3, "animal")],
+        );
+        sentence_match.highlights[1].word = "mammal".to_string();
+        sentence_match.regenerate_output();
+
+        let mut parser = InteractiveParser::new();
+        parser.matches.push(sentence_match);
+        parser.lock("bear is an animal.");
+        parser.clear();
+
+        assert!(parser.is_locked("bear is an animal."));
+        assert_eq!(
+            parser.locked["bear is an animal."].output,
+            "// FROM: bear is an animal.\n// LOCKED\nis_a(bear, mammal).\n"
+        );
+
+        parser.unlock("bear is an animal.");
+        assert!(!parser.is_locked("bear is an animal."));
+    }
+
+    #[test]
+    fn test_typed_capture_values_are_normalized() {
+        assert_eq!(
+            normalize_capture_value("  New York ", Locale::English),
+            Ok("new_york".to_string())
+        );
+        assert_eq!(
+            normalize_capture_value("İstanbul", Locale::Turkish),
+            Ok("istanbul".to_string())
+        );
+        assert_eq!(normalize_capture_value("42", Locale::English), Ok("42".to_string()));
+        assert!(normalize_capture_value("", Locale::English).is_err());
+        assert!(normalize_capture_value("f(x)", Locale::English).is_err());
+        assert!(normalize_capture_value("2nd place", Locale::English).is_err());
+
+        let mut parser = InteractiveParser::new();
+        parser.matches.push(sentence_match(
+            "he lives there.",
+            "lives_in($1, $2).",
+            &[(0, "he"), (2, "there")],
+        ));
+
+        assert_eq!(
+            parser.set_capture(0, 2, "New York", Locale::English),
+            Ok("he lives there.".to_string())
+        );
+        assert_eq!(parser.matches[0].generated_output, "lives_in(he, new_york).");
+        assert!(parser.corrected.contains_key("he lives there."));
+        assert!(parser.set_capture(0, 1, "paris", Locale::English).is_err());
+    }
+
+    #[test]
+    fn test_corrections_survive_a_restart() {
+        let sentence_match =
+            sentence_match("cat has fur.", "has($1, $2).", &[(0, "kitten"), (2, "fur")]);
+        let mut parser = InteractiveParser::new();
+        parser.matches.push(sentence_match.clone());
+        parser.record_correction("cat has fur.");
+        parser.disabled_tags.insert("legal".to_string());
+
+        let input_path = std::env::temp_dir().join("simple_prolog_corrections_test.txt");
+        parser.save_corrections(&input_path).unwrap();
+        let mut restored = InteractiveParser::new();
+        restored.load_corrections(&input_path).unwrap();
+        std::fs::remove_file(corrections_path(&input_path)).unwrap();
+        assert!(restored.disabled_tags.contains("legal"));
+
+        let corrected = restored.correction_for("cat has fur.", &[sentence_match.clone()]).unwrap();
+        assert_eq!(corrected[0].generated_output, "has(kitten, fur).");
+
+        let mut reparsed = sentence_match;
+        reparsed.pattern_name = "owns".to_string();
+        assert!(restored.correction_for("cat has fur.", &[reparsed]).is_none());
+        assert_eq!(
+            corrections_path(Path::new("assets/base.txt")),
+            Path::new("assets/base.txt.corrections.json")
+        );
+    }
+
+    #[test]
+    fn test_chosen_alternatives_are_kept_as_corrections() {
+        let interpretation = |pattern_name: &str, output: &str| SentenceMatch {
+            sentence: String::new(),
+            pattern_name: pattern_name.to_string(),
+            generated_output: output.to_string(),
+            confidence: 0.8,
+            ..sentence_match("time flies", "", &[])
+        };
+        let mut parsed = interpretation("moves", "moves(time).");
+        parsed.sentence = "time flies.".to_string();
+        parsed.alternatives = vec![interpretation("insect", "insect(time_flies).")];
+
+        let mut parser = InteractiveParser::new();
+        parser.matches.push(parsed.clone());
+        assert_eq!(parser.choose_alternative(0, 0), Ok("time flies.".to_string()));
+        assert_eq!(parser.matches[0].pattern_name, "insect");
+        assert_eq!(parser.matches[0].sentence, "time flies.");
+        assert_eq!(parser.matches[0].confidence, 1.0);
+        assert_eq!(parser.matches[0].alternatives[0].pattern_name, "moves");
+        assert!(parser.choose_alternative(0, 1).is_err());
+
+        // A re-parse still offering the chosen pattern keeps the choice
+        let corrected = parser.correction_for("time flies.", &[parsed.clone()]).unwrap();
+        assert_eq!(corrected[0].generated_output, "insect(time_flies).");
+        parsed.alternatives.clear();
+        assert!(parser.correction_for("time flies.", &[parsed]).is_none());
+    }
+
+    #[test]
+    fn test_unknown_words_are_queued_once_and_kept_across_clear() {
+        let mut parser = InteractiveParser::new();
+        parser.queue_unknown_word("zorp".to_string(), WordType::Noun, "the zorp sleeps");
+        parser.queue_unknown_word("glibly".to_string(), WordType::Adverb, "he ran glibly");
+        parser.queue_unknown_word("zorp".to_string(), WordType::Verb, "cats zorp");
+        parser.clear();
+
+        assert_eq!(
+            parser.unknown_words,
+            vec![
+                UnknownWord {
+                    word: "zorp".to_string(),
+                    word_type: WordType::Noun,
+                    sentence: "the zorp sleeps".to_string(),
+                },
+                UnknownWord {
+                    word: "glibly".to_string(),
+                    word_type: WordType::Adverb,
+                    sentence: "he ran glibly".to_string(),
+                },
+            ]
+        );
     }
 }
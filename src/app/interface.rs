@@ -1,94 +1,249 @@
-use std::{path::Path, sync::{Arc, RwLock}};
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::Arc};
 
-use crate::app::{database::Database, database_editor::DatabaseEditor, parser, interactive_parser::InteractiveParser, query_engine::QueryEngine};
+use crate::app::{annotation_merge::AnnotationMerge, batch_report, clipboard_watcher::ClipboardWatcher, commands::{self, AppCommand}, coverage::{self, WordCoverage}, crash_report::{self, CrashReport}, data_dir, database::{Database, SharedDatabase}, database_editor::DatabaseEditor, entity_inspector::{self, EntityInspector}, generator::Generator, parser, interactive_parser::{CaptureEdit, InteractiveParser}, match_debugger::MatchDebugger, notifications, predicate_browser::{FactEdit, PredicateBrowser}, query_engine::{QueryEngine, graphviz, souffle}, settings::Settings, snapshots::{Snapshot, SnapshotStore}, training_data};
 
 const DATABASE_PATH: &str = "prolog_database.bin";
+const QUERY_CONFIG_PATH: &str = "query_config.txt";
 const BOTTOM_GAP: f32 = 35.0;
+/// Text fields of the parser tab that F6 moves focus between, left to right.
+const PANEL_FOCUS_ORDER: [&str; 3] = ["input_text", "query_text", "query_results"];
+/// Words substituted for a pronoun in the Interactive Parsing panel.
+const PRONOUN_COLOR: egui::Color32 = egui::Color32::from_rgb(220, 140, 220);
 
 #[derive(PartialEq)]
 enum AppTab {
     Parser,
     DatabaseEditor,
+    Generator,
+    Predicates,
+    Snapshots,
+    MatchDebugger,
+    Settings,
 }
 
 pub struct PrologApp {
     input_text: String,
+    /// File the input text was opened from; its locks and capture corrections
+    /// are saved next to it.
+    input_path: Option<PathBuf>,
+    /// Path typed next to the open button.
+    open_path_text: String,
     parsed_output: String,
     query_text: String,
     query_results: String,
+    /// Show the facts and rules behind each query answer.
+    explain_queries: bool,
+    /// Color the input words by how the parse covered them.
+    show_coverage: bool,
 
-    pub database: Arc<RwLock<Database>>,
+    pub database: Arc<SharedDatabase>,
+    /// Version of `database` taken when a parse starts. Matching reads this
+    /// instead of holding the lock, so editor writes never wait on a parse.
+    pub database_snapshot: Arc<Database>,
+    /// Confidence weight of each pattern's priority in `database_snapshot`,
+    /// worked out when the snapshot is taken rather than for every match.
+    pub priority_weights: HashMap<String, f32>,
     pub interactive_parser: InteractiveParser,
     pub query_engine: QueryEngine,
     
     current_tab: AppTab,
     database_editor: DatabaseEditor,
+    generator: Generator,
+    predicate_browser: PredicateBrowser,
+    snapshots: SnapshotStore,
+    match_debugger: MatchDebugger,
+    pub settings: Settings,
+    entity_inspector: EntityInspector,
+    annotation_merge: AnnotationMerge,
+    clipboard_watcher: ClipboardWatcher,
+    /// Parsed output before each fact edit, most recent last.
+    fact_edit_history: Vec<String>,
+    /// Source sentence picked in the entity inspector, highlighted in the parser tab.
+    focused_source: Option<String>,
+    scroll_to_focused_source: bool,
+    /// Left by a crash in the previous run; shown until dealt with.
+    crash_report: Option<CrashReport>,
+    /// Command palette line, `Some` while the palette is open.
+    command_line: Option<String>,
+    /// Result of the last palette command.
+    command_output: String,
 }
 
 impl Default for PrologApp {
     fn default() -> Self {
-        let database = Database::new(Path::new(DATABASE_PATH)).unwrap();
+        let database = Database::new(data_dir::path(DATABASE_PATH)).unwrap();
         let mut query_engine = QueryEngine::new();
         
         // Try to load query config file
-        if let Err(e) = query_engine.load_config_file("query_config.txt") {
+        if let Err(e) = query_engine.load_config_file(data_dir::path(QUERY_CONFIG_PATH)) {
             eprintln!("Note: Could not load query_config.txt: {}", e);
             eprintln!("You can create this file to define custom rules and patterns.");
         }
         
-        Self {
-            input_text: String::new(),
-            parsed_output: "// Parsed Prolog code will appear here...".to_string(),
-            query_text: String::new(),
-            query_results: "// Query results will appear here...".to_string(),
-            database: Arc::new(RwLock::new(database)),
-            current_tab: AppTab::Parser,
-            database_editor: DatabaseEditor::new(),
-            interactive_parser: InteractiveParser::new(),
-            query_engine,
-        }
+        Self::with_database(database, query_engine)
     }
 }
 
 impl eframe::App for PrologApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        notifications::raise_pending(ctx);
+        self.show_crash_dialog(ctx);
+        
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.selectable_value(&mut self.current_tab, AppTab::Parser, "📝 Parser");
                 ui.selectable_value(&mut self.current_tab, AppTab::DatabaseEditor, "🗄 Database Editor");
+                ui.selectable_value(&mut self.current_tab, AppTab::Generator, "🎲 Generator");
+                ui.selectable_value(&mut self.current_tab, AppTab::Predicates, "📚 Predicates");
+                ui.selectable_value(&mut self.current_tab, AppTab::Snapshots, "📸 Snapshots");
+                ui.selectable_value(&mut self.current_tab, AppTab::MatchDebugger, "🐞 Matcher Debugger");
+                ui.selectable_value(&mut self.current_tab, AppTab::Settings, "⚙ Settings");
             });
         });
         
+        if let Some(source) = self.entity_inspector.show(ctx, &self.query_engine) {
+            self.current_tab = AppTab::Parser;
+            self.focused_source = Some(source);
+            self.scroll_to_focused_source = true;
+        }
+        
+        let taken = self.annotation_merge.show(ctx, &self.interactive_parser);
+        if !taken.is_empty() {
+            for review in taken {
+                self.interactive_parser.adopt(&review.sentence, review.their_matches, review.their_lock);
+            }
+            self.save_corrections();
+            self.update_parsed_output();
+        }
+        
+        if let Some(text) = self.clipboard_watcher.show(ctx) {
+            self.current_tab = AppTab::Parser;
+            self.append_input(&text);
+        }
+        
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::P)) {
+            self.command_line = match self.command_line {
+                Some(_) => None,
+                None => Some(String::new()),
+            };
+        }
+        self.show_command_palette(ctx);
+        
+        if self.current_tab == AppTab::Parser
+            && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F6))
+        {
+            focus_next_panel(ctx);
+        }
+        
         match self.current_tab {
             AppTab::Parser => self.show_parser_tab(ctx),
-            AppTab::DatabaseEditor => self.database_editor.show(ctx, &self.database.clone()),
+            AppTab::DatabaseEditor => {
+                if let Some(pending) = self.database_editor.show(ctx, &self.database.clone(), &mut self.interactive_parser.unknown_words) {
+                    self.refresh_database_snapshot();
+                    let regressions = parser::pattern_matcher::pattern_example_regressions(&pending.pattern, self);
+                    self.database_editor.store_pattern(pending, regressions, &self.database);
+                }
+            }
+            AppTab::Generator => self.generator.show(ctx, &self.database),
+            AppTab::Predicates => {
+                if let Some(edit) = self.predicate_browser.show(ctx, &self.query_engine, !self.fact_edit_history.is_empty()) {
+                    self.apply_fact_edit(edit);
+                }
+            }
+            AppTab::Snapshots => {
+                if let Some(snapshot) = self.snapshots.show(ctx, &self.parsed_output, &self.query_text) {
+                    self.restore_snapshot(snapshot);
+                }
+            }
+            AppTab::MatchDebugger => {
+                if let Some(request) = self.match_debugger.show(ctx, &self.database_snapshot) {
+                    self.refresh_database_snapshot();
+                    let (matched_len, steps) = parser::pattern_matcher::trace_match(&request.words, &request.tokens, self);
+                    self.match_debugger.load_trace(request, matched_len, steps);
+                }
+            }
+            AppTab::Settings => {
+                let previous_parsing = self.settings.parsing();
+                if self.settings.show(ctx) {
+                    ctx.set_visuals(self.settings.visuals());
+                    self.settings.apply_to_engine(&mut self.query_engine);
+                    if self.settings.parsing() != previous_parsing {
+                        self.update_parsed_output();
+                    }
+                    self.execute_query();
+                }
+            }
         }
     }
 }
 
 impl PrologApp {
-    pub fn with_text(text: String) -> Self {
-        let database = Database::new(Path::new(DATABASE_PATH)).unwrap();
+    /// Starts with `text` as input, restoring the corrections saved for
+    /// `input_path` when the text was read from a file.
+    pub fn with_text(text: String, input_path: Option<PathBuf>) -> Self {
+        let database = Database::new(data_dir::path(DATABASE_PATH)).expect("could not load the word database");
         let mut query_engine = QueryEngine::new();
         
         // Try to load query config file
-        if let Err(e) = query_engine.load_config_file("query_config.txt") {
+        if let Err(e) = query_engine.load_config_file(data_dir::path(QUERY_CONFIG_PATH)) {
             eprintln!("Note: Could not load query_config.txt: {}", e);
         }
 
-        let mut app = Self {
-            parsed_output: String::new(),
-            input_text: text,
+        let mut app = Self::with_database(database, query_engine);
+        crash_report::watch_database(Arc::clone(&app.database));
+        app.crash_report = crash_report::load_crash_report();
+        app.input_text = text;
+        app.open_path_text = input_path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        app.input_path = input_path;
+        if let Some(path) = app.input_path.clone()
+            && let Err(e) = app.interactive_parser.load_corrections(&path)
+        {
+            eprintln!("Note: Could not load corrections for {}: {}", path.display(), e);
+        }
+        app.update_parsed_output();
+        app
+    }
+    
+    /// Starts with an empty input on `database` and `query_engine`, without
+    /// reading anything from the data directory.
+    pub fn with_database(database: Database, query_engine: QueryEngine) -> Self {
+        let database = Arc::new(SharedDatabase::new(database));
+        let database_snapshot = database.snapshot().unwrap_or_default();
+        let priority_weights = parser::pattern_matcher::priority_weights(&database_snapshot);
+        Self {
+            input_text: String::new(),
+            input_path: None,
+            open_path_text: String::new(),
+            parsed_output: "// Parsed Prolog code will appear here...".to_string(),
             query_text: String::new(),
             query_results: "// Query results will appear here...".to_string(),
-            database: Arc::new(RwLock::new(database)),
+            explain_queries: false,
+            show_coverage: false,
+            database_snapshot,
+            priority_weights,
+            database,
             current_tab: AppTab::Parser,
             database_editor: DatabaseEditor::new(),
+            generator: Generator::new(),
+            predicate_browser: PredicateBrowser::new(),
+            snapshots: SnapshotStore::new(),
+            match_debugger: MatchDebugger::new(),
+            settings: Settings::new(),
+            entity_inspector: EntityInspector::new(),
+            annotation_merge: AnnotationMerge::new(),
+            clipboard_watcher: ClipboardWatcher::new(),
+            fact_edit_history: Vec::new(),
+            focused_source: None,
+            scroll_to_focused_source: false,
             interactive_parser: InteractiveParser::new(),
             query_engine,
-        };
-        app.update_parsed_output();
-        app
+            crash_report: None,
+            command_line: None,
+            command_output: String::new(),
+        }
     }
     
     fn show_parser_tab(&mut self, ctx: &egui::Context) {
@@ -105,7 +260,7 @@ impl PrologApp {
                     egui::vec2(panel_width, available_height),
                     egui::Layout::top_down(egui::Align::Min),
                     |ui| {
-                        ui.heading("Input Text");
+                        let input_heading = ui.heading("Input Text");
                         ui.separator();
 
                         let text_height = ui.available_height() - BOTTOM_GAP;
@@ -187,28 +342,48 @@ impl PrologApp {
                                             
                                             if ui.button("Apply Selection").clicked() {
                                                 if let Some((match_idx, word_idx)) = self.interactive_parser.dragging_highlight {
+                                                    let mut corrected_sentence = None;
                                                     if let Some(sentence_match) = self.interactive_parser.matches.get_mut(match_idx) {
                                                         if let Some(word) = self.interactive_parser.temp_selected_word.take() {
                                                             if let Some(highlight) = sentence_match.highlights.iter_mut()
                                                                 .find(|h| h.word_index == word_idx) {
                                                                 highlight.word = word;
                                                                 sentence_match.regenerate_output();
+                                                                corrected_sentence = Some(sentence_match.sentence.clone());
                                                             }
                                                         }
                                                     }
                                                     self.interactive_parser.dragging_highlight = None;
                                                     
+                                                    if let Some(sentence) = corrected_sentence {
+                                                        self.interactive_parser.record_correction(&sentence);
+                                                        self.save_corrections();
+                                                    }
+                                                    
                                                     self.rebuild_parsed_output_from_interactive();
                                                 }
                                             }
                                         });
                                     }
                                 } else {
+                                    let sentences = &self.interactive_parser.coverage;
+                                    let locale = self.database_snapshot.locale;
+                                    let mut coverage_layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                                        let ranges = coverage::input_coverage(text, sentences, locale);
+                                        let font_id = egui::TextStyle::Body.resolve(ui.style());
+                                        let job = coverage::coverage_layout(text, &ranges, font_id, ui.visuals().text_color(), wrap_width);
+                                        ui.fonts(|f| f.layout_job(job))
+                                    };
+                                    let mut input_edit = egui::TextEdit::multiline(&mut self.input_text)
+                                        .id(egui::Id::new(PANEL_FOCUS_ORDER[0]))
+                                        .hint_text("Enter natural language text here...\n\nExample:\nBear is an animal\nCat is a mammal\nMammals are animals");
+                                    if self.show_coverage {
+                                        input_edit = input_edit.layouter(&mut coverage_layouter);
+                                    }
                                     let response = ui.add_sized(
                                         [ui.available_width(), text_height.max(100.0)],
-                                        egui::TextEdit::multiline(&mut self.input_text)
-                                            .hint_text("Enter natural language text here...\n\nExample:\nBear is an animal\nCat is a mammal\nMammals are animals")
-                                    );
+                                        input_edit
+                                    ).labelled_by(input_heading.id);
                                     
                                     if response.changed() {
                                         self.update_parsed_output();
@@ -218,10 +393,50 @@ impl PrologApp {
                         
                         ui.separator();
                         
-                        if ui.button("Clear Input Text").clicked() {
-                            self.input_text.clear();
-                            self.parsed_output.clear();
-                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("Clear Input Text").clicked() {
+                                self.input_text.clear();
+                                self.parsed_output.clear();
+                            }
+
+                            let path_label = ui.label("File:");
+                            ui.add(egui::TextEdit::singleline(&mut self.open_path_text)
+                                .hint_text("path/to/input.txt")
+                                .desired_width(160.0))
+                                .labelled_by(path_label.id);
+                            if ui.button("📂 Open")
+                                .on_hover_text("Open a text file, restoring the locks and corrections saved for it")
+                                .clicked()
+                            {
+                                let path = PathBuf::from(self.open_path_text.trim());
+                                let opened = self.open_input_file(path)
+                                    .map(|()| format!("Parsed {} sentences into {} matches", self.interactive_parser.coverage.len(), self.interactive_parser.matches.len()));
+                                if let Err(e) = &opened {
+                                    self.query_results = format!("// Error opening input file: {}", e);
+                                }
+                                notifications::job_finished(ctx, "Parsing", opened.as_deref().map_err(String::as_str));
+                            }
+                            
+                            self.show_pattern_sets_menu(ui);
+                            
+                            if ui.button("👥 Merge")
+                                .on_hover_text("Compare another annotator's corrections of this text with yours and settle where you disagree")
+                                .clicked()
+                            {
+                                self.annotation_merge.open = true;
+                            }
+
+                            ui.checkbox(&mut self.clipboard_watcher.enabled, "📋 Watch clipboard")
+                                .on_hover_text("Offer text copied in other programs for parsing");
+
+                            ui.checkbox(&mut self.show_coverage, "🗺 Coverage")
+                                .on_hover_text("Color the input words by how the parse covered them");
+                            if self.show_coverage {
+                                for word_coverage in WordCoverage::ALL {
+                                    ui.label(egui::RichText::new(word_coverage.name()).background_color(word_coverage.color()));
+                                }
+                            }
+                        });
                     },
                 );
                 
@@ -245,9 +460,19 @@ impl PrologApp {
                         
                         ui.separator();
 
-                        if ui.button("Copy Output Text").clicked() {
-                            ui.output_mut(|o| o.copied_text = self.parsed_output.clone());
-                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("Copy Output Text").clicked() {
+                                ui.output_mut(|o| o.copied_text = self.parsed_output.clone());
+                            }
+
+                            if ui.button("📈 Learn Word Frequencies")
+                                .on_hover_text("Count the word readings used in this parse so ambiguous words prefer them")
+                                .clicked()
+                            {
+                                let learned = self.learn_word_frequencies();
+                                self.query_results = format!("// Learned {} word readings from the current parse", learned);
+                            }
+                        });
                     },
                 );
                 
@@ -260,15 +485,16 @@ impl PrologApp {
                         ui.heading("Query Executor");
                         ui.separator();
                         
-                        ui.label(egui::RichText::new("Enter Prolog query:")
+                        let query_label = ui.label(egui::RichText::new("Enter Prolog query:")
                             .color(egui::Color32::from_rgb(150, 150, 150)));
                         
                         let query_input_height = 60.0;
                         let response = ui.add_sized(
                             [ui.available_width(), query_input_height],
                             egui::TextEdit::multiline(&mut self.query_text)
-                                .hint_text("Examples:\nanimal(X).\nis_a(cat, X).\nhas_property(X, Y).")
-                        );
+                                .id(egui::Id::new(PANEL_FOCUS_ORDER[1]))
+                                .hint_text("Examples:\nanimal(X).\nis_a(cat, X).\nhas_property(X, Y).\nassert(animal(owl)).")
+                        ).labelled_by(query_label.id);
                         
                         if response.changed() {
                             self.execute_query();
@@ -276,15 +502,99 @@ impl PrologApp {
                         
                         ui.add_space(5.0);
                         
-                        if ui.button("Clear Query").clicked() {
-                            self.query_text.clear();
-                            self.query_results = "// Query results will appear here...".to_string();
-                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("Clear Query").clicked() {
+                                self.query_text.clear();
+                                self.query_results = "// Query results will appear here...".to_string();
+                            }
+                            
+                            if ui.checkbox(&mut self.explain_queries, "🔍 Explain")
+                                .on_hover_text("Show the facts and rules each answer was derived from")
+                                .changed()
+                            {
+                                self.execute_query();
+                            }
+                            
+                            if ui.button("⚖ Check consistency")
+                                .on_hover_text("List facts that hold while their negation (-fact or not_fact) holds too")
+                                .clicked()
+                            {
+                                let contradictions = self.query_engine.contradictions();
+                                self.query_results = if contradictions.is_empty() {
+                                    "// No contradictions found".to_string()
+                                } else {
+                                    let lines: Vec<String> = contradictions.iter().map(|c| c.to_string()).collect();
+                                    format!("// {} contradictions found\n{}", contradictions.len(), lines.join("\n"))
+                                };
+                            }
+                            
+                            if ui.button("💾 Export .pl")
+                                .on_hover_text("Write the facts and rules to a file SWI-Prolog can consult")
+                                .clicked()
+                            {
+                                let path = &self.settings.export_path;
+                                self.query_results = match std::fs::write(path, self.query_engine.export_swi_prolog()) {
+                                    Ok(()) => format!("// Knowledge base exported to {}", path),
+                                    Err(e) => format!("// Error exporting to {}: {}", path, e),
+                                };
+                            }
+                            
+                            if ui.button("💾 Export Soufflé")
+                                .on_hover_text("Write the facts as Soufflé .facts files with a .dl schema")
+                                .clicked()
+                            {
+                                let dir = &self.settings.souffle_dir;
+                                self.query_results = match souffle::export_souffle(&self.query_engine, Path::new(dir)) {
+                                    Ok(relations) => format!("// {} relations exported to {}", relations, dir),
+                                    Err(e) => format!("// Error exporting to {}: {}", dir, e),
+                                };
+                            }
+                            
+                            if ui.button("🕸 Export graph")
+                                .on_hover_text("Write the two-argument facts as a GraphViz graph: entities as nodes, predicates as edges")
+                                .clicked()
+                            {
+                                let path = Path::new(&self.settings.graph_path);
+                                self.query_results = match graphviz::export_dot(&self.query_engine, path) {
+                                    Ok(edges) if self.settings.render_graph_png => match graphviz::render_png(path) {
+                                        Ok(png_path) => format!("// {} edges exported to {} and rendered to {}", edges, path.display(), png_path.display()),
+                                        Err(e) => format!("// {} edges exported to {}, but rendering failed: {}", edges, path.display(), e),
+                                    },
+                                    Ok(edges) => format!("// {} edges exported to {}", edges, path.display()),
+                                    Err(e) => format!("// Error exporting to {}: {}", path.display(), e),
+                                };
+                            }
+                            
+                            if ui.button("🏷 Export training data")
+                                .on_hover_text("Write every locked or corrected match as a labelled JSON record: tokens, capture spans, BIO labels and facts")
+                                .clicked()
+                            {
+                                let path = Path::new(&self.settings.training_data_path);
+                                self.query_results = match training_data::export_jsonl(&self.interactive_parser, path) {
+                                    Ok(0) => "// No locked or corrected matches to export yet".to_string(),
+                                    Ok(records) => format!("// {} annotated matches exported to {}", records, path.display()),
+                                    Err(e) => format!("// Error exporting to {}: {}", path.display(), e),
+                                };
+                            }
+                            
+                            if ui.button("📊 Run Report").clicked() {
+                                let label = format!("{} sentences", self.interactive_parser.matches.len());
+                                let report = self.run_batch_report(&self.settings.report_queries_path, &self.settings.report_output_path, &label);
+                                match &report {
+                                    Ok(summary) => notifications::job_finished(ctx, "Batch report", Ok(&batch_report::outcome_counts(summary))),
+                                    Err(e) => notifications::job_finished(ctx, "Batch report", Err(e)),
+                                }
+                                self.query_results = match report {
+                                    Ok(summary) => format!("// Report written to {}\n{}", self.settings.report_output_path, summary),
+                                    Err(e) => format!("// Error running report: {}", e),
+                                };
+                            }
+                        });
                         
                         ui.add_space(10.0);
                         ui.separator();
                         
-                        ui.label(egui::RichText::new("Results:")
+                        let results_label = ui.label(egui::RichText::new("Results:")
                             .strong()
                             .color(egui::Color32::from_rgb(150, 200, 150)));
                         
@@ -294,11 +604,21 @@ impl PrologApp {
                             .id_source("query_results_scroll")
                             .max_height(results_height.max(100.0))
                             .show(ui, |ui| {
-                                ui.add_sized(
-                                    [ui.available_width(), results_height.max(100.0)],
-                                    egui::TextEdit::multiline(&mut self.query_results)
-                                        .code_editor()
-                                );
+                                // code_editor() keeps focus on Tab; let it move on instead
+                                let output = egui::TextEdit::multiline(&mut self.query_results)
+                                    .id(egui::Id::new(PANEL_FOCUS_ORDER[2]))
+                                    .code_editor()
+                                    .lock_focus(false)
+                                    .desired_width(ui.available_width())
+                                    .min_size(egui::vec2(ui.available_width(), results_height.max(100.0)))
+                                    .show(ui);
+                                
+                                if output.response.labelled_by(results_label.id).clicked()
+                                    && let Some(cursor) = output.cursor_range
+                                    && let Some(entity) = entity_inspector::entity_at(&self.query_results, cursor.primary.ccursor.index)
+                                {
+                                    self.entity_inspector.open(entity);
+                                }
                             });
                     },
                 );
@@ -307,21 +627,74 @@ impl PrologApp {
     }
     
     fn show_interactive_matches(&mut self, ui: &mut egui::Ui) {
-        if self.interactive_parser.matches.is_empty() {
+        if self.interactive_parser.matches.is_empty() && self.interactive_parser.questions.is_empty() {
             ui.label("// Parsed Prolog code will appear here...");
             ui.label("// Highlighted words show captured values");
             ui.label("// Drag highlights to reassign references");
             return;
         }
         
-        for (match_idx, sentence_match) in self.interactive_parser.matches.iter().enumerate() {
+        let mut toggled_lock = None;
+        let mut edited_capture = None;
+        let mut chosen_alternative = None;
+        let mut picked_antecedent = None;
+        let mut asked_query = None;
+        
+        ui.horizontal(|ui| {
+            let label = ui.label("Min confidence:");
+            let slider = ui.add(egui::Slider::new(&mut self.interactive_parser.min_confidence, 0.0..=1.0))
+                .labelled_by(label.id)
+                .on_hover_text("Matches below this score stay here, greyed out, but their facts are left out of the output and queries");
+            // Re-parse once the drag ends rather than on every frame of it
+            if slider.drag_stopped() || (slider.changed() && !slider.dragged()) {
+                self.update_parsed_output();
+            }
+            ui.checkbox(&mut self.interactive_parser.least_confident_first, "Least confident first")
+                .on_hover_text("List the matches the parser is least sure of first, so they can be reviewed before the rest");
+        });
+        ui.add_space(5.0);
+        
+        for match_idx in self.interactive_parser.review_order() {
+            let sentence_match = &self.interactive_parser.matches[match_idx];
+            let is_focused = self.focused_source.as_deref()
+                .map(|source| source_contains_words(source, &sentence_match.words))
+                .unwrap_or(false);
+            let is_locked = self.interactive_parser.is_locked(&sentence_match.sentence);
+            let is_hidden = self.interactive_parser.is_hidden(sentence_match);
+            let resolutions: Vec<_> = (0..sentence_match.words.len())
+                .map(|word_idx| self.interactive_parser.resolution_at(sentence_match, word_idx).cloned())
+                .collect();
+            
             ui.push_id(match_idx, |ui| {
-                ui.group(|ui| {
+                let group = ui.group(|ui| {
                     ui.set_min_width(ui.available_width() - 24.0);
+                    if is_hidden {
+                        ui.set_opacity(0.4);
+                    }
                     
-                    ui.label(egui::RichText::new(&sentence_match.pattern_name)
-                        .strong()
-                        .color(egui::Color32::from_rgb(100, 150, 200)));
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new(&sentence_match.pattern_name)
+                            .strong()
+                            .color(egui::Color32::from_rgb(100, 150, 200)));
+                        let confidence = format!("{:.0}%", sentence_match.confidence * 100.0);
+                        let hover = if is_hidden {
+                            "Parser confidence. Below the threshold, so this match's facts are hidden."
+                        } else {
+                            "Parser confidence. Unknown words, long greedy captures, wildcards and low pattern priority lower it."
+                        };
+                        ui.label(egui::RichText::new(confidence).weak()).on_hover_text(hover);
+                        
+                        if !sentence_match.sentence.is_empty() {
+                            let (icon, hover) = if is_locked {
+                                ("🔒", "Locked: re-parses keep this sentence's facts. Click to unlock.")
+                            } else {
+                                ("🔓", "Lock this parse so later database or pattern changes never alter it")
+                            };
+                            if ui.small_button(icon).on_hover_text(hover).clicked() {
+                                toggled_lock = Some((sentence_match.sentence.clone(), is_locked));
+                            }
+                        }
+                    });
                     
                     ui.add_space(5.0);
                     
@@ -332,6 +705,7 @@ impl PrologApp {
                             if word_idx < skip_until_idx {
                                 continue;
                             }
+                            let resolution = resolutions[word_idx].clone();
                             
                             if let Some(highlight) = sentence_match.highlights.iter()
                                 .find(|h| h.word_index == word_idx) {
@@ -357,13 +731,29 @@ impl PrologApp {
                                 }
                                 
                                 let display_text = if highlight.token_type == crate::app::interactive_parser::TokenType::Greedy {
-                                    let word_count = highlight.word.split('_').count();
-                                    skip_until_idx = word_idx + word_count;
+                                    skip_until_idx = highlight.word_end;
                                     &highlight.word 
                                 } else {
                                     word 
                                 };
                                 
+                                let editing = self.interactive_parser.editing_capture.as_mut()
+                                    .filter(|edit| edit.match_idx == match_idx && edit.word_idx == word_idx);
+                                if let Some(edit) = editing {
+                                    let response = ui.add(
+                                        egui::TextEdit::singleline(&mut edit.text)
+                                            .desired_width(120.0)
+                                            .hint_text(format!("${}", highlight.capture_index)),
+                                    );
+                                    response.request_focus();
+                                    if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                                        self.interactive_parser.editing_capture = None;
+                                    } else if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                                        edited_capture = Some(edit.text.clone());
+                                    }
+                                    continue;
+                                }
+                                
                                 let label_text = format!("{}(${})", display_text, highlight.capture_index);
                                 
                                 let button = egui::Button::new(
@@ -374,7 +764,10 @@ impl PrologApp {
                                 .fill(egui::Color32::from_rgb(40, 40, 40))
                                 .stroke(egui::Stroke::NONE);
                                 
-                                let response = ui.add(button);
+                                let mut response = ui.add(button);
+                                if !is_locked {
+                                    response = response.on_hover_text("Click to reassign, double-click to type a value");
+                                }
                                 
                                 if is_selected {
                                     ui.painter().rect_stroke(
@@ -384,11 +777,20 @@ impl PrologApp {
                                     );
                                 }
                                 
-                                if response.hovered() {
+                                if response.hovered() && !is_locked {
                                     ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
                                 }
                                 
-                                if response.clicked() {
+                                if response.double_clicked() && !is_locked {
+                                    self.interactive_parser.dragging_highlight = None;
+                                    self.interactive_parser.temp_selected_word = None;
+                                    self.interactive_parser.editing_capture = Some(CaptureEdit {
+                                        match_idx,
+                                        word_idx,
+                                        text: highlight.word.replace('_', " "),
+                                        error: None,
+                                    });
+                                } else if response.clicked() && !is_locked {
                                     if is_selected {
                                         self.interactive_parser.dragging_highlight = None;
                                         self.interactive_parser.temp_selected_word = None;
@@ -397,32 +799,239 @@ impl PrologApp {
                                         self.interactive_parser.temp_selected_word = None;
                                     }
                                 }
+                            } else if resolution.is_some() {
+                                ui.label(egui::RichText::new(word).color(PRONOUN_COLOR));
                             } else {
                                 ui.label(word);
                             }
+                            
+                            // The pronoun the word stands for, clickable to pick another antecedent
+                            if let Some(resolution) = resolution {
+                                let label = egui::RichText::new(format!("↩{}", resolution.pronoun)).color(PRONOUN_COLOR).small();
+                                if is_locked {
+                                    ui.label(label).on_hover_text(format!("Resolved from '{}'", resolution.pronoun));
+                                } else {
+                                    ui.menu_button(label, |ui| {
+                                        ui.label(format!("'{}' stands for:", resolution.pronoun));
+                                        for candidate in &resolution.candidates {
+                                            let current = *candidate == resolution.antecedent;
+                                            if ui.selectable_label(current, candidate).clicked() {
+                                                if !current {
+                                                    picked_antecedent = Some((sentence_match.sentence.clone(), resolution.word_index, candidate.clone()));
+                                                }
+                                                ui.close_menu();
+                                            }
+                                        }
+                                    }).response.on_hover_text(format!("Resolved from '{}'. Click to pick another antecedent", resolution.pronoun));
+                                }
+                            }
                         }
                     });
                     
+                    if let Some(error) = self.interactive_parser.editing_capture.as_ref()
+                        .filter(|edit| edit.match_idx == match_idx)
+                        .and_then(|edit| edit.error.as_ref()) {
+                        ui.colored_label(egui::Color32::from_rgb(220, 80, 80), error);
+                    }
+                    
                     ui.add_space(5.0);
                     
                     ui.label(egui::RichText::new("Output:")
                         .italics()
                         .color(egui::Color32::from_rgb(150, 150, 150)));
-                    ui.monospace(&sentence_match.generated_output);
+                    if let Some(entity) = entity_inspector::clickable_terms(ui, &sentence_match.generated_output) {
+                        self.entity_inspector.open(entity);
+                    }
+                    
+                    if !sentence_match.alternatives.is_empty() && !is_locked {
+                        ui.add_space(5.0);
+                        let title = format!("⇄ {} alternative interpretations", sentence_match.alternatives.len());
+                        ui.collapsing(title, |ui| {
+                            for (alternative_idx, alternative) in sentence_match.alternatives.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    if ui.small_button("Use").on_hover_text("Parse these words with this pattern instead").clicked() {
+                                        chosen_alternative = Some((match_idx, alternative_idx));
+                                    }
+                                    ui.label(egui::RichText::new(&alternative.pattern_name)
+                                        .color(egui::Color32::from_rgb(100, 150, 200)));
+                                    ui.label(egui::RichText::new(format!("{:.0}%", alternative.confidence * 100.0)).weak());
+                                });
+                                ui.monospace(&alternative.generated_output);
+                            }
+                        });
+                    }
                 });
+                
+                if is_focused {
+                    ui.painter().rect_stroke(
+                        group.response.rect,
+                        3.0,
+                        egui::Stroke::new(2.0, egui::Color32::from_rgb(100, 150, 200)),
+                    );
+                    if self.scroll_to_focused_source {
+                        group.response.scroll_to_me(Some(egui::Align::Center));
+                        self.scroll_to_focused_source = false;
+                    }
+                }
             });
             
             ui.add_space(10.0);
         }
+        
+        for question in &self.interactive_parser.questions {
+            ui.group(|ui| {
+                ui.set_min_width(ui.available_width() - 24.0);
+                ui.label(egui::RichText::new(format!("❓ {}", question.sentence))
+                    .strong()
+                    .color(egui::Color32::from_rgb(100, 150, 200)));
+                
+                match &question.query {
+                    Some(query) => {
+                        ui.horizontal(|ui| {
+                            ui.monospace(format!("?- {}", query));
+                            if ui.small_button("➡").on_hover_text("Run this query in the query panel").clicked() {
+                                asked_query = Some(query.clone());
+                            }
+                        });
+                        ui.label(egui::RichText::new("Answer:")
+                            .italics()
+                            .color(egui::Color32::from_rgb(150, 150, 150)));
+                        for answer in &question.answers {
+                            ui.monospace(answer);
+                        }
+                    }
+                    None => {
+                        ui.colored_label(egui::Color32::from_rgb(220, 160, 80), "No pattern matched this question");
+                    }
+                }
+            });
+            ui.add_space(10.0);
+        }
+        
+        if let Some(query) = asked_query {
+            self.query_text = query;
+            self.execute_query();
+        }
+        
+        match toggled_lock {
+            Some((sentence, false)) => {
+                self.interactive_parser.lock(&sentence);
+                self.save_corrections();
+            }
+            Some((sentence, true)) => {
+                self.interactive_parser.unlock(&sentence);
+                self.save_corrections();
+                self.update_parsed_output();
+            }
+            None => {}
+        }
+        
+        if let Some((sentence, word_index, antecedent)) = picked_antecedent {
+            self.interactive_parser.override_antecedent(&sentence, word_index, antecedent);
+            self.save_corrections();
+            self.update_parsed_output();
+        }
+        
+        if let Some((match_idx, alternative_idx)) = chosen_alternative {
+            match self.interactive_parser.choose_alternative(match_idx, alternative_idx) {
+                Ok(_) => {
+                    self.save_corrections();
+                    self.rebuild_parsed_output_from_interactive();
+                }
+                Err(e) => self.query_results = format!("// Error choosing an alternative: {}", e),
+            }
+        }
+        
+        if let Some(text) = edited_capture
+            && let Some(edit) = self.interactive_parser.editing_capture.take() {
+            let locale = self.database_snapshot.locale;
+            match self.interactive_parser.set_capture(edit.match_idx, edit.word_idx, &text, locale) {
+                Ok(_) => {
+                    self.save_corrections();
+                    self.rebuild_parsed_output_from_interactive();
+                }
+                Err(error) => {
+                    self.interactive_parser.editing_capture = Some(CaptureEdit {
+                        text,
+                        error: Some(error),
+                        ..edit
+                    });
+                }
+            }
+        }
+    }
+    
+    /// Lets the open document leave out pattern tag groups. The choice is
+    /// saved with its corrections, without touching the shared database.
+    fn show_pattern_sets_menu(&mut self, ui: &mut egui::Ui) {
+        let tags: Vec<String> = self.database_snapshot.pattern_tags().into_iter().map(str::to_string).collect();
+        let mut changed = false;
+        ui.menu_button("🏷 Pattern sets", |ui| {
+            if tags.is_empty() {
+                ui.label("No pattern has tags yet. Add them in the Database Editor.");
+            }
+            for tag in &tags {
+                let mut enabled = !self.interactive_parser.disabled_tags.contains(tag);
+                if ui.checkbox(&mut enabled, tag).changed() {
+                    if enabled {
+                        self.interactive_parser.disabled_tags.remove(tag);
+                    } else {
+                        self.interactive_parser.disabled_tags.insert(tag.clone());
+                    }
+                    changed = true;
+                }
+            }
+        })
+        .response
+        .on_hover_text("Choose the pattern groups used for this document");
+        
+        if changed {
+            self.save_corrections();
+            self.update_parsed_output();
+        }
+    }
+    
+    /// Replaces the input with the contents of `path` and restores the locks
+    /// and corrections saved for it.
+    fn open_input_file(&mut self, path: PathBuf) -> Result<(), String> {
+        let text = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        self.interactive_parser.load_corrections(&path)?;
+        self.input_text = text;
+        self.input_path = Some(path);
+        self.update_parsed_output();
+        Ok(())
+    }
+    
+    /// Adds `text` to the end of the input on a line of its own and parses it.
+    fn append_input(&mut self, text: &str) {
+        if !self.input_text.is_empty() && !self.input_text.ends_with('\n') {
+            self.input_text.push('\n');
+        }
+        self.input_text.push_str(text.trim());
+        self.input_text.push('\n');
+        self.update_parsed_output();
+    }
+    
+    /// Saves the locks and corrections next to the open input file, if any.
+    fn save_corrections(&mut self) {
+        let Some(path) = &self.input_path else {
+            return;
+        };
+        if let Err(e) = self.interactive_parser.save_corrections(path) {
+            self.query_results = format!("// Error saving corrections: {}", e);
+        }
     }
     
     fn update_parsed_output(&mut self) {
+        self.fact_edit_history.clear();
+        crash_report::record_input(&self.input_text, self.input_path.as_deref());
+        
         if self.input_text.is_empty() {
             self.parsed_output = "// Parsed Prolog code will appear here...".to_string();
             self.interactive_parser.clear();
             
             let mut new_engine = QueryEngine::new();
-            if let Err(e) = new_engine.load_config_file("query_config.txt") {
+            if let Err(e) = new_engine.load_config_file(data_dir::path(QUERY_CONFIG_PATH)) {
                 eprintln!("Note: Could not load query_config.txt: {}", e);
             }
             self.query_engine = new_engine;
@@ -431,7 +1040,192 @@ impl PrologApp {
             let parse_result = parser::parse_input(self, &input);
             self.parsed_output = parse_result;
             
-            self.query_engine.load_facts_from_output(&self.parsed_output);
+            let errors = self.query_engine.load_facts_from_output(&self.parsed_output);
+            for error in errors {
+                self.parsed_output.push_str(&format!("\n// WARNING: {}", error));
+            }
+            self.answer_questions();
+        }
+    }
+    
+    /// Answers the input's questions from the facts parsed out of it.
+    fn answer_questions(&mut self) {
+        for question in &mut self.interactive_parser.questions {
+            question.answer(&self.query_engine);
+        }
+    }
+    
+    /// Runs one automation command. Returns the query results for `query`,
+    /// the parsed output for `output` and a short status otherwise.
+    pub fn run_command(&mut self, command: AppCommand) -> Result<String, String> {
+        match command {
+            AppCommand::LoadText(text) => {
+                self.input_text = text;
+                self.input_path = None;
+                self.update_parsed_output();
+                Ok(format!("Parsed {} matches", self.interactive_parser.matches.len()))
+            }
+            AppCommand::Open(path) => {
+                self.open_input_file(PathBuf::from(&path))?;
+                self.open_path_text = path;
+                Ok(format!("Parsed {} matches", self.interactive_parser.matches.len()))
+            }
+            AppCommand::Parse => {
+                self.update_parsed_output();
+                Ok(format!("Parsed {} matches", self.interactive_parser.matches.len()))
+            }
+            AppCommand::Query(query) => {
+                self.query_text = query;
+                self.execute_query();
+                Ok(self.query_results.clone())
+            }
+            AppCommand::Accept(number) => {
+                let sentence = self.match_sentence(number)?;
+                self.interactive_parser.lock(&sentence);
+                self.save_corrections();
+                Ok(format!("Locked: {}", sentence))
+            }
+            AppCommand::Unlock(number) => {
+                let sentence = self.match_sentence(number)?;
+                self.interactive_parser.unlock(&sentence);
+                self.save_corrections();
+                self.update_parsed_output();
+                Ok(format!("Unlocked: {}", sentence))
+            }
+            AppCommand::Output => Ok(self.parsed_output.clone()),
+        }
+    }
+    
+    /// Sentence of the `number`th match in the Parsing panel, counting from 1.
+    fn match_sentence(&self, number: usize) -> Result<String, String> {
+        number
+            .checked_sub(1)
+            .and_then(|idx| self.interactive_parser.matches.get(idx))
+            .map(|sentence_match| sentence_match.sentence.clone())
+            .ok_or(format!("There is no match {}", number))
+    }
+    
+    /// Runs a script of commands (see `commands::parse_script`), stopping at
+    /// the first that fails. Returns each command's result.
+    pub fn run_script(&mut self, script: &str) -> Result<Vec<String>, String> {
+        commands::parse_script(script)?
+            .into_iter()
+            .map(|command| self.run_command(command))
+            .collect()
+    }
+    
+    fn show_command_palette(&mut self, ctx: &egui::Context) {
+        let Some(command_line) = &mut self.command_line else {
+            return;
+        };
+        
+        let mut submitted = false;
+        egui::Window::new("Command Palette")
+            .collapsible(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(command_line)
+                        .hint_text("load | open | parse | query | accept N | unlock N | output")
+                        .desired_width(400.0),
+                );
+                response.request_focus();
+                submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                
+                if !self.command_output.is_empty() {
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        ui.monospace(&self.command_output);
+                    });
+                }
+            });
+        
+        if submitted {
+            let line = std::mem::take(command_line);
+            self.command_output = match AppCommand::parse(&line).and_then(|command| self.run_command(command)) {
+                Ok(output) => output,
+                Err(e) => format!("Error: {}", e),
+            };
+        }
+    }
+    
+    /// Offers to bring back the input, and the database with its unsaved
+    /// edits, from before the previous run crashed.
+    fn show_crash_dialog(&mut self, ctx: &egui::Context) {
+        let Some(report) = self.crash_report.take() else {
+            return;
+        };
+        
+        let mut restore_input = false;
+        let mut restore_database = false;
+        let mut dismiss = false;
+        egui::Window::new("💥 The app crashed last time")
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(&report.message);
+                ui.collapsing("Backtrace", |ui| {
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        ui.monospace(&report.backtrace);
+                    });
+                });
+                if report.database_poisoned {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(220, 160, 80),
+                        "⚠ An edit to the database had failed before the crash, so the saved database may be half changed",
+                    );
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Restore input").clicked() {
+                        restore_input = true;
+                    }
+                    if report.database_path.is_some()
+                        && ui
+                            .button("Restore input and database")
+                            .on_hover_text("Load the database as it was in memory, unsaved edits included")
+                            .clicked()
+                    {
+                        restore_input = true;
+                        restore_database = true;
+                    }
+                    if ui.button("Dismiss").clicked() {
+                        dismiss = true;
+                    }
+                });
+            });
+        
+        if !restore_input && !dismiss {
+            self.crash_report = Some(report);
+            return;
+        }
+        
+        if restore_database && let Some(path) = &report.database_path {
+            match Database::new(path) {
+                Ok(recovered) => {
+                    if let Ok(mut write_database) = self.database.write() {
+                        write_database.replace(recovered);
+                    }
+                }
+                Err(e) => self.query_results = format!("// Error restoring database: {}", e),
+            }
+        }
+        if restore_input {
+            self.input_text = report.input_text;
+            self.open_path_text = report
+                .input_path
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default();
+            self.input_path = report.input_path;
+            self.update_parsed_output();
+        }
+        crash_report::discard_crash_report();
+    }
+    
+    /// Points `database_snapshot` at the current database. While an edit is
+    /// being written the previous snapshot is kept, so a parse never waits.
+    pub fn refresh_database_snapshot(&mut self) {
+        if let Some(snapshot) = self.database.snapshot() {
+            self.priority_weights = parser::pattern_matcher::priority_weights(&snapshot);
+            self.database_snapshot = snapshot;
         }
     }
     
@@ -441,24 +1235,57 @@ impl PrologApp {
         }
         
         let mut output_lines = Vec::new();
+        let directives = parser::directives_output(&self.interactive_parser.directives);
+        if !directives.is_empty() {
+            output_lines.push(directives);
+        }
         
         for sentence_match in &self.interactive_parser.matches {
             output_lines.push(format!("// PATTERN: {}", sentence_match.pattern_name));
-            output_lines.push(sentence_match.generated_output.clone());
+            if self.interactive_parser.is_hidden(sentence_match) {
+                let facts: Vec<String> = sentence_match.generated_output.lines().map(str::to_string).collect();
+                output_lines.extend(parser::hidden_facts(&facts, sentence_match.confidence));
+            } else {
+                output_lines.push(parser::confidence_comment(sentence_match.confidence));
+                output_lines.push(sentence_match.generated_output.clone());
+            }
         }
         
         self.parsed_output = output_lines.join("\n");
         
         self.query_engine.load_facts_from_output(&self.parsed_output);
+        self.answer_questions();
     }
     
-    fn execute_query(&mut self) {
-        if self.query_text.trim().is_empty() {
-            self.query_results = "// Query results will appear here...".to_string();
-            return;
+    /// Counts the reading of every single-word `<Type>` capture in the current
+    /// parse, then re-ranks lookups. Returns how many readings were counted.
+    fn learn_word_frequencies(&mut self) -> usize {
+        let Ok(mut write_database) = self.database.write() else {
+            return 0;
+        };
+
+        let mut learned = 0;
+        for sentence_match in &self.interactive_parser.matches {
+            for highlight in &sentence_match.highlights {
+                let Some(word_type) = highlight.token_type.word_type() else {
+                    continue;
+                };
+                if highlight.word_end != highlight.word_index + 1 {
+                    continue;
+                }
+                if write_database.record_reading(&sentence_match.words[highlight.word_index], &word_type) {
+                    learned += 1;
+                }
+            }
         }
-        
+
+        write_database.rebuild_index();
+        learned
+    }
+    
+    fn build_query_engine(&self) -> QueryEngine {
         let mut query_engine = QueryEngine::new();
+        self.settings.apply_to_engine(&mut query_engine);
         
         let has_fact_lines = self
             .parsed_output
@@ -472,6 +1299,75 @@ impl PrologApp {
             query_engine.load_facts_from_output(&self.parsed_output);
         }
         
+        query_engine
+    }
+    
+    /// Runs the saved report queries against the current knowledge base and
+    /// appends the summary to `output_path`.
+    pub fn run_batch_report(&self, queries_path: &str, output_path: &str, batch_label: &str) -> Result<String, String> {
+        let mut query_engine = self.build_query_engine();
+        batch_report::run(&mut query_engine, queries_path, output_path, batch_label)
+    }
+    
+    /// Rewrites every line of the parsed output holding an edited fact, then
+    /// reloads the knowledge base from it. Each rewrite can be undone.
+    fn apply_fact_edit(&mut self, edit: FactEdit) {
+        match edit {
+            FactEdit::Undo => {
+                let Some(previous) = self.fact_edit_history.pop() else {
+                    return;
+                };
+                self.parsed_output = previous;
+            }
+            FactEdit::Rewrite(changes) => {
+                let changes: HashMap<String, Option<String>> = changes.into_iter().collect();
+                
+                let lines: Vec<String> = self
+                    .parsed_output
+                    .lines()
+                    .filter_map(|line| {
+                        let change = if line.trim().starts_with("//") {
+                            None
+                        } else {
+                            self.query_engine.parse_fact_public(line).and_then(|fact| changes.get(&fact.to_string()))
+                        };
+                        match change {
+                            None => Some(line.to_string()),
+                            Some(Some(new)) => Some(format!("{}.", new)),
+                            Some(None) => None,
+                        }
+                    })
+                    .collect();
+                
+                let previous = std::mem::replace(&mut self.parsed_output, lines.join("\n"));
+                self.fact_edit_history.push(previous);
+            }
+        }
+        
+        self.query_engine.load_facts_from_output(&self.parsed_output);
+        self.execute_query();
+    }
+    
+    /// Replaces the facts and the rules in the query executor with a snapshot's,
+    /// keeping the current queries so results can be compared.
+    fn restore_snapshot(&mut self, snapshot: Snapshot) {
+        self.parsed_output = snapshot.parsed_output;
+        self.query_engine = self.build_query_engine();
+        
+        let queries = self.query_text.lines().filter(|l| !l.contains(":-"));
+        self.query_text = snapshot.rules.iter().map(String::as_str).chain(queries).collect::<Vec<_>>().join("\n");
+        
+        self.execute_query();
+    }
+    
+    fn execute_query(&mut self) {
+        if self.query_text.trim().is_empty() {
+            self.query_results = "// Query results will appear here...".to_string();
+            return;
+        }
+        
+        let mut query_engine = self.build_query_engine();
+        
         let mut results = Vec::new();
         let mut errors = Vec::new();
         
@@ -500,6 +1396,15 @@ impl PrologApp {
                         errors.push(format!("// Error adding pattern: {}", e));
                     }
                 }
+            } else if let Some(outcome) = query_engine.update(line) {
+                match outcome {
+                    Ok(change) => {
+                        results.push(format!("// {}", change));
+                    }
+                    Err(e) => {
+                        errors.push(format!("// Error in '{}': {}", line, e));
+                    }
+                }
             } else if line.ends_with('.') && !line.contains('?') {
                 if let Some(fact) = query_engine.parse_fact_public(line) {
                     query_engine.add_fact(fact);
@@ -507,6 +1412,15 @@ impl PrologApp {
                 } else {
                     errors.push(format!("// Error parsing fact: {}", line));
                 }
+            } else if let Some(Ok(explanations)) = self.explain_queries.then(|| query_engine.query_with_trace(line)) {
+                results.push(format!("// Query: {}", line));
+                if explanations.is_empty() {
+                    results.push("// No results found.".to_string());
+                }
+                for explanation in explanations {
+                    results.push(explanation.answer);
+                    results.extend(explanation.steps.iter().map(|step| format!("//   {}", step)));
+                }
             } else {
                 match query_engine.query(line) {
                     Ok(query_results) => {
@@ -540,3 +1454,28 @@ impl PrologApp {
         self.query_results = output.join("\n");
     }
 }
+
+/// Whether a sentence match's words appear in `source`, ignoring case and punctuation.
+fn source_contains_words(source: &str, words: &[String]) -> bool {
+    let normalize = |text: &str| -> String {
+        text.split_whitespace()
+            .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+            .filter(|w| !w.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    
+    let needle = normalize(&words.join(" "));
+    !needle.is_empty() && normalize(source).contains(&needle)
+}
+
+/// Moves keyboard focus to the next parser panel text field, wrapping around.
+fn focus_next_panel(ctx: &egui::Context) {
+    let ids = PANEL_FOCUS_ORDER.map(egui::Id::new);
+    let focused = ctx.memory(|memory| memory.focused());
+    let next = ids
+        .iter()
+        .position(|id| Some(*id) == focused)
+        .map_or(0, |i| (i + 1) % ids.len());
+    ctx.memory_mut(|memory| memory.request_focus(ids[next]));
+}
@@ -0,0 +1,383 @@
+//! Step-through replay of the backtracking matcher: every token tried at
+//! every word of one sentence, and every choice undone, so a pattern author
+//! can see where and why a pattern stops matching.
+
+use std::ops::Range;
+
+use crate::app::{
+    database::Database,
+    parser::{
+        pattern_matcher::{MAX_TRACE_STEPS, MatchStep, PatternToken, parse_pattern, token_label},
+        sentence_body,
+    },
+};
+
+const MATCHED_COLOR: egui::Color32 = egui::Color32::from_rgb(60, 160, 60);
+const FAILED_COLOR: egui::Color32 = egui::Color32::from_rgb(200, 60, 60);
+const BACKTRACK_COLOR: egui::Color32 = egui::Color32::from_rgb(220, 140, 40);
+
+/// A sentence and pattern to trace, picked in the debugger tab.
+pub struct TraceRequest {
+    pub words: Vec<String>,
+    pub tokens: Vec<PatternToken>,
+}
+
+pub struct MatchDebugger {
+    sentence: String,
+    pattern_text: String,
+    /// Sentence words and pattern tokens of the shown trace.
+    words: Vec<String>,
+    tokens: Vec<PatternToken>,
+    steps: Vec<MatchStep>,
+    /// Words matched, `None` when the pattern failed; unset before the first trace.
+    result: Option<Option<usize>>,
+    /// Index into `steps` of the step shown.
+    current: usize,
+    scroll_to_current: bool,
+}
+
+impl MatchDebugger {
+    pub fn new() -> Self {
+        Self {
+            sentence: String::new(),
+            pattern_text: String::new(),
+            words: Vec::new(),
+            tokens: Vec::new(),
+            steps: Vec::new(),
+            result: None,
+            current: 0,
+            scroll_to_current: false,
+        }
+    }
+
+    /// Shows the trace of `request`, from its first step.
+    pub fn load_trace(
+        &mut self,
+        request: TraceRequest,
+        matched_len: Option<usize>,
+        steps: Vec<MatchStep>,
+    ) {
+        self.words = request.words;
+        self.tokens = request.tokens;
+        self.steps = steps;
+        self.result = Some(matched_len);
+        self.current = 0;
+        self.scroll_to_current = true;
+    }
+
+    /// Draws the debugger tab. Returns the sentence and pattern to trace when
+    /// the trace button was clicked.
+    pub fn show(&mut self, ctx: &egui::Context, database: &Database) -> Option<TraceRequest> {
+        let mut request = None;
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Matcher Debugger");
+            ui.label(
+                egui::RichText::new(
+                    "Replays how the matcher tries a pattern against the whole sentence. The sentence is matched as typed, without pronoun resolution, negation or question handling.",
+                )
+                .italics()
+                .color(egui::Color32::from_rgb(100, 100, 100))
+                .size(11.0),
+            );
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Pattern:");
+                egui::ComboBox::from_id_source("debugger_pattern")
+                    .selected_text("Pick from database...")
+                    .width(200.0)
+                    .show_ui(ui, |ui| {
+                        for pattern in &database.patterns {
+                            let label = format!("{} ({})", pattern.name, pattern.pattern);
+                            let selected = self.pattern_text == pattern.pattern;
+                            if ui.selectable_label(selected, label).clicked() {
+                                self.pattern_text = pattern.pattern.clone();
+                            }
+                        }
+                    });
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.pattern_text)
+                        .hint_text("<Noun> is a <Noun>")
+                        .desired_width(f32::INFINITY),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Sentence:");
+                let sentence = ui.add(
+                    egui::TextEdit::singleline(&mut self.sentence)
+                        .hint_text("Bear is a big animal.")
+                        .desired_width(ui.available_width() - 80.0),
+                );
+                let submitted =
+                    sentence.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("▶ Trace").clicked() || submitted {
+                    let sentence = database.locale.lowercase(self.sentence.trim());
+                    request = Some(TraceRequest {
+                        words: sentence_body(&sentence)
+                            .split_whitespace()
+                            .map(str::to_string)
+                            .collect(),
+                        tokens: parse_pattern(&database.expand_subpatterns(&self.pattern_text)),
+                    });
+                }
+            });
+
+            let Some(result) = self.result else {
+                return;
+            };
+            ui.add_space(5.0);
+            match result {
+                Some(matched_len) => ui.colored_label(
+                    MATCHED_COLOR,
+                    format!("✓ Matched all {} words in {} steps", matched_len, self.steps.len()),
+                ),
+                None => ui.colored_label(
+                    FAILED_COLOR,
+                    format!("✗ No match after {} steps", self.steps.len()),
+                ),
+            };
+            if self.steps.len() >= MAX_TRACE_STEPS {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "The trace stops at the first {} steps.",
+                        MAX_TRACE_STEPS
+                    ))
+                    .weak(),
+                );
+            }
+            if self.steps.is_empty() {
+                return;
+            }
+
+            ui.add_space(5.0);
+            self.show_controls(ui);
+            ui.add_space(5.0);
+
+            let step = self.steps[self.current];
+            let (token_focus, word_focus) = step_focus(&step);
+            let color = step_color(&step);
+            ui.horizontal_wrapped(|ui| {
+                ui.label(egui::RichText::new("Words:").strong());
+                for (idx, word) in self.words.iter().enumerate() {
+                    let mut text = egui::RichText::new(word).monospace();
+                    if word_focus.contains(&idx) {
+                        text = text.background_color(color.gamma_multiply(0.4));
+                    }
+                    ui.label(text);
+                }
+            });
+            ui.horizontal_wrapped(|ui| {
+                ui.label(egui::RichText::new("Pattern:").strong());
+                for (idx, token) in self.tokens.iter().enumerate() {
+                    let mut text = egui::RichText::new(token_label(token)).monospace();
+                    if token_focus == Some(idx) {
+                        text = text.background_color(color.gamma_multiply(0.4));
+                    }
+                    ui.label(text);
+                }
+            });
+            ui.add_space(5.0);
+            ui.label(
+                egui::RichText::new(describe_step(&step, &self.words, &self.tokens))
+                    .strong()
+                    .color(color),
+            );
+            ui.separator();
+
+            let mut clicked = None;
+            egui::ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .show(ui, |ui| {
+                    for (idx, step) in self.steps.iter().enumerate() {
+                        let text = egui::RichText::new(format!(
+                            "{:>5}  {}",
+                            idx + 1,
+                            describe_step(step, &self.words, &self.tokens)
+                        ))
+                        .monospace()
+                        .color(step_color(step));
+                        let response = ui.selectable_label(idx == self.current, text);
+                        if response.clicked() {
+                            clicked = Some(idx);
+                        }
+                        if idx == self.current && self.scroll_to_current {
+                            response.scroll_to_me(Some(egui::Align::Center));
+                            self.scroll_to_current = false;
+                        }
+                    }
+                });
+            if let Some(idx) = clicked {
+                self.current = idx;
+            }
+        });
+
+        request
+    }
+
+    /// Buttons and slider that move through the steps, backwards as well.
+    fn show_controls(&mut self, ui: &mut egui::Ui) {
+        let last = self.steps.len() - 1;
+        let previous = self.current;
+        ui.horizontal(|ui| {
+            if ui.button("⏮").on_hover_text("First step").clicked() {
+                self.current = 0;
+            }
+            if ui.button("◀").on_hover_text("Previous step").clicked() {
+                self.current = self.current.saturating_sub(1);
+            }
+            if ui.button("▶").on_hover_text("Next step").clicked() {
+                self.current = (self.current + 1).min(last);
+            }
+            if ui.button("⏭").on_hover_text("Last step").clicked() {
+                self.current = last;
+            }
+            let next_backtrack = self.steps[self.current + 1..]
+                .iter()
+                .position(|step| matches!(step, MatchStep::Backtracked { .. }))
+                .map(|offset| self.current + 1 + offset);
+            if ui
+                .add_enabled(next_backtrack.is_some(), egui::Button::new("⏩ Next backtrack"))
+                .clicked()
+                && let Some(idx) = next_backtrack
+            {
+                self.current = idx;
+            }
+            ui.add(
+                egui::Slider::new(&mut self.current, 0..=last)
+                    .custom_formatter(|n, _| format!("{}", n as usize + 1))
+                    .text(format!("of {}", self.steps.len())),
+            );
+        });
+        if self.current != previous {
+            self.scroll_to_current = true;
+        }
+    }
+}
+
+/// The token a step is about, and the words it covers.
+fn step_focus(step: &MatchStep) -> (Option<usize>, Range<usize>) {
+    match *step {
+        MatchStep::Tried { token, word, .. }
+        | MatchStep::LeftOut { token, word }
+        | MatchStep::Backtracked { token, word } => (Some(token), word..word + 1),
+        MatchStep::Took { token, start, end } => (Some(token), start..end),
+        MatchStep::WordsEnded { token, .. } => (Some(token), 0..0),
+        MatchStep::TokensEnded { word, .. } => (None, word..usize::MAX),
+    }
+}
+
+fn step_color(step: &MatchStep) -> egui::Color32 {
+    match step {
+        MatchStep::Tried { matched: true, .. }
+        | MatchStep::WordsEnded { matched: true, .. }
+        | MatchStep::TokensEnded { matched: true, .. } => MATCHED_COLOR,
+        MatchStep::Tried { matched: false, .. }
+        | MatchStep::WordsEnded { matched: false, .. }
+        | MatchStep::TokensEnded { matched: false, .. } => FAILED_COLOR,
+        MatchStep::Backtracked { .. } => BACKTRACK_COLOR,
+        MatchStep::Took { .. } | MatchStep::LeftOut { .. } => {
+            egui::Color32::from_rgb(120, 120, 200)
+        }
+    }
+}
+
+/// One line of the step log.
+fn describe_step(step: &MatchStep, words: &[String], tokens: &[PatternToken]) -> String {
+    let token = |idx: usize| tokens.get(idx).map(token_label).unwrap_or_default();
+    let word = |idx: usize| words.get(idx).cloned().unwrap_or_default();
+    match *step {
+        MatchStep::Tried {
+            token: t,
+            word: w,
+            matched,
+        } => format!(
+            "{} at '{}' (word {}): {}",
+            token(t),
+            word(w),
+            w + 1,
+            if matched { "matches" } else { "does not match" }
+        ),
+        MatchStep::Took { token: t, start, end } => format!(
+            "{} takes '{}' ({} of them)",
+            token(t),
+            words[start..end].join(" "),
+            end - start
+        ),
+        MatchStep::LeftOut { token: t, word: w } => {
+            format!("{} left out before '{}'", token(t), word(w))
+        }
+        MatchStep::Backtracked { token: t, word: w } => format!(
+            "Backtrack: the rest failed, so {} at '{}' gives up its choice",
+            token(t),
+            word(w)
+        ),
+        MatchStep::WordsEnded {
+            token: t,
+            matched: true,
+        } => format!("Words ran out at {}; the tokens left are optional", token(t)),
+        MatchStep::WordsEnded {
+            token: t,
+            matched: false,
+        } => format!("Words ran out at {}, which needs a word", token(t)),
+        MatchStep::TokensEnded {
+            word: w,
+            matched: true,
+        } => format!("Pattern ended after all {} words: match", w),
+        MatchStep::TokensEnded {
+            word: w,
+            matched: false,
+        } => format!("Pattern ended with '{}' left over", words[w..].join(" ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_are_described_with_their_token_and_words() {
+        let words: Vec<String> = "big brown bear is here"
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        let tokens = parse_pattern("<Adjective>+ <Noun> is");
+        let describe = |step: MatchStep| describe_step(&step, &words, &tokens);
+
+        assert_eq!(
+            describe(MatchStep::Tried {
+                token: 1,
+                word: 2,
+                matched: true
+            }),
+            "<Noun> at 'bear' (word 3): matches"
+        );
+        assert_eq!(
+            describe(MatchStep::Took {
+                token: 0,
+                start: 0,
+                end: 2
+            }),
+            "<Adjective>+ takes 'big brown' (2 of them)"
+        );
+        assert_eq!(
+            describe(MatchStep::Backtracked { token: 0, word: 0 }),
+            "Backtrack: the rest failed, so <Adjective>+ at 'big' gives up its choice"
+        );
+        assert_eq!(
+            describe(MatchStep::TokensEnded {
+                word: 4,
+                matched: false
+            }),
+            "Pattern ended with 'here' left over"
+        );
+        assert_eq!(
+            step_focus(&MatchStep::Took {
+                token: 0,
+                start: 0,
+                end: 2
+            }),
+            (Some(0), 0..2)
+        );
+    }
+}
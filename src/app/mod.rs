@@ -1,8 +1,26 @@
+mod annotation_merge;
+mod batch_report;
+mod clipboard_watcher;
+pub mod commands;
+mod coverage;
+pub mod crash_report;
+pub mod data_dir;
 pub mod database;
 mod database_editor;
+mod entity_inspector;
+mod generator;
 mod interactive_parser;
 mod interface;
+mod match_debugger;
+pub mod morphology;
+mod notifications;
 pub mod parser;
+mod predicate_browser;
 pub mod query_engine;
+mod random;
+mod settings;
+mod snapshots;
+mod training_data;
+mod updates;
 
 pub use interface::PrologApp;
@@ -0,0 +1,448 @@
+/// English inflection: plurals, singulars and verb forms by regular rules,
+/// with irregular words looked up in exception tables stored in the database.
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::database::{Number, Tense, WordType};
+
+/// Past forms of an irregular verb, and its third-person present when that is
+/// irregular too (`be` -> `is`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IrregularVerb {
+    pub past: String,
+    pub past_participle: String,
+    #[serde(default)]
+    pub third_person: Option<String>,
+}
+
+/// Words the regular rules get wrong, keyed by lemma.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct MorphologyExceptions {
+    /// Singular -> plural, e.g. `child` -> `children`.
+    #[serde(default)]
+    pub plurals: BTreeMap<String, String>,
+    #[serde(default)]
+    pub verbs: BTreeMap<String, IrregularVerb>,
+}
+
+impl MorphologyExceptions {
+    /// The common English irregulars, used for databases that have no table yet.
+    pub fn english() -> Self {
+        let plurals = [
+            ("child", "children"),
+            ("person", "people"),
+            ("man", "men"),
+            ("woman", "women"),
+            ("mouse", "mice"),
+            ("goose", "geese"),
+            ("foot", "feet"),
+            ("tooth", "teeth"),
+            ("ox", "oxen"),
+            ("sheep", "sheep"),
+            ("fish", "fish"),
+            ("deer", "deer"),
+            ("leaf", "leaves"),
+            ("wolf", "wolves"),
+            ("knife", "knives"),
+            ("life", "lives"),
+            ("wife", "wives"),
+            ("half", "halves"),
+            ("potato", "potatoes"),
+            ("tomato", "tomatoes"),
+            ("hero", "heroes"),
+        ]
+        .into_iter()
+        .map(|(singular, plural)| (singular.to_string(), plural.to_string()))
+        .collect();
+
+        let verbs = [
+            ("be", "was", "been", Some("is")),
+            ("have", "had", "had", Some("has")),
+            ("do", "did", "done", None),
+            ("go", "went", "gone", None),
+            ("run", "ran", "run", None),
+            ("eat", "ate", "eaten", None),
+            ("see", "saw", "seen", None),
+            ("take", "took", "taken", None),
+            ("give", "gave", "given", None),
+            ("make", "made", "made", None),
+            ("come", "came", "come", None),
+            ("know", "knew", "known", None),
+            ("think", "thought", "thought", None),
+            ("find", "found", "found", None),
+            ("catch", "caught", "caught", None),
+            ("buy", "bought", "bought", None),
+            ("bring", "brought", "brought", None),
+            ("write", "wrote", "written", None),
+            ("read", "read", "read", None),
+            ("swim", "swam", "swum", None),
+            ("fly", "flew", "flown", None),
+            ("sing", "sang", "sung", None),
+            ("drink", "drank", "drunk", None),
+            ("sleep", "slept", "slept", None),
+            ("feel", "felt", "felt", None),
+            ("get", "got", "gotten", None),
+            ("say", "said", "said", None),
+        ]
+        .into_iter()
+        .map(|(lemma, past, past_participle, third_person)| {
+            (
+                lemma.to_string(),
+                IrregularVerb {
+                    past: past.to_string(),
+                    past_participle: past_participle.to_string(),
+                    third_person: third_person.map(str::to_string),
+                },
+            )
+        })
+        .collect();
+
+        Self { plurals, verbs }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerbForm {
+    ThirdPerson,
+    Past,
+    PastParticiple,
+    Gerund,
+}
+
+impl VerbForm {
+    pub const ALL: [VerbForm; 4] = [
+        VerbForm::ThirdPerson,
+        VerbForm::Past,
+        VerbForm::PastParticiple,
+        VerbForm::Gerund,
+    ];
+
+    /// The tense the form speaks of without an auxiliary: "eaten" is past
+    /// and "eating" present.
+    pub fn tense(self) -> Tense {
+        match self {
+            VerbForm::ThirdPerson | VerbForm::Gerund => Tense::Present,
+            VerbForm::Past | VerbForm::PastParticiple => Tense::Past,
+        }
+    }
+}
+
+/// An inflection applied to a lemma.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Inflection {
+    Plural,
+    Verb(VerbForm),
+}
+
+impl Inflection {
+    pub const ALL: [Inflection; 5] = [
+        Inflection::Plural,
+        Inflection::Verb(VerbForm::ThirdPerson),
+        Inflection::Verb(VerbForm::Past),
+        Inflection::Verb(VerbForm::PastParticiple),
+        Inflection::Verb(VerbForm::Gerund),
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Inflection::Plural => "plural",
+            Inflection::Verb(VerbForm::ThirdPerson) => "3rd person",
+            Inflection::Verb(VerbForm::Past) => "past",
+            Inflection::Verb(VerbForm::PastParticiple) => "past participle",
+            Inflection::Verb(VerbForm::Gerund) => "gerund",
+        }
+    }
+
+    pub fn apply(self, lemma: &str, exceptions: &MorphologyExceptions) -> String {
+        match self {
+            Inflection::Plural => pluralize(lemma, exceptions),
+            Inflection::Verb(form) => conjugate(lemma, form, exceptions),
+        }
+    }
+}
+
+/// The inflected forms of a new word entry: the plural of a noun, the
+/// conjugations of a verb, nothing for other types.
+pub fn generate_forms(
+    lemma: &str,
+    word_type: &WordType,
+    exceptions: &MorphologyExceptions,
+) -> Vec<String> {
+    let mut forms = match word_type {
+        WordType::Noun => vec![pluralize(lemma, exceptions)],
+        WordType::Verb => VerbForm::ALL
+            .iter()
+            .map(|&form| conjugate(lemma, form, exceptions))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let mut seen = HashSet::new();
+    forms.retain(|form| form != lemma && seen.insert(form.clone()));
+    forms
+}
+
+/// The tense of `form` as a form of the verb `lemma`. The lemma itself is
+/// present, which settles verbs like "read" whose past is spelled the same.
+pub fn verb_form_tense(
+    lemma: &str,
+    form: &str,
+    exceptions: &MorphologyExceptions,
+) -> Option<Tense> {
+    if form == lemma {
+        return Some(Tense::Present);
+    }
+    VerbForm::ALL
+        .into_iter()
+        .find(|&verb_form| conjugate(lemma, verb_form, exceptions) == form)
+        .map(VerbForm::tense)
+}
+
+/// The tenses of the lemma and `forms` of a verb, for
+/// [`WordEntry::tenses`](crate::app::database::WordEntry::tenses).
+pub fn verb_tenses(
+    lemma: &str,
+    forms: &[String],
+    exceptions: &MorphologyExceptions,
+) -> BTreeMap<String, Tense> {
+    std::iter::once(lemma)
+        .chain(forms.iter().map(String::as_str))
+        .filter_map(|form| Some((form.to_string(), verb_form_tense(lemma, form, exceptions)?)))
+        .collect()
+}
+
+/// The number of `form` as a form of the noun `lemma`. The lemma itself is
+/// singular, which settles nouns like "sheep" whose plural is spelled the
+/// same.
+pub fn noun_form_number(
+    lemma: &str,
+    form: &str,
+    exceptions: &MorphologyExceptions,
+) -> Option<Number> {
+    if form == lemma {
+        Some(Number::Singular)
+    } else {
+        (pluralize(lemma, exceptions) == form).then_some(Number::Plural)
+    }
+}
+
+/// The numbers of the lemma and `forms` of a noun, for
+/// [`WordEntry::numbers`](crate::app::database::WordEntry::numbers). A noun
+/// inflects only for number, so every form other than the lemma is plural.
+pub fn noun_numbers(lemma: &str, forms: &[String]) -> BTreeMap<String, Number> {
+    std::iter::once((lemma.to_string(), Number::Singular))
+        .chain(
+            forms
+                .iter()
+                .filter(|form| *form != lemma)
+                .map(|form| (form.clone(), Number::Plural)),
+        )
+        .collect()
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+/// Ends in a consonant followed by `y`, as in `fly` but not `play`.
+fn ends_with_consonant_y(word: &str) -> bool {
+    let mut chars = word.chars().rev();
+    chars.next() == Some('y') && chars.next().is_some_and(|c| !is_vowel(c))
+}
+
+/// One-syllable words ending consonant-vowel-consonant double their last
+/// letter before `-ed`/`-ing`: `stop` -> `stopped`, `run` -> `running`.
+fn doubles_final_consonant(word: &str) -> bool {
+    let chars: Vec<char> = word.chars().collect();
+    let [.., a, b, c] = chars[..] else {
+        return false;
+    };
+    chars.iter().filter(|&&ch| is_vowel(ch)).count() == 1
+        && !is_vowel(a)
+        && is_vowel(b)
+        && !is_vowel(c)
+        && !matches!(c, 'w' | 'x' | 'y')
+}
+
+/// Whether `-s` needs an `e`: `box` -> `boxes`, `go` -> `goes`.
+fn takes_es(word: &str) -> bool {
+    ["s", "x", "z", "ch", "sh"].iter().any(|s| word.ends_with(s))
+}
+
+pub fn pluralize(noun: &str, exceptions: &MorphologyExceptions) -> String {
+    if let Some(plural) = exceptions.plurals.get(noun) {
+        plural.clone()
+    } else if takes_es(noun) {
+        format!("{}es", noun)
+    } else if ends_with_consonant_y(noun) {
+        format!("{}ies", &noun[..noun.len() - 1])
+    } else {
+        format!("{}s", noun)
+    }
+}
+
+pub fn singularize(noun: &str, exceptions: &MorphologyExceptions) -> String {
+    if let Some((singular, _)) = exceptions.plurals.iter().find(|(_, p)| *p == noun) {
+        return singular.clone();
+    }
+
+    if let Some(stem) = noun.strip_suffix("ies")
+        && stem.len() > 1
+    {
+        format!("{}y", stem)
+    } else if let Some(stem) = noun.strip_suffix("es")
+        && takes_es(stem)
+        && (stem.ends_with("ss") || !stem.ends_with('s'))
+    {
+        stem.to_string()
+    } else if let Some(stem) = noun.strip_suffix('s')
+        && !stem.ends_with('s')
+        && !stem.is_empty()
+    {
+        stem.to_string()
+    } else {
+        noun.to_string()
+    }
+}
+
+pub fn conjugate(verb: &str, form: VerbForm, exceptions: &MorphologyExceptions) -> String {
+    let irregular = exceptions.verbs.get(verb);
+
+    match form {
+        VerbForm::ThirdPerson => {
+            if let Some(third_person) = irregular.and_then(|v| v.third_person.clone()) {
+                third_person
+            } else if takes_es(verb) || verb.ends_with('o') {
+                format!("{}es", verb)
+            } else if ends_with_consonant_y(verb) {
+                format!("{}ies", &verb[..verb.len() - 1])
+            } else {
+                format!("{}s", verb)
+            }
+        }
+        VerbForm::Past | VerbForm::PastParticiple => {
+            if let Some(irregular) = irregular {
+                if form == VerbForm::Past {
+                    irregular.past.clone()
+                } else {
+                    irregular.past_participle.clone()
+                }
+            } else if verb.ends_with('e') {
+                format!("{}d", verb)
+            } else if ends_with_consonant_y(verb) {
+                format!("{}ied", &verb[..verb.len() - 1])
+            } else if let Some(last) = verb
+                .chars()
+                .last()
+                .filter(|_| doubles_final_consonant(verb))
+            {
+                format!("{}{}ed", verb, last)
+            } else {
+                format!("{}ed", verb)
+            }
+        }
+        VerbForm::Gerund => {
+            if let Some(stem) = verb.strip_suffix("ie") {
+                format!("{}ying", stem)
+            } else if verb.len() > 2
+                && verb.ends_with('e')
+                && !["ee", "ye", "oe"].iter().any(|s| verb.ends_with(s))
+            {
+                format!("{}ing", &verb[..verb.len() - 1])
+            } else if let Some(last) = verb
+                .chars()
+                .last()
+                .filter(|_| doubles_final_consonant(verb))
+            {
+                format!("{}{}ing", verb, last)
+            } else {
+                format!("{}ing", verb)
+            }
+        }
+    }
+}
+
+/// Possible lemmas of an inflected word, most likely first: irregular forms,
+/// then regular noun and verb endings undone. Callers keep the candidates
+/// their vocabulary knows.
+pub fn lemma_candidates(word: &str, exceptions: &MorphologyExceptions) -> Vec<String> {
+    let mut candidates = Vec::new();
+
+    for (lemma, verb) in &exceptions.verbs {
+        if verb.past == word
+            || verb.past_participle == word
+            || verb.third_person.as_deref() == Some(word)
+        {
+            candidates.push(lemma.clone());
+        }
+    }
+
+    candidates.push(singularize(word, exceptions));
+
+    for (suffix, replacements) in [
+        ("ies", &["y"][..]),
+        ("ied", &["y"][..]),
+        ("es", &[""][..]),
+        ("ed", &["", "e"][..]),
+        ("ing", &["", "e"][..]),
+        ("ying", &["ie"][..]),
+    ] {
+        let Some(stem) = word.strip_suffix(suffix) else {
+            continue;
+        };
+        for replacement in replacements {
+            candidates.push(format!("{}{}", stem, replacement));
+        }
+        // stopped -> stop, running -> run
+        let mut chars = stem.chars().rev();
+        if let (Some(last), Some(before)) = (chars.next(), chars.next())
+            && last == before
+            && !is_vowel(last)
+        {
+            candidates.push(stem[..stem.len() - last.len_utf8()].to_string());
+        }
+    }
+
+    let mut seen = HashSet::new();
+    candidates.retain(|c| !c.is_empty() && c != word && seen.insert(c.clone()));
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regular_and_irregular_inflection() {
+        let exceptions = MorphologyExceptions::english();
+
+        assert_eq!(pluralize("box", &exceptions), "boxes");
+        assert_eq!(pluralize("city", &exceptions), "cities");
+        assert_eq!(pluralize("day", &exceptions), "days");
+        assert_eq!(pluralize("child", &exceptions), "children");
+        assert_eq!(singularize("cities", &exceptions), "city");
+        assert_eq!(singularize("churches", &exceptions), "church");
+        assert_eq!(singularize("glass", &exceptions), "glass");
+        assert_eq!(singularize("horses", &exceptions), "horse");
+        assert_eq!(singularize("people", &exceptions), "person");
+
+        assert_eq!(conjugate("watch", VerbForm::ThirdPerson, &exceptions), "watches");
+        assert_eq!(conjugate("be", VerbForm::ThirdPerson, &exceptions), "is");
+        assert_eq!(conjugate("stop", VerbForm::Past, &exceptions), "stopped");
+        assert_eq!(conjugate("carry", VerbForm::Past, &exceptions), "carried");
+        assert_eq!(conjugate("go", VerbForm::PastParticiple, &exceptions), "gone");
+        assert_eq!(conjugate("make", VerbForm::Gerund, &exceptions), "making");
+        assert_eq!(conjugate("lie", VerbForm::Gerund, &exceptions), "lying");
+        assert_eq!(conjugate("see", VerbForm::Gerund, &exceptions), "seeing");
+        assert_eq!(conjugate("be", VerbForm::Gerund, &exceptions), "being");
+
+        assert_eq!(
+            generate_forms("run", &WordType::Verb, &exceptions),
+            vec!["runs", "ran", "running"]
+        );
+
+        let candidates = lemma_candidates("running", &exceptions);
+        assert!(candidates.contains(&"run".to_string()));
+        assert_eq!(lemma_candidates("went", &exceptions)[0], "go");
+    }
+}
@@ -0,0 +1,117 @@
+//! Desktop notifications for long jobs that finish while the window is in
+//! the background, so an ingestion can be left running. They are raised with
+//! the platform's own tool: `notify-send` on Linux, `osascript` on macOS and
+//! PowerShell on Windows. Without the tool nothing is shown.
+
+use std::process::Command;
+
+/// Where finished jobs wait in the context's memory until the next frame.
+const PENDING_ID: &str = "pending_notifications";
+
+/// Reports a finished job with a desktop notification if the window is not
+/// focused by the next frame; a focused window already shows the outcome.
+/// The check waits a frame because a job run on the UI thread only sees the
+/// focus from before it started. `outcome` says what the job did, with
+/// counts, or why it failed.
+pub fn job_finished(ctx: &egui::Context, job: &str, outcome: Result<&str, &str>) {
+    let notification = notification_text(job, outcome);
+    ctx.data_mut(|data| {
+        data.get_temp_mut_or_default::<Vec<(String, String)>>(egui::Id::new(PENDING_ID))
+            .push(notification)
+    });
+    ctx.request_repaint();
+}
+
+/// Raises the notifications of the jobs that finished last frame if the
+/// window is now in the background. Called once per frame.
+pub fn raise_pending(ctx: &egui::Context) {
+    let pending: Vec<(String, String)> =
+        ctx.data_mut(|data| data.remove_temp(egui::Id::new(PENDING_ID)).unwrap_or_default());
+    if pending.is_empty() || ctx.input(|i| i.viewport().focused) != Some(false) {
+        return;
+    }
+    for (title, body) in pending {
+        let Some(mut command) = notification_command(&title, &body) else {
+            return;
+        };
+        // Waited on off the UI thread so the process is reaped
+        std::thread::spawn(move || {
+            if let Err(e) = command.status() {
+                eprintln!("Note: Could not show a desktop notification: {}", e);
+            }
+        });
+    }
+}
+
+/// The title and body of the notification about a finished job.
+fn notification_text(job: &str, outcome: Result<&str, &str>) -> (String, String) {
+    match outcome {
+        Ok(summary) => (format!("✅ {} finished", job), summary.to_string()),
+        Err(e) => (format!("❌ {} failed", job), e.to_string()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn notification_command(title: &str, body: &str) -> Option<Command> {
+    let mut command = Command::new("notify-send");
+    command.arg("--app-name=Simple Prolog").arg(title).arg(body);
+    Some(command)
+}
+
+#[cfg(target_os = "macos")]
+fn notification_command(title: &str, body: &str) -> Option<Command> {
+    // Passed as arguments rather than spliced into the script, so quotes are safe
+    let mut command = Command::new("osascript");
+    command
+        .args(["-e", "on run argv"])
+        .args(["-e", "display notification (item 2 of argv) with title (item 1 of argv)"])
+        .args(["-e", "end run"])
+        .arg(title)
+        .arg(body);
+    Some(command)
+}
+
+#[cfg(target_os = "windows")]
+fn notification_command(title: &str, body: &str) -> Option<Command> {
+    const SCRIPT: &str = "Add-Type -AssemblyName System.Windows.Forms; \
+        $icon = New-Object System.Windows.Forms.NotifyIcon; \
+        $icon.Icon = [System.Drawing.SystemIcons]::Information; \
+        $icon.Visible = $true; \
+        $icon.ShowBalloonTip(5000, $env:NOTIFICATION_TITLE, $env:NOTIFICATION_BODY, 'Info'); \
+        Start-Sleep -Seconds 6; \
+        $icon.Dispose()";
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-WindowStyle", "Hidden", "-Command", SCRIPT])
+        .env("NOTIFICATION_TITLE", title)
+        .env("NOTIFICATION_BODY", body);
+    Some(command)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn notification_command(_title: &str, _body: &str) -> Option<Command> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifications_say_whether_the_job_succeeded() {
+        assert_eq!(
+            notification_text("Database save", Ok("12034 words saved")),
+            (
+                "✅ Database save finished".to_string(),
+                "12034 words saved".to_string()
+            )
+        );
+        assert_eq!(
+            notification_text("Batch report", Err("Failed to read report_queries.txt")),
+            (
+                "❌ Batch report failed".to_string(),
+                "Failed to read report_queries.txt".to_string()
+            )
+        );
+    }
+}
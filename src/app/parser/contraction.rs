@@ -0,0 +1,139 @@
+//! Contractions. "Isn't", "can't" or "john's" (for "john is") are not in the
+//! word database, so they would default to nouns and spoil the match. Before
+//! matching they are expanded into the words they stand for, from the
+//! contraction table of the database: "john isn't happy" -> "john is not
+//! happy".
+
+use crate::app::{
+    PrologApp,
+    database::{Contractions, Database, WordType},
+};
+
+use super::pattern_matcher::spelled_out_pattern_matches;
+
+/// The ending that is either a contracted "is" or a possessive.
+const IS_OR_POSSESSIVE: &str = "'s";
+
+/// Words after which an `'s` can only be a contracted "is": "john's a cat".
+const DETERMINERS: [&str; 3] = ["a", "an", "the"];
+
+/// What `word` stands for in `contractions`, if it is contracted. Curly
+/// apostrophes count as straight ones.
+fn expansion<'a>(word: &str, contractions: &'a Contractions) -> Option<(String, &'a str)> {
+    let word = word.replace('’', "'");
+    if let Some(long) = contractions.words.get(&word) {
+        return Some((String::new(), long));
+    }
+    contractions.endings.iter().find_map(|(ending, long)| {
+        word.strip_suffix(ending.as_str())
+            .filter(|host| !host.is_empty())
+            .map(|host| (host.to_string(), long.as_str()))
+    })
+}
+
+/// The words of a sentence with its contractions expanded, and for each of
+/// them the position of the word it came from. An `'s` ending is taken for
+/// "is" only when the next word cannot be what is owned, so "john's dog"
+/// stays a possessive.
+pub fn expand_contractions(words: &[String], database: &Database) -> (Vec<String>, Vec<usize>) {
+    let mut expanded = Vec::new();
+    let mut sources = Vec::new();
+    for (idx, word) in words.iter().enumerate() {
+        let is_ending = word.ends_with(IS_OR_POSSESSIVE) || word.ends_with("’s");
+        let contracted = expansion(word, &database.contractions).filter(|(host, _)| {
+            host.is_empty() || !is_ending || !may_be_owned(&words[idx + 1..], database)
+        });
+        let Some((host, long)) = contracted else {
+            expanded.push(word.clone());
+            sources.push(idx);
+            continue;
+        };
+        for part in std::iter::once(host.as_str())
+            .filter(|host| !host.is_empty())
+            .chain(long.split_whitespace())
+        {
+            expanded.push(part.to_string());
+            sources.push(idx);
+        }
+    }
+    (expanded, sources)
+}
+
+/// Whether the words after an `'s` may start what it owns: a noun, or an
+/// adjective before one, but not an article, a verb form or the end of the
+/// sentence.
+fn may_be_owned(rest: &[String], database: &Database) -> bool {
+    let types = |word: &String| -> Vec<WordType> {
+        database
+            .get_word_entries(word)
+            .map(|entries| entries.iter().map(|entry| entry.word_type.clone()).collect())
+            .unwrap_or_default()
+    };
+    let Some(next) = rest.first() else {
+        return true;
+    };
+    if DETERMINERS.contains(&next.as_str()) {
+        return false;
+    }
+    let next_types = types(next);
+    if next_types.is_empty() || next_types.contains(&WordType::Noun) {
+        return true;
+    }
+    // "john's happy" is an "is", "john's big dog" a possessive
+    next_types.contains(&WordType::Adjective)
+        && rest.get(1).is_some_and(|after| {
+            let after = types(after);
+            after.is_empty() || after.contains(&WordType::Noun)
+        })
+}
+
+/// Whether a pattern that spells out a contraction itself matches `words`;
+/// it is matched against the words as written, so they are not expanded.
+pub fn contraction_pattern_matches(app: &PrologApp, words: &[String]) -> bool {
+    let contractions = &app.database_snapshot.contractions;
+    spelled_out_pattern_matches(app, words, |literal| {
+        expansion(literal, contractions).is_some()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::database::fixtures::word;
+
+    #[test]
+    fn test_contractions_expand_but_possessives_stay() {
+        let mut database = Database {
+            contractions: Contractions::english(),
+            ..Database::default()
+        };
+        for (lemma, word_type, forms) in [
+            ("dog", WordType::Noun, vec![]),
+            ("big", WordType::Adjective, vec![]),
+            ("happy", WordType::Adjective, vec![]),
+            ("run", WordType::Verb, vec!["running"]),
+        ] {
+            database.words.push(word(lemma, word_type, &forms));
+        }
+        database.rebuild_index();
+
+        let expand = |sentence: &str| {
+            let words: Vec<String> = sentence.split_whitespace().map(str::to_string).collect();
+            let (expanded, sources) = expand_contractions(&words, &database);
+            (expanded.join(" "), sources)
+        };
+        assert_eq!(
+            expand("john isn't happy"),
+            ("john is not happy".to_string(), vec![0, 1, 1, 2])
+        );
+        assert_eq!(expand("cats can’t swim").0, "cats can not swim");
+        assert_eq!(expand("they're sure we'll win").0, "they are sure we will win");
+        assert_eq!(expand("it's late").0, "it is late");
+        assert_eq!(expand("john's happy").0, "john is happy");
+        assert_eq!(expand("john's running").0, "john is running");
+        assert_eq!(expand("john's a dog").0, "john is a dog");
+        assert_eq!(expand("john's dog barks").0, "john's dog barks");
+        assert_eq!(expand("john's big dog barks").0, "john's big dog barks");
+        assert_eq!(expand("the dog is john's").0, "the dog is john's");
+    }
+}
@@ -0,0 +1,136 @@
+//! Coordinated lists. "John likes pizza and pasta" states two facts, one
+//! for each item of the list: the sentence is expanded into "john likes
+//! pizza" and "john likes pasta", which are matched on their own. Lists of
+//! any length work, with or without commas ("cats, dogs, and birds"), at
+//! the end of the sentence or, sharing what follows, at its start ("cats
+//! and dogs are animals").
+
+/// Words that join the last item of a list to the others.
+const COORDINATORS: [&str; 2] = ["and", "or"];
+
+/// The ways `words` may expand into one sentence per item of a coordinated
+/// list, most likely first. Each expansion holds, for every sentence, the
+/// positions of its words in `words`. Lists at the end come first, with the
+/// longest shared start; lists at the start then follow, with the shortest
+/// last item.
+pub fn coordinated_expansions(words: &[String]) -> Vec<Vec<Vec<usize>>> {
+    let Some(coordinator) = words
+        .iter()
+        .rposition(|word| COORDINATORS.contains(&word.as_str()))
+    else {
+        return Vec::new();
+    };
+    if coordinator == 0 || coordinator + 1 == words.len() {
+        return Vec::new();
+    }
+
+    let mut expansions = Vec::new();
+    // The list runs to the end: a shared start, then the items
+    for start in (1..coordinator).rev() {
+        if words[start - 1].ends_with(',') {
+            continue;
+        }
+        let Some(mut items) = list_items(words, start..coordinator) else {
+            continue;
+        };
+        items.push((coordinator + 1..words.len()).collect());
+        let shared: Vec<usize> = (0..start).collect();
+        expansions.push(
+            items
+                .into_iter()
+                .map(|item| shared.iter().copied().chain(item).collect())
+                .collect(),
+        );
+    }
+    // The list starts the sentence: the items, then a shared end
+    for end in coordinator + 2..words.len() {
+        if words[end - 1].ends_with(',') {
+            continue;
+        }
+        let Some(mut items) = list_items(words, 0..coordinator) else {
+            continue;
+        };
+        items.push((coordinator + 1..end).collect());
+        expansions.push(
+            items
+                .into_iter()
+                .map(|item| item.into_iter().chain(end..words.len()).collect())
+                .collect(),
+        );
+    }
+    expansions
+}
+
+/// The items of the list before its last coordinator, split at commas and
+/// at earlier coordinators. `None` when an item would be empty.
+pub fn list_items(words: &[String], range: std::ops::Range<usize>) -> Option<Vec<Vec<usize>>> {
+    let mut items = Vec::new();
+    let mut item = Vec::new();
+    for idx in range {
+        let word = words[idx].as_str();
+        if word == "," || COORDINATORS.contains(&word) {
+            items.push(std::mem::take(&mut item));
+            continue;
+        }
+        item.push(idx);
+        if word.ends_with(',') {
+            items.push(std::mem::take(&mut item));
+        }
+    }
+    // "cats, dogs, and birds" ends its items at the comma before "and"
+    if !item.is_empty() || items.is_empty() {
+        items.push(item);
+    }
+    (!items.iter().any(Vec::is_empty)).then_some(items)
+}
+
+/// The words of an expanded sentence, without the commas that separated
+/// the items.
+pub fn expanded_words(words: &[String], positions: &[usize]) -> Vec<String> {
+    positions
+        .iter()
+        .map(|&idx| words[idx].trim_end_matches(',').to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lists_expand_into_one_sentence_per_item() {
+        let expand = |sentence: &str| -> Vec<Vec<String>> {
+            let words: Vec<String> = sentence.split_whitespace().map(str::to_string).collect();
+            coordinated_expansions(&words)
+                .iter()
+                .map(|expansion| {
+                    expansion
+                        .iter()
+                        .map(|positions| expanded_words(&words, positions).join(" "))
+                        .collect()
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            expand("john likes pizza and pasta"),
+            vec![
+                vec!["john likes pizza", "john likes pasta"],
+                vec!["john likes pizza", "john pasta"],
+            ]
+        );
+        assert_eq!(
+            expand("john likes cats, dogs, and birds")[0],
+            vec!["john likes cats", "john likes dogs", "john likes birds"]
+        );
+        assert_eq!(
+            expand("cats, dogs and birds are animals"),
+            vec![
+                vec!["cats are animals", "dogs are animals", "birds are animals"],
+                vec!["cats animals", "dogs animals", "birds are animals"],
+            ]
+        );
+        assert!(expand("john likes pizza").is_empty());
+        assert!(expand("and so on").is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+//! "Either ... or" and "neither ... nor". Neither item of "John likes neither
+//! pizza nor pasta" is liked, so each becomes a negated fact,
+//! `not_likes(john, pizza).` Only one item of "John likes either pizza or
+//! pasta" is, and nothing says which, so each of its facts is written as an
+//! alternative to the others: `alternative(likes(john, pizza),
+//! likes(john, pasta)).`
+
+use crate::app::{
+    PrologApp,
+    coverage::{SentenceCoverage, WordCoverage},
+};
+
+use super::{
+    coordination::{expanded_words, list_items},
+    negation::negate_template,
+    pattern_matcher::spelled_out_pattern_matches,
+    rules::goals_of_clause,
+};
+
+/// Predicate of the facts naming a fact and one of its alternatives.
+const ALTERNATIVE_PREDICATE: &str = "alternative";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Correlative {
+    Either,
+    Neither,
+}
+
+impl Correlative {
+    pub const ALL: [Correlative; 2] = [Correlative::Either, Correlative::Neither];
+
+    /// The word opening the construction and the one before its last item.
+    pub fn words(self) -> (&'static str, &'static str) {
+        match self {
+            Correlative::Either => ("either", "or"),
+            Correlative::Neither => ("neither", "nor"),
+        }
+    }
+}
+
+/// A construction found in a sentence.
+#[derive(Debug, PartialEq)]
+pub struct FoundCorrelative {
+    pub correlative: Correlative,
+    /// Position of the word opening it.
+    pub opener: usize,
+    /// Position of the word before its last item.
+    pub coordinator: usize,
+}
+
+/// The construction in `words`, if any.
+pub fn find_correlative(words: &[String]) -> Option<FoundCorrelative> {
+    Correlative::ALL.into_iter().find_map(|correlative| {
+        let (opener, coordinator) = correlative.words();
+        let opener_idx = words.iter().position(|word| word == opener)?;
+        let coordinator_idx = words[opener_idx..]
+            .iter()
+            .rposition(|word| word == coordinator)?;
+        Some(FoundCorrelative {
+            correlative,
+            opener: opener_idx,
+            coordinator: opener_idx + coordinator_idx,
+        })
+    })
+}
+
+/// The ways the sentence may expand into one sentence per item, most likely
+/// first, as in [`coordinated_expansions`](super::coordination::coordinated_expansions).
+/// After a shared start the items run to the end; at the start of the
+/// sentence the last item may be followed by a shared end, shortest first.
+fn correlative_expansions(
+    words: &[String],
+    opener: usize,
+    coordinator: usize,
+) -> Vec<Vec<Vec<usize>>> {
+    let Some(items) = list_items(words, opener + 1..coordinator) else {
+        return Vec::new();
+    };
+    let shared_start: Vec<usize> = (0..opener).collect();
+    let first_end = if opener == 0 { coordinator + 2 } else { words.len() };
+
+    (first_end..=words.len())
+        .filter(|&end| end > coordinator + 1)
+        .map(|end| {
+            let mut items = items.clone();
+            items.push((coordinator + 1..end).collect());
+            items
+                .into_iter()
+                .map(|item| {
+                    shared_start
+                        .iter()
+                        .copied()
+                        .chain(item)
+                        .chain(end..words.len())
+                        .collect()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Whether a pattern that spells out "either" or "neither" itself matches
+/// `words`; its template already says what the construction means.
+pub fn correlative_pattern_matches(app: &PrologApp, words: &[String]) -> bool {
+    spelled_out_pattern_matches(app, words, |literal| {
+        Correlative::ALL
+            .iter()
+            .any(|correlative| correlative.words().0 == literal)
+    })
+}
+
+/// Parses a sentence holding the construction `found`. `None` when an item
+/// matches no pattern, so the sentence is parsed as a whole instead.
+pub fn parse_correlative(
+    app: &PrologApp,
+    sentence: &str,
+    words: &[String],
+    found: FoundCorrelative,
+    coverage: &mut SentenceCoverage,
+) -> Option<String> {
+    let item_goals = correlative_expansions(words, found.opener, found.coordinator)
+        .iter()
+        .find_map(|expansion| {
+            expansion
+                .iter()
+                .map(|positions| goals_of_clause(app, &expanded_words(words, positions)).ok())
+                .collect::<Option<Vec<_>>>()
+        })?;
+
+    let mut lines = vec![format!("// FROM: {}", sentence)];
+    match found.correlative {
+        Correlative::Either => {
+            lines.push(format!("// EITHER: {} alternatives", item_goals.len()));
+            lines.extend(alternative_facts(&item_goals));
+        }
+        Correlative::Neither => {
+            lines.push("// NEITHER".to_string());
+            lines.extend(
+                item_goals
+                    .iter()
+                    .flatten()
+                    .map(|goal| format!("{}.", negate_template(goal))),
+            );
+        }
+    }
+    coverage.coverage.fill(WordCoverage::Matched);
+    Some(lines.join("\n") + "\n")
+}
+
+/// A fact pairing the goals of each item with those of every other item.
+/// An item stating several goals is written as their conjunction.
+fn alternative_facts(item_goals: &[Vec<String>]) -> Vec<String> {
+    let items: Vec<String> = item_goals
+        .iter()
+        .map(|goals| match goals.as_slice() {
+            [goal] => goal.clone(),
+            goals => format!("({})", goals.join(", ")),
+        })
+        .collect();
+
+    let mut facts = Vec::new();
+    for (idx, item) in items.iter().enumerate() {
+        for (other_idx, other) in items.iter().enumerate() {
+            if idx != other_idx {
+                facts.push(format!("{}({}, {}).", ALTERNATIVE_PREDICATE, item, other));
+            }
+        }
+    }
+    facts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_items_become_alternatives_or_negations() {
+        let expand = |sentence: &str| -> Vec<Vec<String>> {
+            let words: Vec<String> = sentence.split_whitespace().map(str::to_string).collect();
+            let found = find_correlative(&words).unwrap();
+            correlative_expansions(&words, found.opener, found.coordinator)
+                .iter()
+                .map(|expansion| {
+                    expansion
+                        .iter()
+                        .map(|positions| expanded_words(&words, positions).join(" "))
+                        .collect()
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            expand("john likes either pizza or pasta"),
+            vec![vec!["john likes pizza", "john likes pasta"]]
+        );
+        assert_eq!(
+            expand("neither cats nor dogs fly"),
+            vec![
+                vec!["cats fly", "dogs fly"],
+                vec!["cats", "dogs fly"],
+            ]
+        );
+        assert_eq!(
+            expand("john eats either fish, rice or bread"),
+            vec![vec!["john eats fish", "john eats rice", "john eats bread"]]
+        );
+        assert_eq!(find_correlative(&["cats".to_string(), "or".to_string()]), None);
+
+        let goals = |goals: &[&str]| -> Vec<String> {
+            goals.iter().map(|goal| goal.to_string()).collect()
+        };
+        assert_eq!(
+            alternative_facts(&[goals(&["likes(john, pizza)"]), goals(&["likes(john, pasta)"])]),
+            vec![
+                "alternative(likes(john, pizza), likes(john, pasta)).",
+                "alternative(likes(john, pasta), likes(john, pizza)).",
+            ]
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Multi-word proper nouns. Sentences are lowercased before matching, which
+//! loses what set "New York" or "John Smith" apart from two common words, so
+//! runs of capitalized words are joined into one word first, while the case
+//! still shows: "John Smith lives in New York" -> "John_Smith lives in
+//! New_York", matched and written as `john_smith` and `new_york`.
+
+/// Words capitalized only for starting the sentence, which never begin a
+/// name there: "The Hague" is still read as "the" and "hague".
+const SENTENCE_STARTERS: [&str; 24] = [
+    "the", "a", "an", "this", "that", "these", "those", "my", "your", "his", "her", "its", "our",
+    "their", "in", "on", "at", "when", "if", "then", "but", "and", "yesterday", "today",
+];
+
+/// Punctuation a name may end with; it ends the run of capitalized words.
+const TRAILING_PUNCTUATION: [char; 7] = ['.', ',', ';', ':', '?', '!', ')'];
+
+/// `sentence` with every run of two or more capitalized words joined by
+/// `_`. Whitespace elsewhere is kept as it is.
+pub fn join_proper_nouns(sentence: &str) -> String {
+    let words: Vec<(usize, &str)> = sentence
+        .split_whitespace()
+        .map(|word| (word.as_ptr() as usize - sentence.as_ptr() as usize, word))
+        .collect();
+
+    // Gaps between words of the same name, by the index of the word before
+    let mut joins = Vec::new();
+    let mut idx = 0;
+    while idx < words.len() {
+        let mut end = idx;
+        while end < words.len() && is_name_word(words[end].1, end == 0) {
+            end += 1;
+            if words[end - 1].1.ends_with(TRAILING_PUNCTUATION) {
+                break;
+            }
+        }
+        if end - idx >= 2 {
+            joins.extend(idx..end - 1);
+        }
+        idx = end.max(idx + 1);
+    }
+
+    let mut joined = String::with_capacity(sentence.len());
+    let mut copied = 0;
+    for word_idx in joins {
+        let (start, word) = words[word_idx];
+        let gap_start = start + word.len();
+        joined.push_str(&sentence[copied..gap_start]);
+        joined.push('_');
+        copied = words[word_idx + 1].0;
+    }
+    joined.push_str(&sentence[copied..]);
+    joined
+}
+
+/// Whether `word` may be part of a name: capitalized, made of letters, and
+/// not "I" or, at the start of the sentence, a word that starts sentences.
+fn is_name_word(word: &str, starts_sentence: bool) -> bool {
+    let core = word.trim_end_matches(TRAILING_PUNCTUATION);
+    let core = core
+        .strip_suffix("'s")
+        .or_else(|| core.strip_suffix("’s"))
+        .unwrap_or(core);
+    let capitalized = core.chars().next().is_some_and(char::is_uppercase);
+    let letters = core
+        .chars()
+        .all(|ch| ch.is_alphabetic() || ch == '-' || ch == '\'');
+    let starter = starts_sentence && SENTENCE_STARTERS.contains(&core.to_lowercase().as_str());
+    capitalized && letters && core != "I" && !starter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capitalized_runs_become_one_word() {
+        assert_eq!(
+            join_proper_nouns("John Smith lives in New York."),
+            "John_Smith lives in New_York."
+        );
+        assert_eq!(
+            join_proper_nouns("The Hague is near Den Haag's port"),
+            "The Hague is near Den_Haag's port"
+        );
+        assert_eq!(
+            join_proper_nouns("Paris, London and Rome\nare cities"),
+            "Paris, London and Rome\nare cities"
+        );
+        assert_eq!(join_proper_nouns("Then I Saw it"), "Then I Saw it");
+        assert_eq!(join_proper_nouns("Dr. Smith likes pizza."), "Dr. Smith likes pizza.");
+    }
+}
@@ -1,192 +1,70 @@
 use crate::app::{
-    PrologApp,
+    database::{Database, WordType},
     interactive_parser::{SentenceMatch, TokenHighlight, TokenType},
-    parser::pattern_matcher::{PatternMatch, PatternToken},
+    parser::pattern_matcher::{PatternMatch, PatternToken, allows_type},
 };
 
+/// Builds the interactive view of a match from the same capture spans the
+/// facts were generated from. `words` is the sentence `pattern_match` was
+/// made against. A `<Type>` capture is shown as the most frequent reading of
+/// the word among the token's types.
 pub fn create_interactive_match(
     words: &[String],
     pattern_match: &PatternMatch,
     pattern_tokens: &[PatternToken],
-    app: &PrologApp,
+    read_database: &Database,
 ) -> SentenceMatch {
-    let mut highlights = Vec::new();
-    let mut capture_index = 1;
-
-    let mut word_to_capture = std::collections::HashMap::new();
-
-    if let Some(captures_with_indices) = extract_captures_with_indices(words, pattern_tokens, app) {
-        for (word_idx, word, token_type) in captures_with_indices {
-            word_to_capture.insert(word_idx, capture_index);
+    let highlights = pattern_match
+        .spans
+        .iter()
+        .map(|span| {
+            let captured_by = match &pattern_tokens[span.token] {
+                PatternToken::Optional(inner) => inner.as_ref(),
+                token => token,
+            };
+            let token_type = match captured_by {
+                PatternToken::TypeMatch(types, excluded, _) => {
+                    let reading = read_database
+                        .get_word_entries(&words[span.start])
+                        .and_then(|entries| {
+                            entries
+                                .iter()
+                                .find(|entry| allows_type(types, excluded, &entry.word_type))
+                        })
+                        .map(|entry| &entry.word_type);
+                    // A `<!Type>` capture of an unknown word reads as a noun
+                    word_type_to_token_type(reading.or(types.first()).unwrap_or(&WordType::Noun))
+                }
+                _ => TokenType::Greedy,
+            };
 
-            highlights.push(TokenHighlight {
-                word: word.clone(),
-                word_index: word_idx,
-                capture_index,
+            TokenHighlight {
+                word: pattern_match.captures[span.slot].clone(),
+                word_index: span.start - pattern_match.start_idx,
+                word_end: span.end - pattern_match.start_idx,
+                capture_index: span.slot + 1,
                 token_type,
                 //is_editable: true,
-            });
-
-            capture_index += 1;
-        }
-    }
+            }
+        })
+        .collect();
 
     let mut sentence_match = SentenceMatch {
-        words: words.to_vec(),
+        sentence: String::new(),
+        words: words[pattern_match.start_idx..pattern_match.end_idx].to_vec(),
         pattern_name: pattern_match.pattern_name.clone(),
         template: pattern_match.template.clone(),
         highlights,
         generated_output: String::new(),
+        confidence: 1.0,
+        alternatives: Vec::new(),
     };
 
     sentence_match.regenerate_output();
     sentence_match
 }
 
-fn extract_captures_with_indices(
-    words: &[String],
-    pattern_tokens: &[PatternToken],
-    app: &PrologApp,
-) -> Option<Vec<(usize, String, TokenType)>> {
-    fn backtrack(
-        words: &[String],
-        word_idx: usize,
-        pattern_tokens: &[PatternToken],
-        pattern_idx: usize,
-        captures: &mut Vec<(usize, String, TokenType)>,
-        app: &PrologApp,
-    ) -> bool {
-        if pattern_idx >= pattern_tokens.len() {
-            return word_idx == words.len();
-        }
-
-        if word_idx >= words.len() {
-            return pattern_tokens[pattern_idx..]
-                .iter()
-                .all(|t| matches!(t, PatternToken::Optional(_)));
-        }
-
-        match &pattern_tokens[pattern_idx] {
-            PatternToken::Optional(inner) => {
-                if matches_token(&words[word_idx], inner, app) {
-                    if let PatternToken::TypeMatch(types) = inner.as_ref() {
-                        let token_type = word_type_to_token_type(&types[0]);
-                        captures.push((word_idx, words[word_idx].clone(), token_type));
-                    }
-                    if backtrack(
-                        words,
-                        word_idx + 1,
-                        pattern_tokens,
-                        pattern_idx + 1,
-                        captures,
-                        app,
-                    ) {
-                        return true;
-                    }
-                    if let PatternToken::TypeMatch(_) = inner.as_ref() {
-                        captures.pop();
-                    }
-                }
-                backtrack(
-                    words,
-                    word_idx,
-                    pattern_tokens,
-                    pattern_idx + 1,
-                    captures,
-                    app,
-                )
-            }
-            PatternToken::Wildcard => backtrack(
-                words,
-                word_idx + 1,
-                pattern_tokens,
-                pattern_idx + 1,
-                captures,
-                app,
-            ),
-            PatternToken::Greedy(inner) => {
-                let mut matched_words = Vec::new();
-                let mut end_idx = word_idx;
-
-                while end_idx < words.len() && matches_token(&words[end_idx], inner, app) {
-                    matched_words.push(words[end_idx].clone());
-                    end_idx += 1;
-                }
-
-                for try_end in (word_idx + 1..=end_idx).rev() {
-                    let greedy_words = &words[word_idx..try_end];
-                    captures.push((word_idx, greedy_words.join("_"), TokenType::Greedy));
-
-                    if backtrack(
-                        words,
-                        try_end,
-                        pattern_tokens,
-                        pattern_idx + 1,
-                        captures,
-                        app,
-                    ) {
-                        return true;
-                    }
-                    captures.pop();
-                }
-                false
-            }
-            token => {
-                if matches_token(&words[word_idx], token, app) {
-                    if let PatternToken::TypeMatch(types) = token {
-                        let token_type = word_type_to_token_type(&types[0]);
-                        captures.push((word_idx, words[word_idx].clone(), token_type));
-                    }
-                    backtrack(
-                        words,
-                        word_idx + 1,
-                        pattern_tokens,
-                        pattern_idx + 1,
-                        captures,
-                        app,
-                    )
-                } else {
-                    false
-                }
-            }
-        }
-    }
-
-    let mut captures = Vec::new();
-    if backtrack(words, 0, pattern_tokens, 0, &mut captures, app) {
-        Some(captures)
-    } else {
-        None
-    }
-}
-
-fn matches_token(word: &str, token: &PatternToken, app: &PrologApp) -> bool {
-    use crate::app::database::WordType;
-
-    match token {
-        PatternToken::Literal(literal) => word.eq_ignore_ascii_case(literal),
-        PatternToken::TypeMatch(required_types) => {
-            let Ok(read_database) = app.database.read() else {
-                return false;
-            };
-
-            if let Some(entries) = read_database.get_word_entries(word) {
-                entries
-                    .iter()
-                    .any(|entry| required_types.contains(&entry.word_type))
-            } else {
-                required_types.contains(&WordType::Noun)
-            }
-        }
-        PatternToken::Wildcard => true,
-        PatternToken::Optional(inner) => matches_token(word, inner, app),
-        PatternToken::Greedy(inner) => matches_token(word, inner, app),
-    }
-}
-
-fn word_type_to_token_type(word_type: &crate::app::database::WordType) -> TokenType {
-    use crate::app::database::WordType;
-
+fn word_type_to_token_type(word_type: &WordType) -> TokenType {
     match word_type {
         WordType::Noun => TokenType::Noun,
         WordType::Verb => TokenType::Verb,
@@ -194,3 +72,79 @@ fn word_type_to_token_type(word_type: &crate::app::database::WordType) -> TokenT
         other => TokenType::Other(other.clone()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{
+        database::{WordEntry, fixtures::word},
+        parser::pattern_matcher::CaptureSpan,
+    };
+
+    #[test]
+    fn test_highlights_follow_the_match_spans() {
+        let words: Vec<String> = "yesterday new york city is big"
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        let pattern_tokens = vec![
+            PatternToken::Greedy(Box::new(PatternToken::TypeMatch(
+                vec![WordType::Noun],
+                vec![],
+                None,
+            ))),
+            PatternToken::Literal("is".to_string()),
+            PatternToken::TypeMatch(vec![WordType::Noun, WordType::Adjective], vec![], None),
+        ];
+        let pattern_match = PatternMatch {
+            pattern_name: "is_property".to_string(),
+            template: "property($1, $2).".to_string(),
+            captures: vec!["new_york_city".to_string(), "big".to_string()],
+            spans: vec![
+                CaptureSpan {
+                    start: 1,
+                    end: 4,
+                    token: 0,
+                    slot: 0,
+                },
+                CaptureSpan {
+                    start: 5,
+                    end: 6,
+                    token: 2,
+                    slot: 1,
+                },
+            ],
+            skipped: Vec::new(),
+            start_idx: 1,
+            end_idx: 6,
+            confidence: 1.0,
+        };
+
+        // "big" is listed as a noun too, but is far more often an adjective.
+        let mut database = Database::default();
+        for (word_type, frequency) in [(WordType::Noun, 1), (WordType::Adjective, 9)] {
+            database.words.push(WordEntry {
+                frequency,
+                ..word("big", word_type, &[])
+            });
+        }
+        database.rebuild_index();
+
+        let sentence_match =
+            create_interactive_match(&words, &pattern_match, &pattern_tokens, &database);
+
+        assert_eq!(sentence_match.words.len(), 5);
+        assert_eq!(sentence_match.highlights[0].word_index, 0);
+        assert_eq!(sentence_match.highlights[0].word_end, 3);
+        assert_eq!(sentence_match.highlights[0].token_type, TokenType::Greedy);
+        assert_eq!(sentence_match.highlights[1].word_index, 4);
+        assert_eq!(
+            sentence_match.highlights[1].token_type,
+            TokenType::Adjective
+        );
+        assert_eq!(
+            sentence_match.generated_output,
+            "property(new_york_city, big)."
+        );
+    }
+}
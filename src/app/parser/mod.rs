@@ -1,6 +1,20 @@
+pub mod contraction;
+pub mod coordination;
+pub mod correlative;
+pub mod entities;
 pub mod interactive_converter;
+pub mod negation;
 pub mod parser;
 pub mod pattern_matcher;
+pub mod possessive;
 pub mod pronoun_resolver;
+pub mod question;
+pub mod relative;
+pub mod rules;
+pub mod subordinate;
+pub mod temporal;
+pub mod tense;
 
-pub use parser::parse_input;
+pub use parser::{
+    confidence_comment, directives_output, hidden_facts, parse_input, sentence_body,
+};
@@ -0,0 +1,168 @@
+//! Negated sentences. "Bear is not a fish" would otherwise match the same
+//! patterns as "Bear is a fish" and state the opposite, so the negation is
+//! stripped, the positive sentence matched, and the facts of its template
+//! negated: `not_is_a(bear, fish)`.
+
+use crate::app::{
+    PrologApp,
+    database::PrologPattern,
+    morphology::{MorphologyExceptions, VerbForm, conjugate},
+};
+
+use super::{pattern_matcher::spelled_out_pattern_matches, question::DO_AUXILIARIES};
+
+const NEGATION_WORDS: [&str; 2] = ["not", "never"];
+
+/// Prefix of a negated fact's predicate.
+const NEGATED_PREFIX: &str = "not_";
+
+/// The word a negative contraction was made from: `isn't` -> `is`.
+fn contraction_base(word: &str) -> Option<String> {
+    let word = word.replace('’', "'");
+    match word.as_str() {
+        "can't" | "cannot" => Some("can".to_string()),
+        "won't" => Some("will".to_string()),
+        "shan't" => Some("shall".to_string()),
+        _ => word
+            .strip_suffix("n't")
+            .filter(|base| !base.is_empty())
+            .map(str::to_string),
+    }
+}
+
+/// The sentence without its negation, and for each of its words the
+/// position of the word it came from; `None` when the sentence is not
+/// negated. A `do` auxiliary goes with the negation, leaving its form on
+/// the main verb: "john doesn't like fish" -> "john likes fish".
+pub fn strip_negation(
+    words: &[String],
+    exceptions: &MorphologyExceptions,
+) -> Option<(Vec<String>, Vec<usize>)> {
+    let mut positive: Vec<(String, usize)> = Vec::new();
+    let mut negated = false;
+    let mut verb_form = None;
+
+    for (idx, word) in words.iter().enumerate() {
+        let (base, negation) = match contraction_base(word) {
+            Some(base) => (Some(base), true),
+            None if NEGATION_WORDS.contains(&word.as_str()) => (None, true),
+            None => (Some(word.clone()), false),
+        };
+
+        if negation {
+            negated = true;
+            // "does not like" and "doesn't like" both leave "likes"
+            let auxiliary = match base {
+                Some(base) => Some(base),
+                None if positive.last().is_some_and(|(last, _)| do_form(last).is_some()) => {
+                    positive.pop().map(|(last, _)| last)
+                }
+                None => None,
+            };
+            if let Some(auxiliary) = auxiliary {
+                match do_form(&auxiliary) {
+                    Some(form) => verb_form = Some(form),
+                    None => positive.push((auxiliary, idx)),
+                }
+            }
+            continue;
+        }
+
+        let word = base.unwrap_or_default();
+        let word = match verb_form.take() {
+            Some(Some(form)) => conjugate(&word, form, exceptions),
+            _ => word,
+        };
+        positive.push((word, idx));
+    }
+
+    negated.then(|| positive.into_iter().unzip())
+}
+
+/// The form a `do` auxiliary leaves on the main verb, if `word` is one.
+fn do_form(word: &str) -> Option<Option<VerbForm>> {
+    DO_AUXILIARIES
+        .iter()
+        .find(|(auxiliary, _)| *auxiliary == word)
+        .map(|(_, form)| *form)
+}
+
+/// Whether a pattern that spells out a negation itself matches `words`; its
+/// template already says what the negation means, so it is left to match.
+pub fn negation_pattern_matches(app: &PrologApp, words: &[String]) -> bool {
+    spelled_out_pattern_matches(app, words, |literal| {
+        NEGATION_WORDS.contains(&literal) || contraction_base(literal).is_some()
+    })
+}
+
+/// `pattern` with every fact of its template negated.
+pub fn negated_pattern(pattern: &PrologPattern) -> PrologPattern {
+    PrologPattern {
+        template: negate_template(&pattern.template),
+        ..pattern.clone()
+    }
+}
+
+/// Negates each fact line of a template, including the body of `$N.split`
+/// lines. Rules are left as they are, and a fact already negated becomes
+/// positive again.
+pub fn negate_template(template: &str) -> String {
+    let negate = |fact: &str| match fact.strip_prefix(NEGATED_PREFIX) {
+        Some(positive) => positive.to_string(),
+        None => format!("{}{}", NEGATED_PREFIX, fact),
+    };
+
+    template
+        .lines()
+        .map(|line| {
+            let fact = line.trim();
+            if fact.is_empty() || fact.contains(":-") {
+                line.to_string()
+            } else if let Some((head, body)) = fact.split_once("->") {
+                format!("{}-> {}", head, negate(body.trim()))
+            } else {
+                negate(fact)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negations_are_stripped_and_facts_negated() {
+        let words = |sentence: &str| -> Vec<String> {
+            sentence.split_whitespace().map(str::to_string).collect()
+        };
+        let strip = |sentence: &str| {
+            strip_negation(&words(sentence), &MorphologyExceptions::english())
+                .map(|(positive, positions)| (positive.join(" "), positions))
+        };
+
+        assert_eq!(
+            strip("bear is not a fish"),
+            Some(("bear is a fish".to_string(), vec![0, 1, 3, 4]))
+        );
+        assert_eq!(
+            strip("john doesn't like broccoli"),
+            Some(("john likes broccoli".to_string(), vec![0, 2, 3]))
+        );
+        assert_eq!(
+            strip("tom did not eat the fish"),
+            Some(("tom ate the fish".to_string(), vec![0, 3, 4, 5]))
+        );
+        assert_eq!(
+            strip("cats can't swim"),
+            Some(("cats can swim".to_string(), vec![0, 1, 2]))
+        );
+        assert_eq!(strip("bear is a fish"), None);
+
+        assert_eq!(
+            negate_template("$2($1, $3)\n$1.split -> likes(#, $2)\nnot_big($1)\nx(A) :- y(A)"),
+            "not_$2($1, $3)\n$1.split -> not_likes(#, $2)\nbig($1)\nx(A) :- y(A)"
+        );
+    }
+}
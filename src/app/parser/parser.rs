@@ -19,86 +19,338 @@
 
 */
 
-use crate::app::PrologApp;
+use std::{borrow::Cow, sync::Arc};
+
+use crate::app::{
+    PrologApp,
+    coverage::{SentenceCoverage, WordCoverage},
+    database::{Locale, PrologPattern, is_rule_template},
+    interactive_parser::SentenceMatch,
+};
 
 use super::{
+    contraction::{contraction_pattern_matches, expand_contractions},
+    coordination::{coordinated_expansions, expanded_words},
+    entities::join_proper_nouns,
+    correlative::{correlative_pattern_matches, find_correlative, parse_correlative},
     interactive_converter::create_interactive_match,
+    negation::{negated_pattern, negation_pattern_matches, strip_negation},
     pattern_matcher::{
-        apply_template, find_all_pattern_matches, parse_pattern, try_match_pattern,
-        try_match_pattern_substring,
+        PatternMatch, PatternToken, apply_template, fallback_types, fallback_warnings,
+        find_all_pattern_matches, match_pattern, match_pattern_substring, parse_pattern,
+    },
+    possessive::{
+        owning_pattern, possessive_pattern_matches, split_possessives, strip_possessives,
     },
     pronoun_resolver::PronounResolver,
+    question::parse_question,
+    relative::{find_relative_clause, relative_facts, relative_pattern_matches},
+    rules::{rule_lines, universal_rule_lines},
+    subordinate::{parse_subordinate, split_subordinate, subordinate_pattern_matches},
+    tense::{match_tense, tensed_template},
 };
 
+const SENTENCE_BREAK_MARKER: &str = "---";
+const NO_SPLIT_MARKER: &str = "#nosplit";
+const SPLIT_MARKER: &str = "#split";
+
+// Punctuation that ends a sentence, and what may close a quoted one after it.
+const SENTENCE_TERMINATORS: [char; 3] = ['.', '?', '!'];
+// Single quotes are left out, as they double as apostrophes ("the cats'").
+const CLOSING_QUOTES: [char; 3] = ['"', '”', ')'];
+const OPENING_QUOTES: [char; 3] = ['"', '“', '('];
+
+// Words whose period does not end the sentence, as in "Dr. Smith".
+const ABBREVIATIONS: [&str; 16] = [
+    "mr", "mrs", "ms", "dr", "prof", "st", "jr", "sr", "vs", "e.g", "i.e", "fig", "no", "approx",
+    "inc", "ltd",
+];
+
+// How much a match's confidence is scaled by the way it was found.
+const CONJUNCTION_CERTAINTY: f32 = 0.9;
+const SUBSTRING_CERTAINTY: f32 = 0.8;
+
+// Directives naming who "I" and "you" stand for: `:- speaker(alice).`
+const SPEAKER_ROLE: &str = "speaker";
+const ADDRESSEE_ROLE: &str = "addressee";
+
 // Method for parsing input text chunk into sentences.
 // This method assumes that input text will strictly follow grammatical rules.
-// Specifically, sentences end with a period (.) followed by either a newline,
-// carriage return, or a space followed by an uppercase letter.
+// Specifically, sentences end with a period (.), an exclamation mark (!), or a
+// question mark (?) for questions, with any closing quotes after it, followed
+// by either a newline, carriage return, or a space followed by an uppercase
+// letter (which may be quoted). The period of an abbreviation ("Dr. Smith"),
+// an initial ("J. Smith") or a decimal number ("3.5") never ends a sentence.
 // Each identified sentence is trimmed of leading and trailing whitespace
-// before being added to the output vector.
-pub fn parse_sentences(input: &String) -> Vec<String> {
+// before being added to the output vector, and lowercased by the database locale
+// once its multi-word proper nouns are joined into single words ("New York" ->
+// "new_york").
+//
+// Marker lines override the punctuation rules for text that doesn't follow them:
+// a line of `---` always ends the current sentence, and between `#nosplit` and
+// `#split` periods never do, so everything up to the next `---` or `#split`
+// stays one sentence. Marker lines themselves are dropped.
+pub fn parse_sentences(input: &String, locale: Locale) -> Vec<String> {
     let mut sentences = Vec::new();
+    let mut block = String::new();
+    let mut split = true;
+
+    for line in input.split_inclusive('\n') {
+        let marker = match line.trim() {
+            SENTENCE_BREAK_MARKER => Some(split),
+            NO_SPLIT_MARKER => Some(false),
+            SPLIT_MARKER => Some(true),
+            _ => None,
+        };
+
+        match marker {
+            Some(next_split) => {
+                push_block(&block, split, locale, &mut sentences);
+                block.clear();
+                split = next_split;
+            }
+            None => block.push_str(line),
+        }
+    }
+    push_block(&block, split, locale, &mut sentences);
+
+    sentences
+}
+
+// Adds the sentences of a block of text between marker lines: split on
+// sentence ends, or kept whole when splitting is suppressed.
+fn push_block(block: &str, split: bool, locale: Locale, sentences: &mut Vec<String>) {
+    if !split {
+        let trimmed = block.trim();
+        if !trimmed.is_empty() {
+            sentences.push(locale.lowercase(&join_proper_nouns(trimmed)));
+        }
+        return;
+    }
+
     let mut current_sentence = String::new();
-    let chars: Vec<char> = input.chars().collect();
+    let chars: Vec<char> = block.chars().collect();
 
-    for i in 0..chars.len() {
+    let mut i = 0;
+    while i < chars.len() {
         let ch = chars[i];
         current_sentence.push(ch);
+        i += 1;
 
-        if ch == '.' {
-            let next_char = if i + 1 < chars.len() {
-                Some(chars[i + 1])
-            } else {
-                None
-            };
+        let is_terminator = SENTENCE_TERMINATORS.contains(&ch);
+        if !is_terminator || (ch == '.' && !period_ends_sentence(&chars, i - 1)) {
+            continue;
+        }
+        // "Really?!" and quoted sentences end after all of their punctuation
+        while i < chars.len()
+            && (SENTENCE_TERMINATORS.contains(&chars[i]) || CLOSING_QUOTES.contains(&chars[i]))
+        {
+            current_sentence.push(chars[i]);
+            i += 1;
+        }
 
-            let is_sentence_end = match next_char {
-                None => true,                    // Is end of input
-                Some('\n') | Some('\r') => true, // Newline or carriage return
-                Some(' ') => {
-                    // Space followed by uppercase letter
-                    let mut j = i + 1;
-                    while j < chars.len() && chars[j].is_whitespace() {
-                        j += 1;
-                    }
-                    j < chars.len() && chars[j].is_uppercase()
+        let is_sentence_end = match chars.get(i) {
+            None => true,                    // Is end of input
+            Some('\n') | Some('\r') => true, // Newline or carriage return
+            Some(' ') => {
+                // Space followed by uppercase letter, possibly quoted
+                let mut j = i;
+                while j < chars.len() && chars[j].is_whitespace() {
+                    j += 1;
                 }
-                _ => false,
-            };
-
-            if is_sentence_end {
-                let trimmed = current_sentence.trim();
-                if !trimmed.is_empty() {
-                    sentences.push(trimmed.to_string().to_lowercase());
+                while j < chars.len() && OPENING_QUOTES.contains(&chars[j]) {
+                    j += 1;
                 }
-                current_sentence.clear();
+                j < chars.len() && chars[j].is_uppercase()
+            }
+            _ => false,
+        };
+
+        if is_sentence_end {
+            let trimmed = current_sentence.trim();
+            if !trimmed.is_empty() {
+                sentences.push(locale.lowercase(&join_proper_nouns(trimmed)));
             }
+            current_sentence.clear();
         }
     }
 
     let trimmed = current_sentence.trim();
     if !trimmed.is_empty() {
-        sentences.push(trimmed.to_string().to_lowercase());
+        sentences.push(locale.lowercase(&join_proper_nouns(trimmed)));
     }
+}
 
-    sentences
+// Whether the period at `chars[idx]` may end a sentence: not inside a decimal
+// number, nor after an abbreviation or a capital initial.
+fn period_ends_sentence(chars: &[char], idx: usize) -> bool {
+    let is_digit = |idx: usize| chars.get(idx).is_some_and(char::is_ascii_digit);
+    if idx > 0 && is_digit(idx - 1) && is_digit(idx + 1) {
+        return false;
+    }
+
+    let word_start = chars[..idx]
+        .iter()
+        .rposition(|ch| ch.is_whitespace())
+        .map_or(0, |space| space + 1);
+    let word: String = chars[word_start..idx]
+        .iter()
+        .skip_while(|ch| OPENING_QUOTES.contains(ch))
+        .collect();
+    let is_initial = word.chars().count() == 1 && word.chars().all(char::is_uppercase);
+    !is_initial && !ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+// The words of a sentence without its surrounding quotes and final
+// punctuation; also strips a single token the same way.
+pub fn sentence_body(sentence: &str) -> &str {
+    sentence
+        .trim_start_matches(OPENING_QUOTES)
+        .trim_end_matches(CLOSING_QUOTES)
+        .trim_end_matches(SENTENCE_TERMINATORS)
+        .trim_end_matches(CLOSING_QUOTES)
+}
+
+// Whether a sentence is a question, quoted or not.
+pub fn is_question(sentence: &str) -> bool {
+    sentence.trim_end_matches(CLOSING_QUOTES).ends_with('?')
 }
 
 pub fn parse_prolog(app: &mut PrologApp, sentence: &String) -> String {
-    let words: Vec<String> = sentence
-        .trim_end_matches('.')
+    let input_words: Vec<String> = sentence_body(sentence)
         .split_whitespace()
         .map(|s| s.to_string())
         .collect();
-    if words.is_empty() {
+    if input_words.is_empty() {
         return String::new();
     }
+    // Contractions are expanded, so "isn't" reads as "is not"
+    let (mut expanded, mut expanded_sources) =
+        expand_contractions(&input_words, &app.database_snapshot);
+    if expanded != input_words && contraction_pattern_matches(app, &input_words) {
+        expanded = input_words.clone();
+        expanded_sources = (0..input_words.len()).collect();
+    }
+    // Possessive endings are words of their own, so "john's" reads as "john 's"
+    let (words, split_sources) = split_possessives(&expanded);
+    let sources: Vec<usize> = split_sources
+        .iter()
+        .map(|&idx| expanded_sources[idx])
+        .collect();
 
-    let Ok(read_database) = app.database.read() else {
-        return "// ERROR: Unable to read database\n".to_string();
+    let mut coverage = SentenceCoverage::new(&words);
+    let output = if is_question(sentence) {
+        parse_question(app, sentence, &words, &mut coverage)
+    } else if let Some(correlative) = find_correlative(&words)
+        && !correlative_pattern_matches(app, &words)
+        && let Some(output) = parse_correlative(app, sentence, &words, correlative, &mut coverage)
+    {
+        output
+    } else if let Some(clauses) = split_subordinate(&words)
+        && !subordinate_pattern_matches(app, &words)
+        && let Some(output) = parse_subordinate(app, sentence, clauses, &mut coverage)
+    {
+        output
+    } else if let Some(relative) = find_relative_clause(&words, &app.database_snapshot)
+        && !relative_pattern_matches(app, &words)
+        && let Some(facts) = relative_facts(app, &relative)
+    {
+        match relative.main {
+            Some((main, positions)) => {
+                let mut main_coverage = SentenceCoverage::new(&main);
+                let output = parse_statement(app, sentence, main, &facts, &mut main_coverage);
+                coverage = main_coverage.mapped_onto(&words, &positions);
+                output
+            }
+            None => {
+                coverage.coverage.fill(WordCoverage::Matched);
+                format!("// FROM: {}\n// RELATIVE\n{}\n", sentence, facts.join("\n"))
+            }
+        }
+    } else if let Some(possessions) = strip_possessives(&words, &app.database_snapshot)
+        && !possessive_pattern_matches(app, &words)
+    {
+        let mut owned_coverage = SentenceCoverage::new(&possessions.words);
+        let output = parse_statement(
+            app,
+            sentence,
+            possessions.words,
+            &possessions.facts,
+            &mut owned_coverage,
+        );
+        coverage = owned_coverage.mapped_onto(&words, &possessions.positions);
+        output
+    } else {
+        parse_statement(app, sentence, words, &[], &mut coverage)
     };
+    // Coverage is shown over the input words, which keep their contractions and
+    // possessive endings
+    app.interactive_parser
+        .coverage
+        .push(coverage.mapped_onto(&input_words, &sources));
+    output
+}
+
+/// Matches a statement, stripping its negation unless a pattern spells it
+/// out. `ownership` holds the facts of possessives or a relative clause
+/// taken out of `words`, which every match writes along with its own.
+fn parse_statement(
+    app: &mut PrologApp,
+    sentence: &String,
+    words: Vec<String>,
+    ownership: &[String],
+    coverage: &mut SentenceCoverage,
+) -> String {
+    match strip_negation(&words, &app.database_snapshot.morphology) {
+        Some((positive, positions)) if !negation_pattern_matches(app, &words) => {
+            let mut positive_coverage = SentenceCoverage::new(&positive);
+            let output = match_sentence(
+                app,
+                sentence,
+                positive,
+                true,
+                ownership,
+                &mut positive_coverage,
+            );
+            *coverage = positive_coverage.mapped_onto(&words, &positions);
+            output
+        }
+        _ => match_sentence(app, sentence, words, false, ownership, coverage),
+    }
+}
+
+/// Matches the words of `sentence` against the patterns, marking the words
+/// each match covers in `coverage`. For a `negated` sentence, `words` have
+/// the negation stripped and every pattern emits its facts negated. The
+/// `ownership` facts are emitted by every pattern as they are.
+fn match_sentence(
+    app: &mut PrologApp,
+    sentence: &String,
+    words: Vec<String>,
+    negated: bool,
+    ownership: &[String],
+    coverage: &mut SentenceCoverage,
+) -> String {
+    let read_database = Arc::clone(&app.database_snapshot);
+    let identity: Vec<usize> = (0..words.len()).collect();
 
-    let sorted_patterns = read_database.get_sorted_patterns();
+    let sorted_patterns: Vec<Cow<PrologPattern>> = read_database
+        .get_sorted_patterns(&app.interactive_parser.disabled_tags)
+        .into_iter()
+        .map(|pattern| {
+            let pattern = read_database.with_subpatterns(pattern);
+            let pattern = if negated {
+                Cow::Owned(negated_pattern(&pattern))
+            } else {
+                pattern
+            };
+            if ownership.is_empty() {
+                pattern
+            } else {
+                Cow::Owned(owning_pattern(&pattern, ownership))
+            }
+        })
+        .collect();
 
     let patterns_with_tokens: Vec<(String, String, Vec<_>)> = sorted_patterns
         .iter()
@@ -122,41 +374,72 @@ pub fn parse_prolog(app: &mut PrologApp, sentence: &String) -> String {
         let matches = find_all_pattern_matches(&words, &patterns_with_tokens, &app);
 
         if !matches.is_empty() {
-            for m in &matches {
-                let pattern_tokens = parse_pattern(
-                    &read_database
-                        .patterns
-                        .iter()
-                        .find(|p| p.name == m.pattern_name)
-                        .map(|p| &p.pattern)
-                        .unwrap_or(&String::new()),
-                );
-
-                let interactive_match = create_interactive_match(
-                    &words[m.start_idx..m.end_idx],
-                    m,
-                    &pattern_tokens,
-                    app,
-                );
-                app.interactive_parser.matches.push(interactive_match);
-            }
-
             let mut outputs = Vec::new();
             outputs.push(format!("// FROM: {}", sentence));
 
             for m in &matches {
-                let prolog_outputs = apply_template(&m.captures, &m.template);
+                let pattern_tokens = patterns_with_tokens
+                    .iter()
+                    .find(|(name, _, _)| *name == m.pattern_name)
+                    .map(|(_, _, tokens)| tokens.as_slice())
+                    .unwrap_or_default();
+
                 outputs.push(format!(
                     "// PATTERN: {} (words {}-{})",
                     m.pattern_name, m.start_idx, m.end_idx
                 ));
-                outputs.extend(prolog_outputs);
+                coverage.mark(m, pattern_tokens, &identity);
+                outputs.extend(push_match(app, &words, m, pattern_tokens, 1.0));
+                outputs.extend(push_alternatives(app, &words, m, &patterns_with_tokens, 1.0));
             }
 
             return outputs.join("\n") + "\n";
         }
     }
 
+    // A coordinated list states the same thing of each of its items
+    for expansion in coordinated_expansions(&words) {
+        let expanded: Vec<Vec<String>> = expansion
+            .iter()
+            .map(|positions| expanded_words(&words, positions))
+            .collect();
+        let matched: Option<Vec<(PatternMatch, &[PatternToken])>> = expanded
+            .iter()
+            .map(|item_words| {
+                patterns_with_tokens.iter().find_map(|(name, template, tokens)| {
+                    match_pattern(item_words, tokens, name, template, &app)
+                        .map(|m| (m, tokens.as_slice()))
+                })
+            })
+            .collect();
+        let Some(matched) = matched else {
+            continue;
+        };
+
+        let mut outputs = Vec::new();
+        outputs.push(format!("// FROM: {}", sentence));
+        for (idx, (pattern_match, pattern_tokens)) in matched.iter().enumerate() {
+            coverage.mark(pattern_match, pattern_tokens, &expansion[idx]);
+            let how = if idx == 0 { " (list expansion)" } else { "" };
+            outputs.push(format!("// PATTERN: {}{}", pattern_match.pattern_name, how));
+            outputs.extend(push_match(
+                app,
+                &expanded[idx],
+                pattern_match,
+                pattern_tokens,
+                CONJUNCTION_CERTAINTY,
+            ));
+            outputs.extend(push_alternatives(
+                app,
+                &expanded[idx],
+                pattern_match,
+                &patterns_with_tokens,
+                CONJUNCTION_CERTAINTY,
+            ));
+        }
+        return outputs.join("\n") + "\n";
+    }
+
     for &conj_idx in words
         .iter()
         .enumerate()
@@ -179,19 +462,17 @@ pub fn parse_prolog(app: &mut PrologApp, sentence: &String) -> String {
 
         let mut subject_end_idx = 0;
         for (i, word) in words.iter().enumerate() {
-            if let Ok(read_database) = app.database.read() {
-                if let Some(entries) = read_database.get_word_entries(word) {
-                    if entries
-                        .iter()
-                        .any(|e| matches!(e.word_type, crate::app::database::WordType::Noun))
-                    {
-                        subject_end_idx = i + 1;
-                        break;
-                    }
-                } else {
+            if let Some(entries) = read_database.get_word_entries(word) {
+                if entries
+                    .iter()
+                    .any(|e| matches!(e.word_type, crate::app::database::WordType::Noun))
+                {
                     subject_end_idx = i + 1;
                     break;
                 }
+            } else {
+                subject_end_idx = i + 1;
+                break;
             }
         }
 
@@ -203,32 +484,30 @@ pub fn parse_prolog(app: &mut PrologApp, sentence: &String) -> String {
 
             let mut first_match = None;
             let mut second_match = None;
-            let mut first_pattern_name = String::new();
-            let mut second_pattern_name = String::new();
-            let mut first_pattern_tokens = Vec::new();
-            let mut second_pattern_tokens = Vec::new();
 
             for pattern in sorted_patterns.iter() {
                 let pattern_tokens = parse_pattern(&pattern.pattern);
 
                 if first_match.is_none() {
-                    if let Some(captures) =
-                        try_match_pattern(&first_sentence, &pattern_tokens, &app)
-                    {
-                        first_match = Some((captures, pattern.template.clone()));
-                        first_pattern_name = pattern.name.clone();
-                        first_pattern_tokens = pattern_tokens.clone();
-                    }
+                    first_match = match_pattern(
+                        &first_sentence,
+                        &pattern_tokens,
+                        &pattern.name,
+                        &pattern.template,
+                        &app,
+                    )
+                    .map(|m| (m, pattern_tokens.clone()));
                 }
 
                 if second_match.is_none() {
-                    if let Some(captures) =
-                        try_match_pattern(&second_sentence, &pattern_tokens, &app)
-                    {
-                        second_match = Some((captures, pattern.template.clone()));
-                        second_pattern_name = pattern.name.clone();
-                        second_pattern_tokens = pattern_tokens.clone();
-                    }
+                    second_match = match_pattern(
+                        &second_sentence,
+                        &pattern_tokens,
+                        &pattern.name,
+                        &pattern.template,
+                        &app,
+                    )
+                    .map(|m| (m, pattern_tokens.clone()));
                 }
 
                 if first_match.is_some() && second_match.is_some() {
@@ -237,49 +516,36 @@ pub fn parse_prolog(app: &mut PrologApp, sentence: &String) -> String {
             }
 
             if let (
-                Some((first_captures, first_template)),
-                Some((second_captures, second_template)),
+                Some((first_pattern_match, first_pattern_tokens)),
+                Some((second_pattern_match, second_pattern_tokens)),
             ) = (first_match, second_match)
             {
-                let first_pattern_match = super::pattern_matcher::PatternMatch {
-                    pattern_name: first_pattern_name.clone(),
-                    template: first_template.clone(),
-                    captures: first_captures.clone(),
-                    start_idx: 0,
-                    end_idx: first_sentence.len(),
-                };
-                let first_interactive = create_interactive_match(
+                let second_positions: Vec<usize> =
+                    (0..subject_end_idx).chain(conj_idx + 1..words.len()).collect();
+                coverage.mark(&first_pattern_match, &first_pattern_tokens, &identity);
+                coverage.mark(&second_pattern_match, &second_pattern_tokens, &second_positions);
+
+                let mut outputs = Vec::new();
+                outputs.push(format!("// FROM: {}", sentence));
+                outputs.push(format!(
+                    "// PATTERN: {} (conjunction expansion)",
+                    first_pattern_match.pattern_name
+                ));
+                outputs.extend(push_match(
+                    app,
                     &first_sentence,
                     &first_pattern_match,
                     &first_pattern_tokens,
+                    CONJUNCTION_CERTAINTY,
+                ));
+                outputs.push(format!("// PATTERN: {}", second_pattern_match.pattern_name));
+                outputs.extend(push_match(
                     app,
-                );
-                app.interactive_parser.matches.push(first_interactive);
-
-                let second_pattern_match = super::pattern_matcher::PatternMatch {
-                    pattern_name: second_pattern_name.clone(),
-                    template: second_template.clone(),
-                    captures: second_captures.clone(),
-                    start_idx: 0,
-                    end_idx: second_sentence.len(),
-                };
-                let second_interactive = create_interactive_match(
                     &second_sentence,
                     &second_pattern_match,
                     &second_pattern_tokens,
-                    app,
-                );
-                app.interactive_parser.matches.push(second_interactive);
-
-                let mut outputs = Vec::new();
-                outputs.push(format!("// FROM: {}", sentence));
-                outputs.push(format!(
-                    "// PATTERN: {} (conjunction expansion)",
-                    first_pattern_name
+                    CONJUNCTION_CERTAINTY,
                 ));
-                outputs.extend(apply_template(&first_captures, &first_template));
-                outputs.push(format!("// PATTERN: {}", second_pattern_name));
-                outputs.extend(apply_template(&second_captures, &second_template));
                 return outputs.join("\n") + "\n";
             }
         }
@@ -297,9 +563,27 @@ pub fn parse_prolog(app: &mut PrologApp, sentence: &String) -> String {
             for pattern in sorted_patterns.iter() {
                 let pattern_tokens = parse_pattern(&pattern.pattern);
 
-                if try_match_pattern(&first_sentence, &pattern_tokens, &app).is_some()
-                    && try_match_pattern(&second_sentence, &pattern_tokens, &app).is_some()
-                {
+                let first_match = match_pattern(
+                    &first_sentence,
+                    &pattern_tokens,
+                    &pattern.name,
+                    &pattern.template,
+                    &app,
+                );
+                let second_match = match_pattern(
+                    &second_sentence,
+                    &pattern_tokens,
+                    &pattern.name,
+                    &pattern.template,
+                    &app,
+                );
+
+                if let (Some(first_match), Some(second_match)) = (first_match, second_match) {
+                    let second_positions: Vec<usize> =
+                        (0..split_point).chain(conj_idx + 1..words.len()).collect();
+                    coverage.mark(&first_match, &pattern_tokens, &identity);
+                    coverage.mark(&second_match, &pattern_tokens, &second_positions);
+
                     let mut outputs = Vec::new();
                     outputs.push(format!("// FROM: {}", sentence));
                     outputs.push(format!(
@@ -307,46 +591,20 @@ pub fn parse_prolog(app: &mut PrologApp, sentence: &String) -> String {
                         pattern.name
                     ));
 
-                    if let Some(first_captures) =
-                        try_match_pattern(&first_sentence, &pattern_tokens, &app)
-                    {
-                        let first_pattern_match = super::pattern_matcher::PatternMatch {
-                            pattern_name: pattern.name.clone(),
-                            template: pattern.template.clone(),
-                            captures: first_captures.clone(),
-                            start_idx: 0,
-                            end_idx: first_sentence.len(),
-                        };
-                        let first_interactive = create_interactive_match(
-                            &first_sentence,
-                            &first_pattern_match,
-                            &pattern_tokens,
-                            app,
-                        );
-                        app.interactive_parser.matches.push(first_interactive);
-
-                        outputs.extend(apply_template(&first_captures, &pattern.template));
-                    }
-                    if let Some(second_captures) =
-                        try_match_pattern(&second_sentence, &pattern_tokens, &app)
-                    {
-                        let second_pattern_match = super::pattern_matcher::PatternMatch {
-                            pattern_name: pattern.name.clone(),
-                            template: pattern.template.clone(),
-                            captures: second_captures.clone(),
-                            start_idx: 0,
-                            end_idx: second_sentence.len(),
-                        };
-                        let second_interactive = create_interactive_match(
-                            &second_sentence,
-                            &second_pattern_match,
-                            &pattern_tokens,
-                            app,
-                        );
-                        app.interactive_parser.matches.push(second_interactive);
-
-                        outputs.extend(apply_template(&second_captures, &pattern.template));
-                    }
+                    outputs.extend(push_match(
+                        app,
+                        &first_sentence,
+                        &first_match,
+                        &pattern_tokens,
+                        CONJUNCTION_CERTAINTY,
+                    ));
+                    outputs.extend(push_match(
+                        app,
+                        &second_sentence,
+                        &second_match,
+                        &pattern_tokens,
+                        CONJUNCTION_CERTAINTY,
+                    ));
 
                     return outputs.join("\n") + "\n";
                 }
@@ -357,19 +615,22 @@ pub fn parse_prolog(app: &mut PrologApp, sentence: &String) -> String {
     for pattern in sorted_patterns {
         let pattern_tokens = parse_pattern(&pattern.pattern);
 
-        if let Some(captures) = try_match_pattern(&words, &pattern_tokens, &app) {
-            let pattern_match = super::pattern_matcher::PatternMatch {
-                pattern_name: pattern.name.clone(),
-                template: pattern.template.clone(),
-                captures: captures.clone(),
-                start_idx: 0,
-                end_idx: words.len(),
-            };
-            let interactive_match =
-                create_interactive_match(&words, &pattern_match, &pattern_tokens, app);
-            app.interactive_parser.matches.push(interactive_match);
-
-            let prolog_outputs = apply_template(&captures, &pattern.template);
+        if let Some(pattern_match) = match_pattern(
+            &words,
+            &pattern_tokens,
+            &pattern.name,
+            &pattern.template,
+            &app,
+        ) {
+            coverage.mark(&pattern_match, &pattern_tokens, &identity);
+            let mut prolog_outputs = push_match(app, &words, &pattern_match, &pattern_tokens, 1.0);
+            prolog_outputs.extend(push_alternatives(
+                app,
+                &words,
+                &pattern_match,
+                &patterns_with_tokens,
+                1.0,
+            ));
             let output = prolog_outputs.join("\n");
             return format!(
                 "// FROM: {}\n// PATTERN: {}\n{}\n",
@@ -377,30 +638,32 @@ pub fn parse_prolog(app: &mut PrologApp, sentence: &String) -> String {
             );
         }
 
-        if let Some((captures, start_idx)) =
-            try_match_pattern_substring(&words, &pattern_tokens, &app)
-        {
-            let match_len = captures
-                .iter()
-                .map(|c| c.split_whitespace().count())
-                .sum::<usize>()
-                .max(1);
-            let pattern_match = super::pattern_matcher::PatternMatch {
-                pattern_name: pattern.name.clone(),
-                template: pattern.template.clone(),
-                captures: captures.clone(),
-                start_idx,
-                end_idx: start_idx + match_len,
-            };
-            let interactive_match =
-                create_interactive_match(&words[start_idx..], &pattern_match, &pattern_tokens, app);
-            app.interactive_parser.matches.push(interactive_match);
-
-            let prolog_outputs = apply_template(&captures, &pattern.template);
+        if let Some(pattern_match) = match_pattern_substring(
+            &words,
+            &pattern_tokens,
+            &pattern.name,
+            &pattern.template,
+            &app,
+        ) {
+            coverage.mark(&pattern_match, &pattern_tokens, &identity);
+            let mut prolog_outputs = push_match(
+                app,
+                &words,
+                &pattern_match,
+                &pattern_tokens,
+                SUBSTRING_CERTAINTY,
+            );
+            prolog_outputs.extend(push_alternatives(
+                app,
+                &words,
+                &pattern_match,
+                &patterns_with_tokens,
+                SUBSTRING_CERTAINTY,
+            ));
             let output = prolog_outputs.join("\n");
             return format!(
                 "// FROM: {}\n// PATTERN: {} (substring match at word {})\n{}\n",
-                sentence, pattern.name, start_idx, output
+                sentence, pattern.name, pattern_match.start_idx, output
             );
         }
     }
@@ -412,34 +675,457 @@ pub fn parse_prolog(app: &mut PrologApp, sentence: &String) -> String {
     )
 }
 
+/// Adds the interactive view of a match and returns its output: a warning
+/// for every unknown word that only matched through the word-type fallback,
+/// then the template output. Those words are queued for review in the
+/// Database Editor. `certainty` scales the match's confidence for
+/// how it was found; below the Parsing panel threshold the facts are
+/// commented out. The rules of a rule template or universal pattern are
+/// written out in the interactive view, which leaves nothing to reassign.
+/// With tense tagging on, the facts carry the tense of the matched verb.
+fn push_match(
+    app: &mut PrologApp,
+    words: &[String],
+    pattern_match: &PatternMatch,
+    pattern_tokens: &[PatternToken],
+    certainty: f32,
+) -> Vec<String> {
+    let universal = is_universal(app, &pattern_match.pattern_name);
+    let pattern_match = tensed_match(app, words, pattern_match, pattern_tokens);
+    let pattern_match = &*pattern_match;
+
+    let mut lines = fallback_warnings(words, pattern_match, pattern_tokens, app);
+    for (word, word_type) in fallback_types(words, pattern_match, pattern_tokens, app) {
+        app.interactive_parser.queue_unknown_word(word, word_type, &words.join(" "));
+    }
+    let mut interactive_match =
+        create_interactive_match(words, pattern_match, pattern_tokens, &app.database_snapshot);
+    interactive_match.confidence = certainty * pattern_match.confidence;
+
+    let rules = if is_rule_template(&pattern_match.template) {
+        Some(rule_lines(app, words, pattern_match).unwrap_or_else(|e| {
+            lines.push(format!("// WARNING: {}", e));
+            Vec::new()
+        }))
+    } else if universal {
+        Some(universal_rule_lines(
+            pattern_match,
+            pattern_tokens,
+            &app.database_snapshot.morphology,
+        ))
+    } else {
+        None
+    };
+    let facts = match rules {
+        Some(rules) => {
+            interactive_match.template = rules.join("\n");
+            interactive_match.highlights.clear();
+            interactive_match.regenerate_output();
+            rules
+        }
+        None => apply_template(&pattern_match.captures, &pattern_match.template),
+    };
+    if app.interactive_parser.is_hidden(&interactive_match) {
+        lines.extend(hidden_facts(&facts, interactive_match.confidence));
+    } else {
+        lines.push(confidence_comment(interactive_match.confidence));
+        lines.extend(facts);
+    }
+    app.interactive_parser.matches.push(interactive_match);
+    lines
+}
+
+/// Whether the pattern named `pattern_name` writes universal rules.
+fn is_universal(app: &PrologApp, pattern_name: &str) -> bool {
+    app.database_snapshot
+        .patterns
+        .iter()
+        .any(|pattern| pattern.name == pattern_name && pattern.universal_rules)
+}
+
+/// `pattern_match` with its template tagged with the tense of the matched
+/// verb, when tense tagging is on. Tagged in the template, so corrections of
+/// the match keep the tense.
+fn tensed_match<'a>(
+    app: &PrologApp,
+    words: &[String],
+    pattern_match: &'a PatternMatch,
+    pattern_tokens: &[PatternToken],
+) -> Cow<'a, PatternMatch> {
+    let tagged = app.settings.tag_tense
+        && !is_universal(app, &pattern_match.pattern_name)
+        && !is_rule_template(&pattern_match.template);
+    let tense = if tagged {
+        match_tense(words, pattern_match, pattern_tokens, &app.database_snapshot)
+    } else {
+        None
+    };
+    match tense {
+        Some(tense) => Cow::Owned(PatternMatch {
+            template: tensed_template(&pattern_match.template, tense),
+            ..pattern_match.clone()
+        }),
+        None => Cow::Borrowed(pattern_match),
+    }
+}
+
+/// Records on the last interactive match the other patterns that match
+/// exactly the words `pattern_match` covers, most confident first, so the
+/// Parsing panel can offer them as alternative interpretations. Rule
+/// templates and universal patterns are left out, as what they write
+/// depends on more than the captures. Returns a comment naming them, if
+/// there are any.
+fn push_alternatives(
+    app: &mut PrologApp,
+    words: &[String],
+    pattern_match: &PatternMatch,
+    patterns_with_tokens: &[(String, String, Vec<PatternToken>)],
+    certainty: f32,
+) -> Option<String> {
+    let span = &words[pattern_match.start_idx..pattern_match.end_idx];
+    let mut alternatives: Vec<SentenceMatch> = patterns_with_tokens
+        .iter()
+        .filter(|(name, template, _)| {
+            *name != pattern_match.pattern_name
+                && !is_rule_template(template)
+                && !is_universal(app, name)
+        })
+        .filter_map(|(name, template, tokens)| {
+            let alternative = match_pattern(span, tokens, name, template, app)?;
+            let alternative = tensed_match(app, span, &alternative, tokens);
+            let mut interactive =
+                create_interactive_match(span, &alternative, tokens, &app.database_snapshot);
+            interactive.confidence = certainty * alternative.confidence;
+            Some(interactive)
+        })
+        .collect();
+    if alternatives.is_empty() {
+        return None;
+    }
+    alternatives.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    let names: Vec<&str> = alternatives
+        .iter()
+        .map(|alternative| alternative.pattern_name.as_str())
+        .collect();
+    let comment = format!("// ALTERNATIVES: {}", names.join(", "));
+    if let Some(last) = app.interactive_parser.matches.last_mut() {
+        last.alternatives = alternatives;
+    }
+    Some(comment)
+}
+
+/// The comment giving the confidence of the match whose facts follow.
+pub fn confidence_comment(confidence: f32) -> String {
+    format!("// CONFIDENCE: {:.2}", confidence)
+}
+
+/// Facts of a match below the confidence threshold, commented out so they
+/// stay readable in the output without reaching the query engine.
+pub fn hidden_facts(facts: &[String], confidence: f32) -> Vec<String> {
+    let mut lines = vec![format!("// HIDDEN: confidence {:.2}", confidence)];
+    lines.extend(facts.iter().map(|fact| format!("// {}", fact)));
+    lines
+}
+
 pub fn parse_input(app: &mut PrologApp, input: &String) -> String {
     app.interactive_parser.clear();
-    let sentences = parse_sentences(input);
+    // Everything below reads this snapshot, leaving the database free for edits
+    app.refresh_database_snapshot();
+    let locale = app.database_snapshot.locale;
+    let (text, directives) = extract_directives(input);
+    let sentences = parse_sentences(&text, locale);
+    app.interactive_parser.directives = directives;
 
     // Initialize pronoun resolver for this document
-    let mut pronoun_resolver = PronounResolver::new();
+    let participant = |role: &str, setting: &str| {
+        let name = document_participant(&app.interactive_parser.directives, role)
+            .unwrap_or_else(|| setting.trim().to_string());
+        let words: Vec<String> = name.split_whitespace().map(|w| locale.lowercase(w)).collect();
+        (!words.is_empty()).then(|| words.join("_"))
+    };
+    let mut pronoun_resolver = PronounResolver::new()
+        .with_window(app.settings.pronoun_window)
+        .with_participants(
+            participant(SPEAKER_ROLE, &app.settings.speaker),
+            participant(ADDRESSEE_ROLE, &app.settings.addressee),
+        );
 
     let mut parsed_sentences = Vec::new();
     for sentence in &sentences {
         // Resolve pronouns in the sentence
-        let words: Vec<String> = sentence
-            .trim_end_matches('.')
+        let words: Vec<String> = sentence_body(sentence)
             .split_whitespace()
             .map(|s| s.to_string())
             .collect();
 
-        let resolved_words = pronoun_resolver.resolve_sentence(&words, &app.database);
+        let overrides = app
+            .interactive_parser
+            .antecedent_overrides
+            .get(sentence)
+            .cloned()
+            .unwrap_or_default();
+        let resolved_words =
+            pronoun_resolver.resolve_sentence(&words, &app.database_snapshot, &overrides);
+        app.interactive_parser
+            .pronoun_resolutions
+            .insert(sentence.clone(), pronoun_resolver.resolutions().to_vec());
 
-        // Reconstruct sentence with resolved pronouns
-        let resolved_sentence = resolved_words.join(" ") + ".";
+        // Locked sentences keep their accepted parse, and count as covered
+        if let Some(locked) = app.interactive_parser.locked.get(sentence).cloned() {
+            app.interactive_parser.coverage.push(SentenceCoverage {
+                coverage: vec![WordCoverage::Matched; words.len()],
+                words,
+            });
+            app.interactive_parser.matches.extend(locked.matches);
+            parsed_sentences.push(locked.output);
+            pronoun_resolver.next_sentence();
+            continue;
+        }
+
+        // Reconstruct sentence with resolved pronouns, keeping it a question
+        let terminator = if is_question(sentence) { "?" } else { "." };
+        let resolved_sentence = resolved_words.join(" ") + terminator;
 
         // Parse the resolved sentence
+        let first_match = app.interactive_parser.matches.len();
+        let first_coverage = app.interactive_parser.coverage.len();
         let parsed = parse_prolog(app, &resolved_sentence);
-        parsed_sentences.push(parsed);
+        for sentence_match in &mut app.interactive_parser.matches[first_match..] {
+            sentence_match.sentence = sentence.clone();
+        }
+        // Coverage is shown over the input, so it keeps the unresolved pronouns
+        for sentence_coverage in &mut app.interactive_parser.coverage[first_coverage..] {
+            sentence_coverage.words = words.clone();
+        }
+
+        // Captures reassigned by hand stick while the parse is unchanged
+        let corrected = app
+            .interactive_parser
+            .correction_for(sentence, &app.interactive_parser.matches[first_match..])
+            .map(<[_]>::to_vec);
+        match corrected {
+            Some(mut corrected) => {
+                // The interpretations not chosen stay on offer
+                let fresh = &app.interactive_parser.matches[first_match..];
+                for (corrected_match, fresh_match) in corrected.iter_mut().zip(fresh) {
+                    corrected_match.alternatives =
+                        fresh_match.alternatives_to(&corrected_match.pattern_name);
+                }
+                let mut output = format!("// FROM: {}\n// CORRECTED\n", sentence);
+                for sentence_match in &corrected {
+                    output.push_str(&sentence_match.generated_output);
+                    output.push('\n');
+                }
+                app.interactive_parser.matches.truncate(first_match);
+                app.interactive_parser.matches.extend(corrected);
+                parsed_sentences.push(output);
+            }
+            None => parsed_sentences.push(parsed),
+        }
 
         // Move to next sentence for pronoun tracking
         pronoun_resolver.next_sentence();
     }
 
-    parsed_sentences.join("\n\n")
+    let mut output = parsed_sentences.join("\n\n");
+    let directives = directives_output(&app.interactive_parser.directives);
+    if !directives.is_empty() {
+        output = format!("{}\n{}", directives, output);
+    }
+    output
+}
+
+/// A line of the input that bypasses sentence parsing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Directive {
+    /// `:- ...`: a rule or pattern for the query engine.
+    Clause(String),
+    /// `> ...`: a fact written to the output as-is, for patching gaps in
+    /// extraction.
+    Fact(String),
+}
+
+/// Removes comment lines (`%`) and directive lines (`:-` and `>`) from the
+/// input, returning the remaining text and the directives in order.
+fn extract_directives(input: &str) -> (String, Vec<Directive>) {
+    let mut text = String::new();
+    let mut directives = Vec::new();
+
+    for line in input.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with('%') {
+            continue;
+        }
+        if let Some(clause) = trimmed.strip_prefix(":-") {
+            let clause = clause.trim();
+            if !clause.is_empty() {
+                directives.push(Directive::Clause(clause.to_string()));
+            }
+            continue;
+        }
+        if let Some(fact) = trimmed.strip_prefix('>') {
+            let fact = fact.trim().trim_end_matches('.');
+            if !fact.is_empty() {
+                directives.push(Directive::Fact(format!("{}.", fact)));
+            }
+            continue;
+        }
+        text.push_str(line);
+    }
+
+    (text, directives)
+}
+
+/// The name a document gives the speaker or addressee with
+/// `:- speaker(alice).` or `:- addressee(bob).`; the last one counts.
+fn document_participant(directives: &[Directive], role: &str) -> Option<String> {
+    directives.iter().rev().find_map(|directive| match directive {
+        Directive::Clause(clause) => participant_name(clause, role),
+        Directive::Fact(_) => None,
+    })
+}
+
+/// The name in a `role(name).` clause.
+fn participant_name(clause: &str, role: &str) -> Option<String> {
+    let name = clause
+        .trim_end_matches('.')
+        .strip_prefix(role)?
+        .trim()
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Prolog output for input directives: facts, rules (`:-`), patterns (`-->`)
+/// and `table`, `dynamic` and `discontiguous` declarations are written for
+/// the query engine to load, the speaker and addressee as comments, any
+/// other clause as a warning.
+pub fn directives_output(directives: &[Directive]) -> String {
+    let mut output = String::new();
+    for directive in directives {
+        match directive {
+            Directive::Fact(fact) => output.push_str(&format!("// VERBATIM\n{}\n", fact)),
+            Directive::Clause(clause) if clause.contains(":-") || clause.contains("-->") => {
+                output.push_str(&format!("// DIRECTIVE\n{}\n", clause))
+            }
+            Directive::Clause(clause)
+                if ["table ", "dynamic ", "discontiguous "]
+                    .iter()
+                    .any(|declaration| clause.starts_with(declaration)) =>
+            {
+                output.push_str(&format!("// DIRECTIVE\n:- {}\n", clause))
+            }
+            Directive::Clause(clause)
+                if [SPEAKER_ROLE, ADDRESSEE_ROLE]
+                    .iter()
+                    .any(|role| participant_name(clause, role).is_some()) =>
+            {
+                output.push_str(&format!("// PARTICIPANT: {}\n", clause))
+            }
+            Directive::Clause(clause) => output.push_str(&format!(
+                "// WARNING: Unsupported directive (expected a rule, pattern or declaration): {}\n",
+                clause
+            )),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{
+        database::{Database, fixtures::pattern},
+        query_engine::QueryEngine,
+    };
+
+    #[test]
+    fn test_comments_and_directives_are_taken_out_of_the_text() {
+        let input = "% a comment\nThe cat sleeps.\n:- mortal(X) :- cat(X).\n  :- dynamic cat/1.\n:- ensure_loaded(lib).\n> cat(tom)\nThe dog runs.";
+        let (text, directives) = extract_directives(input);
+
+        assert_eq!(text, "The cat sleeps.\nThe dog runs.");
+        assert_eq!(
+            directives,
+            vec![
+                Directive::Clause("mortal(X) :- cat(X).".to_string()),
+                Directive::Clause("dynamic cat/1.".to_string()),
+                Directive::Clause("ensure_loaded(lib).".to_string()),
+                Directive::Fact("cat(tom).".to_string()),
+            ]
+        );
+        assert_eq!(
+            directives_output(&directives),
+            "// DIRECTIVE\nmortal(X) :- cat(X).\n// DIRECTIVE\n:- dynamic cat/1.\n// WARNING: Unsupported directive (expected a rule, pattern or declaration): ensure_loaded(lib).\n// VERBATIM\ncat(tom).\n"
+        );
+
+        let (_, directives) = extract_directives(":- speaker(alice).\n:- addressee( bob ).");
+        assert_eq!(document_participant(&directives, SPEAKER_ROLE), Some("alice".to_string()));
+        assert_eq!(document_participant(&directives, ADDRESSEE_ROLE), Some("bob".to_string()));
+        assert_eq!(
+            directives_output(&directives),
+            "// PARTICIPANT: speaker(alice).\n// PARTICIPANT: addressee( bob ).\n"
+        );
+    }
+
+    #[test]
+    fn test_marker_lines_override_sentence_boundaries() {
+        let input = "Section 2.1 applies\n---\nThe cat sleeps. The dog runs.\n#nosplit\nSee fig. 3. It shows cats.\n---\nLet x = 1. Then y = 2.\n#split\nBirds fly.".to_string();
+
+        assert_eq!(
+            parse_sentences(&input, Locale::English),
+            vec![
+                "section 2.1 applies",
+                "the cat sleeps.",
+                "the dog runs.",
+                "see fig. 3. it shows cats.",
+                "let x = 1. then y = 2.",
+                "birds fly.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sentences_end_at_terminators_but_not_abbreviations_or_decimals() {
+        let input = "Dr. Smith likes pizza. J. Doe counted 3.5 million people! Did he? \"Yes.\" \"No,\" he said.".to_string();
+
+        assert_eq!(
+            parse_sentences(&input, Locale::English),
+            vec![
+                "dr. smith likes pizza.",
+                "j. doe counted 3.5 million people!",
+                "did he?",
+                "\"yes.\"",
+                "\"no,\" he said.",
+            ]
+        );
+        assert_eq!(sentence_body("\"yes.\""), "yes");
+        assert_eq!(sentence_body("wow!"), "wow");
+        assert!(is_question("\"did he?\""));
+    }
+
+    #[test]
+    fn test_parses_read_one_database_version_while_an_edit_is_written() {
+        let pattern = pattern("<Noun> is a <Noun>", "is_a($1, $2).");
+        let database = Database {
+            patterns: vec![pattern],
+            ..Database::default()
+        };
+        let mut app = PrologApp::with_database(database, QueryEngine::new());
+        let input = "Tom is a cat.".to_string();
+
+        let shared = Arc::clone(&app.database);
+        let mut write_database = shared.write().unwrap();
+        write_database.patterns[0].template = "kind($1, $2).".to_string();
+        // The edit is not finished, so the parse keeps the version before it
+        let output = parse_input(&mut app, &input);
+        assert!(output.contains("is_a(tom, cat)."), "{}", output);
+        drop(write_database);
+
+        let output = parse_input(&mut app, &input);
+        assert!(output.contains("kind(tom, cat)."), "{}", output);
+        assert!(!output.contains("is_a("), "{}", output);
+    }
 }
@@ -1,18 +1,98 @@
-use crate::app::{PrologApp, database::WordType};
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::app::{
+    PrologApp,
+    database::{Database, Number, PrologPattern, WordType},
+    morphology::singularize,
+};
+
+use super::temporal::TemporalKind;
+
+
+/// What a `<Type>` token does with a word that is not in the database.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WordFallback {
+    /// Unknown words never match.
+    Strict,
+    /// Unknown words count as nouns.
+    Noun,
+    /// Unknown words get a type guessed from their ending.
+    Guess,
+}
+
+impl WordFallback {
+    pub const ALL: [WordFallback; 3] = [
+        WordFallback::Strict,
+        WordFallback::Noun,
+        WordFallback::Guess,
+    ];
+
+    /// The name used in patterns, e.g. `<Noun:strict>`.
+    pub fn name(self) -> &'static str {
+        match self {
+            WordFallback::Strict => "strict",
+            WordFallback::Noun => "noun",
+            WordFallback::Guess => "guess",
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum PatternToken {
-    Literal(String),             // literal word match
-    TypeMatch(Vec<WordType>),    // <Noun|Verb> matches any of the specified types
+    Literal(String), // literal word match; "such as" matches the whole phrase, word by word
+    // (is|are|was) matches any one of the literal words (not captured)
+    Alternatives(Vec<String>),
+    // <Noun|Verb> matches any of the specified types and <Noun&!Pronoun> or <!Verb>
+    // none of the excluded ones (an empty list allows any type); <Noun:strict>
+    // overrides the fallback for unknown words, otherwise the Settings default applies
+    TypeMatch(Vec<WordType>, Vec<WordType>, Option<WordFallback>),
+    // <Date> and <Time> match date and time expressions, captured as atoms like date(1990)
+    Temporal(TemporalKind),
+    // <re:^[0-9]+$> matches a word the regex finds a match in, like <Type> (captured)
+    Regex(Regex),
     Wildcard,                    // * matches any single word (not captured)
+    // word:$, (is|are):$ or *:$ also captures what the literal or wildcard matched
+    Captured(Box<PatternToken>),
     Optional(Box<PatternToken>), // [token] matches 0 or 1 times
     Greedy(Box<PatternToken>), // token+ matches one or more times (captured and formatted as lowercase_with_underscores)
 }
 
+/// The suffix that makes a literal or wildcard capture: `*:$`.
+const CAPTURE_MARKER: &str = ":$";
+
+/// What opens a regex token, `<re:^[0-9]+$>`.
+const REGEX_PREFIX: &str = "<re:";
+
+/// The elements of `pattern`, split at whitespace except within double
+/// quotes, so `"such as"+` stays one element.
+pub fn pattern_elements(pattern: &str) -> Vec<&str> {
+    let mut elements = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    for (idx, ch) in pattern.char_indices() {
+        if ch.is_whitespace() && !quoted {
+            if let Some(start) = start.take() {
+                elements.push(&pattern[start..idx]);
+            }
+            continue;
+        }
+        if ch == '"' {
+            quoted = !quoted;
+        }
+        start.get_or_insert(idx);
+    }
+    if let Some(start) = start {
+        elements.push(&pattern[start..]);
+    }
+    elements
+}
+
 pub fn parse_pattern(pattern: &str) -> Vec<PatternToken> {
     let mut tokens = Vec::new();
 
-    for element in pattern.split_whitespace() {
+    for element in pattern_elements(pattern) {
         if element.is_empty() {
             continue;
         }
@@ -22,32 +102,73 @@ pub fn parse_pattern(pattern: &str) -> Vec<PatternToken> {
         } else {
             (element, false)
         };
+        let (base_element, is_captured) = match base_element.strip_suffix(CAPTURE_MARKER) {
+            Some(inner) if !inner.is_empty() => (inner, true),
+            _ => (base_element, false),
+        };
 
         let base_token = if base_element == "*" {
             Some(PatternToken::Wildcard)
+        } else if let Some(kind) = TemporalKind::ALL
+            .into_iter()
+            .find(|kind| base_element == format!("<{}>", kind.name()))
+        {
+            Some(PatternToken::Temporal(kind))
+        } else if let Some(expression) = base_element
+            .strip_prefix(REGEX_PREFIX)
+            .and_then(|rest| rest.strip_suffix('>'))
+        {
+            // An invalid regex is left out, like an unknown type
+            Regex::new(expression).ok().map(PatternToken::Regex)
         } else if base_element.starts_with('<') && base_element.ends_with('>') {
-            let type_str = &base_element[1..base_element.len() - 1];
-            let types: Vec<WordType> = type_str
-                .split('|')
-                .filter_map(|s| match s.trim() {
-                    "Noun" => Some(WordType::Noun),
-                    "Verb" => Some(WordType::Verb),
-                    "Adjective" => Some(WordType::Adjective),
-                    "Adverb" => Some(WordType::Adverb),
-                    "Pronoun" => Some(WordType::Pronoun),
-                    "Preposition" => Some(WordType::Preposition),
-                    "Conjunction" => Some(WordType::Conjunction),
-                    "Interjection" => Some(WordType::Interjection),
-                    "Determiner" => Some(WordType::Determiner),
-                    _ => None,
-                })
-                .collect();
+            let inner = &base_element[1..base_element.len() - 1];
+            let (type_str, fallback) = match inner.split_once(':') {
+                Some((types, policy)) => (
+                    types,
+                    WordFallback::ALL
+                        .into_iter()
+                        .find(|f| f.name() == policy.trim()),
+                ),
+                None => (inner, None),
+            };
+            let type_list = |list: &str| -> Vec<WordType> {
+                list.split('|')
+                    .filter_map(|s| match s.trim() {
+                        "Noun" => Some(WordType::Noun),
+                        "Verb" => Some(WordType::Verb),
+                        "Adjective" => Some(WordType::Adjective),
+                        "Adverb" => Some(WordType::Adverb),
+                        "Pronoun" => Some(WordType::Pronoun),
+                        "Preposition" => Some(WordType::Preposition),
+                        "Conjunction" => Some(WordType::Conjunction),
+                        "Interjection" => Some(WordType::Interjection),
+                        "Determiner" => Some(WordType::Determiner),
+                        _ => None,
+                    })
+                    .collect()
+            };
+            let mut types = Vec::new();
+            let mut excluded = Vec::new();
+            for part in type_str.split('&').map(str::trim) {
+                match part.strip_prefix('!') {
+                    Some(list) => excluded.extend(type_list(list)),
+                    None => types.extend(type_list(part)),
+                }
+            }
 
-            if !types.is_empty() {
-                Some(PatternToken::TypeMatch(types))
+            if !types.is_empty() || !excluded.is_empty() {
+                Some(PatternToken::TypeMatch(types, excluded, fallback))
             } else {
                 None
             }
+        } else if base_element.starts_with('(') && base_element.ends_with(')') {
+            let alternatives: Vec<String> = base_element[1..base_element.len() - 1]
+                .split('|')
+                .map(str::trim)
+                .filter(|word| !word.is_empty())
+                .map(str::to_string)
+                .collect();
+            (!alternatives.is_empty()).then_some(PatternToken::Alternatives(alternatives))
         } else if base_element.starts_with('[') && base_element.ends_with(']') {
             let inner = &base_element[1..base_element.len() - 1];
             let inner_tokens = parse_pattern(inner);
@@ -56,10 +177,25 @@ pub fn parse_pattern(pattern: &str) -> Vec<PatternToken> {
             } else {
                 None
             }
+        } else if let Some(phrase) = base_element
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+        {
+            let words: Vec<&str> = phrase.split_whitespace().collect();
+            (!words.is_empty()).then(|| PatternToken::Literal(words.join(" ")))
         } else {
             Some(PatternToken::Literal(base_element.to_string()))
         };
 
+        // Tokens that capture anyway need no marker
+        let base_token = base_token.map(|token| {
+            if is_captured && !is_capturing(&token) {
+                PatternToken::Captured(Box::new(token))
+            } else {
+                token
+            }
+        });
+
         if let Some(token) = base_token {
             if is_greedy {
                 tokens.push(PatternToken::Greedy(Box::new(token)));
@@ -72,312 +208,1091 @@ pub fn parse_pattern(pattern: &str) -> Vec<PatternToken> {
     tokens
 }
 
-pub fn matches_token(word: &str, token: &PatternToken, app: &PrologApp) -> bool {
+/// Whether `token` captures the words it matches, taking a `$n` slot of its
+/// own: `<Type>`, `<Date>`, `<Time>` and `<re:..>` tokens and those marked
+/// with `:$`, optional or not, and greedy tokens.
+pub fn is_capturing(token: &PatternToken) -> bool {
     match token {
-        PatternToken::Literal(literal) => word.eq_ignore_case(literal),
-        PatternToken::TypeMatch(required_types) => {
-            let Ok(read_database) = app.database.read() else {
-                return false;
-            };
+        PatternToken::TypeMatch(..)
+        | PatternToken::Temporal(_)
+        | PatternToken::Regex(_)
+        | PatternToken::Captured(_)
+        | PatternToken::Greedy(_) => true,
+        PatternToken::Optional(inner) => matches!(
+            inner.as_ref(),
+            PatternToken::TypeMatch(..)
+                | PatternToken::Temporal(_)
+                | PatternToken::Regex(_)
+                | PatternToken::Captured(_)
+        ),
+        PatternToken::Literal(_) | PatternToken::Alternatives(_) | PatternToken::Wildcard => false,
+    }
+}
+
+/// The `$n` slot of the token at `token_idx`, counted from 0, if it
+/// captures. Slots are fixed by the pattern alone, so a `[<Adjective>]` that
+/// is left out leaves its `$n` empty instead of shifting the later ones.
+pub fn capture_slot(pattern_tokens: &[PatternToken], token_idx: usize) -> Option<usize> {
+    is_capturing(&pattern_tokens[token_idx]).then(|| {
+        pattern_tokens[..token_idx]
+            .iter()
+            .filter(|token| is_capturing(token))
+            .count()
+    })
+}
+
+/// The token as it is written in a pattern.
+pub fn token_label(token: &PatternToken) -> String {
+    match token {
+        PatternToken::Literal(phrase) if phrase.contains(' ') => format!("\"{}\"", phrase),
+        PatternToken::Literal(word) => word.clone(),
+        PatternToken::Alternatives(words) => format!("({})", words.join("|")),
+        PatternToken::TypeMatch(types, excluded, fallback) => {
+            let names = type_names(types, excluded);
+            match fallback {
+                Some(fallback) => format!("<{}:{}>", names, fallback.name()),
+                None => format!("<{}>", names),
+            }
+        }
+        PatternToken::Temporal(kind) => format!("<{}>", kind.name()),
+        PatternToken::Regex(regex) => format!("{}{}>", REGEX_PREFIX, regex.as_str()),
+        PatternToken::Wildcard => "*".to_string(),
+        PatternToken::Captured(inner) => format!("{}{}", token_label(inner), CAPTURE_MARKER),
+        PatternToken::Optional(inner) => format!("[{}]", token_label(inner)),
+        PatternToken::Greedy(inner) => format!("{}+", token_label(inner)),
+    }
+}
+
+/// The types of a `<Type>` token as written between its brackets:
+/// `Noun|Adjective`, `Noun&!Pronoun` or `!Verb`.
+fn type_names(types: &[WordType], excluded: &[WordType]) -> String {
+    let allowed = (!types.is_empty()).then(|| {
+        let names: Vec<String> = types.iter().map(|t| t.to_string()).collect();
+        names.join("|")
+    });
+    allowed
+        .into_iter()
+        .chain(excluded.iter().map(|t| format!("!{}", t)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
 
+/// Whether a `<Type>` token allows a reading as `word_type`: one of `types`,
+/// or any when there are none, and none of `excluded`.
+pub fn allows_type(types: &[WordType], excluded: &[WordType], word_type: &WordType) -> bool {
+    (types.is_empty() || types.contains(word_type)) && !excluded.contains(word_type)
+}
+
+/// A starter template for `pattern`: the first `<Verb>` capture names the
+/// predicate, or failing that the literal words do (`is_a`), an alternation
+/// group by its first word, and the other captures are its arguments in
+/// order. Determiner captures are counted but left out, as is a negation
+/// word, which prefixes the predicate with `not_`.
+pub fn suggest_template(pattern: &str) -> String {
+    let mut verb = None;
+    let mut negated = false;
+    let mut literals = Vec::new();
+    let mut arguments = Vec::new();
+    let mut capture = 0;
+
+    for token in parse_pattern(pattern) {
+        let literal = match &token {
+            PatternToken::Literal(word) => Some(word),
+            PatternToken::Alternatives(words) => words.first(),
+            _ => None,
+        };
+        if let Some(word) = literal {
+            match word.to_lowercase().as_str() {
+                "not" | "never" => negated = true,
+                word => literals.push(word.replace(' ', "_")),
+            }
+            continue;
+        }
+
+        let captured_by = match &token {
+            token if !is_capturing(token) => continue,
+            PatternToken::Optional(inner) | PatternToken::Greedy(inner) => inner.as_ref(),
+            token => token,
+        };
+        capture += 1;
+
+        let placeholder = format!("${}", capture);
+        match captured_by {
+            PatternToken::TypeMatch(types, ..) if types.as_slice() == [WordType::Determiner] => {}
+            PatternToken::TypeMatch(types, ..)
+                if verb.is_none() && types.contains(&WordType::Verb) =>
+            {
+                verb = Some(placeholder)
+            }
+            _ => arguments.push(placeholder),
+        }
+    }
+
+    let mut predicate = verb.unwrap_or_else(|| {
+        if literals.is_empty() {
+            "relation".to_string()
+        } else {
+            literals.join("_")
+        }
+    });
+    if negated {
+        predicate = format!("not_{}", predicate);
+    }
+    if arguments.is_empty() {
+        predicate
+    } else {
+        format!("{}({})", predicate, arguments.join(", "))
+    }
+}
+
+pub fn matches_token(word: &str, token: &PatternToken, app: &PrologApp) -> bool {
+    match token {
+        PatternToken::Literal(literal) => {
+            app.database_snapshot.locale.eq_ignore_case(word, literal)
+        }
+        PatternToken::Alternatives(literals) => literals
+            .iter()
+            .any(|literal| app.database_snapshot.locale.eq_ignore_case(word, literal)),
+        PatternToken::TypeMatch(..) | PatternToken::Temporal(_) | PatternToken::Regex(_)
+            if is_variable_word(word) =>
+        {
+            true
+        }
+        PatternToken::Temporal(kind) => kind.recognizes(word),
+        PatternToken::Regex(regex) => regex.is_match(word),
+        PatternToken::TypeMatch(required_types, excluded, fallback) => {
+            let read_database = &app.database_snapshot;
             if let Some(entries) = read_database.get_word_entries(word) {
+                // An excluded type rules the word out whatever its other readings
                 entries
                     .iter()
-                    .any(|entry| required_types.contains(&entry.word_type))
+                    .any(|entry| allows_type(required_types, &[], &entry.word_type))
+                    && !entries.iter().any(|entry| excluded.contains(&entry.word_type))
             } else {
-                required_types.contains(&WordType::Noun)
+                match fallback.unwrap_or(app.settings.word_fallback) {
+                    WordFallback::Strict => false,
+                    WordFallback::Noun => allows_type(required_types, excluded, &WordType::Noun),
+                    WordFallback::Guess => {
+                        allows_type(required_types, excluded, &guess_word_type(word))
+                    }
+                }
             }
         }
         PatternToken::Wildcard => true,
+        PatternToken::Captured(inner) => matches_token(word, inner, app),
         PatternToken::Optional(inner) => matches_token(word, inner, app),
         PatternToken::Greedy(inner) => matches_token(word, inner, app),
     }
 }
 
-pub fn try_match_pattern(
+/// How many words one match of `token` takes: those of a literal phrase,
+/// one for any other token.
+pub fn token_width(token: &PatternToken) -> usize {
+    match token {
+        PatternToken::Literal(phrase) => phrase.split(' ').count(),
+        PatternToken::Captured(inner)
+        | PatternToken::Optional(inner)
+        | PatternToken::Greedy(inner) => token_width(inner),
+        _ => 1,
+    }
+}
+
+/// Whether `token` matches the [`token_width`] words from `words[word_idx]`
+/// on; a literal phrase matches them word by word.
+fn matches_at(words: &[String], word_idx: usize, token: &PatternToken, app: &PrologApp) -> bool {
+    match token {
+        PatternToken::Literal(phrase) if phrase.contains(' ') => {
+            let locale = &app.database_snapshot.locale;
+            let mut run = words[word_idx..].iter();
+            phrase
+                .split(' ')
+                .all(|literal| run.next().is_some_and(|word| locale.eq_ignore_case(word, literal)))
+        }
+        PatternToken::Captured(inner)
+        | PatternToken::Optional(inner)
+        | PatternToken::Greedy(inner) => matches_at(words, word_idx, inner, app),
+        token => matches_token(&words[word_idx], token, app),
+    }
+}
+
+/// Guesses the type of a word missing from the database from its ending.
+pub fn guess_word_type(word: &str) -> WordType {
+    let word = word.to_lowercase();
+    let long_enough = |suffix: &str| word.len() > suffix.len() + 2 && word.ends_with(suffix);
+
+    if long_enough("ly") {
+        WordType::Adverb
+    } else if ["ing", "ed", "ize", "ise"].iter().any(|s| long_enough(s)) {
+        WordType::Verb
+    } else if ["ous", "ful", "ive", "able", "ible", "less", "ic", "al"]
+        .iter()
+        .any(|s| long_enough(s))
+    {
+        WordType::Adjective
+    } else {
+        WordType::Noun
+    }
+}
+
+/// One warning line per captured word that is not in the database and only
+/// matched its `<Type>` token through the fallback policy. `words` is the
+/// sentence the match was made against.
+pub fn fallback_warnings(
     words: &[String],
+    pattern_match: &PatternMatch,
     pattern_tokens: &[PatternToken],
     app: &PrologApp,
-) -> Option<Vec<String>> {
-    fn backtrack(
-        words: &[String],
-        word_idx: usize,
-        pattern_tokens: &[PatternToken],
-        pattern_idx: usize,
-        captures: &mut Vec<String>,
-        app: &PrologApp,
-    ) -> bool {
-        if pattern_idx >= pattern_tokens.len() {
-            return word_idx == words.len();
+) -> Vec<String> {
+    fallback_captures(words, pattern_match, pattern_tokens, &app.database_snapshot)
+        .into_iter()
+        .map(|(word, (types, excluded), fallback)| {
+            format!(
+                "// WARNING: '{}' is not in the database; matched <{}> by {} fallback",
+                word,
+                type_names(types, excluded),
+                fallback.unwrap_or(app.settings.word_fallback).name()
+            )
+        })
+        .collect()
+}
+
+/// The captured words that are not in the database, each with the type the
+/// fallback policy read it as: a noun, or the type guessed from its ending.
+/// Variables are left out; they are never meant to be words.
+pub fn fallback_types(
+    words: &[String],
+    pattern_match: &PatternMatch,
+    pattern_tokens: &[PatternToken],
+    app: &PrologApp,
+) -> Vec<(String, WordType)> {
+    fallback_captures(words, pattern_match, pattern_tokens, &app.database_snapshot)
+        .into_iter()
+        .filter(|(word, _, _)| !is_variable_word(word))
+        .map(|(word, _, fallback)| {
+            let word_type = match fallback.unwrap_or(app.settings.word_fallback) {
+                WordFallback::Guess => guess_word_type(word),
+                WordFallback::Strict | WordFallback::Noun => WordType::Noun,
+            };
+            (word.clone(), word_type)
+        })
+        .collect()
+}
+
+/// The allowed and excluded types of a `<Type>` token.
+type TokenTypes<'a> = (&'a [WordType], &'a [WordType]);
+
+/// The captured words that are not in the database, with the types and
+/// fallback policy of the `<Type>` token they matched.
+fn fallback_captures<'a>(
+    words: &'a [String],
+    pattern_match: &PatternMatch,
+    pattern_tokens: &'a [PatternToken],
+    database: &Database,
+) -> Vec<(&'a String, TokenTypes<'a>, Option<WordFallback>)> {
+    let mut captures = Vec::new();
+    for span in &pattern_match.spans {
+        let captured_by = match &pattern_tokens[span.token] {
+            PatternToken::Optional(inner) | PatternToken::Greedy(inner) => inner.as_ref(),
+            token => token,
+        };
+        let PatternToken::TypeMatch(types, excluded, fallback) = captured_by else {
+            continue;
+        };
+
+        for word in &words[span.start..span.end] {
+            if database.get_word_entries(word).is_none() {
+                captures.push((word, (types.as_slice(), excluded.as_slice()), *fallback));
+            }
         }
+    }
+    captures
+}
 
-        if word_idx >= words.len() {
-            return pattern_tokens[pattern_idx..]
-                .iter()
-                .all(|t| matches!(t, PatternToken::Optional(_)));
+/// How sure the parser is of a match, from 0 to 1. Every captured word that
+/// only matched through the fallback policy (one per `fallback_warnings`
+/// line) lowers it, and so does every greedy capture over several words and
+/// every word a `*` wildcard skipped. `priority_weight` comes from
+/// [`priority_weights`], so matches of low-priority patterns score lower.
+pub fn match_confidence(
+    pattern_match: &PatternMatch,
+    pattern_tokens: &[PatternToken],
+    unknown_words: usize,
+    priority_weight: f32,
+) -> f32 {
+    let long_greedy_captures = pattern_match
+        .spans
+        .iter()
+        .filter(|span| {
+            matches!(pattern_tokens[span.token], PatternToken::Greedy(_))
+                && span.end - span.start > 1
+        })
+        .count();
+
+    0.6_f32.powi(unknown_words as i32)
+        * 0.9_f32.powi(long_greedy_captures as i32)
+        * 0.85_f32.powi(pattern_match.skipped.len() as i32)
+        * priority_weight
+}
+
+/// How the priority of each pattern weighs on the confidence of its
+/// matches, by pattern name: 1 for the highest priority in the database,
+/// down to 0.9 for the lowest. A parse computes these once, into
+/// [`PrologApp::priority_weights`].
+pub fn priority_weights(database: &Database) -> HashMap<String, f32> {
+    let priorities = database.patterns.iter().map(|pattern| pattern.priority);
+    let (Some(lowest), Some(highest)) = (priorities.clone().min(), priorities.max()) else {
+        return HashMap::new();
+    };
+    database
+        .patterns
+        .iter()
+        .map(|pattern| {
+            let weight = if highest == lowest {
+                1.0
+            } else {
+                0.9 + 0.1 * (pattern.priority - lowest) as f32 / (highest - lowest) as f32
+            };
+            (pattern.name.clone(), weight)
+        })
+        .collect()
+}
+
+/// One capture of a match: `words[start..end]`, captured by
+/// `pattern_tokens[token]` into its `$n` slot, `slot + 1`.
+#[derive(Debug, Clone, Copy)]
+pub struct CaptureSpan {
+    pub start: usize,
+    pub end: usize,
+    pub token: usize,
+    pub slot: usize,
+}
+
+/// Most steps a trace keeps; patterns with several greedy tokens can try
+/// far more splits than anyone would step through.
+pub const MAX_TRACE_STEPS: usize = 10_000;
+
+/// One decision of the backtracker, recorded by [`trace_match`]. `token` is
+/// an index into the pattern tokens and `word` into the sentence words.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchStep {
+    /// The token was tried against the word.
+    Tried { token: usize, word: usize, matched: bool },
+    /// A greedy token takes `words[start..end]`, the longest run first.
+    Took { token: usize, start: usize, end: usize },
+    /// An optional token is left out before `word`.
+    LeftOut { token: usize, word: usize },
+    /// Everything after the choice made by the token at `word` failed, so
+    /// the choice is undone.
+    Backtracked { token: usize, word: usize },
+    /// The words ran out at the token; fine when every token left is optional.
+    WordsEnded { token: usize, matched: bool },
+    /// The tokens ran out at the word; fine when every word was consumed.
+    TokensEnded { word: usize, matched: bool },
+}
+
+/// Scratch space reused by every match attempt on one sentence, so trying
+/// each pattern at each position only records indices instead of allocating
+/// capture strings that are thrown away on backtracking.
+#[derive(Default)]
+pub struct MatchScratch {
+    spans: Vec<CaptureSpan>,
+    /// Positions of the words consumed by `*`.
+    skipped: Vec<usize>,
+    /// Choices the backtracker can still undo, latest last.
+    choices: Vec<Choice>,
+    /// Decisions of the backtracker, kept only when tracing.
+    trace: Option<Vec<MatchStep>>,
+}
+
+impl MatchScratch {
+    fn record(&mut self, step: MatchStep) {
+        if let Some(trace) = &mut self.trace
+            && trace.len() < MAX_TRACE_STEPS
+        {
+            trace.push(step);
         }
+    }
+}
+
+/// Most token attempts one match may make before it gives up. Patterns with
+/// several greedy tokens can try a number of splits that grows with every
+/// word of a long sentence; past the budget the pattern counts as not
+/// matching.
+pub const MAX_MATCH_STEPS: usize = 100_000;
 
-        match &pattern_tokens[pattern_idx] {
-            PatternToken::Optional(inner) => {
-                if matches_token(&words[word_idx], inner, app) {
-                    if matches!(inner.as_ref(), PatternToken::TypeMatch(_)) {
-                        captures.push(words[word_idx].clone());
+/// A choice the backtracker made and can undo: what to try instead when
+/// everything after it fails. `spans` and `skipped` are how many of each
+/// the scratch held before the choice.
+#[derive(Debug, Clone, Copy)]
+enum Choice {
+    /// An optional token matched at `word`; it may still be left out.
+    Optional {
+        token: usize,
+        word: usize,
+        spans: usize,
+        skipped: usize,
+    },
+    /// A greedy token took `words[start..end]`; it may still take fewer
+    /// runs of `width` words, down to one.
+    Greedy {
+        token: usize,
+        start: usize,
+        end: usize,
+        width: usize,
+        spans: usize,
+        skipped: usize,
+    },
+}
+
+/// Matches `pattern_tokens` against the start of `words`, leaving the captures
+/// in `scratch`. With `anchored` the match must consume every word. Returns
+/// the number of words matched, or `None` when the pattern does not match or
+/// the match takes more than [`MAX_MATCH_STEPS`]. The choices to undo are
+/// kept on a stack of their own rather than the call stack, so long
+/// sentences cannot overflow it.
+fn match_prefix(
+    words: &[String],
+    pattern_tokens: &[PatternToken],
+    anchored: bool,
+    app: &PrologApp,
+    scratch: &mut MatchScratch,
+) -> Option<usize> {
+    let span = |token, start, end| CaptureSpan {
+        start,
+        end,
+        token,
+        slot: capture_slot(pattern_tokens, token).unwrap_or_default(),
+    };
+    let tried = |scratch: &mut MatchScratch, token, word, matched| {
+        scratch.record(MatchStep::Tried {
+            token,
+            word,
+            matched,
+        });
+        matched
+    };
+
+    scratch.spans.clear();
+    scratch.skipped.clear();
+    scratch.choices.clear();
+    let mut word_idx = 0;
+    let mut pattern_idx = 0;
+    let mut steps = 0;
+
+    loop {
+        steps += 1;
+        if steps > MAX_MATCH_STEPS {
+            return None;
+        }
+
+        // Moves on to the next token, or falls through to backtracking
+        if pattern_idx >= pattern_tokens.len() {
+            let matched = !anchored || word_idx == words.len();
+            scratch.record(MatchStep::TokensEnded {
+                word: word_idx,
+                matched,
+            });
+            if matched {
+                return Some(word_idx);
+            }
+        } else if word_idx >= words.len() {
+            let matched = pattern_tokens[pattern_idx..]
+                .iter()
+                .all(|t| matches!(t, PatternToken::Optional(_)));
+            scratch.record(MatchStep::WordsEnded {
+                token: pattern_idx,
+                matched,
+            });
+            if matched {
+                return Some(word_idx);
+            }
+        } else {
+            let token = &pattern_tokens[pattern_idx];
+            let width = token_width(token);
+            match token {
+                PatternToken::Optional(inner) => {
+                    let matched = matches_at(words, word_idx, inner, app);
+                    if tried(scratch, pattern_idx, word_idx, matched) {
+                        scratch.choices.push(Choice::Optional {
+                            token: pattern_idx,
+                            word: word_idx,
+                            spans: scratch.spans.len(),
+                            skipped: scratch.skipped.len(),
+                        });
+                        if is_capturing(token) {
+                            scratch.spans.push(span(pattern_idx, word_idx, word_idx + width));
+                        }
+                        if matches!(inner.as_ref(), PatternToken::Wildcard) {
+                            scratch.skipped.push(word_idx);
+                        }
+                        word_idx += width;
+                    } else {
+                        scratch.record(MatchStep::LeftOut {
+                            token: pattern_idx,
+                            word: word_idx,
+                        });
                     }
-                    if backtrack(
-                        words,
-                        word_idx + 1,
-                        pattern_tokens,
-                        pattern_idx + 1,
-                        captures,
-                        app,
-                    ) {
-                        return true;
+                    pattern_idx += 1;
+                    continue;
+                }
+                PatternToken::Wildcard => {
+                    tried(scratch, pattern_idx, word_idx, true);
+                    scratch.skipped.push(word_idx);
+                    word_idx += 1;
+                    pattern_idx += 1;
+                    continue;
+                }
+                PatternToken::Greedy(inner) => {
+                    let mut end_idx = word_idx;
+                    while end_idx < words.len() {
+                        let matched = matches_at(words, end_idx, inner, app);
+                        if !tried(scratch, pattern_idx, end_idx, matched) {
+                            break;
+                        }
+                        end_idx += width;
                     }
-                    if matches!(inner.as_ref(), PatternToken::TypeMatch(_)) {
-                        captures.pop();
+                    if end_idx > word_idx {
+                        scratch.choices.push(Choice::Greedy {
+                            token: pattern_idx,
+                            start: word_idx,
+                            end: end_idx,
+                            width,
+                            spans: scratch.spans.len(),
+                            skipped: scratch.skipped.len(),
+                        });
+                        scratch.record(MatchStep::Took {
+                            token: pattern_idx,
+                            start: word_idx,
+                            end: end_idx,
+                        });
+                        scratch.spans.push(span(pattern_idx, word_idx, end_idx));
+                        word_idx = end_idx;
+                        pattern_idx += 1;
+                        continue;
                     }
                 }
-                backtrack(
-                    words,
-                    word_idx,
-                    pattern_tokens,
-                    pattern_idx + 1,
-                    captures,
-                    app,
-                )
-            }
-            PatternToken::Wildcard => backtrack(
-                words,
-                word_idx + 1,
-                pattern_tokens,
-                pattern_idx + 1,
-                captures,
-                app,
-            ),
-            PatternToken::Greedy(inner) => {
-                let mut end_idx = word_idx;
-
-                while end_idx < words.len() && matches_token(&words[end_idx], inner, app) {
-                    end_idx += 1;
+                token => {
+                    let matched = matches_at(words, word_idx, token, app);
+                    if tried(scratch, pattern_idx, word_idx, matched) {
+                        if is_capturing(token) {
+                            scratch.spans.push(span(pattern_idx, word_idx, word_idx + width));
+                        }
+                        word_idx += width;
+                        pattern_idx += 1;
+                        continue;
+                    }
                 }
+            }
+        }
 
-                if end_idx == word_idx {
-                    return false;
+        // Undoes the latest choice that has something left to try
+        loop {
+            match scratch.choices.pop()? {
+                Choice::Optional {
+                    token,
+                    word,
+                    spans,
+                    skipped,
+                } => {
+                    scratch.spans.truncate(spans);
+                    scratch.skipped.truncate(skipped);
+                    scratch.record(MatchStep::Backtracked { token, word });
+                    scratch.record(MatchStep::LeftOut { token, word });
+                    word_idx = word;
+                    pattern_idx = token + 1;
+                    break;
                 }
-
-                for try_end in (word_idx + 1..=end_idx).rev() {
-                    let greedy_words: Vec<String> = words[word_idx..try_end].to_vec();
-                    let formatted_capture = greedy_words.join(" ").to_lowercase().replace(' ', "_");
-
-                    captures.push(formatted_capture);
-
-                    if backtrack(
-                        words,
-                        try_end,
-                        pattern_tokens,
-                        pattern_idx + 1,
-                        captures,
-                        app,
-                    ) {
-                        return true;
+                Choice::Greedy {
+                    token,
+                    start,
+                    end,
+                    width,
+                    spans,
+                    skipped,
+                } => {
+                    scratch.spans.truncate(spans);
+                    scratch.skipped.truncate(skipped);
+                    scratch.record(MatchStep::Backtracked { token, word: start });
+                    // One run of the token fewer, until only one is left
+                    let shorter = end - width;
+                    if shorter > start {
+                        scratch.choices.push(Choice::Greedy {
+                            token,
+                            start,
+                            end: shorter,
+                            width,
+                            spans,
+                            skipped,
+                        });
+                        scratch.record(MatchStep::Took {
+                            token,
+                            start,
+                            end: shorter,
+                        });
+                        scratch.spans.push(span(token, start, shorter));
+                        word_idx = shorter;
+                        pattern_idx = token + 1;
+                        break;
                     }
-
-                    captures.pop();
                 }
-
-                false
             }
-            token => {
-                if matches_token(&words[word_idx], token, app) {
-                    if matches!(token, PatternToken::TypeMatch(_)) {
-                        captures.push(words[word_idx].clone());
+        }
+    }
+}
+
+/// Whether `word` is a variable the parser put in for a word, such as the
+/// wh-word of a question. Sentences are lowercased, so only the parser
+/// writes capitalized words; `<Type>` tokens capture them whatever their
+/// types.
+pub fn is_variable_word(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_uppercase())
+}
+
+/// The text of a capture. Greedy captures and captured phrases are
+/// formatted as lowercase_with_underscores, except a lone variable. With a
+/// `database`, the words of `<Type>` captures are replaced by their lemmas,
+/// so "chased" and "chases" both write `chase`, and `<Date>` and `<Time>`
+/// captures become atoms like `date(1990)`; without one they stay as
+/// written.
+fn capture_text(
+    words: &[String],
+    span: &CaptureSpan,
+    pattern_tokens: &[PatternToken],
+    database: Option<&Database>,
+) -> String {
+    let captured_by = match &pattern_tokens[span.token] {
+        PatternToken::Optional(inner) | PatternToken::Greedy(inner) => inner.as_ref(),
+        token => token,
+    };
+    if let (PatternToken::Temporal(kind), Some(_)) = (captured_by, database)
+        && let Some(atom) = kind.normalize(&words[span.start..span.end])
+    {
+        return atom;
+    }
+    let lemma = |word: &String| match (captured_by, database) {
+        (PatternToken::TypeMatch(types, ..), Some(database)) if !is_variable_word(word) => database
+            .lemma_of(word, types)
+            .map_or_else(|| word.clone(), |lemma| database.locale.lowercase(lemma)),
+        _ => word.clone(),
+    };
+
+    let lone_variable = span.end - span.start == 1 && is_variable_word(&words[span.start]);
+    let greedy = matches!(pattern_tokens[span.token], PatternToken::Greedy(_));
+    if (greedy && !lone_variable) || span.end - span.start > 1 {
+        words[span.start..span.end]
+            .iter()
+            .map(lemma)
+            .collect::<Vec<_>>()
+            .join("_")
+            .to_lowercase()
+    } else {
+        lemma(&words[span.start])
+    }
+}
+
+/// Fills the `$N.raw` placeholders of `template` with the captures as they
+/// were written, before lemmatizing: `$2.raw` writes "chased" where `$2`
+/// writes `chase`.
+pub fn fill_raw_placeholders(raw_captures: &[String], template: &str) -> String {
+    let mut result = template.to_string();
+    for (i, word) in raw_captures.iter().enumerate() {
+        result = result.replace(&format!("${}.raw", i + 1), word);
+    }
+    result
+}
+
+/// What a template can do to a capture by naming it after the placeholder:
+/// `$1:lemma`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureModifier {
+    /// The lemma of each word: "was" -> `be`.
+    Lemma,
+    /// The last word singularized: "ice_creams" -> `ice_cream`.
+    Singular,
+    Lower,
+    /// lowercase_with_underscores: "New York" -> `new_york`.
+    Snake,
+}
+
+impl CaptureModifier {
+    pub const ALL: [CaptureModifier; 4] = [
+        CaptureModifier::Lemma,
+        CaptureModifier::Singular,
+        CaptureModifier::Lower,
+        CaptureModifier::Snake,
+    ];
+
+    /// The name used in templates, e.g. `$1:snake`.
+    pub fn name(self) -> &'static str {
+        match self {
+            CaptureModifier::Lemma => "lemma",
+            CaptureModifier::Singular => "singular",
+            CaptureModifier::Lower => "lower",
+            CaptureModifier::Snake => "snake",
+        }
+    }
+
+    /// `capture` modified. `Lemma` and `Singular` look its words up in
+    /// `database`; without one they leave it as it is, as do variables.
+    pub fn apply(self, capture: &str, database: Option<&Database>) -> String {
+        match (self, database) {
+            (CaptureModifier::Lower, _) => capture.to_lowercase(),
+            (CaptureModifier::Snake, _) => capture
+                .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join("_")
+                .to_lowercase(),
+            _ if is_variable_word(capture) => capture.to_string(),
+            (CaptureModifier::Lemma, Some(database)) => capture
+                .split('_')
+                .map(|word| {
+                    database
+                        .lemma_of(word, &[])
+                        .map_or_else(|| word.to_string(), |lemma| database.locale.lowercase(lemma))
+                })
+                .collect::<Vec<_>>()
+                .join("_"),
+            (CaptureModifier::Singular, Some(database)) => {
+                let (modifiers, noun) = match capture.rsplit_once('_') {
+                    Some((modifiers, noun)) => (Some(modifiers), noun),
+                    None => (None, capture),
+                };
+                let singular = match database.number_of(noun) {
+                    Some(Number::Plural) => database
+                        .lemma_of(noun, &[WordType::Noun])
+                        .map(|lemma| database.locale.lowercase(lemma)),
+                    None if database.get_word_entries(noun).is_none() => {
+                        Some(singularize(noun, &database.morphology))
                     }
-                    backtrack(
-                        words,
-                        word_idx + 1,
-                        pattern_tokens,
-                        pattern_idx + 1,
-                        captures,
-                        app,
-                    )
-                } else {
-                    false
+                    _ => None,
+                }
+                .unwrap_or_else(|| noun.to_string());
+                match modifiers {
+                    Some(modifiers) => format!("{}_{}", modifiers, singular),
+                    None => singular,
                 }
             }
+            (CaptureModifier::Lemma | CaptureModifier::Singular, None) => capture.to_string(),
         }
     }
+}
 
-    let mut captures = Vec::new();
-    if backtrack(words, 0, pattern_tokens, 0, &mut captures, app) {
-        Some(captures)
-    } else {
-        None
+/// Fills the `$N:modifier` placeholders of `template` with the captures,
+/// modified; see [`CaptureModifier::apply`]. Placeholders of captures the
+/// match does not have are left as written.
+pub fn fill_modified_placeholders(
+    captures: &[String],
+    template: &str,
+    database: Option<&Database>,
+) -> String {
+    fill_slots(captures, template, database, false)
+}
+
+/// Reads `template` once, taking `$` and every digit after it as the slot
+/// number, so `$10` is never read as `$1` and capture text is never read
+/// as a placeholder. Plain `$N` placeholders are filled too if `fill_plain`.
+fn fill_slots(
+    captures: &[String],
+    template: &str,
+    database: Option<&Database>,
+    fill_plain: bool,
+) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(dollar) = rest.find('$') {
+        result.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        let digits = after
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after.len());
+        let filled = after[digits..].strip_prefix(':').and_then(|suffix| {
+            let modifier = CaptureModifier::ALL
+                .into_iter()
+                .find(|modifier| suffix.starts_with(modifier.name()))?;
+            let index = after[..digits].parse::<usize>().ok()?.checked_sub(1)?;
+            let text = modifier.apply(captures.get(index)?, database);
+            Some((text, digits + 1 + modifier.name().len()))
+        });
+        let filled = filled.or_else(|| {
+            let index = after[..digits].parse::<usize>().ok()?.checked_sub(1)?;
+            let capture = captures.get(index).filter(|_| fill_plain)?;
+            Some((capture.clone(), digits))
+        });
+        match filled {
+            Some((text, len)) => {
+                result.push_str(&text);
+                rest = &after[len..];
+            }
+            None => {
+                result.push('$');
+                rest = after;
+            }
+        }
     }
+    result.push_str(rest);
+    result
 }
 
-pub fn try_match_pattern_substring(
+/// The text of each capture slot of `pattern_tokens`, taken from `spans` of
+/// `words`; slots of optional tokens that were left out stay empty.
+fn slot_captures(
     words: &[String],
+    spans: &[CaptureSpan],
     pattern_tokens: &[PatternToken],
-    app: &PrologApp,
-) -> Option<(Vec<String>, usize)> {
-    for start_idx in 0..words.len() {
-        if let Some(captures) = try_match_pattern(&words[start_idx..], pattern_tokens, app) {
-            return Some((captures, start_idx));
-        }
+    database: Option<&Database>,
+) -> Vec<String> {
+    let slots = pattern_tokens.iter().filter(|token| is_capturing(token)).count();
+    let mut captures = vec![String::new(); slots];
+    for span in spans {
+        captures[span.slot] = capture_text(words, span, pattern_tokens, database);
     }
-    None
+    captures
 }
 
+/// A pattern matched against `words[start_idx..end_idx]` of a sentence.
+/// `captures` has one text per `$n` slot of the pattern, lemmatized, and
+/// `captures[span.slot]` is that of each span, so the emitted facts and the
+/// highlighted words always come from the same match. The `$N.raw` and
+/// `$N:modifier` placeholders of `template` are already filled.
 #[derive(Debug, Clone)]
 pub struct PatternMatch {
     pub pattern_name: String,
     pub template: String,
     pub captures: Vec<String>,
+    /// Capture positions in the sentence the match was made against.
+    pub spans: Vec<CaptureSpan>,
+    /// Positions of the words skipped by `*`, in the same sentence.
+    pub skipped: Vec<usize>,
     pub start_idx: usize,
     pub end_idx: usize,
+    /// How sure the parser is of the match, from 0 to 1; see
+    /// [`match_confidence`].
+    pub confidence: f32,
 }
 
-fn try_match_at_position(
+impl PatternMatch {
+    fn from_scratch(
+        words: &[String],
+        start_idx: usize,
+        matched_len: usize,
+        scratch: &MatchScratch,
+        pattern_tokens: &[PatternToken],
+        pattern_name: &str,
+        template: &str,
+    ) -> Self {
+        let suffix = &words[start_idx..];
+        let captures = slot_captures(suffix, &scratch.spans, pattern_tokens, None);
+        let spans = scratch
+            .spans
+            .iter()
+            .map(|span| CaptureSpan {
+                start: span.start + start_idx,
+                end: span.end + start_idx,
+                token: span.token,
+                slot: span.slot,
+            })
+            .collect();
+        let skipped = scratch.skipped.iter().map(|idx| idx + start_idx).collect();
+
+        PatternMatch {
+            pattern_name: pattern_name.to_string(),
+            template: template.to_string(),
+            captures,
+            spans,
+            skipped,
+            start_idx,
+            end_idx: start_idx + matched_len,
+            confidence: 1.0,
+        }
+    }
+
+    /// Sets the confidence of the match, from the words of `words` (the
+    /// sentence the match was made against) it captured without knowing
+    /// them, its greedy captures and wildcards, and the pattern priority.
+    fn scored(
+        mut self,
+        words: &[String],
+        pattern_tokens: &[PatternToken],
+        app: &PrologApp,
+    ) -> Self {
+        let database = &app.database_snapshot;
+        let unknown_words = fallback_captures(words, &self, pattern_tokens, database).len();
+        let weight = app.priority_weights.get(&self.pattern_name).copied().unwrap_or(1.0);
+        self.confidence = match_confidence(&self, pattern_tokens, unknown_words, weight);
+        self
+    }
+
+    /// Replaces the captures, still as written, with their lemmas, after
+    /// filling the `$N.raw` placeholders of the template with them. The
+    /// `$N:modifier` placeholders are filled from the lemmas, looking words
+    /// up in `database`. `words` is the sentence the match was made against.
+    fn lemmatized(
+        mut self,
+        words: &[String],
+        pattern_tokens: &[PatternToken],
+        database: &Database,
+    ) -> Self {
+        self.template = fill_raw_placeholders(&self.captures, &self.template);
+        self.captures = slot_captures(words, &self.spans, pattern_tokens, Some(database));
+        self.template = fill_modified_placeholders(&self.captures, &self.template, Some(database));
+        self
+    }
+}
+
+/// The goals `words` state as a whole: the facts the first pattern to match
+/// all of them would emit, without their final periods, and that pattern's
+/// name. Comment lines, rules and rule templates are left out, so the goals
+/// can go into a query or a rule body.
+pub fn statement_goals(words: &[String], app: &PrologApp) -> Option<(String, Vec<String>)> {
+    app.database_snapshot
+        .get_sorted_patterns(&app.interactive_parser.disabled_tags)
+        .into_iter()
+        .filter(|pattern| !pattern.is_rule_template())
+        .find_map(|pattern| {
+            let pattern_tokens =
+                parse_pattern(&app.database_snapshot.expand_subpatterns(&pattern.pattern));
+            let pattern_match =
+                match_pattern(words, &pattern_tokens, &pattern.name, &pattern.template, app)?;
+            let goals: Vec<String> =
+                apply_template(&pattern_match.captures, &pattern_match.template)
+                    .iter()
+                    .map(|fact| fact.trim().trim_end_matches('.').to_string())
+                    .filter(|goal| {
+                        !goal.is_empty() && !goal.starts_with("//") && !goal.contains(":-")
+                    })
+                    .collect();
+            (!goals.is_empty()).then(|| (pattern.name.clone(), goals))
+        })
+}
+
+/// Whether a pattern with a literal word that `is_literal` accepts, given
+/// lowercased, matches `words`. Constructions the parser rewrites, such as
+/// negations, are left to the patterns that spell them out.
+pub fn spelled_out_pattern_matches(
+    app: &PrologApp,
+    words: &[String],
+    is_literal: impl Fn(&str) -> bool,
+) -> bool {
+    let spells_out = |token: &PatternToken| match token {
+        PatternToken::Literal(phrase) => {
+            phrase.split(' ').any(|literal| is_literal(&literal.to_lowercase()))
+        }
+        PatternToken::Alternatives(literals) => {
+            literals.iter().any(|literal| is_literal(&literal.to_lowercase()))
+        }
+        _ => false,
+    };
+    let patterns: Vec<(String, String, Vec<PatternToken>)> = app
+        .database_snapshot
+        .get_sorted_patterns(&app.interactive_parser.disabled_tags)
+        .into_iter()
+        .map(|pattern| {
+            (
+                pattern.name.clone(),
+                pattern.template.clone(),
+                parse_pattern(&app.database_snapshot.expand_subpatterns(&pattern.pattern)),
+            )
+        })
+        .filter(|(_, _, tokens)| tokens.iter().any(spells_out))
+        .collect();
+
+    !patterns.is_empty() && !find_all_pattern_matches(words, &patterns, app).is_empty()
+}
+
+/// Matches a pattern against the whole of `words`.
+pub fn match_pattern(
     words: &[String],
-    start_idx: usize,
     pattern_tokens: &[PatternToken],
     pattern_name: &str,
     template: &str,
     app: &PrologApp,
 ) -> Option<PatternMatch> {
-    fn backtrack_with_end(
-        words: &[String],
-        word_idx: usize,
-        pattern_tokens: &[PatternToken],
-        pattern_idx: usize,
-        captures: &mut Vec<String>,
-        app: &PrologApp,
-    ) -> Option<usize> {
-        if pattern_idx >= pattern_tokens.len() {
-            return Some(word_idx);
-        }
+    let mut scratch = MatchScratch::default();
+    let matched_len = match_prefix(words, pattern_tokens, true, app, &mut scratch)?;
+    Some(
+        PatternMatch::from_scratch(
+            words,
+            0,
+            matched_len,
+            &scratch,
+            pattern_tokens,
+            pattern_name,
+            template,
+        )
+        .lemmatized(words, pattern_tokens, &app.database_snapshot)
+        .scored(words, pattern_tokens, app),
+    )
+}
 
-        if word_idx >= words.len() {
-            if pattern_tokens[pattern_idx..]
-                .iter()
-                .all(|t| matches!(t, PatternToken::Optional(_)))
-            {
-                return Some(word_idx);
-            }
-            return None;
-        }
+/// Matches a pattern against the whole of `words` as [`match_pattern`]
+/// does, recording every decision the backtracker makes on the way. Returns
+/// the number of words matched, if the pattern matched, and the decisions.
+pub fn trace_match(
+    words: &[String],
+    pattern_tokens: &[PatternToken],
+    app: &PrologApp,
+) -> (Option<usize>, Vec<MatchStep>) {
+    let mut scratch = MatchScratch {
+        trace: Some(Vec::new()),
+        ..Default::default()
+    };
+    let matched_len = match_prefix(words, pattern_tokens, true, app, &mut scratch);
+    (matched_len, scratch.trace.unwrap_or_default())
+}
 
-        match &pattern_tokens[pattern_idx] {
-            PatternToken::Optional(inner) => {
-                if matches_token(&words[word_idx], inner, app) {
-                    if matches!(inner.as_ref(), PatternToken::TypeMatch(_)) {
-                        captures.push(words[word_idx].clone());
-                    }
-                    if let Some(end) = backtrack_with_end(
-                        words,
-                        word_idx + 1,
-                        pattern_tokens,
-                        pattern_idx + 1,
-                        captures,
-                        app,
-                    ) {
-                        return Some(end);
-                    }
-                    if matches!(inner.as_ref(), PatternToken::TypeMatch(_)) {
-                        captures.pop();
-                    }
-                }
-                backtrack_with_end(
-                    words,
-                    word_idx,
-                    pattern_tokens,
-                    pattern_idx + 1,
-                    captures,
-                    app,
-                )
-            }
-            PatternToken::Wildcard => backtrack_with_end(
+/// Matches a pattern from the earliest position of `words` where it fits
+/// through to the end of the sentence.
+pub fn match_pattern_substring(
+    words: &[String],
+    pattern_tokens: &[PatternToken],
+    pattern_name: &str,
+    template: &str,
+    app: &PrologApp,
+) -> Option<PatternMatch> {
+    let mut scratch = MatchScratch::default();
+    (0..words.len()).find_map(|start_idx| {
+        let matched_len =
+            match_prefix(&words[start_idx..], pattern_tokens, true, app, &mut scratch)?;
+        Some(
+            PatternMatch::from_scratch(
                 words,
-                word_idx + 1,
+                start_idx,
+                matched_len,
+                &scratch,
                 pattern_tokens,
-                pattern_idx + 1,
-                captures,
-                app,
-            ),
-            PatternToken::Greedy(inner) => {
-                let mut matched_words = Vec::new();
-                let mut end_idx = word_idx;
-
-                while end_idx < words.len() && matches_token(&words[end_idx], inner, app) {
-                    matched_words.push(words[end_idx].clone());
-                    end_idx += 1;
-                }
-
-                if matched_words.is_empty() {
-                    return None;
-                }
-
-                for try_end in (word_idx + 1..=end_idx).rev() {
-                    let greedy_words: Vec<String> = words[word_idx..try_end].to_vec();
-                    let formatted_capture = greedy_words.join(" ").to_lowercase().replace(' ', "_");
-
-                    captures.push(formatted_capture);
-
-                    if let Some(end) = backtrack_with_end(
-                        words,
-                        try_end,
-                        pattern_tokens,
-                        pattern_idx + 1,
-                        captures,
-                        app,
-                    ) {
-                        return Some(end);
-                    }
-
-                    captures.pop();
-                }
-
-                None
-            }
-            token => {
-                if matches_token(&words[word_idx], token, app) {
-                    if matches!(token, PatternToken::TypeMatch(_)) {
-                        captures.push(words[word_idx].clone());
-                    }
-                    backtrack_with_end(
-                        words,
-                        word_idx + 1,
-                        pattern_tokens,
-                        pattern_idx + 1,
-                        captures,
-                        app,
-                    )
-                } else {
-                    None
-                }
-            }
-        }
-    }
+                pattern_name,
+                template,
+            )
+            .lemmatized(words, pattern_tokens, &app.database_snapshot)
+            .scored(words, pattern_tokens, app),
+        )
+    })
+}
 
-    let mut captures = Vec::new();
-    if let Some(end_idx) = backtrack_with_end(
-        &words[start_idx..],
-        0,
-        pattern_tokens,
-        0,
-        &mut captures,
-        app,
-    ) {
-        Some(PatternMatch {
-            pattern_name: pattern_name.to_string(),
-            template: template.to_string(),
-            captures,
+fn try_match_at_position(
+    words: &[String],
+    start_idx: usize,
+    pattern_tokens: &[PatternToken],
+    pattern_name: &str,
+    template: &str,
+    app: &PrologApp,
+    scratch: &mut MatchScratch,
+) -> Option<PatternMatch> {
+    let matched_len = match_prefix(&words[start_idx..], pattern_tokens, false, app, scratch)?;
+    Some(
+        PatternMatch::from_scratch(
+            words,
             start_idx,
-            end_idx: start_idx + end_idx,
-        })
-    } else {
-        None
-    }
+            matched_len,
+            scratch,
+            pattern_tokens,
+            pattern_name,
+            template,
+        )
+        .lemmatized(words, pattern_tokens, &app.database_snapshot)
+        .scored(words, pattern_tokens, app),
+    )
 }
 
 pub fn find_all_pattern_matches(
@@ -394,6 +1309,7 @@ pub fn find_all_pattern_matches(
 
     let mut matches = Vec::new();
     let mut used_positions = vec![false; words.len()];
+    let mut scratch = MatchScratch::default();
 
     loop {
         let mut best_match: Option<PatternMatch> = None;
@@ -415,6 +1331,7 @@ pub fn find_all_pattern_matches(
                     pattern_name,
                     template,
                     app,
+                    &mut scratch,
                 ) {
                     let overlap =
                         (pattern_match.start_idx..pattern_match.end_idx).any(|i| used_positions[i]);
@@ -444,9 +1361,33 @@ pub fn find_all_pattern_matches(
         }
     }
 
+    // Matches are found longest first; report them by pattern priority (the
+    // order of `patterns`) and then by position so re-parses give identical output.
+    let pattern_rank = |name: &str| patterns.iter().position(|(n, _, _)| n == name);
+    matches.sort_by_key(|m| (pattern_rank(&m.pattern_name), m.start_idx));
+
     matches
 }
 
+/// Re-runs a pattern's examples and counter-examples against the whole
+/// sentence, as `PrologPattern::example_regressions` describes them.
+pub fn pattern_example_regressions(pattern: &PrologPattern, app: &PrologApp) -> Vec<String> {
+    let tokens = parse_pattern(&app.database_snapshot.expand_subpatterns(&pattern.pattern));
+    pattern.example_regressions(|sentence| {
+        let words: Vec<String> = sentence
+            .trim()
+            .trim_end_matches('.')
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect();
+        match_pattern(&words, &tokens, &pattern.name, &pattern.template, app).is_some()
+    })
+}
+
+/// Fills the `$N` placeholders of each template line. A line written as
+/// `$2.split -> likes($1, #)` is emitted once per element of greedy capture
+/// `$2`, with `#` standing for the element: "pizza, pasta and salad" gives
+/// three facts.
 pub fn apply_template(captures: &[String], template: &str) -> Vec<String> {
     let templates: Vec<&str> = template
         .lines()
@@ -456,14 +1397,13 @@ pub fn apply_template(captures: &[String], template: &str) -> Vec<String> {
     let mut results = Vec::new();
 
     for tmpl in templates {
-        let mut result = tmpl.to_string();
-
-        for (i, word) in captures.iter().enumerate() {
-            let placeholder = format!("${}", i + 1);
-            result = result.replace(&placeholder, word);
+        match split_template_line(tmpl, captures) {
+            Some((elements, body)) => {
+                let body = fill_placeholders(captures, body);
+                results.extend(elements.iter().map(|element| body.replace('#', element)));
+            }
+            None => results.push(fill_placeholders(captures, tmpl)),
         }
-
-        results.push(result);
     }
 
     if results.is_empty() {
@@ -473,12 +1413,411 @@ pub fn apply_template(captures: &[String], template: &str) -> Vec<String> {
     results
 }
 
-trait StrExt {
-    fn eq_ignore_case(&self, other: &str) -> bool;
+/// Fills the `$N` and `$N:modifier` placeholders of `template`. Modifiers
+/// that look words up leave the captures as they are; a match fills those
+/// when it is lemmatized.
+pub fn fill_placeholders(captures: &[String], template: &str) -> String {
+    fill_slots(captures, template, None, true)
+}
+
+/// The elements and body of a `$N.split -> body` line, if `tmpl` is one.
+fn split_template_line<'a>(tmpl: &'a str, captures: &[String]) -> Option<(Vec<String>, &'a str)> {
+    let (head, body) = tmpl.split_once("->")?;
+    let index: usize = head
+        .trim()
+        .strip_prefix('$')?
+        .strip_suffix(".split")?
+        .parse()
+        .ok()?;
+    let capture = captures.get(index.checked_sub(1)?)?;
+    Some((split_elements(capture), body.trim()))
 }
 
-impl StrExt for str {
-    fn eq_ignore_case(&self, other: &str) -> bool {
-        self.eq_ignore_ascii_case(other)
+/// Splits a greedy capture such as `ice_cream,_pasta_and_salad` into its
+/// elements at commas and at "and"/"or": `ice_cream`, `pasta`, `salad`.
+pub fn split_elements(capture: &str) -> Vec<String> {
+    let mut elements = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+
+    for word in capture.split('_') {
+        let ends_element = word.ends_with(',');
+        let word = word.trim_end_matches(',');
+
+        if matches!(word, "and" | "or" | "") {
+            if !current.is_empty() {
+                elements.push(current.join("_"));
+                current.clear();
+            }
+            continue;
+        }
+
+        current.push(word);
+        if ends_element {
+            elements.push(current.join("_"));
+            current.clear();
+        }
+    }
+
+    if !current.is_empty() {
+        elements.push(current.join("_"));
+    }
+
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{
+        database::fixtures::{pattern, word},
+        query_engine::QueryEngine,
+    };
+
+    #[test]
+    fn test_type_tokens_take_an_optional_fallback_policy() {
+        let tokens = parse_pattern("<Noun:strict> is <Adjective|Noun:guess> <Verb>");
+        assert!(matches!(
+            &tokens[0],
+            PatternToken::TypeMatch(types, _, Some(WordFallback::Strict))
+                if types == &[WordType::Noun]
+        ));
+        assert!(matches!(
+            &tokens[2],
+            PatternToken::TypeMatch(types, _, Some(WordFallback::Guess)) if types.len() == 2
+        ));
+        assert!(matches!(&tokens[3], PatternToken::TypeMatch(_, _, None)));
+
+        assert_eq!(guess_word_type("quickly"), WordType::Adverb);
+        assert_eq!(guess_word_type("jumped"), WordType::Verb);
+        assert_eq!(guess_word_type("dangerous"), WordType::Adjective);
+        assert_eq!(guess_word_type("zebra"), WordType::Noun);
+        assert_eq!(guess_word_type("red"), WordType::Noun);
+    }
+
+    #[test]
+    fn test_type_tokens_can_exclude_types() {
+        let pattern = "<!Verb> <Noun&!Pronoun:strict> <Noun|Adjective&!Verb>";
+        let tokens = parse_pattern(pattern);
+        assert!(matches!(
+            &tokens[0],
+            PatternToken::TypeMatch(types, excluded, None)
+                if types.is_empty() && excluded == &[WordType::Verb]
+        ));
+        let labels: Vec<String> = tokens.iter().map(token_label).collect();
+        assert_eq!(labels.join(" "), pattern);
+
+        assert!(allows_type(&[], &[WordType::Verb], &WordType::Noun));
+        assert!(!allows_type(&[], &[WordType::Verb], &WordType::Verb));
+        assert!(!allows_type(&[WordType::Noun], &[WordType::Pronoun], &WordType::Adjective));
+    }
+
+    #[test]
+    fn test_marked_literals_and_wildcards_capture() {
+        let tokens = parse_pattern("<Noun> (is|was):$ [very:$] *:$ <Noun>:$ of");
+        let labels: Vec<String> = tokens.iter().map(token_label).collect();
+        assert_eq!(labels.join(" "), "<Noun> (is|was):$ [very:$] *:$ <Noun> of");
+        let slots: Vec<Option<usize>> =
+            (0..tokens.len()).map(|idx| capture_slot(&tokens, idx)).collect();
+        assert_eq!(slots, vec![Some(0), Some(1), Some(2), Some(3), Some(4), None]);
+        assert_eq!(suggest_template("<Noun> (is|was):$ *:$ <Noun>"), "relation($1, $2, $3, $4)");
+    }
+
+    #[test]
+    fn test_quoted_phrases_are_single_tokens() {
+        let tokens = parse_pattern("<Noun> \"is  made of\" [\"such as\":$] <Noun>+");
+        let labels: Vec<String> = tokens.iter().map(token_label).collect();
+        assert_eq!(labels.join(" "), "<Noun> \"is made of\" [\"such as\":$] <Noun>+");
+        let widths: Vec<usize> = tokens.iter().map(token_width).collect();
+        assert_eq!(widths, vec![1, 3, 2, 1]);
+        assert_eq!(capture_slot(&tokens, 2), Some(1));
+        assert_eq!(suggest_template("<Noun> \"is made of\" <Noun>"), "is_made_of($1, $2)");
+
+        let words: Vec<String> = "cheese such as brie"
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        let spans = [CaptureSpan {
+            start: 1,
+            end: 3,
+            token: 2,
+            slot: 1,
+        }];
+        assert_eq!(slot_captures(&words, &spans, &tokens, None), vec!["", "such_as", ""]);
+    }
+
+    #[test]
+    fn test_regex_tokens_match_words_and_capture() {
+        let tokens =
+            parse_pattern("<Noun> was born in [<re:^[0-9]{4}$>] <re:[> <re:^\\d+(st|nd|th)$>");
+        let labels: Vec<String> = tokens.iter().map(token_label).collect();
+        assert_eq!(
+            labels.join(" "),
+            "<Noun> was born in [<re:^[0-9]{4}$>] <re:^\\d+(st|nd|th)$>"
+        );
+        let slots: Vec<Option<usize>> =
+            (0..tokens.len()).map(|idx| capture_slot(&tokens, idx)).collect();
+        assert_eq!(slots, vec![Some(0), None, None, None, Some(1), Some(2)]);
+        let PatternToken::Regex(ordinal) = &tokens[5] else {
+            panic!("expected a regex token, got {:?}", tokens[5]);
+        };
+        assert!(ordinal.is_match("21st") && !ordinal.is_match("first"));
+    }
+
+    #[test]
+    fn test_alternation_groups_compose_with_optional_and_greedy() {
+        let tokens = parse_pattern("<Noun> (is|are) [(very|really)] (quite|so)+ <Adjective>");
+        assert!(matches!(&tokens[1], PatternToken::Alternatives(words) if words == &["is", "are"]));
+        assert!(matches!(
+            &tokens[2],
+            PatternToken::Optional(inner) if matches!(inner.as_ref(), PatternToken::Alternatives(_))
+        ));
+        assert!(matches!(
+            &tokens[3],
+            PatternToken::Greedy(inner) if matches!(inner.as_ref(), PatternToken::Alternatives(_))
+        ));
+        assert_eq!(token_label(&tokens[2]), "[(very|really)]");
+        assert_eq!(capture_slot(&tokens, 4), Some(2));
+    }
+
+    #[test]
+    fn test_templates_are_suggested_from_verbs_or_literals() {
+        assert_eq!(suggest_template("<Noun> <Verb> <Noun>"), "$2($1, $3)");
+        assert_eq!(
+            suggest_template("<Noun> did not <Verb> <Determiner> <Noun>"),
+            "not_$2($1, $4)"
+        );
+        assert_eq!(suggest_template("<Noun>+ is a <Noun>"), "is_a($1, $2)");
+        assert_eq!(
+            suggest_template("<Noun> is <Determiner> <Noun> of <Noun>"),
+            "is_of($1, $3, $4)"
+        );
+        assert_eq!(suggest_template("hello *"), "hello");
+        assert_eq!(suggest_template("<Noun> (is|are) a <Noun>"), "is_a($1, $2)");
+    }
+
+    #[test]
+    fn test_unknown_words_wildcards_and_low_priority_lower_confidence() {
+        let tokens = parse_pattern("<Noun>+ is <Adjective>");
+        let span = |start, end, token| CaptureSpan {
+            start,
+            end,
+            token,
+            slot: capture_slot(&tokens, token).unwrap(),
+        };
+        let mut pattern_match = PatternMatch {
+            pattern_name: "is_property".to_string(),
+            template: "property($1, $2).".to_string(),
+            captures: vec!["cat".to_string(), "big".to_string()],
+            spans: vec![span(0, 1, 0), span(2, 3, 2)],
+            skipped: Vec::new(),
+            start_idx: 0,
+            end_idx: 3,
+            confidence: 1.0,
+        };
+        assert_eq!(match_confidence(&pattern_match, &tokens, 0, 1.0), 1.0);
+        assert!((match_confidence(&pattern_match, &tokens, 2, 1.0) - 0.36).abs() < 1e-6);
+
+        pattern_match.spans[0] = span(0, 3, 0);
+        assert!((match_confidence(&pattern_match, &tokens, 0, 1.0) - 0.9).abs() < 1e-6);
+
+        pattern_match.spans[0] = span(0, 1, 0);
+        pattern_match.skipped = vec![1];
+        assert!((match_confidence(&pattern_match, &tokens, 0, 0.9) - 0.765).abs() < 1e-6);
+
+        let prioritized = |name: &str, priority| PrologPattern {
+            name: name.to_string(),
+            priority,
+            ..pattern("", "")
+        };
+        let database = Database {
+            patterns: vec![
+                prioritized("high", 50),
+                prioritized("mid", 30),
+                prioritized("low", 10),
+            ],
+            ..Database::default()
+        };
+        let weights = priority_weights(&database);
+        assert_eq!(weights["high"], 1.0);
+        assert!((weights["mid"] - 0.95).abs() < 1e-6);
+        assert!((weights["low"] - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_captures_are_lemmatized_unless_raw() {
+        let mut database = Database::default();
+        for (lemma, word_type, forms) in [
+            ("chase", WordType::Verb, vec!["chased", "chases"]),
+            ("cat", WordType::Noun, vec!["cats"]),
+        ] {
+            database.words.push(word(lemma, word_type, &forms));
+        }
+        database.rebuild_index();
+
+        let words: Vec<String> = "black cats chased mice"
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        let tokens = parse_pattern("<Noun>+ <Verb> <Noun>");
+        let span = |start, end, token| CaptureSpan {
+            start,
+            end,
+            token,
+            slot: capture_slot(&tokens, token).unwrap(),
+        };
+        let pattern_match = PatternMatch {
+            pattern_name: "chases".to_string(),
+            template: "$2($1, $3).\nsaid($2.raw).".to_string(),
+            captures: vec!["black_cats".to_string(), "chased".to_string(), "mice".to_string()],
+            spans: vec![span(0, 2, 0), span(2, 3, 1), span(3, 4, 2)],
+            skipped: Vec::new(),
+            start_idx: 0,
+            end_idx: 4,
+            confidence: 1.0,
+        }
+        .lemmatized(&words, &tokens, &database);
+
+        assert_eq!(pattern_match.captures, vec!["black_cat", "chase", "mice"]);
+        assert_eq!(
+            apply_template(&pattern_match.captures, &pattern_match.template),
+            vec!["chase(black_cat, mice).", "said(chased)."]
+        );
+    }
+
+    #[test]
+    fn test_template_modifiers_transform_captures() {
+        let mut database = Database::default();
+        for (lemma, word_type, forms) in [
+            ("be", WordType::Verb, vec!["is", "was"]),
+            ("dog", WordType::Noun, vec!["dogs"]),
+        ] {
+            database.words.push(word(lemma, word_type, &forms));
+        }
+        database.fill_noun_numbers();
+        database.rebuild_index();
+
+        let captures: Vec<String> = ["was", "big_dogs", "boxes", "New York", "X"]
+            .iter()
+            .map(|capture| capture.to_string())
+            .collect();
+        let template = "$1:lemma($2:singular, $3:singular, $4:snake, $5:lemma) $4:lower $9:lower";
+        assert_eq!(
+            fill_modified_placeholders(&captures, template, Some(&database)),
+            "be(big_dog, box, new_york, X) new york $9:lower"
+        );
+        assert_eq!(
+            apply_template(&captures, "$1:lemma($2:singular, $4:snake)."),
+            vec!["was(big_dogs, new_york)."]
+        );
+    }
+
+    #[test]
+    fn test_placeholders_past_nine() {
+        let captures: Vec<String> = (1..=11).map(|i| format!("c{}", i)).collect();
+        assert_eq!(
+            fill_placeholders(&captures, "f($10, $1, $11:lower)."),
+            "f(c10, c1, c11)."
+        );
+        assert_eq!(fill_placeholders(&captures, "g($12)."), "g($12).");
+
+        // Captured text is not read as a placeholder
+        let captures = vec!["$2".to_string(), "x".to_string()];
+        assert_eq!(fill_placeholders(&captures, "h($1, $2)."), "h($2, x).");
+    }
+
+    #[test]
+    fn test_left_out_optionals_keep_their_slot() {
+        let tokens = parse_pattern("[<Adjective>] <Noun> <Verb> * [<Noun>] <Noun>");
+        let slots: Vec<Option<usize>> =
+            (0..tokens.len()).map(|idx| capture_slot(&tokens, idx)).collect();
+        assert_eq!(slots, vec![Some(0), Some(1), Some(2), None, Some(3), Some(4)]);
+        assert_eq!(
+            suggest_template("[<Adjective>] <Noun> <Verb> * [<Noun>] <Noun>"),
+            "$3($1, $2, $4, $5)"
+        );
+
+        let words: Vec<String> = "cats chase the mice"
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        let span = |start, token| CaptureSpan {
+            start,
+            end: start + 1,
+            token,
+            slot: capture_slot(&tokens, token).unwrap(),
+        };
+        let scratch = MatchScratch {
+            spans: vec![span(0, 1), span(1, 2), span(3, 5)],
+            skipped: vec![2],
+            ..Default::default()
+        };
+        let pattern_match =
+            PatternMatch::from_scratch(&words, 0, 4, &scratch, &tokens, "chases", "$3($2, $5).")
+                .lemmatized(&words, &tokens, &Database::default());
+        assert_eq!(pattern_match.captures, vec!["", "cats", "chase", "", "mice"]);
+        assert_eq!(
+            apply_template(&pattern_match.captures, &pattern_match.template),
+            vec!["chase(cats, mice)."]
+        );
+    }
+
+    #[test]
+    fn test_split_lines_emit_one_fact_per_element() {
+        assert_eq!(
+            split_elements("ice_cream,_pasta_and_salad"),
+            vec!["ice_cream", "pasta", "salad"]
+        );
+
+        let captures = vec!["john".to_string(), "pizza,_pasta_and_salad".to_string()];
+        assert_eq!(
+            apply_template(&captures, "person($1).\n$2.split -> likes($1, #)."),
+            vec![
+                "person(john).",
+                "likes(john, pizza).",
+                "likes(john, pasta).",
+                "likes(john, salad).",
+            ]
+        );
+        assert_eq!(
+            apply_template(&captures, "likes($1, $2)."),
+            vec!["likes(john, pizza,_pasta_and_salad)."]
+        );
+    }
+
+    #[test]
+    fn test_match_gives_up_past_the_step_budget() {
+        let app = PrologApp::with_database(Database::default(), QueryEngine::new());
+        let mut words = vec!["word".to_string(); 60];
+        let mut scratch = MatchScratch::default();
+
+        // Every way of splitting 60 words between four greedy tokens is tried
+        // before the missing "end" fails the match, far more than the budget.
+        let tokens = parse_pattern("*+ *+ *+ *+ end");
+        assert_eq!(match_prefix(&words, &tokens, true, &app, &mut scratch), None);
+
+        words.push("end".to_string());
+        assert_eq!(
+            match_prefix(&words, &tokens, true, &app, &mut scratch),
+            Some(61)
+        );
+    }
+
+    #[test]
+    fn test_long_matches_do_not_overflow_the_stack() {
+        let app = PrologApp::with_database(Database::default(), QueryEngine::new());
+        let words = vec!["word".to_string(); 40_000];
+        let mut scratch = MatchScratch::default();
+
+        // One choice per optional token, each left on the choice stack
+        let optional = PatternToken::Optional(Box::new(PatternToken::Literal("word".to_string())));
+        let tokens = vec![optional; 40_000];
+        assert_eq!(
+            match_prefix(&words, &tokens, true, &app, &mut scratch),
+            Some(40_000)
+        );
+
+        let mut tokens = parse_pattern("*+");
+        tokens.push(PatternToken::Literal("end".to_string()));
+        assert_eq!(match_prefix(&words, &tokens, true, &app, &mut scratch), None);
     }
 }
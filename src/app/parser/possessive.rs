@@ -0,0 +1,178 @@
+//! Possessives. "John's dog barks" is about the dog, and says on the side
+//! that John owns it: the `'s` is split off the owner as a word of its own,
+//! the owner is taken out of the sentence, the rest matched as usual, and
+//! `owns(john, dog).` written along with the facts of its template.
+
+use crate::app::{
+    PrologApp,
+    database::{Database, PrologPattern, WordType},
+};
+
+use super::pattern_matcher::spelled_out_pattern_matches;
+
+/// The word a possessive ending becomes, so patterns can spell it out.
+pub const POSSESSIVE_MARKER: &str = "'s";
+
+/// Words whose `'s` is a contracted "is" or "us" rather than a possessive.
+const CONTRACTION_HOSTS: [&str; 10] = [
+    "it", "he", "she", "that", "there", "here", "what", "who", "where", "let",
+];
+
+/// The owner a possessive word names: `john's` and `cats'` -> `john`, `cats`.
+fn possessor(word: &str) -> Option<&str> {
+    let owner = word
+        .strip_suffix("'s")
+        .or_else(|| word.strip_suffix("’s"))
+        .or_else(|| {
+            word.strip_suffix(['\'', '’'])
+                .filter(|owner| owner.ends_with('s'))
+        })?;
+    (!owner.is_empty() && !CONTRACTION_HOSTS.contains(&owner)).then_some(owner)
+}
+
+/// The words of a sentence with every possessive ending split off as
+/// [`POSSESSIVE_MARKER`]: "john's dog barks" -> "john 's dog barks". Returns
+/// for each of them the position of the word it came from.
+pub fn split_possessives(words: &[String]) -> (Vec<String>, Vec<usize>) {
+    let mut split = Vec::new();
+    let mut sources = Vec::new();
+    for (idx, word) in words.iter().enumerate() {
+        match possessor(word) {
+            Some(owner) => {
+                split.extend([owner.to_string(), POSSESSIVE_MARKER.to_string()]);
+                sources.extend([idx, idx]);
+            }
+            None => {
+                split.push(word.clone());
+                sources.push(idx);
+            }
+        }
+    }
+    (split, sources)
+}
+
+/// A sentence with its owners taken out.
+#[derive(Debug)]
+pub struct Possessions {
+    pub words: Vec<String>,
+    /// For each of `words`, the position of the word it came from.
+    pub positions: Vec<usize>,
+    /// An `owns(owner, thing).` fact for each possessive.
+    pub facts: Vec<String>,
+}
+
+/// The sentence without the owners of its possessives, and who owns what;
+/// `None` when it has no possessive. What an owner owns is the first noun
+/// after the `'s`, or the word right after it when none is known: "john 's
+/// big dog barks" -> "big dog barks" and `owns(john, dog).` Both are
+/// written as their lemmas where the database knows them.
+pub fn strip_possessives(words: &[String], database: &Database) -> Option<Possessions> {
+    let is_noun = |word: &str| {
+        database.get_word_entries(word).is_some_and(|entries| {
+            entries
+                .iter()
+                .any(|entry| entry.word_type == WordType::Noun)
+        })
+    };
+    let lemma = |word: &str| {
+        database
+            .lemma_of(word, &[WordType::Noun])
+            .unwrap_or(word)
+            .to_string()
+    };
+
+    let mut kept: Vec<(String, usize)> = Vec::new();
+    let mut facts = Vec::new();
+    for (idx, word) in words.iter().enumerate() {
+        let owned = &words[idx + 1..];
+        if word != POSSESSIVE_MARKER || kept.is_empty() || owned.is_empty() {
+            kept.push((word.clone(), idx));
+            continue;
+        }
+        let thing = owned
+            .iter()
+            .take_while(|word| *word != POSSESSIVE_MARKER)
+            .find(|word| is_noun(word))
+            .unwrap_or(&owned[0]);
+        if let Some((owner, _)) = kept.pop() {
+            facts.push(format!("owns({}, {}).", lemma(&owner), lemma(thing)));
+        }
+    }
+
+    if facts.is_empty() {
+        return None;
+    }
+    let (words, positions) = kept.into_iter().unzip();
+    Some(Possessions {
+        words,
+        positions,
+        facts,
+    })
+}
+
+/// Whether a pattern that spells out a possessive itself matches `words`;
+/// its template already says what the possessive means, so it is left to
+/// match.
+pub fn possessive_pattern_matches(app: &PrologApp, words: &[String]) -> bool {
+    spelled_out_pattern_matches(app, words, |literal| {
+        literal == POSSESSIVE_MARKER || literal == "’s"
+    })
+}
+
+/// `pattern` writing the ownership `facts` before those of its template.
+pub fn owning_pattern(pattern: &PrologPattern, facts: &[String]) -> PrologPattern {
+    PrologPattern {
+        template: format!("{}\n{}", facts.join("\n"), pattern.template),
+        ..pattern.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::database::fixtures::word;
+
+    #[test]
+    fn test_possessives_are_split_off_and_become_ownership() {
+        let words = |sentence: &str| -> Vec<String> {
+            sentence.split_whitespace().map(str::to_string).collect()
+        };
+        let (split, sources) = split_possessives(&words("john's dogs’ owner isn't here it's late"));
+        assert_eq!(split.join(" "), "john 's dogs 's owner isn't here it's late");
+        assert_eq!(sources, vec![0, 0, 1, 1, 2, 3, 4, 5, 6]);
+
+        let mut database = Database::default();
+        database.words.push(word("dog", WordType::Noun, &["dogs"]));
+        database.rebuild_index();
+
+        let strip = |sentence: &str| {
+            strip_possessives(&words(sentence), &database).map(|possessions| {
+                (
+                    possessions.words.join(" "),
+                    possessions.positions,
+                    possessions.facts,
+                )
+            })
+        };
+        assert_eq!(
+            strip("john 's big dogs bark"),
+            Some((
+                "big dogs bark".to_string(),
+                vec![2, 3, 4],
+                vec!["owns(john, dog).".to_string()]
+            ))
+        );
+        assert_eq!(
+            strip("mary 's brother 's car is red"),
+            Some((
+                "car is red".to_string(),
+                vec![4, 5, 6],
+                vec![
+                    "owns(mary, brother).".to_string(),
+                    "owns(brother, car).".to_string()
+                ]
+            ))
+        );
+        assert_eq!(strip("dogs bark"), None);
+    }
+}
@@ -1,12 +1,32 @@
-/// Simple pronoun resolution using heuristics without gender
+/// Simple pronoun resolution using heuristics and the gender, animacy and
+/// number of the words in the database
 ///
 /// Heuristics:
-/// - Singular pronouns (he, she, him, her, it) -> most recent singular noun (likely proper noun/unknown word)
-/// - Plural pronouns (they, them) -> most recent plural noun (word ending in 's')
+/// - Singular pronouns (he, she, him, her, it) -> most salient singular noun, favouring those
+///   whose features agree ("she" a female one, "it" an inanimate one), likely names for he/she
+///   and common nouns for it
+/// - Plural pronouns (they, them) -> most salient plural noun (marked plural, a plural form in the
+///   database, or for unknown words an irregular plural or an -s ending)
+/// - Salience comes from recency and from being the subject of its sentence; nouns more than
+///   `window` sentences back are forgotten
 /// - Reflexive pronouns (himself, herself, themselves) -> subject of current sentence
 /// - Possessive pronouns (his, her, their) -> possessive form of antecedent
-use crate::app::database::{Database, WordType};
-use std::sync::{Arc, RwLock};
+/// - First and second person pronouns (I, me, my, you, your) -> the speaker and addressee, when set
+use std::collections::BTreeMap;
+
+use crate::app::database::{Animacy, Database, Gender, Number, WordFeatures, WordType};
+
+use super::relative::RELATIVE_PRONOUNS;
+
+/// Salience an entity gains from agreeing with the pronoun's features.
+const AGREEMENT_SALIENCE: f32 = 2.0;
+/// Salience a likely name gains for he/she, or a common noun for it.
+const NAME_SALIENCE: f32 = 0.75;
+/// Salience the subject of a sentence gains over its other nouns.
+const SUBJECT_SALIENCE: f32 = 0.5;
+
+/// How many sentences back antecedents are looked for by default.
+pub const DEFAULT_WINDOW: usize = 3;
 
 /// Pronoun categories
 #[derive(Debug, Clone, PartialEq)]
@@ -24,11 +44,37 @@ struct Entity {
     word: String,
     is_plural: bool,
     is_proper_noun: bool, // Likely a name (not in database)
+    features: WordFeatures,
+    sentence_index: usize,
+    is_subject: bool,
+}
+
+/// A pronoun of the last sentence that was replaced by its antecedent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PronounResolution {
+    pub pronoun: String,
+    /// Position of the pronoun in the sentence words.
+    pub word_index: usize,
+    pub antecedent: String,
+    /// How many times the antecedent occurs in the resolved words before
+    /// it, which tells the substituted word apart from the same word written
+    /// out.
+    pub occurrence: usize,
+    /// The entities known at that point, most recent first, any of which the
+    /// user may pick instead.
+    pub candidates: Vec<String>,
 }
 
 pub struct PronounResolver {
     entities: Vec<Entity>,
     current_sentence_index: usize,
+    /// How many sentences back an entity may still be an antecedent.
+    window: usize,
+    resolutions: Vec<PronounResolution>,
+    /// Who "I" stands for, if anyone.
+    speaker: Option<String>,
+    /// Who "you" stands for, if anyone.
+    addressee: Option<String>,
 }
 
 impl PronounResolver {
@@ -36,26 +82,71 @@ impl PronounResolver {
         Self {
             entities: Vec::new(),
             current_sentence_index: 0,
+            window: DEFAULT_WINDOW,
+            resolutions: Vec::new(),
+            speaker: None,
+            addressee: None,
         }
     }
 
+    /// Forgets entities more than `window` sentences back.
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Resolves "I", "me" and "my" to `speaker` and "you" and "your" to
+    /// `addressee`; without them those are left as written.
+    pub fn with_participants(mut self, speaker: Option<String>, addressee: Option<String>) -> Self {
+        self.speaker = speaker;
+        self.addressee = addressee;
+        self
+    }
+
+    /// The pronouns replaced in the last sentence resolved.
+    pub fn resolutions(&self) -> &[PronounResolution] {
+        &self.resolutions
+    }
+
     pub fn next_sentence(&mut self) {
         self.current_sentence_index += 1;
     }
 
+    /// The words with their pronouns replaced by their antecedents.
+    /// `overrides` holds antecedents picked by the user, by word position;
+    /// they replace the ones the heuristics would find.
     pub fn resolve_sentence(
         &mut self,
         words: &[String],
-        database: &Arc<RwLock<Database>>,
+        database: &Database,
+        overrides: &BTreeMap<usize, String>,
     ) -> Vec<String> {
-        let mut resolved = Vec::new();
+        let mut resolved: Vec<String> = Vec::new();
         let mut subject_entity: Option<String> = None;
+        self.resolutions.clear();
 
-        for (_, word) in words.iter().enumerate() {
+        for (word_index, word) in words.iter().enumerate() {
             let word_lower = word.to_lowercase();
 
-            if let Some(pronoun_type) = self.identify_pronoun(&word_lower) {
-                if let Some(antecedent) = self.resolve_pronoun(&pronoun_type, &subject_entity) {
+            let pronoun_type = self.identify_pronoun(&word_lower);
+            let participant = self.participant(&word_lower);
+            if pronoun_type.is_some() || participant.is_some() {
+                let antecedent = overrides
+                    .get(&word_index)
+                    .cloned()
+                    .or(participant)
+                    .or_else(|| {
+                        let wanted = self.pronoun_features(&word_lower);
+                        self.resolve_pronoun(pronoun_type.as_ref()?, &wanted, &subject_entity)
+                    });
+                if let Some(antecedent) = antecedent {
+                    self.resolutions.push(PronounResolution {
+                        pronoun: word.clone(),
+                        word_index,
+                        occurrence: resolved.iter().filter(|w| **w == antecedent).count(),
+                        antecedent: antecedent.clone(),
+                        candidates: self.known_entities(),
+                    });
                     resolved.push(antecedent);
                 } else {
                     resolved.push(word.clone());
@@ -63,14 +154,25 @@ impl PronounResolver {
             } else {
                 resolved.push(word.clone());
 
-                let is_plural = self.is_plural_form(&word_lower);
                 let is_proper_noun = self.is_likely_proper_noun(&word_lower, database);
 
-                if self.is_noun(&word_lower, database) || is_proper_noun {
+                // "who" and "which" stand for a noun already seen
+                let is_relative = RELATIVE_PRONOUNS.contains(&word_lower.as_str());
+
+                if (self.is_noun(&word_lower, database) || is_proper_noun) && !is_relative {
+                    let features = self.noun_features(&word_lower, database);
+                    let number = features.number.or_else(|| database.number_of(&word_lower));
+                    let is_plural = match number {
+                        Some(number) => number == Number::Plural,
+                        None => self.is_plural_form(&word_lower, database),
+                    };
                     let entity = Entity {
                         word: word.clone(),
                         is_plural,
                         is_proper_noun,
+                        features,
+                        sentence_index: self.current_sentence_index,
+                        is_subject: subject_entity.is_none(),
                     };
 
                     if subject_entity.is_none() {
@@ -85,6 +187,17 @@ impl PronounResolver {
         resolved
     }
 
+    /// The distinct words of the entities seen so far, most recent first.
+    fn known_entities(&self) -> Vec<String> {
+        let mut known: Vec<String> = Vec::new();
+        for entity in self.entities.iter().rev() {
+            if !known.contains(&entity.word) {
+                known.push(entity.word.clone());
+            }
+        }
+        known
+    }
+
     fn identify_pronoun(&self, word: &str) -> Option<PronounType> {
         match word {
             // Singular subject pronouns
@@ -109,53 +222,106 @@ impl PronounResolver {
         }
     }
 
+    /// The speaker or addressee a first or second person pronoun stands for.
+    fn participant(&self, word: &str) -> Option<String> {
+        match word {
+            "i" | "me" | "my" | "mine" | "myself" => self.speaker.clone(),
+            "you" | "your" | "yours" | "yourself" => self.addressee.clone(),
+            _ => None,
+        }
+    }
+
+    /// The features an antecedent of `pronoun` should have: "she" a female
+    /// person, "it" something inanimate. Plural pronouns ask for nothing.
+    fn pronoun_features(&self, pronoun: &str) -> WordFeatures {
+        let (gender, animacy) = match pronoun {
+            "he" | "him" | "his" | "himself" => (Some(Gender::Male), Some(Animacy::Animate)),
+            "she" | "her" | "hers" | "herself" => (Some(Gender::Female), Some(Animacy::Animate)),
+            "it" | "its" | "itself" => (None, Some(Animacy::Inanimate)),
+            _ => (None, None),
+        };
+        WordFeatures {
+            gender,
+            animacy,
+            number: None,
+        }
+    }
+
     fn resolve_pronoun(
         &self,
         pronoun_type: &PronounType,
+        wanted: &WordFeatures,
         subject_entity: &Option<String>,
     ) -> Option<String> {
         match pronoun_type {
             PronounType::SingularSubject | PronounType::SingularObject => {
-                self.find_most_recent_entity(false, true)
+                self.find_most_salient_entity(false, wanted)
             }
 
             PronounType::PluralSubject | PronounType::PluralObject => {
-                self.find_most_recent_entity(true, false)
+                self.find_most_salient_entity(true, wanted)
             }
 
             PronounType::Reflexive => subject_entity.clone(),
 
             PronounType::Possessive => self
-                .find_most_recent_entity(false, true)
-                .or_else(|| self.find_most_recent_entity(true, false)),
+                .find_most_salient_entity(false, wanted)
+                .or_else(|| self.find_most_salient_entity(true, wanted)),
         }
     }
 
-    fn find_most_recent_entity(&self, is_plural: bool, prefer_proper_noun: bool) -> Option<String> {
+    /// The most salient entity of the right number that `wanted` allows,
+    /// among those at most `window` sentences back. Ties go to the most
+    /// recent one.
+    fn find_most_salient_entity(&self, is_plural: bool, wanted: &WordFeatures) -> Option<String> {
+        let mut best: Option<(&Entity, f32)> = None;
         for entity in self.entities.iter().rev() {
-            if entity.is_plural == is_plural {
-                if prefer_proper_noun && entity.is_proper_noun {
-                    return Some(entity.word.clone());
-                }
-                if !prefer_proper_noun {
-                    return Some(entity.word.clone());
-                }
+            let distance = self.current_sentence_index - entity.sentence_index;
+            if entity.is_plural != is_plural
+                || distance > self.window
+                || !wanted.allows(&entity.features)
+            {
+                continue;
             }
-        }
-
-        if prefer_proper_noun {
-            for entity in self.entities.iter().rev() {
-                if entity.is_plural == is_plural {
-                    return Some(entity.word.clone());
-                }
+            let salience = self.salience(entity, distance, wanted);
+            if best.is_none_or(|(_, best_salience)| salience > best_salience) {
+                best = Some((entity, salience));
             }
         }
+        best.map(|(entity, _)| entity.word.clone())
+    }
 
-        None
+    /// How likely `entity`, `distance` sentences back, is the antecedent of
+    /// a pronoun wanting `wanted`: recent sentences and subjects score
+    /// higher, and so do agreeing features and, for a singular pronoun, a
+    /// likely name for he/she or a common noun for it.
+    fn salience(&self, entity: &Entity, distance: usize, wanted: &WordFeatures) -> f32 {
+        let mut salience = 1.0 / (1 + distance) as f32;
+        if entity.is_subject {
+            salience += SUBJECT_SALIENCE;
+        }
+        if wanted.agrees_with(&entity.features) {
+            salience += AGREEMENT_SALIENCE;
+        }
+        let prefers_names = wanted.animacy != Some(Animacy::Inanimate);
+        if !entity.is_plural && entity.is_proper_noun == prefers_names {
+            salience += NAME_SALIENCE;
+        }
+        salience
     }
 
-    fn is_plural_form(&self, word: &str) -> bool {
-        if word.ends_with("ies") || word.ends_with("es") || word.ends_with('s') {
+    /// Whether a word missing from the database looks plural: an irregular
+    /// plural from the morphology table, or an -s ending that is not a known
+    /// singular one.
+    fn is_plural_form(&self, word: &str, database: &Database) -> bool {
+        let irregular = database
+            .morphology
+            .plurals
+            .iter()
+            .any(|(singular, plural)| plural == word && singular != plural);
+        if irregular {
+            true
+        } else if word.ends_with("ies") || word.ends_with("es") || word.ends_with('s') {
             !matches!(
                 word,
                 "was"
@@ -189,38 +355,81 @@ impl PronounResolver {
         }
     }
 
-    fn is_likely_proper_noun(&self, word: &str, database: &Arc<RwLock<Database>>) -> bool {
-        if let Ok(db) = database.read() {
-            db.get_word_entries(word).is_none()
-        } else {
-            false
-        }
+    fn is_likely_proper_noun(&self, word: &str, database: &Database) -> bool {
+        database.get_word_entries(word).is_none()
     }
 
-    fn is_noun(&self, word: &str, database: &Arc<RwLock<Database>>) -> bool {
-        if let Ok(db) = database.read() {
-            if let Some(entries) = db.get_word_entries(word) {
-                return entries
+    /// The features of the noun reading of `word`; unset for unknown words.
+    fn noun_features(&self, word: &str, database: &Database) -> WordFeatures {
+        database
+            .get_word_entries(word)
+            .and_then(|entries| {
+                entries
                     .iter()
-                    .any(|e| matches!(e.word_type, WordType::Noun));
-            }
-        }
-        false
+                    .find(|entry| entry.word_type == WordType::Noun)
+            })
+            .map(|entry| entry.features)
+            .unwrap_or_default()
+    }
+
+    fn is_noun(&self, word: &str, database: &Database) -> bool {
+        database.get_word_entries(word).is_some_and(|entries| {
+            entries
+                .iter()
+                .any(|e| matches!(e.word_type, WordType::Noun))
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::app::{
+        database::{WordEntry, fixtures::word},
+        morphology::MorphologyExceptions,
+    };
 
     #[test]
     fn test_plural_detection() {
         let resolver = PronounResolver::new();
-        assert!(resolver.is_plural_form("books"));
-        assert!(resolver.is_plural_form("people"));
-        assert!(resolver.is_plural_form("children"));
-        assert!(!resolver.is_plural_form("book"));
-        assert!(!resolver.is_plural_form("was"));
+        let database = Database {
+            morphology: MorphologyExceptions::english(),
+            ..Database::default()
+        };
+        assert!(resolver.is_plural_form("books", &database));
+        assert!(resolver.is_plural_form("people", &database));
+        assert!(resolver.is_plural_form("children", &database));
+        assert!(!resolver.is_plural_form("book", &database));
+        assert!(!resolver.is_plural_form("was", &database));
+        assert!(!resolver.is_plural_form("sheep", &database));
+    }
+
+    #[test]
+    fn test_plural_detection_from_database() {
+        let mut database = Database::default();
+        for (lemma, word_type, forms) in [
+            ("bus", WordType::Noun, vec!["buses"]),
+            ("sheep", WordType::Noun, vec!["sheep"]),
+            ("met", WordType::Verb, vec![]),
+        ] {
+            database.words.push(word(lemma, word_type, &forms));
+        }
+        database.fill_noun_numbers();
+        database.rebuild_index();
+        assert_eq!(database.number_of("bus"), Some(Number::Singular));
+        assert_eq!(database.number_of("buses"), Some(Number::Plural));
+        assert_eq!(database.number_of("sheep"), Some(Number::Singular));
+        assert_eq!(database.number_of("met"), None);
+
+        let words = |sentence: &str| -> Vec<String> {
+            sentence.split_whitespace().map(str::to_string).collect()
+        };
+        let mut resolver = PronounResolver::new();
+        resolver.resolve_sentence(&words("buses met bus"), &database, &BTreeMap::new());
+        resolver.next_sentence();
+        let resolved =
+            resolver.resolve_sentence(&words("they met it"), &database, &BTreeMap::new());
+        assert_eq!(resolved, words("buses met bus"));
     }
 
     #[test]
@@ -236,4 +445,115 @@ mod tests {
         );
         assert_eq!(resolver.identify_pronoun("book"), None);
     }
+
+    #[test]
+    fn test_resolution_report_and_override() {
+        let database = Database::default();
+        let words = |sentence: &str| -> Vec<String> {
+            sentence.split_whitespace().map(str::to_string).collect()
+        };
+
+        let mut resolver = PronounResolver::new();
+        resolver.resolve_sentence(&words("john met mary"), &database, &BTreeMap::new());
+        resolver.next_sentence();
+        let resolved =
+            resolver.resolve_sentence(&words("she likes him"), &database, &BTreeMap::new());
+        assert_eq!(resolved, words("john likes john"));
+        assert_eq!(resolver.resolutions()[1].occurrence, 1);
+        assert_eq!(resolver.resolutions()[0].candidates, words("mary met john"));
+
+        let overrides = BTreeMap::from([(0, "mary".to_string())]);
+        let resolved = resolver.resolve_sentence(&words("she likes him"), &database, &overrides);
+        assert_eq!(resolved, words("mary likes john"));
+        assert_eq!(resolver.resolutions()[0].antecedent, "mary");
+    }
+
+    #[test]
+    fn test_gender_and_animacy_agreement() {
+        let mut database = Database::default();
+        for (lemma, gender, animacy) in [
+            ("john", Some(Gender::Male), Animacy::Animate),
+            ("mary", Some(Gender::Female), Animacy::Animate),
+            ("book", None, Animacy::Inanimate),
+        ] {
+            database.words.push(WordEntry {
+                features: WordFeatures {
+                    gender,
+                    animacy: Some(animacy),
+                    number: Some(Number::Singular),
+                },
+                ..word(lemma, WordType::Noun, &[])
+            });
+        }
+        database.rebuild_index();
+        let words = |sentence: &str| -> Vec<String> {
+            sentence.split_whitespace().map(str::to_string).collect()
+        };
+
+        let mut resolver = PronounResolver::new();
+        resolver.resolve_sentence(&words("john gave mary the book"), &database, &BTreeMap::new());
+        resolver.next_sentence();
+        let resolved =
+            resolver.resolve_sentence(&words("he thanked her for it"), &database, &BTreeMap::new());
+        assert_eq!(resolved, words("john thanked mary for book"));
+    }
+
+    #[test]
+    fn test_first_and_second_person() {
+        let database = Database::default();
+        let words = |sentence: &str| -> Vec<String> {
+            sentence.split_whitespace().map(str::to_string).collect()
+        };
+
+        let mut resolver = PronounResolver::new();
+        let resolved = resolver.resolve_sentence(&words("i like you"), &database, &BTreeMap::new());
+        assert_eq!(resolved, words("i like you"));
+
+        let mut resolver = PronounResolver::new()
+            .with_participants(Some("alice".to_string()), Some("bob".to_string()));
+        let resolved =
+            resolver.resolve_sentence(&words("you like my cat"), &database, &BTreeMap::new());
+        assert_eq!(resolved, words("bob like alice cat"));
+        assert_eq!(resolver.resolutions()[0].pronoun, "you");
+    }
+
+    #[test]
+    fn test_salience_and_forgetting() {
+        let mut database = Database::default();
+        for (lemma, word_type, animacy) in [
+            ("met", WordType::Verb, None),
+            ("sleeps", WordType::Verb, None),
+            ("falls", WordType::Verb, None),
+            ("eats", WordType::Verb, None),
+            ("rain", WordType::Noun, Some(Animacy::Inanimate)),
+        ] {
+            database.words.push(WordEntry {
+                features: WordFeatures {
+                    animacy,
+                    ..WordFeatures::default()
+                },
+                ..word(lemma, word_type, &[])
+            });
+        }
+        database.rebuild_index();
+        let words = |sentence: &str| -> Vec<String> {
+            sentence.split_whitespace().map(str::to_string).collect()
+        };
+        let resolve = |window: usize, sentences: &[&str]| -> Vec<String> {
+            let mut resolver = PronounResolver::new().with_window(window);
+            let mut resolved = Vec::new();
+            for sentence in sentences {
+                resolved = resolver.resolve_sentence(&words(sentence), &database, &BTreeMap::new());
+                resolver.next_sentence();
+            }
+            resolved
+        };
+
+        assert_eq!(resolve(3, &["john met bill", "he eats"]), words("john eats"));
+        assert_eq!(
+            resolve(3, &["john sleeps", "rain falls", "he eats"]),
+            words("john eats")
+        );
+        assert_eq!(resolve(1, &["john sleeps", "rain falls", "he eats"]), words("he eats"));
+    }
 }
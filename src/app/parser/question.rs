@@ -0,0 +1,245 @@
+//! Questions in the input. Instead of facts, "What does John like?" becomes
+//! a query: the question is turned back into the statement it asks about
+//! ("john likes X"), the sentence patterns match that, and the facts their
+//! template would emit become the goals of the query.
+
+use crate::app::{
+    PrologApp,
+    coverage::{SentenceCoverage, WordCoverage},
+    database::WordType,
+    morphology::{MorphologyExceptions, VerbForm, conjugate},
+    query_engine::QueryEngine,
+};
+
+use super::{pattern_matcher::statement_goals, tense::any_tense};
+
+/// What the wh-word of a question becomes; a variable word, so the template
+/// writes it as a query variable.
+pub const QUESTION_VARIABLE: &str = "X";
+
+const WH_WORDS: [&str; 4] = ["what", "who", "whom", "which"];
+
+/// Auxiliaries that move back after the subject as they are.
+const AUXILIARIES: [&str; 16] = [
+    "is", "are", "am", "was", "were", "can", "could", "will", "would", "shall", "should", "may",
+    "might", "must", "has", "have",
+];
+
+/// `do`-support auxiliaries, and the form they leave on the main verb.
+pub const DO_AUXILIARIES: [(&str, Option<VerbForm>); 3] = [
+    ("do", None),
+    ("does", Some(VerbForm::ThirdPerson)),
+    ("did", Some(VerbForm::Past)),
+];
+
+/// A question of the input and the query it was compiled into.
+#[derive(Debug, Clone)]
+pub struct Question {
+    pub sentence: String,
+    /// `None` when no pattern matched any statement form of the question.
+    pub query: Option<String>,
+    /// Answers from the last time the query ran: what the wh-word stands for,
+    /// `true.` for a yes/no question, `false.` when there were none.
+    pub answers: Vec<String>,
+}
+
+impl Question {
+    /// Runs the query against `engine`, replacing the previous answers.
+    pub fn answer(&mut self, engine: &QueryEngine) {
+        let Some(query) = &self.query else {
+            return;
+        };
+        self.answers = match engine.query_bindings(query) {
+            Ok(answers) if answers.is_empty() => vec!["false.".to_string()],
+            Ok(answers) => {
+                let mut values = Vec::new();
+                for bindings in answers {
+                    let value = bindings
+                        .get(QUESTION_VARIABLE)
+                        .map_or("true.".to_string(), ToString::to_string);
+                    // Other variables of the query can repeat the same answer
+                    if !values.contains(&value) {
+                        values.push(value);
+                    }
+                }
+                values
+            }
+            Err(e) => vec![format!("// Error: {}", e)],
+        };
+    }
+}
+
+/// The statements a question may be asking about, most likely first. A
+/// leading wh-word becomes [`QUESTION_VARIABLE`], either as the subject
+/// ("who likes mary" -> "X likes mary") or after the rest of the statement
+/// ("what does john like" -> "john likes X"). An inverted auxiliary goes
+/// back after the subject; where the subject ends is not known, so every
+/// split that does not end it on a determiner is tried.
+pub fn declarative_forms(
+    words: &[String],
+    exceptions: &MorphologyExceptions,
+    is_determiner: impl Fn(&str) -> bool,
+) -> Vec<Vec<String>> {
+    let (asked, rest) = match words.split_first() {
+        Some((first, rest)) if WH_WORDS.contains(&first.as_str()) => {
+            // "which animal eats fish" asks about the animal itself
+            let rest = if first == "which" && rest.len() > 1 {
+                &rest[1..]
+            } else {
+                rest
+            };
+            (Some(QUESTION_VARIABLE.to_string()), rest)
+        }
+        _ => (None, words),
+    };
+
+    let mut forms = Vec::new();
+    if let Some(variable) = &asked {
+        forms.push(std::iter::once(variable.clone()).chain(rest.iter().cloned()).collect());
+    }
+
+    let Some((auxiliary, rest)) = rest.split_first() else {
+        return forms;
+    };
+    let do_form = DO_AUXILIARIES
+        .iter()
+        .find(|(word, _)| *word == auxiliary.as_str())
+        .map(|(_, form)| *form);
+    if do_form.is_none() && !AUXILIARIES.contains(&auxiliary.as_str()) {
+        return forms;
+    }
+
+    for subject_end in 1..=rest.len() {
+        if is_determiner(&rest[subject_end - 1]) {
+            continue;
+        }
+        let (subject, predicate) = rest.split_at(subject_end);
+        let mut form = subject.to_vec();
+        match do_form {
+            // "does john like" -> "john likes"
+            Some(verb_form) => {
+                let Some((verb, objects)) = predicate.split_first() else {
+                    continue;
+                };
+                form.push(match verb_form {
+                    Some(verb_form) => conjugate(verb, verb_form, exceptions),
+                    None => verb.clone(),
+                });
+                form.extend(objects.iter().cloned());
+            }
+            None if predicate.is_empty() && asked.is_none() => continue,
+            None => {
+                form.push(auxiliary.clone());
+                form.extend(predicate.iter().cloned());
+            }
+        }
+        form.extend(asked.clone());
+        forms.push(form);
+    }
+    forms
+}
+
+/// Compiles a question into a query and records it for answering once the
+/// whole input is parsed. Returns its output, which is all comments.
+pub fn parse_question(
+    app: &mut PrologApp,
+    sentence: &str,
+    words: &[String],
+    coverage: &mut SentenceCoverage,
+) -> String {
+    let compiled = question_query(app, words);
+    let output = match &compiled {
+        Some((pattern_name, query)) => {
+            // Statement forms reorder the words, so the coverage is all or nothing
+            coverage.coverage.fill(WordCoverage::Matched);
+            format!(
+                "// QUESTION: {}\n// PATTERN: {}\n// ?- {}\n",
+                sentence, pattern_name, query
+            )
+        }
+        None => format!(
+            "// QUESTION: {}\n// WARNING: No pattern matched the question\n",
+            sentence
+        ),
+    };
+
+    app.interactive_parser.questions.push(Question {
+        sentence: sentence.to_string(),
+        query: compiled.map(|(_, query)| query),
+        answers: Vec::new(),
+    });
+    output
+}
+
+/// The name of the first pattern to match a statement form of the question,
+/// and the query made of the facts its template emits. With tense tagging
+/// on, the goals accept facts of any tense.
+fn question_query(app: &PrologApp, words: &[String]) -> Option<(String, String)> {
+    let database = &app.database_snapshot;
+    let is_determiner = |word: &str| {
+        database.get_word_entries(word).is_some_and(|entries| {
+            entries
+                .iter()
+                .all(|entry| entry.word_type == WordType::Determiner)
+        })
+    };
+
+    declarative_forms(words, &database.morphology, is_determiner)
+        .iter()
+        .find_map(|form| statement_goals(form, app))
+        .map(|(pattern_name, mut goals)| {
+            if app.settings.tag_tense {
+                goals = goals.iter().map(|goal| any_tense(goal)).collect();
+            }
+            (pattern_name, format!("{}.", goals.join(", ")))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_questions_become_statements_with_a_variable() {
+        let words = |sentence: &str| -> Vec<String> {
+            sentence.split_whitespace().map(str::to_string).collect()
+        };
+        let forms = |question: &str| -> Vec<String> {
+            declarative_forms(&words(question), &MorphologyExceptions::english(), |word| {
+                ["a", "an", "the"].contains(&word)
+            })
+            .iter()
+            .map(|form| form.join(" "))
+            .collect()
+        };
+
+        assert_eq!(forms("who likes mary"), vec!["X likes mary"]);
+        assert_eq!(
+            forms("what does john like"),
+            vec!["X does john like", "john likes X"]
+        );
+        assert_eq!(forms("is a bear an animal"), vec!["a bear is an animal"]);
+        assert_eq!(forms("which animal did tom chase"), vec!["X did tom chase", "tom chased X"]);
+        assert!(forms("bears eat fish").is_empty());
+    }
+
+    #[test]
+    fn test_answers_are_what_the_question_asks_for() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("likes(john, mary).\nlikes(tom, mary).\nlikes(tom, pizza).");
+        let answers = |query: &str| {
+            let mut question = Question {
+                sentence: String::new(),
+                query: Some(query.to_string()),
+                answers: Vec::new(),
+            };
+            question.answer(&engine);
+            question.answers
+        };
+
+        assert_eq!(answers("likes(X, mary)"), vec!["john", "tom"]);
+        assert_eq!(answers("likes(X, Y)"), vec!["john", "tom"]);
+        assert_eq!(answers("likes(john, mary)"), vec!["true."]);
+        assert_eq!(answers("likes(mary, X)"), vec!["false."]);
+    }
+}
@@ -0,0 +1,169 @@
+//! Relative clauses. In "The man who owns a dog is tall" the pronoun stands
+//! for the noun phrase right before it, so the clause is matched as "the
+//! man owns a dog" and writes `owns(man, dog).`, and the rest of the
+//! sentence, "the man is tall", is matched as usual, writing the clause's
+//! facts along with its own. A sentence that is only the noun phrase and
+//! its clause, "the man who owns a dog", writes the clause's facts alone.
+
+use crate::app::{
+    PrologApp,
+    database::{Database, WordType},
+};
+
+use super::{pattern_matcher::spelled_out_pattern_matches, rules::goals_of_clause};
+
+/// Pronouns opening a relative clause.
+pub const RELATIVE_PRONOUNS: [&str; 3] = ["who", "which", "that"];
+
+/// A sentence split at its relative clause.
+#[derive(Debug, PartialEq)]
+pub struct RelativeClause {
+    /// The sentence without the clause, with the position of each of its
+    /// words; `None` when only the noun phrase is left.
+    pub main: Option<(Vec<String>, Vec<usize>)>,
+    /// The clause with the noun phrase in place of its pronoun.
+    pub clause: Vec<String>,
+}
+
+/// The first relative clause of `words`, if any. The clause runs from its
+/// pronoun to a comma or to the verb that follows it after other words,
+/// which starts the rest of the sentence. When the clause opens with a
+/// verb the noun phrase is its subject, "the man who owns a dog"; otherwise
+/// its object, "the dog that the man owns".
+pub fn find_relative_clause(words: &[String], database: &Database) -> Option<RelativeClause> {
+    let types = |word: &str| -> Vec<WordType> {
+        database
+            .get_word_entries(word)
+            .map(|entries| entries.iter().map(|entry| entry.word_type.clone()).collect())
+            .unwrap_or_default()
+    };
+    // The most frequent reading decides, since most words can be verbs
+    let is_verb = |word: &str| types(word).first() == Some(&WordType::Verb);
+    let can_end_noun_phrase = |word: &str| {
+        let types = types(word);
+        types.is_empty() || types.contains(&WordType::Noun)
+    };
+    let modifies_noun = |word: &str| {
+        types(word).iter().any(|word_type| {
+            matches!(
+                word_type,
+                WordType::Determiner | WordType::Adjective | WordType::Noun
+            )
+        })
+    };
+    let bare = |word: &String| word.trim_end_matches(',').to_string();
+
+    let pronoun = (1..words.len()).find(|&idx| {
+        RELATIVE_PRONOUNS.contains(&words[idx].as_str())
+            && can_end_noun_phrase(words[idx - 1].trim_end_matches(','))
+    })?;
+    let phrase_start = (0..pronoun - 1)
+        .rev()
+        .take_while(|&idx| !words[idx].ends_with(',') && modifies_noun(&words[idx]))
+        .last()
+        .unwrap_or(pronoun - 1);
+    let noun_phrase: Vec<String> = words[phrase_start..pronoun].iter().map(bare).collect();
+
+    let start = pronoun + 1;
+    let subject_relative = is_verb(words.get(start)?);
+    let mut end = words.len();
+    let mut seen_verb = false;
+    for idx in start..words.len() {
+        let verb = is_verb(&words[idx]);
+        let after_verb_group = seen_verb && !is_verb(&words[idx - 1]);
+        // An object clause ends with its verb, a subject clause at the next one
+        if verb && (after_verb_group || (!subject_relative && seen_verb)) {
+            end = idx;
+            break;
+        }
+        seen_verb |= verb;
+        if words[idx].ends_with(',') {
+            end = idx + 1;
+            break;
+        }
+    }
+    if !subject_relative && !seen_verb {
+        return None;
+    }
+
+    let clause_words: Vec<String> = words[start..end].iter().map(bare).collect();
+    let clause = if subject_relative {
+        noun_phrase.into_iter().chain(clause_words).collect()
+    } else {
+        clause_words.into_iter().chain(noun_phrase).collect()
+    };
+
+    let kept: Vec<usize> = (0..pronoun).chain(end..words.len()).collect();
+    let has_verb = words[end..].iter().any(|word| is_verb(word));
+    let main = has_verb.then(|| {
+        let main_words = kept.iter().map(|&idx| bare(&words[idx])).collect();
+        (main_words, kept)
+    });
+    Some(RelativeClause { main, clause })
+}
+
+/// Whether a pattern that spells out a relative pronoun itself matches
+/// `words`; its template already says what the clause means.
+pub fn relative_pattern_matches(app: &PrologApp, words: &[String]) -> bool {
+    spelled_out_pattern_matches(app, words, |literal| RELATIVE_PRONOUNS.contains(&literal))
+}
+
+/// The facts the clause states. `None` when it matches no pattern, so the
+/// sentence is parsed as a whole instead.
+pub fn relative_facts(app: &PrologApp, relative: &RelativeClause) -> Option<Vec<String>> {
+    let goals = goals_of_clause(app, &relative.clause).ok()?;
+    Some(goals.iter().map(|goal| format!("{}.", goal)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::database::fixtures::word;
+
+    #[test]
+    fn test_relative_pronouns_stand_for_the_noun_phrase_before_them() {
+        let mut database = Database::default();
+        for (lemma, word_type, forms) in [
+            ("the", WordType::Determiner, vec![]),
+            ("a", WordType::Determiner, vec![]),
+            ("old", WordType::Adjective, vec![]),
+            ("tall", WordType::Adjective, vec![]),
+            ("man", WordType::Noun, vec![]),
+            ("dog", WordType::Noun, vec![]),
+            ("own", WordType::Verb, vec!["owns"]),
+            ("bark", WordType::Verb, vec!["barks"]),
+            ("be", WordType::Verb, vec!["is"]),
+            ("say", WordType::Verb, vec!["said"]),
+        ] {
+            database.words.push(word(lemma, word_type, &forms));
+        }
+        database.rebuild_index();
+
+        let split = |sentence: &str| {
+            let words: Vec<String> = sentence.split_whitespace().map(str::to_string).collect();
+            find_relative_clause(&words, &database).map(|relative| {
+                (
+                    relative.main.map(|(main, _)| main.join(" ")),
+                    relative.clause.join(" "),
+                )
+            })
+        };
+        assert_eq!(
+            split("the old man who owns a dog is tall"),
+            Some((Some("the old man is tall".to_string()), "the old man owns a dog".to_string()))
+        );
+        assert_eq!(
+            split("the man who owns a dog"),
+            Some((None, "the man owns a dog".to_string()))
+        );
+        assert_eq!(
+            split("the dog that the man owns barks"),
+            Some((Some("the dog barks".to_string()), "the man owns the dog".to_string()))
+        );
+        assert_eq!(
+            split("john, who owns a dog, is tall"),
+            Some((Some("john is tall".to_string()), "john owns a dog".to_string()))
+        );
+        assert_eq!(split("the man said that"), None);
+    }
+}
@@ -0,0 +1,283 @@
+//! Conditional sentences. A pattern with a rule template writes rules rather
+//! than facts: each `{$n}` capture is a clause of its own, parsed with the
+//! other patterns, and stands for the goals that clause states. With
+//! `if *+ then *+` and `{$2} :- {$1}`, "If X is a mammal then X is an
+//! animal" becomes `animal(X) :- mammal(X).` A pattern flagged as
+//! universal writes rules from plain facts instead, about any member of the
+//! class its first capture names: "All mammals are animals".
+
+use crate::app::{
+    PrologApp,
+    database::{Database, WordType},
+    morphology::{MorphologyExceptions, singularize},
+};
+
+use super::{
+    negation::{negate_template, strip_negation},
+    pattern_matcher::{
+        PatternMatch, PatternToken, apply_template, fill_placeholders, is_variable_word,
+        statement_goals,
+    },
+};
+
+/// Names for the variables that stand for a class, after the single-letter
+/// ones the sentence names itself.
+const VARIABLE_NAMES: [&str; 6] = ["X", "Y", "Z", "W", "U", "V"];
+
+/// The lines a match on a rule template writes: one rule per `:-` line and
+/// goal of its head, and the other lines as facts. Fails when a clause the
+/// template uses matches no pattern.
+pub fn rule_lines(
+    app: &PrologApp,
+    words: &[String],
+    pattern_match: &PatternMatch,
+) -> Result<Vec<String>, String> {
+    let mut clauses: Vec<Vec<String>> = vec![Vec::new(); pattern_match.captures.len()];
+    for span in &pattern_match.spans {
+        clauses[span.slot] = words[span.start..span.end].to_vec();
+    }
+    let class_goals = introduce_variables(&mut clauses, &app.database_snapshot);
+
+    let mut clause_goals = Vec::new();
+    for (idx, clause) in clauses.iter().enumerate() {
+        let placeholder = format!("{{${}}}", idx + 1);
+        if pattern_match.template.contains(&placeholder) {
+            clause_goals.push((placeholder, goals_of_clause(app, clause)?));
+        }
+    }
+    let fill = |text: &str| {
+        let mut text = text.to_string();
+        for (placeholder, goals) in &clause_goals {
+            text = text.replace(placeholder, &goals.join(", "));
+        }
+        fill_placeholders(&pattern_match.captures, &text)
+    };
+
+    let mut lines = Vec::new();
+    for line in pattern_match.template.lines().map(str::trim) {
+        let Some((head, body)) = line.trim_end_matches('.').split_once(":-") else {
+            if !line.is_empty() {
+                lines.extend(apply_template(&pattern_match.captures, &fill(line)));
+            }
+            continue;
+        };
+
+        // A clause can state several goals, but a rule has one head
+        let head = head.trim();
+        let heads = match clause_goals.iter().find(|(placeholder, _)| placeholder == head) {
+            Some((_, goals)) => goals.clone(),
+            None => vec![fill(head)],
+        };
+        let body: Vec<String> = class_goals
+            .iter()
+            .cloned()
+            .chain(std::iter::once(fill(body.trim())))
+            .collect();
+        for head in heads {
+            lines.push(format!("{} :- {}.", head, body.join(", ")));
+        }
+    }
+    Ok(lines)
+}
+
+/// The lines a match on a universal pattern writes: each fact of its
+/// template as a rule that holds for any member `X` of the class the first
+/// capture names. Captures of `<Noun>` tokens name classes, so they are
+/// singularized: "all mammals are animals" with `$2($1)` writes
+/// `animal(X) :- mammal(X).` Comment lines and rules are left as they are,
+/// and without a capture there is no class, so the facts are too.
+pub fn universal_rule_lines(
+    pattern_match: &PatternMatch,
+    pattern_tokens: &[PatternToken],
+    exceptions: &MorphologyExceptions,
+) -> Vec<String> {
+    let mut captures = pattern_match.captures.clone();
+    for span in &pattern_match.spans {
+        let capture = &mut captures[span.slot];
+        if names_class(&pattern_tokens[span.token]) && !is_variable_word(capture) {
+            *capture = class_name(capture, exceptions);
+        }
+    }
+    let Some(class) = captures.first_mut() else {
+        return apply_template(&pattern_match.captures, &pattern_match.template);
+    };
+    let variable = VARIABLE_NAMES[0];
+    let body = format!("{}({})", std::mem::replace(class, variable.to_string()), variable);
+
+    apply_template(&captures, &pattern_match.template)
+        .into_iter()
+        .map(|line| {
+            let fact = line.trim().trim_end_matches('.');
+            if fact.is_empty() || fact.starts_with("//") || fact.contains(":-") {
+                line
+            } else {
+                format!("{} :- {}.", fact, body)
+            }
+        })
+        .collect()
+}
+
+/// Whether a token captures nouns, whose words name a class.
+fn names_class(token: &PatternToken) -> bool {
+    match token {
+        PatternToken::TypeMatch(types, ..) => types.contains(&WordType::Noun),
+        PatternToken::Optional(inner) | PatternToken::Greedy(inner) => names_class(inner),
+        _ => false,
+    }
+}
+
+/// The singular name of a class: `grey_wolves` -> `grey_wolf`.
+fn class_name(capture: &str, exceptions: &MorphologyExceptions) -> String {
+    match capture.rsplit_once('_') {
+        Some((modifiers, noun)) => format!("{}_{}", modifiers, singularize(noun, exceptions)),
+        None => singularize(capture, exceptions),
+    }
+}
+
+/// The goals a clause states; a negated clause states the negated facts.
+pub fn goals_of_clause(app: &PrologApp, clause: &[String]) -> Result<Vec<String>, String> {
+    let (words, negated) = match strip_negation(clause, &app.database_snapshot.morphology) {
+        Some((positive, _)) => (positive, true),
+        None => (clause.to_vec(), false),
+    };
+    let (_, goals) = statement_goals(&words, app)
+        .ok_or_else(|| format!("No pattern matched the clause '{}'", clause.join(" ")))?;
+    if negated {
+        Ok(goals.iter().map(|goal| negate_template(goal)).collect())
+    } else {
+        Ok(goals)
+    }
+}
+
+/// Replaces the words of the clauses that stand for anything with variables:
+/// single letters ("if x is a mammal"), and plural nouns more than one clause
+/// mentions ("birds can fly when birds are not penguins"), which stand for
+/// any member of the class. Returns a goal naming the class of each of the
+/// latter, such as `bird(X)`.
+pub fn introduce_variables(clauses: &mut [Vec<String>], database: &Database) -> Vec<String> {
+    let is_letter = |word: &str| {
+        let mut chars = word.chars();
+        matches!((chars.next(), chars.next()), (Some(c), None) if c.is_alphabetic())
+            && word != "a"
+            && word != "i"
+    };
+    let is_noun = |word: &str| {
+        database.get_word_entries(word).is_some_and(|entries| {
+            entries
+                .iter()
+                .any(|entry| entry.word_type == WordType::Noun)
+        })
+    };
+
+    let mut variables: Vec<(String, String)> = Vec::new();
+    for word in clauses.iter().flatten() {
+        if is_letter(word) && !variables.iter().any(|(named, _)| named == word) {
+            variables.push((word.clone(), word.to_uppercase()));
+        }
+    }
+
+    let mut class_goals = Vec::new();
+    for (idx, clause) in clauses.iter().enumerate() {
+        for word in clause {
+            let class = singularize(word, &database.morphology);
+            let shared = clauses
+                .iter()
+                .enumerate()
+                .any(|(other, words)| other != idx && words.contains(word));
+            if class == *word
+                || !shared
+                || !is_noun(word)
+                || variables.iter().any(|(named, _)| named == word)
+            {
+                continue;
+            }
+            let Some(variable) = VARIABLE_NAMES
+                .iter()
+                .find(|name| !variables.iter().any(|(_, variable)| variable == *name))
+            else {
+                continue;
+            };
+            variables.push((word.clone(), variable.to_string()));
+            class_goals.push(format!("{}({})", class, variable));
+        }
+    }
+
+    for word in clauses.iter_mut().flatten() {
+        if let Some((_, variable)) = variables.iter().find(|(named, _)| named == word) {
+            *word = variable.clone();
+        }
+    }
+    class_goals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{
+        database::fixtures::word,
+        parser::pattern_matcher::{CaptureSpan, capture_slot, parse_pattern},
+    };
+
+    #[test]
+    fn test_letters_and_shared_plural_nouns_become_variables() {
+        let clauses = |sentences: &[&str]| -> Vec<Vec<String>> {
+            sentences
+                .iter()
+                .map(|sentence| sentence.split_whitespace().map(str::to_string).collect())
+                .collect()
+        };
+        let mut database = Database::default();
+        database
+            .words
+            .push(word("bird", WordType::Noun, &["birds"]));
+        database.rebuild_index();
+
+        let mut letters = clauses(&["x is a mammal", "x is an animal"]);
+        assert!(introduce_variables(&mut letters, &database).is_empty());
+        assert_eq!(letters, clauses(&["X is a mammal", "X is an animal"]));
+
+        let mut classes = clauses(&["birds can fly", "birds are not penguins"]);
+        assert_eq!(introduce_variables(&mut classes, &database), vec!["bird(X)"]);
+        assert_eq!(classes, clauses(&["X can fly", "X are not penguins"]));
+    }
+
+    #[test]
+    fn test_universal_patterns_write_rules_about_a_class() {
+        let tokens = parse_pattern("all <Noun>+ are <Noun>");
+        let span = |start, end, token| CaptureSpan {
+            start,
+            end,
+            token,
+            slot: capture_slot(&tokens, token).unwrap(),
+        };
+        let pattern_match = |captures: &[&str], template: &str| PatternMatch {
+            pattern_name: "universal".to_string(),
+            template: template.to_string(),
+            captures: captures.iter().map(|capture| capture.to_string()).collect(),
+            spans: vec![span(1, 3, 1), span(4, 5, 3)],
+            skipped: Vec::new(),
+            start_idx: 0,
+            end_idx: 5,
+            confidence: 1.0,
+        };
+        let exceptions = MorphologyExceptions::english();
+
+        let mammals = pattern_match(&["mammals", "animals"], "$2($1).");
+        assert_eq!(
+            universal_rule_lines(&mammals, &tokens, &exceptions),
+            vec!["animal(X) :- mammal(X)."]
+        );
+        let wolves = pattern_match(
+            &["grey_wolves", "hunters"],
+            "// wolves\nis_a($1, $2).\nnot_$2($1).",
+        );
+        assert_eq!(
+            universal_rule_lines(&wolves, &tokens, &exceptions),
+            vec![
+                "// wolves",
+                "is_a(X, hunter) :- grey_wolf(X).",
+                "not_hunter(X) :- grey_wolf(X).",
+            ]
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Subordinate clauses. "John is sad because he lost his keys" is split at
+//! the conjunction, each clause parsed with the patterns, and the two joined
+//! either as a rule, `sad(john) :- lose(john, key).`, or as a linking fact,
+//! `causes(lose(john, key), sad(john)).` Which one is set per conjunction in
+//! the settings. The clause can lead too: "When x is hungry, x eats".
+
+use crate::app::{
+    PrologApp,
+    coverage::{SentenceCoverage, WordCoverage},
+};
+
+use super::{
+    negation::negate_template,
+    pattern_matcher::spelled_out_pattern_matches,
+    rules::{goals_of_clause, introduce_variables},
+};
+
+/// Predicate of the facts linking a cause to its effect.
+const LINK_PREDICATE: &str = "causes";
+
+/// How the clauses around a conjunction are joined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClauseLink {
+    /// The effect holds if the cause does: `effect :- cause.`
+    Rule,
+    /// A `causes(cause, effect).` fact, with the clauses the sentence states
+    /// as facts of their own.
+    Causes,
+}
+
+impl ClauseLink {
+    pub const ALL: [ClauseLink; 2] = [ClauseLink::Rule, ClauseLink::Causes];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ClauseLink::Rule => "rule",
+            ClauseLink::Causes => "causes/2 fact",
+        }
+    }
+}
+
+/// A subordinating conjunction and how its clauses relate.
+pub struct Subordinator {
+    pub words: &'static [&'static str],
+    /// Whether the subordinate clause is the cause, rather than the effect.
+    pub subordinate_causes: bool,
+    /// Whether the cause holds when its clause does not, as with "unless".
+    pub negates_cause: bool,
+    /// Whether the sentence states the main and subordinate clauses as true.
+    pub states: (bool, bool),
+    pub default_link: ClauseLink,
+}
+
+impl Subordinator {
+    pub fn name(&self) -> String {
+        self.words.join(" ")
+    }
+}
+
+pub const SUBORDINATORS: [Subordinator; 4] = [
+    Subordinator {
+        words: &["because"],
+        subordinate_causes: true,
+        negates_cause: false,
+        states: (true, true),
+        default_link: ClauseLink::Causes,
+    },
+    Subordinator {
+        words: &["when"],
+        subordinate_causes: true,
+        negates_cause: false,
+        states: (false, false),
+        default_link: ClauseLink::Rule,
+    },
+    Subordinator {
+        words: &["unless"],
+        subordinate_causes: true,
+        negates_cause: true,
+        states: (false, false),
+        default_link: ClauseLink::Rule,
+    },
+    Subordinator {
+        words: &["so", "that"],
+        subordinate_causes: false,
+        negates_cause: false,
+        states: (true, false),
+        default_link: ClauseLink::Causes,
+    },
+];
+
+/// The clauses of a sentence split at a subordinating conjunction.
+#[derive(Debug, PartialEq)]
+pub struct SplitClauses {
+    /// Index into [`SUBORDINATORS`].
+    pub subordinator: usize,
+    pub main: Vec<String>,
+    pub subordinate: Vec<String>,
+}
+
+/// Splits `words` at the first subordinating conjunction: "x is sad because
+/// y" or, with the clause leading up to a comma, "because y, x is sad".
+/// Commas at the ends of the clauses are dropped. `None` when there is no
+/// conjunction or a clause would be empty.
+pub fn split_subordinate(words: &[String]) -> Option<SplitClauses> {
+    let starts_at = |idx: usize, subordinator: &Subordinator| {
+        words
+            .get(idx..idx + subordinator.words.len())
+            .is_some_and(|window| {
+                window
+                    .iter()
+                    .zip(subordinator.words)
+                    .all(|(word, expected)| word == expected)
+            })
+    };
+    let clause = |words: &[String]| -> Vec<String> {
+        let mut clause = words.to_vec();
+        if let Some(last) = clause.last_mut() {
+            *last = last.trim_end_matches(',').to_string();
+        }
+        clause.retain(|word| !word.is_empty());
+        clause
+    };
+
+    let (subordinator, idx) = (0..words.len()).find_map(|idx| {
+        SUBORDINATORS
+            .iter()
+            .position(|subordinator| starts_at(idx, subordinator))
+            .map(|subordinator| (subordinator, idx))
+    })?;
+    let after = idx + SUBORDINATORS[subordinator].words.len();
+    let (main, subordinate) = if idx == 0 {
+        let comma = after + words[after..].iter().position(|word| word.ends_with(','))?;
+        (clause(&words[comma + 1..]), clause(&words[after..=comma]))
+    } else {
+        (clause(&words[..idx]), clause(&words[after..]))
+    };
+
+    (!main.is_empty() && !subordinate.is_empty()).then_some(SplitClauses {
+        subordinator,
+        main,
+        subordinate,
+    })
+}
+
+/// Whether a pattern that spells out a subordinating conjunction itself
+/// matches `words`; its template already says how the clauses relate.
+pub fn subordinate_pattern_matches(app: &PrologApp, words: &[String]) -> bool {
+    spelled_out_pattern_matches(app, words, |literal| {
+        SUBORDINATORS
+            .iter()
+            .any(|subordinator| subordinator.words.contains(&literal))
+    })
+}
+
+/// Parses a sentence split at a subordinating conjunction, joining its
+/// clauses the way the settings say. `None` when a clause matches no
+/// pattern, so the sentence is parsed as a whole instead.
+pub fn parse_subordinate(
+    app: &PrologApp,
+    sentence: &str,
+    clauses: SplitClauses,
+    coverage: &mut SentenceCoverage,
+) -> Option<String> {
+    let subordinator = &SUBORDINATORS[clauses.subordinator];
+    let link = app.settings.clause_links[clauses.subordinator];
+    let mut clause_words = vec![clauses.main, clauses.subordinate];
+    // Only a rule can hold for anything; facts keep the words as they are
+    let class_goals = match link {
+        ClauseLink::Rule => introduce_variables(&mut clause_words, &app.database_snapshot),
+        ClauseLink::Causes => Vec::new(),
+    };
+    let main = goals_of_clause(app, &clause_words[0]).ok()?;
+    let subordinate = goals_of_clause(app, &clause_words[1]).ok()?;
+
+    let mut lines = vec![
+        format!("// FROM: {}", sentence),
+        format!("// SUBORDINATE: {} ({})", subordinator.name(), link.name()),
+    ];
+    lines.extend(joined_clauses(subordinator, link, &main, &subordinate, &class_goals));
+    coverage.coverage.fill(WordCoverage::Matched);
+    Some(lines.join("\n") + "\n")
+}
+
+/// The lines joining the goals of the main and subordinate clauses.
+/// `class_goals` name the classes of the variables a rule introduced.
+fn joined_clauses(
+    subordinator: &Subordinator,
+    link: ClauseLink,
+    main: &[String],
+    subordinate: &[String],
+    class_goals: &[String],
+) -> Vec<String> {
+    let (causes, effects) = if subordinator.subordinate_causes {
+        (subordinate, main)
+    } else {
+        (main, subordinate)
+    };
+    let causes: Vec<String> = if subordinator.negates_cause {
+        causes.iter().map(|goal| negate_template(goal)).collect()
+    } else {
+        causes.to_vec()
+    };
+
+    match link {
+        ClauseLink::Rule => {
+            let body: Vec<String> = class_goals.iter().cloned().chain(causes).collect();
+            effects
+                .iter()
+                .map(|effect| format!("{} :- {}.", effect, body.join(", ")))
+                .collect()
+        }
+        ClauseLink::Causes => {
+            let (states_main, states_subordinate) = subordinator.states;
+            let stated = [(states_main, main), (states_subordinate, subordinate)];
+            let mut lines: Vec<String> = stated
+                .iter()
+                .filter(|(states, _)| *states)
+                .flat_map(|(_, goals)| goals.iter().map(|goal| format!("{}.", goal)))
+                .collect();
+            for cause in &causes {
+                for effect in effects {
+                    lines.push(format!("{}({}, {}).", LINK_PREDICATE, cause, effect));
+                }
+            }
+            lines
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clauses_are_split_and_joined() {
+        let words = |sentence: &str| -> Vec<String> {
+            sentence.split_whitespace().map(str::to_string).collect()
+        };
+        let split = |sentence: &str| {
+            split_subordinate(&words(sentence)).map(|clauses| {
+                (
+                    SUBORDINATORS[clauses.subordinator].name(),
+                    clauses.main.join(" "),
+                    clauses.subordinate.join(" "),
+                )
+            })
+        };
+        assert_eq!(
+            split("john is sad, because he lost his keys"),
+            Some((
+                "because".to_string(),
+                "john is sad".to_string(),
+                "he lost his keys".to_string()
+            ))
+        );
+        assert_eq!(
+            split("unless it rains, we walk"),
+            Some(("unless".to_string(), "we walk".to_string(), "it rains".to_string()))
+        );
+        assert_eq!(
+            split("tom saves so that he can travel"),
+            Some(("so that".to_string(), "tom saves".to_string(), "he can travel".to_string()))
+        );
+        assert_eq!(split("when it rains we walk"), None);
+        assert_eq!(split("cats purr because"), None);
+
+        let goals = |goals: &[&str]| -> Vec<String> {
+            goals.iter().map(|goal| goal.to_string()).collect()
+        };
+        let (sad, lost) = (goals(&["sad(john)"]), goals(&["lose(john, key)"]));
+        assert_eq!(
+            joined_clauses(&SUBORDINATORS[0], ClauseLink::Rule, &sad, &lost, &[]),
+            vec!["sad(john) :- lose(john, key)."]
+        );
+        assert_eq!(
+            joined_clauses(&SUBORDINATORS[0], ClauseLink::Causes, &sad, &lost, &[]),
+            vec![
+                "sad(john).",
+                "lose(john, key).",
+                "causes(lose(john, key), sad(john))."
+            ]
+        );
+        let (walk, rain) = (goals(&["walk(X)"]), goals(&["rain"]));
+        assert_eq!(
+            joined_clauses(&SUBORDINATORS[2], ClauseLink::Rule, &walk, &rain, &goals(&["dog(X)"])),
+            vec!["walk(X) :- dog(X), not_rain."]
+        );
+    }
+}
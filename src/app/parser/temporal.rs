@@ -0,0 +1,227 @@
+//! Dates and times. The `<Date>` and `<Time>` pattern tokens match words
+//! like "1990", "monday", "may" and "5pm", and their captures are written
+//! as structured atoms rather than as words: "born in 1990" with
+//! `born($1, $2)` writes `born(john, date(1990))`.
+
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// Words that say which half of the day an hour is in, or that it is a
+/// whole hour.
+const HOUR_SUFFIXES: [&str; 3] = ["am", "pm", "o'clock"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TemporalKind {
+    Date,
+    Time,
+}
+
+impl TemporalKind {
+    pub const ALL: [TemporalKind; 2] = [TemporalKind::Date, TemporalKind::Time];
+
+    /// The name used in patterns, e.g. `<Date>`.
+    pub fn name(self) -> &'static str {
+        match self {
+            TemporalKind::Date => "Date",
+            TemporalKind::Time => "Time",
+        }
+    }
+
+    /// Whether `word` can be part of an expression of this kind. Greedy
+    /// tokens take runs of such words, such as "12 may 1990" or "5 pm".
+    pub fn recognizes(self, word: &str) -> bool {
+        match self {
+            TemporalKind::Date => date_part(word).is_some(),
+            TemporalKind::Time => {
+                HOUR_SUFFIXES.contains(&word)
+                    || word.parse::<u32>().is_ok_and(|hour| hour < 24)
+                    || clock_time(word).is_some()
+            }
+        }
+    }
+
+    /// The atom `words` stand for: `date(1990, 5, 12)`, `date(1990)`,
+    /// `month(may)`, `day(monday)` or `time(17, 30)`. `None` when they do
+    /// not make up an expression of this kind.
+    pub fn normalize(self, words: &[String]) -> Option<String> {
+        match self {
+            TemporalKind::Date => normalize_date(words),
+            TemporalKind::Time => {
+                let (hour, minute) = clock_time(&words.concat())?;
+                Some(format!("time({}, {})", hour, minute))
+            }
+        }
+    }
+
+    /// Expressions the generator fills a token of this kind with.
+    pub fn examples(self) -> &'static [&'static str] {
+        match self {
+            TemporalKind::Date => &["1848", "1990", "2001", "monday", "friday", "may", "october"],
+            TemporalKind::Time => &["9am", "5pm", "7:15am", "17:30", "noon", "midnight"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DatePart {
+    Year(u32),
+    /// 1 for January.
+    Month(usize),
+    DayOfMonth(u32),
+    Weekday(usize),
+    /// A full `1990-05-12` date.
+    Iso(u32, u32, u32),
+}
+
+/// What a word of a date says: "1990", "may", "12th", "monday", "1990-05-12".
+fn date_part(word: &str) -> Option<DatePart> {
+    if let Some(weekday) = WEEKDAYS.iter().position(|day| *day == word) {
+        return Some(DatePart::Weekday(weekday));
+    }
+    if let Some(month) = MONTHS
+        .iter()
+        .position(|month| *month == word || (word.len() == 3 && month.starts_with(word)))
+    {
+        return Some(DatePart::Month(month + 1));
+    }
+
+    let ordinal = ["st", "nd", "rd", "th"]
+        .iter()
+        .find_map(|suffix| word.strip_suffix(suffix));
+    let digits = ordinal.unwrap_or(word);
+    match digits.parse::<u32>() {
+        Ok(day) if digits.len() <= 2 && (1..=31).contains(&day) => {
+            return Some(DatePart::DayOfMonth(day));
+        }
+        Ok(year) if ordinal.is_none() && (3..=4).contains(&digits.len()) => {
+            return Some(DatePart::Year(year));
+        }
+        _ => {}
+    }
+
+    let mut fields = word.split('-').map(|field| field.parse::<u32>().ok());
+    match (fields.next(), fields.next(), fields.next(), fields.next()) {
+        (Some(Some(year)), Some(Some(month)), Some(Some(day)), None)
+            if (1..=12).contains(&month) && (1..=31).contains(&day) =>
+        {
+            Some(DatePart::Iso(year, month, day))
+        }
+        _ => None,
+    }
+}
+
+/// A date from its parts, in any order. A weekday only counts when the
+/// words name no other date.
+fn normalize_date(words: &[String]) -> Option<String> {
+    let (mut year, mut month, mut day, mut weekday) = (None, None, None, None);
+    for word in words {
+        match date_part(word)? {
+            DatePart::Year(y) => year = Some(y),
+            DatePart::Month(m) => month = Some(m),
+            DatePart::DayOfMonth(d) => day = Some(d),
+            DatePart::Weekday(w) => weekday = Some(w),
+            DatePart::Iso(y, m, d) => return Some(format!("date({}, {}, {})", y, m, d)),
+        }
+    }
+
+    match (year, month, day) {
+        (Some(year), Some(month), Some(day)) => {
+            Some(format!("date({}, {}, {})", year, month, day))
+        }
+        (Some(year), Some(month), None) => Some(format!("date({}, {})", year, month)),
+        (Some(year), None, None) => Some(format!("date({})", year)),
+        (None, Some(month), Some(day)) => Some(format!("month_day({}, {})", month, day)),
+        (None, Some(month), None) => Some(format!("month({})", MONTHS[month - 1])),
+        (None, None, None) => weekday.map(|weekday| format!("day({})", WEEKDAYS[weekday])),
+        // A day of the month needs its month
+        _ => None,
+    }
+}
+
+/// The hour and minute of a clock time: "5pm", "5:30pm", "17:30",
+/// "5o'clock", "noon". A bare hour counts too, for "at 5".
+fn clock_time(word: &str) -> Option<(u32, u32)> {
+    match word {
+        "noon" | "midday" => return Some((12, 0)),
+        "midnight" => return Some((0, 0)),
+        _ => {}
+    }
+
+    let (clock, half) = match word.strip_suffix("am") {
+        Some(clock) => (clock, Some(0)),
+        None => match word.strip_suffix("pm") {
+            Some(clock) => (clock, Some(12)),
+            None => (word.trim_end_matches("o'clock"), None),
+        },
+    };
+    let (hour, minute) = match clock.split_once([':', '.']) {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse().ok()?, minute.parse().ok()?),
+        Some(_) => return None,
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    if minute >= 60 {
+        return None;
+    }
+    match half {
+        // 12am is midnight and 12pm noon
+        Some(offset) if (1..=12).contains(&hour) => Some((hour % 12 + offset, minute)),
+        Some(_) => None,
+        None if hour < 24 => Some((hour, minute)),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates_and_times_become_atoms() {
+        let normalize = |kind: TemporalKind, expression: &str| {
+            let words: Vec<String> = expression.split_whitespace().map(str::to_string).collect();
+            kind.normalize(&words)
+        };
+        let date = |expression| normalize(TemporalKind::Date, expression);
+        let time = |expression| normalize(TemporalKind::Time, expression);
+
+        assert_eq!(date("1990").as_deref(), Some("date(1990)"));
+        assert_eq!(date("monday").as_deref(), Some("day(monday)"));
+        assert_eq!(date("may").as_deref(), Some("month(may)"));
+        assert_eq!(date("12th may 1990").as_deref(), Some("date(1990, 5, 12)"));
+        assert_eq!(date("monday dec 3").as_deref(), Some("month_day(12, 3)"));
+        assert_eq!(date("1990-05-12").as_deref(), Some("date(1990, 5, 12)"));
+        assert_eq!(date("12th"), None);
+        assert_eq!(date("fish"), None);
+
+        assert_eq!(time("5pm").as_deref(), Some("time(17, 0)"));
+        assert_eq!(time("7:15 am").as_deref(), Some("time(7, 15)"));
+        assert_eq!(time("12am").as_deref(), Some("time(0, 0)"));
+        assert_eq!(time("17:30").as_deref(), Some("time(17, 30)"));
+        assert_eq!(time("noon").as_deref(), Some("time(12, 0)"));
+        assert_eq!(time("13pm"), None);
+        assert!(TemporalKind::Time.recognizes("pm"));
+        assert!(!TemporalKind::Date.recognizes("pm"));
+    }
+}
@@ -0,0 +1,184 @@
+//! Tense. With tense tagging on, every fact of a sentence gets the time its
+//! verb speaks of as a last argument: "the cat chased the mouse" with
+//! `$2($1, $3)` writes `chase(cat, mouse, past)`.
+
+use crate::app::database::{Database, Tense, WordType};
+
+use super::pattern_matcher::{PatternMatch, PatternToken};
+
+/// Words that put the verb after them in the future.
+const FUTURE_MARKERS: [&str; 2] = ["will", "shall"];
+
+/// Auxiliaries whose own tense is that of the sentence, for databases that
+/// do not list them as verbs.
+const AUXILIARY_TENSES: [(&str, Tense); 11] = [
+    ("am", Tense::Present),
+    ("is", Tense::Present),
+    ("are", Tense::Present),
+    ("has", Tense::Present),
+    ("have", Tense::Present),
+    ("does", Tense::Present),
+    ("was", Tense::Past),
+    ("were", Tense::Past),
+    ("had", Tense::Past),
+    ("did", Tense::Past),
+    ("been", Tense::Past),
+];
+
+/// The tense of the words a match covers: future after "will", "shall" or
+/// "going to", otherwise that of the first auxiliary or verb form. Words
+/// captured by a token that does not read them as verbs are passed over, so
+/// "bear" in "bear chased fish" counts as the noun it matched. `None` when
+/// the match covers no verb.
+pub fn match_tense(
+    words: &[String],
+    pattern_match: &PatternMatch,
+    pattern_tokens: &[PatternToken],
+    database: &Database,
+) -> Option<Tense> {
+    let covered = &words[pattern_match.start_idx..pattern_match.end_idx];
+    let going_to = covered
+        .windows(2)
+        .any(|pair| pair[0] == "going" && pair[1] == "to");
+    if going_to || covered.iter().any(|word| FUTURE_MARKERS.contains(&word.as_str())) {
+        return Some(Tense::Future);
+    }
+
+    let read_as_other = |idx: usize| {
+        pattern_match.spans.iter().any(|span| {
+            (span.start..span.end).contains(&idx) && !reads_as_verb(&pattern_tokens[span.token])
+        })
+    };
+    (pattern_match.start_idx..pattern_match.end_idx)
+        .filter(|&idx| !read_as_other(idx))
+        .find_map(|idx| {
+            AUXILIARY_TENSES
+                .iter()
+                .find(|(auxiliary, _)| *auxiliary == words[idx])
+                .map(|(_, tense)| *tense)
+                .or_else(|| database.tense_of(&words[idx]))
+        })
+}
+
+/// Whether a capture by `token` may be a verb.
+fn reads_as_verb(token: &PatternToken) -> bool {
+    match token {
+        PatternToken::TypeMatch(types, ..) => types.contains(&WordType::Verb),
+        PatternToken::Optional(inner) | PatternToken::Greedy(inner) => reads_as_verb(inner),
+        _ => false,
+    }
+}
+
+/// `template` with `tense` added as the last argument of each fact line,
+/// including the body of `$N.split` lines. Rules are left as they are.
+pub fn tensed_template(template: &str, tense: Tense) -> String {
+    let tag = |fact: &str| with_last_argument(fact, tense.name());
+
+    template
+        .lines()
+        .map(|line| {
+            let fact = line.trim();
+            if fact.is_empty() || fact.starts_with("//") || fact.contains(":-") {
+                line.to_string()
+            } else if let Some((head, body)) = fact.split_once("->") {
+                format!("{}-> {}", head, tag(body.trim()))
+            } else {
+                tag(fact)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A query goal that matches the facts of tagged sentences in any tense:
+/// `chase(cat, X)` -> `chase(cat, X, _)`.
+pub fn any_tense(goal: &str) -> String {
+    with_last_argument(goal, "_")
+}
+
+/// `fact` with `argument` added after its others, keeping its final period.
+fn with_last_argument(fact: &str, argument: &str) -> String {
+    let (fact, period) = match fact.strip_suffix('.') {
+        Some(fact) => (fact, "."),
+        None => (fact, ""),
+    };
+    match fact.strip_suffix(')') {
+        Some(head) => format!("{}, {}){}", head, argument, period),
+        None => format!("{}({}){}", fact, argument, period),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{
+        database::fixtures::word,
+        morphology::{MorphologyExceptions, verb_tenses},
+        parser::pattern_matcher::{CaptureSpan, capture_slot, parse_pattern},
+    };
+
+    #[test]
+    fn test_facts_are_tagged_with_the_tense_of_their_verb() {
+        let exceptions = MorphologyExceptions::english();
+        let forms = |forms: &[&str]| forms.iter().map(|form| form.to_string()).collect::<Vec<_>>();
+        let tenses = verb_tenses("run", &forms(&["runs", "ran", "running", "runned"]), &exceptions);
+        assert_eq!(tenses.get("run"), Some(&Tense::Present));
+        assert_eq!(tenses.get("ran"), Some(&Tense::Past));
+        assert_eq!(tenses.get("running"), Some(&Tense::Present));
+        assert_eq!(tenses.get("runned"), None);
+
+        let mut database = Database::default();
+        for (lemma, word_type, forms) in [
+            ("bear", WordType::Noun, vec![]),
+            ("bear", WordType::Verb, vec!["bears", "bore"]),
+            ("chase", WordType::Verb, vec!["chases", "chased"]),
+            ("fish", WordType::Noun, vec![]),
+        ] {
+            database.words.push(word(lemma, word_type, &forms));
+        }
+        database.fill_verb_tenses();
+        database.rebuild_index();
+
+        // The verb is the second capture, after any auxiliary
+        let tense = |sentence: &str| {
+            let words: Vec<String> = sentence.split_whitespace().map(str::to_string).collect();
+            let tokens = parse_pattern(if words.len() == 4 {
+                "<Noun> will <Verb> <Noun>"
+            } else {
+                "<Noun> <Verb> <Noun>"
+            });
+            let verb = words.len() - 2;
+            let span = |start: usize, token| CaptureSpan {
+                start,
+                end: start + 1,
+                token,
+                slot: capture_slot(&tokens, token).unwrap(),
+            };
+            let pattern_match = PatternMatch {
+                pattern_name: "chases".to_string(),
+                template: "$2($1, $3).".to_string(),
+                captures: Vec::new(),
+                spans: vec![
+                    span(0, 0),
+                    span(verb, tokens.len() - 2),
+                    span(verb + 1, tokens.len() - 1),
+                ],
+                skipped: Vec::new(),
+                start_idx: 0,
+                end_idx: words.len(),
+                confidence: 1.0,
+            };
+            match_tense(&words, &pattern_match, &tokens, &database)
+        };
+        assert_eq!(tense("bear chased fish"), Some(Tense::Past));
+        assert_eq!(tense("bear bears fish"), Some(Tense::Present));
+        assert_eq!(tense("bear will chase fish"), Some(Tense::Future));
+
+        let template = "$2($1, $3).\nraining\n$1.split -> likes(#, $2)\nx(A) :- y(A)";
+        assert_eq!(
+            tensed_template(template, Tense::Past),
+            "$2($1, $3, past).\nraining(past)\n$1.split -> likes(#, $2, past)\nx(A) :- y(A)"
+        );
+        assert_eq!(any_tense("chase(cat, X)"), "chase(cat, X, _)");
+    }
+}
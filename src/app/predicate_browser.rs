@@ -0,0 +1,463 @@
+use crate::app::query_engine::{Fact, QueryEngine};
+
+/// A change to the parsed fact store requested from the browser. Facts are
+/// identified by their canonical text, e.g. `is_a(cat, mammal)`.
+pub enum FactEdit {
+    /// Replace each fact with a new one, or delete it (`None`).
+    Rewrite(Vec<(String, Option<String>)>),
+    /// Restore the fact store as it was before the last rewrite.
+    Undo,
+}
+
+/// A bulk change applied to every matching fact.
+enum Rewrite {
+    RenameConstant {
+        from: String,
+        to: String,
+    },
+    SwapArguments {
+        predicate: String,
+        arity: usize,
+        first: usize,
+        second: usize,
+    },
+}
+
+impl Rewrite {
+    fn apply(&self, fact: &Fact) -> Option<Fact> {
+        match self {
+            Rewrite::RenameConstant { from, to } => {
+                let renamed = Fact {
+                    predicate: if fact.predicate == *from {
+                        to.clone()
+                    } else {
+                        fact.predicate.clone()
+                    },
+                    args: fact
+                        .args
+                        .iter()
+                        .map(|arg| arg.rename_atom(from, to))
+                        .collect(),
+                };
+                (renamed.to_string() != fact.to_string()).then_some(renamed)
+            }
+            Rewrite::SwapArguments {
+                predicate,
+                arity,
+                first,
+                second,
+            } => {
+                if fact.predicate != *predicate || fact.args.len() != *arity || first == second {
+                    return None;
+                }
+                let mut swapped = fact.clone();
+                swapped.args.swap(*first, *second);
+                Some(swapped)
+            }
+        }
+    }
+}
+
+/// `(old, new)` canonical texts for every fact the rewrite changes.
+fn plan_rewrite(facts: &[Fact], rewrite: &Rewrite) -> Vec<(String, String)> {
+    facts
+        .iter()
+        .filter_map(|fact| {
+            rewrite
+                .apply(fact)
+                .map(|new| (fact.to_string(), new.to_string()))
+        })
+        .collect()
+}
+
+#[derive(PartialEq)]
+enum ReplaceMode {
+    RenameConstant,
+    SwapArguments,
+}
+
+pub struct PredicateBrowser {
+    selected: Option<(String, usize)>,
+    predicate_search: String,
+    arg_filters: Vec<String>,
+    editing: Option<String>,
+    edit_text: String,
+    status_message: String,
+
+    replace_mode: ReplaceMode,
+    find_text: String,
+    replace_text: String,
+    swap_first: usize,
+    swap_second: usize,
+    preview: Vec<(String, String)>,
+}
+
+impl PredicateBrowser {
+    pub fn new() -> Self {
+        Self {
+            selected: None,
+            predicate_search: String::new(),
+            arg_filters: Vec::new(),
+            editing: None,
+            edit_text: String::new(),
+            status_message: String::new(),
+            replace_mode: ReplaceMode::RenameConstant,
+            find_text: String::new(),
+            replace_text: String::new(),
+            swap_first: 1,
+            swap_second: 2,
+            preview: Vec::new(),
+        }
+    }
+
+    /// Draws the predicate browser tab. Returns an edit for the caller to apply.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        query_engine: &QueryEngine,
+        can_undo: bool,
+    ) -> Option<FactEdit> {
+        let mut edit = None;
+
+        egui::SidePanel::left("predicate_list")
+            .default_width(220.0)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.heading("Predicates");
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("🔍");
+                    ui.text_edit_singleline(&mut self.predicate_search);
+                });
+                ui.add_space(5.0);
+
+                let search = self.predicate_search.to_lowercase();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (name, arity, count) in query_engine.predicate_counts() {
+                        if !search.is_empty() && !name.to_lowercase().contains(&search) {
+                            continue;
+                        }
+
+                        let is_selected = self
+                            .selected
+                            .as_ref()
+                            .is_some_and(|(n, a)| n == name && *a == arity);
+                        let label = format!("{}/{}  ({})", name, arity, count);
+                        if ui.selectable_label(is_selected, label).clicked() && !is_selected {
+                            self.selected = Some((name.to_string(), arity));
+                            self.arg_filters = vec![String::new(); arity];
+                            self.editing = None;
+                            self.status_message.clear();
+                        }
+                    }
+                });
+            });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Predicate Browser");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui
+                        .add_enabled(can_undo, egui::Button::new("↶ Undo"))
+                        .on_hover_text("Undo the last edit or replacement")
+                        .clicked()
+                    {
+                        edit = Some(FactEdit::Undo);
+                        self.preview.clear();
+                        self.status_message = "Undid last change".to_string();
+                    }
+                });
+            });
+            ui.separator();
+
+            if let Some(replace) = self.show_find_replace(ui, query_engine) {
+                edit = Some(replace);
+            }
+
+            if !self.status_message.is_empty() {
+                ui.label(&self.status_message);
+            }
+            ui.separator();
+
+            let Some((predicate, arity)) = self.selected.clone() else {
+                ui.label(
+                    egui::RichText::new("Select a predicate to browse its facts.")
+                        .italics()
+                        .color(egui::Color32::from_rgb(100, 100, 100)),
+                );
+                return;
+            };
+
+            ui.label(
+                egui::RichText::new(format!("{}/{}", predicate, arity))
+                    .strong()
+                    .size(16.0),
+            );
+
+            let filters: Vec<String> = self.arg_filters.iter().map(|f| f.to_lowercase()).collect();
+            let facts: Vec<_> = query_engine
+                .facts_with_predicate(&predicate, arity)
+                .into_iter()
+                .filter(|fact| {
+                    fact.args.iter().zip(&filters).all(|(arg, filter)| {
+                        filter.is_empty() || arg.to_string().to_lowercase().contains(filter)
+                    })
+                })
+                .collect();
+
+            ui.label(
+                egui::RichText::new(format!("{} facts shown", facts.len()))
+                    .color(egui::Color32::from_rgb(150, 150, 150)),
+            );
+            ui.add_space(5.0);
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("predicate_facts")
+                    .num_columns(arity + 2)
+                    .spacing([10.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label(egui::RichText::new("ID").strong());
+                        for (i, filter) in self.arg_filters.iter_mut().enumerate() {
+                            ui.vertical(|ui| {
+                                ui.label(egui::RichText::new(format!("Arg {}", i + 1)).strong());
+                                ui.add(
+                                    egui::TextEdit::singleline(filter)
+                                        .hint_text("filter")
+                                        .desired_width(120.0),
+                                );
+                            });
+                        }
+                        ui.label(egui::RichText::new("Actions").strong());
+                        ui.end_row();
+
+                        for fact in facts {
+                            let key = fact.to_string();
+                            ui.label(
+                                egui::RichText::new(fact.stable_id())
+                                    .monospace()
+                                    .color(egui::Color32::from_rgb(120, 120, 120)),
+                            );
+
+                            if self.editing.as_deref() == Some(key.as_str()) {
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.edit_text)
+                                        .font(egui::TextStyle::Monospace)
+                                        .desired_width(f32::INFINITY),
+                                );
+                                for _ in 1..arity {
+                                    ui.label("");
+                                }
+                                ui.horizontal(|ui| {
+                                    if ui.button("✓").on_hover_text("Save").clicked() {
+                                        let new_text = self.edit_text.trim().trim_end_matches('.');
+                                        match query_engine.parse_fact_public(new_text) {
+                                            Some(new_fact) => {
+                                                edit = Some(FactEdit::Rewrite(vec![(
+                                                    key.clone(),
+                                                    Some(new_fact.to_string()),
+                                                )]));
+                                                self.status_message =
+                                                    format!("✓ Replaced {} with {}", key, new_fact);
+                                                self.editing = None;
+                                            }
+                                            None => {
+                                                self.status_message = format!(
+                                                    "Error: '{}' is not a valid fact",
+                                                    new_text
+                                                );
+                                            }
+                                        }
+                                    }
+                                    if ui.button("✖").on_hover_text("Cancel").clicked() {
+                                        self.editing = None;
+                                    }
+                                });
+                            } else {
+                                for arg in &fact.args {
+                                    ui.monospace(arg.to_string());
+                                }
+                                ui.horizontal(|ui| {
+                                    if ui.button("✏").on_hover_text("Edit").clicked() {
+                                        self.editing = Some(key.clone());
+                                        self.edit_text = key.clone();
+                                    }
+                                    if ui.button("🗑").on_hover_text("Delete").clicked() {
+                                        self.status_message = format!("Deleted {}", key);
+                                        edit = Some(FactEdit::Rewrite(vec![(key.clone(), None)]));
+                                    }
+                                });
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+
+        edit
+    }
+
+    fn show_find_replace(
+        &mut self,
+        ui: &mut egui::Ui,
+        query_engine: &QueryEngine,
+    ) -> Option<FactEdit> {
+        let mut edit = None;
+
+        egui::CollapsingHeader::new("🔁 Find & Replace")
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(
+                        &mut self.replace_mode,
+                        ReplaceMode::RenameConstant,
+                        "Rename constant",
+                    );
+                    ui.radio_value(
+                        &mut self.replace_mode,
+                        ReplaceMode::SwapArguments,
+                        "Swap arguments",
+                    );
+                });
+
+                let rewrite = match self.replace_mode {
+                    ReplaceMode::RenameConstant => {
+                        ui.horizontal(|ui| {
+                            ui.label("Find:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.find_text)
+                                    .desired_width(150.0),
+                            );
+                            ui.label("Replace with:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.replace_text)
+                                    .desired_width(150.0),
+                            );
+                        });
+                        let from = self.find_text.trim();
+                        let to = self.replace_text.trim();
+                        (!from.is_empty() && !to.is_empty()).then(|| Rewrite::RenameConstant {
+                            from: from.to_string(),
+                            to: to.to_string(),
+                        })
+                    }
+                    ReplaceMode::SwapArguments => match self.selected.clone() {
+                        Some((predicate, arity)) if arity >= 2 => {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("In {}/{} swap argument", predicate, arity));
+                                ui.add(egui::DragValue::new(&mut self.swap_first).range(1..=arity));
+                                ui.label("with");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.swap_second).range(1..=arity),
+                                );
+                            });
+                            Some(Rewrite::SwapArguments {
+                                predicate,
+                                arity,
+                                first: self.swap_first - 1,
+                                second: self.swap_second - 1,
+                            })
+                        }
+                        _ => {
+                            ui.label(
+                                egui::RichText::new(
+                                    "Select a predicate with two or more arguments first.",
+                                )
+                                .italics()
+                                .color(egui::Color32::from_rgb(100, 100, 100)),
+                            );
+                            None
+                        }
+                    },
+                };
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(rewrite.is_some(), egui::Button::new("Preview"))
+                        .clicked()
+                        && let Some(rewrite) = &rewrite
+                    {
+                        self.preview = plan_rewrite(query_engine.facts(), rewrite);
+                        self.status_message = format!("{} facts would change", self.preview.len());
+                    }
+
+                    if ui
+                        .add_enabled(!self.preview.is_empty(), egui::Button::new("Apply"))
+                        .clicked()
+                    {
+                        let changes = std::mem::take(&mut self.preview);
+                        self.status_message = format!("✓ Rewrote {} facts", changes.len());
+                        edit = Some(FactEdit::Rewrite(
+                            changes
+                                .into_iter()
+                                .map(|(old, new)| (old, Some(new)))
+                                .collect(),
+                        ));
+                    }
+                });
+
+                if !self.preview.is_empty() {
+                    egui::ScrollArea::vertical()
+                        .id_source("replace_preview")
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            for (old, new) in &self.preview {
+                                ui.monospace(format!("{}  →  {}", old, new));
+                            }
+                        });
+                }
+            });
+
+        edit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrites_rename_constants_and_swap_arguments() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "lives_in(anna, nyc).\nnyc(city).\nborn_in(nyc, bob).\nborn_in(paris, eve).\nlikes(anna, food(nyc)).",
+        );
+
+        let rename = Rewrite::RenameConstant {
+            from: "nyc".to_string(),
+            to: "new_york".to_string(),
+        };
+        let plan = plan_rewrite(engine.facts(), &rename);
+        assert_eq!(
+            plan.iter().map(|(_, new)| new.as_str()).collect::<Vec<_>>(),
+            vec![
+                "lives_in(anna, new_york)",
+                "new_york(city)",
+                "born_in(new_york, bob)",
+                "likes(anna, food(new_york))"
+            ]
+        );
+
+        let swap = Rewrite::SwapArguments {
+            predicate: "born_in".to_string(),
+            arity: 2,
+            first: 0,
+            second: 1,
+        };
+        let plan = plan_rewrite(engine.facts(), &swap);
+        assert_eq!(
+            plan,
+            vec![
+                (
+                    "born_in(nyc, bob)".to_string(),
+                    "born_in(bob, nyc)".to_string()
+                ),
+                (
+                    "born_in(paris, eve)".to_string(),
+                    "born_in(eve, paris)".to_string()
+                ),
+            ]
+        );
+    }
+}
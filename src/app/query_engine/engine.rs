@@ -0,0 +1,4405 @@
+/// Query Engine for Prolog-like queries
+///
+/// Supports:
+/// - Simple fact queries: animal(X)
+/// - Rules: student(X, Y) :- attends(X, Y), enrolled(X)
+/// - Disjunction and grouping in rule bodies: pet(X) :- (cat(X) ; dog(X)), tame(X)
+/// - Cut in rule bodies: classify(X, bird) :- has_feathers(X), !
+/// - Explanations: query_with_trace lists the facts and rules behind each answer
+/// - Recursive rules: ancestor(X, Y) :- parent(X, Z), ancestor(Z, Y), bounded by a depth limit
+/// - Pattern generation: phrase(pattern_name, X) to generate all combinations,
+///   with arguments threaded through components: phrase(sentence(past), X)
+/// - Bounded/sampled generation: phrase(sentence, X, [max(10)]), phrase(sentence, X, [sample(5), seed(1)])
+/// - DCG-style grammars: patterns name other patterns, alternatives share a head,
+///   and bodies mix in terminals and goals: claim --> {owns(O, T)}, [O, owns, T]
+/// - Conjunction queries: animal(X), action(Y)
+/// - Fact occurrence counts: fact_count(is_a(cat, mammal), N)
+/// - Numeric comparisons: age(X, A), A >= 18
+/// - Atom built-ins: atom_concat/3, sub_atom/5, atom_length/2, upcase_atom/2, downcase_atom/2
+/// - Negative facts: -animal(rock) is stored as not_animal(rock), and
+///   contradictions lists what is both stated or derived to hold and not to
+/// - Collecting solutions: findall(X, animal(X), L), bagof/3, setof/3
+/// - Counting and summing: aggregate_all(count, animal(X), N), aggregate_all(sum(A), age(_, A), S)
+/// - Changing the fact store: assert(animal(owl)), asserta/1, assertz/1, retract(animal(owl))
+/// - Nested terms and lists: likes(john, food(pizza)), route(a, [b, c]), first([H|_], H)
+/// - Limits on solutions, derivation steps and wall-clock time, so a runaway
+///   query reports partial results instead of freezing the UI
+/// - Streaming: query_iter yields Solution values one at a time
+/// - Structured answers: query_bindings returns each answer's bindings by variable
+/// - Indexing: facts are looked up by predicate, arity and first argument
+/// - Tabling: `:- table path/2` memoizes a predicate's answers, so left-recursive
+///   and cyclic rules such as transitive closures terminate
+/// - Bottom-up mode: derives every fact of the plain rules by semi-naive
+///   iteration once, then answers calls to them without running rules
+/// - Consulting Prolog files: consult/1 loads facts, multi-line rules, table
+///   directives and comments from a `.pl` library
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+    path::Path,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use super::term::{COMPARISON_OPERATORS, Term, split_top_level, split_top_level_on};
+use crate::app::random::SplitMix64;
+
+type Bindings = HashMap<String, Term>;
+
+/// Prefix of the predicate a negative fact is stored under: `-animal(rock)`
+/// is read as `not_animal(rock)`.
+const NEGATION_PREFIX: &str = "not_";
+
+/// Built-ins that solve a sub-goal to completion and collect its solutions.
+const AGGREGATE_PREDICATES: [&str; 4] = ["findall", "bagof", "setof", "aggregate_all"];
+
+#[derive(Debug, Clone)]
+pub struct Fact {
+    pub predicate: String,
+    pub args: Vec<Term>,
+}
+
+impl fmt::Display for Fact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if COMPARISON_OPERATORS.contains(&self.predicate.as_str()) && self.args.len() == 2 {
+            return write!(f, "{} {} {}", self.args[0], self.predicate, self.args[1]);
+        }
+
+        let args: Vec<String> = self.args.iter().map(|a| a.to_string()).collect();
+        write!(f, "{}({})", self.predicate, args.join(", "))
+    }
+}
+
+impl Fact {
+    /// Identifier derived from the fact's canonical text, so the same fact gets
+    /// the same ID on every parse and in every session. Uses FNV-1a because
+    /// std's hashers are not guaranteed stable across Rust releases.
+    pub fn stable_id(&self) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in self.to_string().bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        format!("f{:012x}", hash >> 16)
+    }
+}
+
+/// A change made to the facts by `assert/1`, `asserta/1` or `retract/1`.
+#[derive(Debug, Clone)]
+enum FactChange {
+    /// `at_front` puts a new fact before the others, as `asserta/1` does.
+    Assert { fact: Fact, at_front: bool },
+    /// The stored fact that was removed.
+    Retract(Fact),
+}
+
+/// How often a stored fact was extracted and from which sentences.
+#[derive(Debug, Clone, Default)]
+pub struct FactOccurrences {
+    pub count: usize,
+    pub sources: Vec<String>,
+}
+
+/// A fact that holds while its negation holds too.
+#[derive(Debug, Clone)]
+pub struct Contradiction {
+    /// The positive form, e.g. `animal(rock)` for `not_animal(rock)`.
+    pub fact: Fact,
+    /// Whether the positive fact is stored rather than derived by rules.
+    pub positive_stored: bool,
+    /// Whether the negative fact is stored rather than derived by rules.
+    pub negative_stored: bool,
+}
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let origin = |stored| if stored { "stored" } else { "derived" };
+        write!(
+            f,
+            "{} ({}) contradicts {}{} ({})",
+            self.fact,
+            origin(self.positive_stored),
+            NEGATION_PREFIX,
+            self.fact,
+            origin(self.negative_stored)
+        )
+    }
+}
+
+/// One goal of a rule body.
+#[derive(Debug, Clone)]
+pub enum Goal {
+    Call(Fact),
+    /// `!`: commits to the first solution of the goals before it and to the
+    /// current rule, skipping the predicate's remaining rules.
+    Cut,
+    /// `(a, b ; c)`: alternatives tried in order, each a conjunction of goals.
+    Or(Vec<Vec<Goal>>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub head: Fact,
+    /// Goals that must all hold, in order.
+    pub body: Vec<Goal>,
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Goal::Call(fact) => write!(f, "{}", fact),
+            Goal::Cut => write!(f, "!"),
+            Goal::Or(alternatives) => {
+                let alternatives: Vec<String> = alternatives
+                    .iter()
+                    .map(|goals| {
+                        let goals: Vec<String> = goals.iter().map(|g| g.to_string()).collect();
+                        goals.join(", ")
+                    })
+                    .collect();
+                write!(f, "({})", alternatives.join(" ; "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let body: Vec<String> = self.body.iter().map(|goal| goal.to_string()).collect();
+        write!(f, "{} :- {}", self.head, body.join(", "))
+    }
+}
+
+/// One step in the derivation of a solution.
+#[derive(Debug, Clone)]
+enum ProofStep {
+    /// The stored fact at this index matched the goal.
+    Fact(usize),
+    /// A built-in such as `A >= 18` or `findall/3` held.
+    Builtin(Fact),
+    /// The rule at index `rule` proved `goal`; `body` proves its body goals.
+    Rule {
+        goal: Fact,
+        rule: usize,
+        body: Vec<ProofStep>,
+    },
+    /// A memoized answer of a tabled predicate held.
+    Tabled(Fact),
+    /// A fact derived by bottom-up evaluation matched the goal.
+    Derived(Fact),
+}
+
+/// Bindings of one solution and the proof of each goal solved for it.
+type Derivation = (Bindings, Vec<ProofStep>);
+
+/// One answer of [`QueryEngine::query_with_trace`] and how it was derived:
+/// one line per fact, built-in or rule used, indented under the rule it
+/// proved.
+pub struct Explanation {
+    pub answer: String,
+    pub steps: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub head: Fact,
+    pub components: Vec<PhraseComponent>,
+}
+
+/// One part of a `-->` pattern body.
+#[derive(Debug, Clone)]
+pub enum PhraseComponent {
+    /// `c(A1, ..., An)`: the words of a pattern `c/n`, or one word `Word`
+    /// from a fact or rule `c(Word, A1, ..., An)`.
+    NonTerminal(Fact),
+    /// `[the, big]`: these words as written; `[]` emits none.
+    Terminal(Vec<Term>),
+    /// `{Goal}`: goals that must hold. They emit no words, but can bind and
+    /// constrain variables through facts of any arity.
+    Goals(Vec<Goal>),
+}
+
+impl fmt::Display for PhraseComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PhraseComponent::NonTerminal(fact) => write!(f, "{}", fact),
+            PhraseComponent::Terminal(words) => write!(f, "{}", Term::List(words.clone(), None)),
+            PhraseComponent::Goals(goals) => {
+                let goals: Vec<String> = goals.iter().map(|goal| goal.to_string()).collect();
+                write!(f, "{{{}}}", goals.join(", "))
+            }
+        }
+    }
+}
+
+pub struct QueryEngine {
+    /// Reject bindings that would create cyclic terms such as `X = f(X)`.
+    /// Off by default: it costs a term walk on every variable binding.
+    occurs_check: bool,
+    facts: Vec<Fact>,
+    occurrences: Vec<FactOccurrences>,
+    rules: Vec<Rule>,
+    patterns: Vec<Pattern>,
+    /// Facts by predicate name and arity.
+    fact_map: HashMap<(String, usize), PredicateIndex>,
+    /// Every `(fact, argument)` position holding an atom, by atom, in store
+    /// order. Lets `animal(X)` find `bear(animal)` without scanning all facts.
+    atom_args: HashMap<String, Vec<(usize, usize)>>,
+    /// Rules by head predicate name and arity, in the order they were added.
+    rule_map: HashMap<(String, usize), Vec<usize>>,
+    /// How many of the last rules and patterns were loaded from the parsed
+    /// output's directives; reloading the output replaces them.
+    output_rules: usize,
+    output_patterns: usize,
+    /// Facts loaded by `consult`; they outlive reloads of the parsed output.
+    consulted_facts: Vec<Fact>,
+    /// Asserts and retracts made through `update`, in order. Reloading the
+    /// facts replays them, so they are neither lost nor undone.
+    fact_changes: Vec<FactChange>,
+    /// Predicates declared with `:- table name/arity`.
+    tabled: HashSet<(String, usize)>,
+    /// Table declarations loaded from the parsed output.
+    output_tabled: Vec<(String, usize)>,
+    /// Answer tables of the running query, by call variant.
+    tables: RefCell<HashMap<String, AnswerTable>>,
+    /// Tabled calls being evaluated, innermost last, each with whether it
+    /// consulted an unfinished table of an outer call.
+    table_stack: RefCell<Vec<(String, bool)>>,
+    /// Answers added to any table by the running query.
+    table_answers_added: Cell<usize>,
+    /// Answer calls to plain rules from facts derived bottom-up.
+    bottom_up: bool,
+    /// Facts derived bottom-up; `None` until the next query derives them,
+    /// and again whenever facts or rules change.
+    derived: RefCell<Option<DerivedFacts>>,
+    fact_lookup: HashMap<String, usize>,
+    /// Source of fresh variable names when renaming rules apart.
+    next_rename_id: Cell<usize>,
+    /// Default cap on phrase/2 combinations when the query sets no `max(N)`.
+    phrase_limit: Option<usize>,
+    /// How many rule expansions may be nested while solving one goal.
+    max_rule_depth: usize,
+    /// Set when a query was cut short by `max_rule_depth`.
+    depth_limit_reached: Cell<bool>,
+    /// Answers kept per query; the rest are dropped with a note.
+    max_solutions: Option<usize>,
+    /// Goals a query may resolve before the search is abandoned.
+    max_steps: Option<usize>,
+    /// Wall-clock time a query may run before the search is abandoned.
+    timeout: Option<Duration>,
+    /// Goals resolved by the running query.
+    steps_taken: Cell<usize>,
+    /// When the running query times out, if it has a timeout.
+    deadline: Cell<Option<Instant>>,
+    /// Set when the running query was abandoned by `max_steps` or `timeout`.
+    search_limit_reached: Cell<Option<SearchLimit>>,
+}
+
+/// Rule answers of one tabled call, as the call's arguments.
+#[derive(Default)]
+struct AnswerTable {
+    answers: Vec<Vec<Term>>,
+    seen: HashSet<String>,
+    state: TableState,
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum TableState {
+    /// On the table stack: recursive calls read the answers found so far.
+    #[default]
+    Evaluating,
+    /// Evaluated against an unfinished outer table; re-evaluated when called.
+    Incomplete,
+    /// Holds every answer.
+    Complete,
+}
+
+/// Key shared by calls that are equal up to variable renaming: `path(a, X)`
+/// and `path(a, Y)` read the same table.
+fn variant_key(call: &Fact) -> String {
+    let mut variables = Vec::new();
+    for arg in &call.args {
+        arg.collect_variables(&mut variables);
+    }
+    let rename = |name: &str| {
+        let position = variables.iter().position(|v| v == name).unwrap_or(0);
+        format!("_G{}", position)
+    };
+    let args: Vec<String> = call
+        .args
+        .iter()
+        .map(|arg| arg.rename_variables(&rename).to_string())
+        .collect();
+    format!("{}({})", call.predicate, args.join(", "))
+}
+
+/// Facts derived from rules by bottom-up evaluation.
+#[derive(Default)]
+struct DerivedFacts {
+    facts: Vec<Fact>,
+    /// Derived facts by predicate name and arity, in derivation order.
+    index: HashMap<(String, usize), Vec<usize>>,
+    seen: HashSet<String>,
+    /// Predicates whose rules were all evaluated bottom-up. Calls to them read
+    /// `facts` instead of running their rules.
+    predicates: HashSet<(String, usize)>,
+}
+
+impl DerivedFacts {
+    /// Adds `fact` unless a variant of it was derived before.
+    fn insert(&mut self, fact: Fact) -> bool {
+        if !self.seen.insert(variant_key(&fact)) {
+            return false;
+        }
+        self.index
+            .entry((fact.predicate.clone(), fact.args.len()))
+            .or_default()
+            .push(self.facts.len());
+        self.facts.push(fact);
+        true
+    }
+
+    fn with_predicate(&self, predicate: &str, arity: usize) -> &[usize] {
+        self.index
+            .get(&(predicate.to_string(), arity))
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+/// Declarations SWI-Prolog needs but this engine does not; they are
+/// accepted and have no effect.
+const IGNORED_DECLARATIONS: [&str; 2] = ["dynamic ", "discontiguous "];
+
+/// Whether `directive`, written without its `:-`, is one of the
+/// [`IGNORED_DECLARATIONS`].
+fn is_ignored_declaration(directive: &str) -> bool {
+    let directive = directive.trim();
+    IGNORED_DECLARATIONS
+        .iter()
+        .any(|declaration| directive.starts_with(declaration))
+}
+
+/// Reads a `:-` directive into the predicates it tables: those of a `table`
+/// declaration, none for the [`IGNORED_DECLARATIONS`]. Any other directive
+/// is an error.
+fn parse_directive(line: &str) -> Result<Vec<(String, usize)>, String> {
+    if is_ignored_declaration(line.trim().trim_start_matches(":-")) {
+        return Ok(Vec::new());
+    }
+    parse_table_directive(line)
+}
+
+/// Reads `:- table path/2, edge/2.` into its predicate indicators.
+fn parse_table_directive(line: &str) -> Result<Vec<(String, usize)>, String> {
+    let directive = line
+        .trim()
+        .trim_start_matches(":-")
+        .trim()
+        .trim_end_matches('.');
+    let specs = directive
+        .strip_prefix("table ")
+        .ok_or(format!("Unsupported directive: {}", line.trim()))?;
+
+    split_top_level(specs)
+        .iter()
+        .map(|spec| {
+            let (name, arity) = spec
+                .trim()
+                .rsplit_once('/')
+                .ok_or(format!("Expected name/arity in table directive: {}", spec))?;
+            let arity = arity
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid arity in table directive: {}", spec))?;
+            Ok((name.trim().to_string(), arity))
+        })
+        .collect()
+}
+
+/// Splits Prolog source into clauses, each with the line it starts on. A
+/// clause ends at a `.` followed by layout, a comment or the end of the text;
+/// comments are dropped and layout outside quotes collapses to one space.
+fn split_clauses(source: &str) -> Vec<(usize, String)> {
+    let mut clauses = Vec::new();
+    let mut current = String::new();
+    let mut start_line = 1;
+    let mut line = 1;
+    let mut quote: Option<char> = None;
+    let mut depth = 0i32;
+    let mut chars = source.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch == '\n' {
+            line += 1;
+        }
+
+        if let Some(open) = quote {
+            current.push(ch);
+            if ch == '\\' {
+                current.extend(chars.next());
+            } else if ch == open {
+                quote = None;
+            }
+            continue;
+        }
+
+        match ch {
+            '%' => {
+                while chars.next_if(|&next| next != '\n').is_some() {}
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        line += 1;
+                    }
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+                continue;
+            }
+            '.' if depth <= 0
+                && chars
+                    .peek()
+                    .is_none_or(|next| next.is_whitespace() || *next == '%') =>
+            {
+                if !current.trim().is_empty() {
+                    clauses.push((start_line, current.trim().to_string()));
+                }
+                current.clear();
+                depth = 0;
+                continue;
+            }
+            c if c.is_whitespace() => {
+                if !current.is_empty() && !current.ends_with(' ') {
+                    current.push(' ');
+                }
+                continue;
+            }
+            '\'' | '"' => quote = Some(ch),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+
+        if current.is_empty() {
+            start_line = line;
+        }
+        current.push(ch);
+    }
+
+    if !current.trim().is_empty() {
+        clauses.push((start_line, current.trim().to_string()));
+    }
+    clauses
+}
+
+/// The stored facts of one predicate/arity, also indexed by first argument.
+#[derive(Default)]
+struct PredicateIndex {
+    /// Every fact, in store order.
+    all: Vec<usize>,
+    /// Facts by the key of their constant first argument, in store order.
+    by_first_arg: HashMap<String, Vec<usize>>,
+    /// Set once a fact's first argument is not a constant (a variable, list or
+    /// compound term); first-argument lookups must then scan `all`.
+    has_unindexed: bool,
+}
+
+impl PredicateIndex {
+    fn insert(&mut self, idx: usize, fact: &Fact) {
+        self.all.push(idx);
+        match fact.args.first().and_then(first_arg_key) {
+            Some(key) => self.by_first_arg.entry(key).or_default().push(idx),
+            None => self.has_unindexed = true,
+        }
+    }
+
+    /// Facts that may unify with a goal whose first argument is `first_arg`.
+    fn candidates(&self, first_arg: Option<&Term>) -> &[usize] {
+        match first_arg.and_then(first_arg_key) {
+            Some(key) if !self.has_unindexed => self
+                .by_first_arg
+                .get(&key)
+                .map(|v| v.as_slice())
+                .unwrap_or(&[]),
+            _ => self.all.as_slice(),
+        }
+    }
+}
+
+/// Index key of a constant term. Numbers are keyed by value, since `1` and
+/// `1.0` unify.
+fn first_arg_key(term: &Term) -> Option<String> {
+    if let Some(number) = term.as_number() {
+        // -0.0 == 0.0, so both share a key.
+        return Some(format!("n:{}", if number == 0.0 { 0.0 } else { number }));
+    }
+    match term {
+        Term::Atom(name) => Some(format!("a:{}", name)),
+        Term::Str(text) => Some(format!("s:{}", text)),
+        _ => None,
+    }
+}
+
+/// Why a query stopped searching before it ran out of alternatives.
+#[derive(Clone, Copy)]
+enum SearchLimit {
+    Steps,
+    Timeout,
+}
+
+/// Lazily enumerates the combinations of a phrase pattern depth first, so
+/// callers can stop after the first few without expanding the full product.
+pub struct PhraseCombinations<'a> {
+    engine: &'a QueryEngine,
+    /// One frame per expanded component.
+    frames: Vec<PhraseFrame>,
+    words: Vec<Term>,
+}
+
+/// The components still to expand, each with the number of patterns it is
+/// nested in, and how far the candidates of the first one were tried: stored
+/// facts first, then its other expansions, computed once the facts are
+/// exhausted.
+struct PhraseFrame {
+    goals: Vec<(PhraseComponent, usize)>,
+    bindings: Bindings,
+    /// Length of the words before the step that led to this frame.
+    words_len: usize,
+    next_fact: usize,
+    expansions: Option<std::vec::IntoIter<PhraseStep>>,
+}
+
+impl PhraseFrame {
+    fn new(goals: Vec<(PhraseComponent, usize)>, bindings: Bindings, words_len: usize) -> Self {
+        Self {
+            goals,
+            bindings,
+            words_len,
+            next_fact: 0,
+            expansions: None,
+        }
+    }
+}
+
+/// One way to satisfy a component: the words it emits, the components it is
+/// replaced by, and the bindings after.
+struct PhraseStep {
+    words: Vec<Term>,
+    expansion: Vec<PhraseComponent>,
+    bindings: Bindings,
+}
+
+impl Iterator for PhraseCombinations<'_> {
+    type Item = (Vec<Term>, Bindings);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let engine = self.engine;
+            let frame = self.frames.last_mut()?;
+            let Some((component, depth)) = frame.goals.first() else {
+                let frame = self.frames.pop()?;
+                let combination = self.words.clone();
+                self.words.truncate(frame.words_len);
+                return Some((combination, frame.bindings));
+            };
+            let depth = *depth;
+
+            let mut advanced = None;
+            if let PhraseComponent::NonTerminal(component) = component {
+                let candidates =
+                    engine.candidate_facts(&component.predicate, component.args.len() + 1, None);
+                while frame.next_fact < candidates.len() {
+                    let fact = &engine.facts[candidates[frame.next_fact]];
+                    frame.next_fact += 1;
+
+                    if let Some(bindings) =
+                        engine.unify_with(&component.args, &fact.args[1..], frame.bindings.clone())
+                    {
+                        advanced = Some(PhraseStep {
+                            words: vec![fact.args[0].clone()],
+                            expansion: Vec::new(),
+                            bindings,
+                        });
+                        break;
+                    }
+                }
+            }
+
+            if advanced.is_none() {
+                if frame.expansions.is_none() {
+                    let expansions = engine.phrase_expansions(component, &frame.bindings, depth);
+                    frame.expansions = Some(expansions.into_iter());
+                }
+                advanced = frame.expansions.as_mut().and_then(Iterator::next);
+            }
+
+            match advanced {
+                Some(step) => {
+                    let goals = step
+                        .expansion
+                        .into_iter()
+                        .map(|component| (component, depth + 1))
+                        .chain(frame.goals[1..].iter().cloned())
+                        .collect();
+                    let words_len = self.words.len();
+                    self.words.extend(step.words);
+                    self.frames
+                        .push(PhraseFrame::new(goals, step.bindings, words_len));
+                }
+                None => {
+                    let frame = self.frames.pop()?;
+                    self.words.truncate(frame.words_len);
+                }
+            }
+        }
+    }
+}
+
+/// One answer to a query: each of the query's variables that got bound, in
+/// the order the variables were written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    pub bindings: Vec<(String, Term)>,
+}
+
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bindings.is_empty() {
+            return write!(f, "true.");
+        }
+
+        let pairs: Vec<String> = self
+            .bindings
+            .iter()
+            .map(|(var, value)| format!("{} = {}", var, value))
+            .collect();
+        write!(f, "{}", pairs.join(", "))
+    }
+}
+
+impl Solution {
+    /// The bindings by variable name.
+    pub fn into_map(self) -> BTreeMap<String, Term> {
+        self.bindings.into_iter().collect()
+    }
+}
+
+/// Lazily solves a query depth first, so callers can show the first answers
+/// before the whole search is done. Rule bodies go on the same choice stack
+/// as the query's own goals, so an answer costs only the steps that lead to
+/// it. Returned by [`QueryEngine::query_iter`].
+pub struct Solutions<'a> {
+    engine: &'a QueryEngine,
+    variables: Vec<String>,
+    /// Goals left to prove and the bindings so far, unless backtracking.
+    pending: Option<(Rc<Continuation>, Bindings)>,
+    /// Choice points with alternatives left, innermost last.
+    choices: Vec<ChoicePoint>,
+    /// Answers already yielded; each distinct answer is reported once.
+    seen: HashSet<String>,
+}
+
+/// Goals still to prove, shared by the choice points that resume them.
+enum Continuation {
+    Done,
+    Goal {
+        goal: Goal,
+        /// Rule depth the goal was written at.
+        depth: usize,
+        /// Length the choice stack is cut back to by a `!` of this rule body.
+        cut_barrier: usize,
+        rest: Rc<Continuation>,
+    },
+}
+
+/// A goal with more than one way to hold, and which ways are left.
+struct ChoicePoint {
+    bindings: Bindings,
+    /// Goals to prove after this one.
+    rest: Rc<Continuation>,
+    alternatives: Alternatives,
+}
+
+enum Alternatives {
+    /// Solutions computed up front: built-ins, and answers read from tables
+    /// or the bottom-up derivation.
+    Computed(std::vec::IntoIter<Bindings>),
+    /// A call to a stored or rule-defined predicate.
+    Call {
+        goal: Fact,
+        /// The goal's arguments under the choice point's bindings.
+        args: Vec<Term>,
+        depth: usize,
+        stage: CallStage,
+    },
+    /// The conjunctions of a disjunction not tried yet.
+    Or {
+        alternatives: std::vec::IntoIter<Vec<Goal>>,
+        depth: usize,
+        cut_barrier: usize,
+    },
+}
+
+enum CallStage {
+    /// Next index into the goal's candidate facts.
+    Forward(usize),
+    /// Next index into the goal predicate's `atom_args` entry, for reading
+    /// facts in reverse.
+    Reverse(usize),
+    /// Next index into the goal predicate's rules.
+    Rules(usize),
+}
+
+/// `goals`, written at `depth`, followed by `rest`.
+fn push_goals(
+    goals: Vec<Goal>,
+    depth: usize,
+    cut_barrier: usize,
+    rest: Rc<Continuation>,
+) -> Rc<Continuation> {
+    goals.into_iter().rev().fold(rest, |rest, goal| {
+        Rc::new(Continuation::Goal {
+            goal,
+            depth,
+            cut_barrier,
+            rest,
+        })
+    })
+}
+
+impl Solutions<'_> {
+    /// Proves the first goal of `continuation`: a cut prunes the choice
+    /// stack, anything else pushes a choice point for its alternatives.
+    fn enter(&mut self, continuation: Rc<Continuation>, bindings: Bindings) {
+        let engine = self.engine;
+        let Continuation::Goal {
+            goal,
+            depth,
+            cut_barrier,
+            rest,
+        } = &*continuation
+        else {
+            return;
+        };
+
+        let alternatives = match goal {
+            Goal::Cut => {
+                self.choices.truncate(*cut_barrier);
+                self.pending = Some((Rc::clone(rest), bindings));
+                return;
+            }
+            Goal::Or(alternatives) => Alternatives::Or {
+                alternatives: alternatives.clone().into_iter(),
+                depth: *depth,
+                cut_barrier: *cut_barrier,
+            },
+            Goal::Call(goal) => {
+                if !engine.take_step() {
+                    return;
+                }
+                let builtin = engine
+                    .eval_aggregate(goal, &bindings, *depth)
+                    .or_else(|| engine.eval_builtin(goal, &bindings))
+                    .or_else(|| engine.eval_atom_builtin(goal, &bindings));
+                match builtin {
+                    Some(solutions) => Alternatives::Computed(solutions.into_iter()),
+                    None => Alternatives::Call {
+                        args: goal
+                            .args
+                            .iter()
+                            .map(|arg| engine.substitute(arg, &bindings))
+                            .collect(),
+                        goal: goal.clone(),
+                        depth: *depth,
+                        stage: CallStage::Forward(0),
+                    },
+                }
+            }
+        };
+        self.choices.push(ChoicePoint {
+            bindings,
+            rest: Rc::clone(rest),
+            alternatives,
+        });
+    }
+
+    /// Takes the next alternative of the innermost choice point: the goals
+    /// left to prove with it and their bindings.
+    fn next_alternative(&mut self) -> Option<(Rc<Continuation>, Bindings)> {
+        let engine = self.engine;
+        // A rule body's cut removes this choice point and all above it.
+        let cut_barrier = self.choices.len().checked_sub(1)?;
+        let choice = self.choices.last_mut()?;
+
+        match &mut choice.alternatives {
+            Alternatives::Computed(solutions) => {
+                let bindings = solutions.next()?;
+                Some((Rc::clone(&choice.rest), bindings))
+            }
+            Alternatives::Or {
+                alternatives,
+                depth,
+                cut_barrier,
+            } => {
+                let alternative = alternatives.next()?;
+                let continuation =
+                    push_goals(alternative, *depth, *cut_barrier, Rc::clone(&choice.rest));
+                Some((continuation, choice.bindings.clone()))
+            }
+            Alternatives::Call {
+                goal,
+                args,
+                depth,
+                stage,
+            } => loop {
+                match stage {
+                    CallStage::Forward(next) => {
+                        let indices =
+                            engine.candidate_facts(&goal.predicate, goal.args.len(), args.first());
+                        while *next < indices.len() {
+                            let fact = &engine.facts[indices[*next]];
+                            *next += 1;
+                            if let Some(combined) =
+                                engine.unify_with(args, &fact.args, choice.bindings.clone())
+                            {
+                                return Some((Rc::clone(&choice.rest), combined));
+                            }
+                        }
+                        *stage = CallStage::Reverse(0);
+                    }
+                    // `animal(X)` also matches `bear(animal)`, read as `animal(bear)`.
+                    CallStage::Reverse(next) => {
+                        let positions = engine
+                            .atom_args
+                            .get(&goal.predicate)
+                            .map(|v| v.as_slice())
+                            .unwrap_or(&[]);
+                        while *next < positions.len() {
+                            let (fact_idx, arg_idx) = positions[*next];
+                            *next += 1;
+                            if let Some(combined) = engine.unify_reversed(
+                                args,
+                                fact_idx,
+                                arg_idx,
+                                choice.bindings.clone(),
+                            ) {
+                                return Some((Rc::clone(&choice.rest), combined));
+                            }
+                        }
+
+                        if engine.answers_from_tables(goal) {
+                            let solutions: Vec<Bindings> = engine
+                                .solve_with_rules(goal, &choice.bindings, *depth)
+                                .into_iter()
+                                .map(|(bindings, _)| bindings)
+                                .collect();
+                            choice.alternatives = Alternatives::Computed(solutions.into_iter());
+                            return self.next_alternative();
+                        }
+                        *stage = CallStage::Rules(0);
+                    }
+                    CallStage::Rules(next) => {
+                        let rule_indices = engine
+                            .rule_map
+                            .get(&(goal.predicate.clone(), goal.args.len()))
+                            .map(|v| v.as_slice())
+                            .unwrap_or(&[]);
+                        while *next < rule_indices.len() {
+                            if *depth >= engine.max_rule_depth {
+                                engine.depth_limit_reached.set(true);
+                                return None;
+                            }
+                            let rule = engine.rename_apart(&engine.rules[rule_indices[*next]]);
+                            *next += 1;
+                            if let Some(head_bindings) = engine.unify_with(
+                                &goal.args,
+                                &rule.head.args,
+                                choice.bindings.clone(),
+                            ) {
+                                let continuation = push_goals(
+                                    rule.body,
+                                    *depth + 1,
+                                    cut_barrier,
+                                    Rc::clone(&choice.rest),
+                                );
+                                return Some((continuation, head_bindings));
+                            }
+                        }
+                        return None;
+                    }
+                }
+            },
+        }
+    }
+}
+
+impl Iterator for Solutions<'_> {
+    type Item = Solution;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((continuation, bindings)) = self.pending.take() {
+                if let Continuation::Done = *continuation {
+                    let solution = self.engine.solution(&self.variables, &bindings);
+                    if self.seen.insert(solution.to_string()) {
+                        return Some(solution);
+                    }
+                } else {
+                    self.enter(continuation, bindings);
+                }
+                continue;
+            }
+
+            if self.choices.is_empty() {
+                return None;
+            }
+            match self.next_alternative() {
+                Some(next) => self.pending = Some(next),
+                None => {
+                    self.choices.pop();
+                }
+            }
+        }
+    }
+}
+
+impl QueryEngine {
+    pub fn new() -> Self {
+        Self {
+            occurs_check: false,
+            facts: Vec::new(),
+            occurrences: Vec::new(),
+            rules: Vec::new(),
+            patterns: Vec::new(),
+            fact_map: HashMap::new(),
+            atom_args: HashMap::new(),
+            rule_map: HashMap::new(),
+            output_rules: 0,
+            output_patterns: 0,
+            consulted_facts: Vec::new(),
+            fact_changes: Vec::new(),
+            tabled: HashSet::new(),
+            output_tabled: Vec::new(),
+            tables: RefCell::new(HashMap::new()),
+            table_stack: RefCell::new(Vec::new()),
+            table_answers_added: Cell::new(0),
+            bottom_up: false,
+            derived: RefCell::new(None),
+            fact_lookup: HashMap::new(),
+            next_rename_id: Cell::new(0),
+            phrase_limit: None,
+            max_rule_depth: 50,
+            depth_limit_reached: Cell::new(false),
+            max_solutions: None,
+            max_steps: None,
+            timeout: None,
+            steps_taken: Cell::new(0),
+            deadline: Cell::new(None),
+            search_limit_reached: Cell::new(None),
+        }
+    }
+
+    pub fn set_occurs_check(&mut self, enabled: bool) {
+        self.occurs_check = enabled;
+    }
+
+    pub fn set_phrase_limit(&mut self, limit: Option<usize>) {
+        self.phrase_limit = limit;
+    }
+
+    pub fn set_max_rule_depth(&mut self, depth: usize) {
+        self.max_rule_depth = depth;
+    }
+
+    pub fn set_max_solutions(&mut self, limit: Option<usize>) {
+        self.max_solutions = limit;
+    }
+
+    pub fn set_max_steps(&mut self, limit: Option<usize>) {
+        self.max_steps = limit;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_bottom_up(&mut self, enabled: bool) {
+        self.bottom_up = enabled;
+        self.invalidate_derived();
+    }
+
+    /// Drops the facts derived bottom-up after the facts or rules changed.
+    fn invalidate_derived(&mut self) {
+        *self.derived.get_mut() = None;
+    }
+
+    pub fn load_config_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file: {}", e))?;
+
+        self.load_config(&content)
+    }
+
+    pub fn load_config(&mut self, config: &str) -> Result<(), String> {
+        for line in config.lines() {
+            let line = line.trim();
+
+            // Skip empty lines and comments
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with(":-") {
+                for (predicate, arity) in parse_table_directive(line)? {
+                    self.table_predicate(&predicate, arity);
+                }
+                continue;
+            }
+
+            // Check if it's a rule (contains :-)
+            if line.contains(":-") {
+                self.add_rule(line)?;
+            }
+            // Check if it's a pattern (contains -->)
+            else if line.contains("-->") {
+                self.add_pattern(line)?;
+            }
+            // Otherwise skip (could be a comment or empty)
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the facts with those of `prolog_output`. Rule (`:-`) and
+    /// pattern (`-->`) lines, written by input directives, replace the ones
+    /// loaded from the previous output. Returns why each directive that could
+    /// not be loaded was left out.
+    pub fn load_facts_from_output(&mut self, prolog_output: &str) -> Vec<String> {
+        self.invalidate_derived();
+        self.facts.clear();
+        self.occurrences.clear();
+        self.fact_map.clear();
+        self.atom_args.clear();
+        self.fact_lookup.clear();
+        self.remove_output_clauses();
+        for fact in self.consulted_facts.clone() {
+            self.insert_fact(fact, None);
+        }
+
+        let mut current_source: Option<String> = None;
+        let mut errors = Vec::new();
+
+        for line in prolog_output.lines() {
+            let line = line.trim();
+            if let Some(sentence) = line.strip_prefix("// FROM:") {
+                current_source = Some(sentence.trim().to_string());
+                continue;
+            }
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            if line.starts_with(":-") {
+                match parse_directive(line) {
+                    Ok(tabled) => {
+                        for (predicate, arity) in tabled {
+                            if self.table_predicate(&predicate, arity) {
+                                self.output_tabled.push((predicate, arity));
+                            }
+                        }
+                    }
+                    Err(e) => errors.push(e),
+                }
+                continue;
+            }
+            if line.contains(":-") && self.add_rule(line).is_ok() {
+                self.output_rules += 1;
+                continue;
+            }
+            if line.contains("-->") && self.add_pattern(line.trim_end_matches('.')).is_ok() {
+                self.output_patterns += 1;
+                continue;
+            }
+
+            if let Some(fact) = self.parse_fact(line) {
+                self.insert_fact(fact, current_source.as_deref());
+            }
+        }
+
+        for change in self.fact_changes.clone() {
+            self.apply_fact_change(&change);
+        }
+        errors
+    }
+
+    /// Loads a Prolog source file: facts, rules, `:- table` directives and
+    /// `%` or `/* */` comments, with clauses free to span lines. Returns how
+    /// many clauses were loaded.
+    pub fn consult(&mut self, path: impl AsRef<Path>) -> Result<usize, String> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        self.consult_source(&source)
+    }
+
+    /// Loads Prolog source text, as `consult` does for a file.
+    pub fn consult_source(&mut self, source: &str) -> Result<usize, String> {
+        // Reloading the output drops its rules and patterns from the end, so
+        // consulted ones go in front of them.
+        let output_rules = self.rules.split_off(self.rules.len() - self.output_rules);
+        let output_patterns = self
+            .patterns
+            .split_off(self.patterns.len() - self.output_patterns);
+        let kept_rules = self.rules.len();
+        for indices in self.rule_map.values_mut() {
+            indices.retain(|&idx| idx < kept_rules);
+        }
+        self.rule_map.retain(|_, indices| !indices.is_empty());
+
+        let loaded = self.consult_clauses(source);
+
+        for rule in output_rules {
+            self.push_rule(rule);
+        }
+        self.patterns.extend(output_patterns);
+        loaded
+    }
+
+    fn consult_clauses(&mut self, source: &str) -> Result<usize, String> {
+        let mut loaded = 0;
+        for (line, clause) in split_clauses(source) {
+            let at_line = |e: String| format!("Line {}: {}", line, e);
+
+            if let Some(directive) = clause.strip_prefix(":-") {
+                if is_ignored_declaration(directive) {
+                    continue;
+                }
+                for (predicate, arity) in parse_table_directive(&clause).map_err(at_line)? {
+                    self.table_predicate(&predicate, arity);
+                }
+            } else if clause.contains("-->") {
+                self.add_pattern(&clause).map_err(at_line)?;
+            } else if clause.contains(":-") {
+                self.add_rule(&clause).map_err(at_line)?;
+            } else {
+                let fact = self
+                    .parse_fact(&clause)
+                    .ok_or_else(|| at_line(format!("Invalid fact: {}", clause)))?;
+                self.consulted_facts.push(fact.clone());
+                self.insert_fact(fact, None);
+            }
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    /// Drops the rules and patterns added by the last `load_facts_from_output`.
+    fn remove_output_clauses(&mut self) {
+        let kept_rules = self.rules.len() - self.output_rules;
+        self.rules.truncate(kept_rules);
+        for indices in self.rule_map.values_mut() {
+            indices.retain(|&idx| idx < kept_rules);
+        }
+        self.rule_map.retain(|_, indices| !indices.is_empty());
+
+        let kept_patterns = self.patterns.len() - self.output_patterns;
+        self.patterns.truncate(kept_patterns);
+
+        for predicate in self.output_tabled.drain(..) {
+            self.tabled.remove(&predicate);
+        }
+        self.invalidate_derived();
+
+        self.output_rules = 0;
+        self.output_patterns = 0;
+    }
+
+    /// Memoizes the rule answers of `predicate/arity` for the length of each
+    /// query. Returns false if it was already tabled.
+    pub fn table_predicate(&mut self, predicate: &str, arity: usize) -> bool {
+        self.tabled.insert((predicate.to_string(), arity))
+    }
+
+    fn parse_fact(&self, line: &str) -> Option<Fact> {
+        let line = line.trim_end_matches('.').trim();
+        let open_paren = line.find('(')?;
+        let close_paren = line.rfind(')')?;
+
+        let predicate = line[..open_paren].trim();
+        let predicate = match predicate.strip_prefix('-') {
+            Some(negated) if !negated.trim().is_empty() => {
+                format!("{}{}", NEGATION_PREFIX, negated.trim())
+            }
+            _ => predicate.to_string(),
+        };
+        let args_str = line[open_paren + 1..close_paren].trim();
+
+        let args = if args_str.is_empty() {
+            vec![]
+        } else {
+            self.split_by_top_level_comma(args_str)
+                .iter()
+                .map(|s| Term::parse(s))
+                .collect()
+        };
+
+        Some(Fact { predicate, args })
+    }
+
+    /// Parses a body or query goal: either a comparison such as `A >= 18`
+    /// (stored as a fact with the operator as predicate) or a regular fact.
+    fn parse_goal(&self, text: &str) -> Option<Fact> {
+        let text = text.trim_end_matches('.').trim();
+
+        for op in COMPARISON_OPERATORS {
+            if let Some(pos) = self.find_top_level(text, op) {
+                let lhs = text[..pos].trim();
+                let rhs = text[pos + op.len()..].trim();
+                if lhs.is_empty() || rhs.is_empty() {
+                    return None;
+                }
+                return Some(Fact {
+                    predicate: op.to_string(),
+                    args: vec![Term::parse(lhs), Term::parse(rhs)],
+                });
+            }
+        }
+
+        self.parse_fact(text)
+    }
+
+    fn find_top_level(&self, text: &str, needle: &str) -> Option<usize> {
+        let mut paren_depth = 0;
+        let mut quote: Option<char> = None;
+
+        for (i, ch) in text.char_indices() {
+            match (quote, ch) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '\'' | '"') => quote = Some(ch),
+                (None, '(' | '[') => paren_depth += 1,
+                (None, ')' | ']') => paren_depth -= 1,
+                (None, _) if paren_depth == 0 && text[i..].starts_with(needle) => {
+                    return Some(i);
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    /// Evaluates a built-in goal, returning every set of bindings under which it
+    /// holds. Returns `None` when the goal is not a built-in.
+    fn eval_builtin(&self, goal: &Fact, bindings: &Bindings) -> Option<Vec<Bindings>> {
+        if !COMPARISON_OPERATORS.contains(&goal.predicate.as_str()) || goal.args.len() != 2 {
+            return None;
+        }
+
+        match goal.predicate.as_str() {
+            "=" => {
+                let unified = self.unify_with(&goal.args[..1], &goal.args[1..], bindings.clone());
+                return Some(unified.into_iter().collect());
+            }
+            "\\=" => {
+                let unified = self.unify_with(&goal.args[..1], &goal.args[1..], bindings.clone());
+                return Some(if unified.is_some() {
+                    vec![]
+                } else {
+                    vec![bindings.clone()]
+                });
+            }
+            _ => {}
+        }
+
+        let lhs = self.substitute(&goal.args[0], bindings).as_number();
+        let rhs = self.substitute(&goal.args[1], bindings).as_number();
+
+        let (Some(lhs), Some(rhs)) = (lhs, rhs) else {
+            return Some(vec![]);
+        };
+
+        let holds = match goal.predicate.as_str() {
+            "=:=" => lhs == rhs,
+            "=\\=" => lhs != rhs,
+            ">=" => lhs >= rhs,
+            "=<" => lhs <= rhs,
+            ">" => lhs > rhs,
+            "<" => lhs < rhs,
+            _ => false,
+        };
+
+        Some(if holds {
+            vec![bindings.clone()]
+        } else {
+            vec![]
+        })
+    }
+
+    /// Atom built-ins for post-processing captured words inside rules:
+    /// `atom_length/2`, `atom_concat/3`, `sub_atom/5`, `upcase_atom/2` and
+    /// `downcase_atom/2`. Numbers and strings are read as their text; results
+    /// are atoms. Fails when the input atoms are unbound. Returns `None` when
+    /// the goal is not one of them.
+    fn eval_atom_builtin(&self, goal: &Fact, bindings: &Bindings) -> Option<Vec<Bindings>> {
+        let args: Vec<Term> = goal
+            .args
+            .iter()
+            .map(|arg| self.substitute(arg, bindings))
+            .collect();
+        let unify = |pairs: &[(Term, Term)]| {
+            let (left, right): (Vec<Term>, Vec<Term>) = pairs.iter().cloned().unzip();
+            self.unify_with(&left, &right, bindings.clone())
+        };
+
+        let solutions = match (goal.predicate.as_str(), args.as_slice()) {
+            ("atom_length", [atom, length]) => {
+                let Some(text) = atom.as_text() else {
+                    return Some(vec![]);
+                };
+                let count = Term::Integer(text.chars().count() as i64);
+                unify(&[(length.clone(), count)]).into_iter().collect()
+            }
+            ("upcase_atom" | "downcase_atom", [atom, converted]) => {
+                let Some(text) = atom.as_text() else {
+                    return Some(vec![]);
+                };
+                let text = if goal.predicate == "upcase_atom" {
+                    text.to_uppercase()
+                } else {
+                    text.to_lowercase()
+                };
+                unify(&[(converted.clone(), Term::Atom(text))])
+                    .into_iter()
+                    .collect()
+            }
+            ("atom_concat", [first, second, whole]) => {
+                if let (Some(first_text), Some(second_text)) = (first.as_text(), second.as_text()) {
+                    let joined = Term::Atom(first_text + &second_text);
+                    return Some(unify(&[(whole.clone(), joined)]).into_iter().collect());
+                }
+                let Some(text) = whole.as_text() else {
+                    return Some(vec![]);
+                };
+                // Every way of splitting the whole atom in two.
+                text.char_indices()
+                    .map(|(pos, _)| pos)
+                    .chain(std::iter::once(text.len()))
+                    .filter_map(|pos| {
+                        unify(&[
+                            (first.clone(), Term::Atom(text[..pos].to_string())),
+                            (second.clone(), Term::Atom(text[pos..].to_string())),
+                        ])
+                    })
+                    .collect()
+            }
+            ("sub_atom", [atom, before, length, after, sub]) => {
+                let Some(text) = atom.as_text() else {
+                    return Some(vec![]);
+                };
+                let chars: Vec<char> = text.chars().collect();
+                let mut solutions = Vec::new();
+                for start in 0..=chars.len() {
+                    for end in start..=chars.len() {
+                        let sub_text: String = chars[start..end].iter().collect();
+                        solutions.extend(unify(&[
+                            (before.clone(), Term::Integer(start as i64)),
+                            (length.clone(), Term::Integer((end - start) as i64)),
+                            (after.clone(), Term::Integer((chars.len() - end) as i64)),
+                            (sub.clone(), Term::Atom(sub_text)),
+                        ]));
+                    }
+                }
+                solutions
+            }
+            _ => return None,
+        };
+        Some(solutions)
+    }
+
+    /// Collects the solutions of a sub-goal into a list. `findall/3` lists every
+    /// solution, `bagof/3` fails when there are none and `setof/3` also sorts
+    /// and removes duplicates. `aggregate_all/3` reduces them to a number
+    /// instead: `count` of the solutions or `sum(X)` of `X` over them.
+    /// Returns `None` when the goal is not one of them.
+    fn eval_aggregate(
+        &self,
+        goal: &Fact,
+        bindings: &Bindings,
+        depth: usize,
+    ) -> Option<Vec<Bindings>> {
+        if !AGGREGATE_PREDICATES.contains(&goal.predicate.as_str()) || goal.args.len() != 3 {
+            return None;
+        }
+
+        let Some(sub_goals) = self.term_to_goals(&self.substitute(&goal.args[1], bindings)) else {
+            return Some(vec![]);
+        };
+        let solutions = self.solve_conjunction(&sub_goals, bindings.clone(), depth).0;
+
+        if goal.predicate == "aggregate_all" {
+            let result = match self.substitute(&goal.args[0], bindings) {
+                Term::Atom(spec) if spec == "count" => Term::Integer(solutions.len() as i64),
+                Term::Compound(spec, args) if spec == "sum" && args.len() == 1 => {
+                    let values: Option<Vec<Term>> = solutions
+                        .iter()
+                        .map(|(solution, _)| {
+                            let value = self.substitute(&args[0], solution);
+                            value.as_number().map(|_| value)
+                        })
+                        .collect();
+                    // Non-numeric values make the sum fail
+                    let Some(values) = values else {
+                        return Some(vec![]);
+                    };
+                    sum_numbers(&values)
+                }
+                _ => return Some(vec![]),
+            };
+            return Some(
+                self.unify_with(
+                    &goal.args[2..],
+                    std::slice::from_ref(&result),
+                    bindings.clone(),
+                )
+                .into_iter()
+                .collect(),
+            );
+        }
+
+        let mut items: Vec<Term> = solutions
+            .iter()
+            .map(|(solution, _)| self.substitute(&goal.args[0], solution))
+            .collect();
+
+        if goal.predicate == "setof" {
+            items.sort_by(standard_order);
+            items.dedup();
+        }
+        if items.is_empty() && goal.predicate != "findall" {
+            return Some(vec![]);
+        }
+
+        let list = Term::List(items, None);
+        Some(
+            self.unify_with(
+                &goal.args[2..],
+                std::slice::from_ref(&list),
+                bindings.clone(),
+            )
+            .into_iter()
+            .collect(),
+        )
+    }
+
+    /// Reads a fact passed as an argument, e.g. the `animal(owl)` of `assert/1`.
+    fn term_to_fact(&self, term: &Term) -> Option<Fact> {
+        match term {
+            Term::Compound(name, args) => Some(Fact {
+                predicate: name.clone(),
+                args: args.clone(),
+            }),
+            Term::Atom(name) => Some(Fact {
+                predicate: name.clone(),
+                args: vec![],
+            }),
+            _ => None,
+        }
+    }
+
+    /// Reads a goal passed as an argument, e.g. the `(a(X), b(X))` of `findall/3`.
+    fn term_to_goals(&self, term: &Term) -> Option<Vec<Goal>> {
+        match term {
+            Term::Compound(op, args) if op == "," => Some(
+                args.iter()
+                    .map(|arg| self.term_to_goals(arg))
+                    .collect::<Option<Vec<_>>>()?
+                    .concat(),
+            ),
+            Term::Compound(op, args) if op == ";" => Some(vec![Goal::Or(
+                args.iter()
+                    .map(|arg| self.term_to_goals(arg))
+                    .collect::<Option<_>>()?,
+            )]),
+            _ => Some(vec![Goal::Call(self.term_to_fact(term)?)]),
+        }
+    }
+
+    /// Replaces every bound variable in `term` with its value. Variables that
+    /// are already being expanded are left as-is so cyclic bindings created
+    /// without the occurs check still terminate.
+    fn substitute(&self, term: &Term, bindings: &Bindings) -> Term {
+        fn resolve(term: &Term, bindings: &Bindings, expanding: &mut Vec<String>) -> Term {
+            match term {
+                Term::Variable(name) if !expanding.contains(name) => match bindings.get(name) {
+                    Some(value) => {
+                        expanding.push(name.clone());
+                        let resolved = resolve(value, bindings, expanding);
+                        expanding.pop();
+                        resolved
+                    }
+                    None => term.clone(),
+                },
+                Term::Compound(name, args) => Term::Compound(
+                    name.clone(),
+                    args.iter()
+                        .map(|arg| resolve(arg, bindings, expanding))
+                        .collect(),
+                ),
+                Term::List(items, tail) => Term::list(
+                    items
+                        .iter()
+                        .map(|item| resolve(item, bindings, expanding))
+                        .collect(),
+                    tail.as_ref().map(|tail| resolve(tail, bindings, expanding)),
+                ),
+                _ => term.clone(),
+            }
+        }
+
+        resolve(term, bindings, &mut Vec::new())
+    }
+
+    pub fn parse_fact_public(&self, line: &str) -> Option<Fact> {
+        self.parse_fact(line)
+    }
+
+    pub fn add_fact(&mut self, fact: Fact) {
+        self.insert_fact(fact, None);
+    }
+
+    /// Runs `assert/1`, `asserta/1`, `assertz/1`, `retract/1` or `consult/1`
+    /// and describes what changed. Returns `None` when `statement` is not one
+    /// of them.
+    pub fn update(&mut self, statement: &str) -> Option<Result<String, String>> {
+        let goal = self.parse_goal(statement)?;
+        if goal.predicate == "consult" {
+            let [Term::Atom(path)] = goal.args.as_slice() else {
+                return Some(Err("consult expects a file name".to_string()));
+            };
+            return Some(
+                self.consult(path)
+                    .map(|loaded| format!("Consulted {}: {} clauses.", path, loaded)),
+            );
+        }
+        if !matches!(
+            goal.predicate.as_str(),
+            "assert" | "asserta" | "assertz" | "retract"
+        ) {
+            return None;
+        }
+        let Some(fact) = goal
+            .args
+            .first()
+            .filter(|_| goal.args.len() == 1)
+            .and_then(|term| self.term_to_fact(term))
+        else {
+            return Some(Err(format!("{} expects a single fact", goal.predicate)));
+        };
+
+        if goal.predicate == "retract" {
+            let position = self.facts.iter().position(|stored| {
+                stored.predicate == fact.predicate
+                    && stored.args.len() == fact.args.len()
+                    && self.unify(&fact.args, &stored.args).is_some()
+            });
+            return Some(Ok(match position {
+                Some(idx) => {
+                    let removed = self.facts[idx].clone();
+                    let description = format!("Fact retracted: {}.", removed);
+                    let change = FactChange::Retract(removed);
+                    self.apply_fact_change(&change);
+                    self.fact_changes.push(change);
+                    description
+                }
+                None => format!("No fact to retract: {}.", fact),
+            }));
+        }
+
+        let description = format!("Fact asserted: {}.", fact);
+        let change = FactChange::Assert {
+            fact,
+            at_front: goal.predicate == "asserta",
+        };
+        self.apply_fact_change(&change);
+        self.fact_changes.push(change);
+        Some(Ok(description))
+    }
+
+    /// Makes `change` to the stored facts. A retracted fact that is no
+    /// longer stored is left alone.
+    fn apply_fact_change(&mut self, change: &FactChange) {
+        match change {
+            FactChange::Assert { fact, at_front } => {
+                let is_new = !self.fact_lookup.contains_key(&fact.to_string());
+                self.insert_fact(fact.clone(), None);
+                if *at_front && is_new {
+                    self.facts.rotate_right(1);
+                    self.occurrences.rotate_right(1);
+                    self.reindex_facts();
+                }
+            }
+            FactChange::Retract(fact) => {
+                if let Some(&idx) = self.fact_lookup.get(&fact.to_string()) {
+                    self.facts.remove(idx);
+                    self.occurrences.remove(idx);
+                    self.reindex_facts();
+                }
+            }
+        }
+    }
+
+    /// Rebuilds the predicate and lookup indexes after facts were removed or reordered.
+    fn reindex_facts(&mut self) {
+        self.invalidate_derived();
+        self.fact_map.clear();
+        self.atom_args.clear();
+        self.fact_lookup.clear();
+        for idx in 0..self.facts.len() {
+            self.index_fact(idx);
+            self.fact_lookup.insert(self.facts[idx].to_string(), idx);
+        }
+    }
+
+    /// Adds the fact at `idx` to the predicate and atom indexes.
+    fn index_fact(&mut self, idx: usize) {
+        let fact = &self.facts[idx];
+        self.fact_map
+            .entry((fact.predicate.clone(), fact.args.len()))
+            .or_default()
+            .insert(idx, fact);
+        for (arg_idx, arg) in fact.args.iter().enumerate() {
+            if let Term::Atom(name) = arg {
+                self.atom_args
+                    .entry(name.clone())
+                    .or_default()
+                    .push((idx, arg_idx));
+            }
+        }
+    }
+
+    /// Stored facts of `predicate/arity` that may unify with a goal whose
+    /// first argument is `first_arg`, in store order.
+    fn candidate_facts(&self, predicate: &str, arity: usize, first_arg: Option<&Term>) -> &[usize] {
+        self.fact_map
+            .get(&(predicate.to_string(), arity))
+            .map(|index| index.candidates(first_arg))
+            .unwrap_or(&[])
+    }
+
+    /// Stores a fact once; repeated extractions only bump its occurrence count
+    /// and remember the sentence they came from.
+    fn insert_fact(&mut self, fact: Fact, source: Option<&str>) {
+        self.invalidate_derived();
+        let key = fact.to_string();
+
+        let idx = match self.fact_lookup.get(&key) {
+            Some(&idx) => idx,
+            None => {
+                let idx = self.facts.len();
+                self.fact_lookup.insert(key, idx);
+                self.facts.push(fact);
+                self.occurrences.push(FactOccurrences::default());
+                self.index_fact(idx);
+                idx
+            }
+        };
+
+        let occurrences = &mut self.occurrences[idx];
+        occurrences.count += 1;
+        if let Some(source) = source
+            && !occurrences.sources.iter().any(|s| s == source)
+        {
+            occurrences.sources.push(source.to_string());
+        }
+    }
+
+    pub fn facts(&self) -> &[Fact] {
+        &self.facts
+    }
+
+    /// Writes the facts, rules and table declarations as a `.pl` file that
+    /// SWI-Prolog consults without warnings: clauses grouped by predicate,
+    /// each predicate declared up front, atoms quoted where needed and
+    /// singleton variables marked with `_`. Patterns have no SWI-Prolog
+    /// counterpart and are kept as comments.
+    pub fn export_swi_prolog(&self) -> String {
+        let mut predicates: Vec<(String, usize)> = Vec::new();
+        let mut clauses: HashMap<(String, usize), Vec<String>> = HashMap::new();
+        let heads = self
+            .facts
+            .iter()
+            .map(|fact| (fact, &[] as &[Goal]))
+            .chain(self.rules.iter().map(|rule| (&rule.head, rule.body.as_slice())));
+        for (head, body) in heads {
+            let key = (head.predicate.clone(), head.args.len());
+            if !clauses.contains_key(&key) {
+                predicates.push(key.clone());
+            }
+            clauses.entry(key).or_default().push(swi_clause(head, body));
+        }
+
+        let mut out = String::from("% Exported from Simple Prolog\n\n");
+        for (name, arity) in &predicates {
+            let indicator = format!("{}/{}", Term::Atom(name.clone()), arity);
+            if self.tabled.contains(&(name.clone(), *arity)) {
+                // Tabled predicates cannot also be dynamic
+                out.push_str(&format!(":- table {}.\n", indicator));
+            } else {
+                out.push_str(&format!(":- dynamic {}.\n", indicator));
+            }
+            out.push_str(&format!(":- discontiguous {}.\n", indicator));
+        }
+
+        for key in &predicates {
+            out.push('\n');
+            for clause in &clauses[key] {
+                out.push_str(clause);
+                out.push('\n');
+            }
+        }
+
+        if !self.patterns.is_empty() {
+            out.push_str("\n% Patterns, used by phrase/2 in Simple Prolog only:\n");
+            for pattern in &self.patterns {
+                let components: Vec<String> =
+                    pattern.components.iter().map(|c| c.to_string()).collect();
+                out.push_str(&format!("% {} --> {}\n", pattern.head, components.join(", ")));
+            }
+        }
+        out
+    }
+
+    /// `(predicate, arity, fact count)` for every stored predicate, sorted by name.
+    pub fn predicate_counts(&self) -> Vec<(&str, usize, usize)> {
+        let mut counts: BTreeMap<(&str, usize), usize> = BTreeMap::new();
+        for fact in &self.facts {
+            *counts
+                .entry((&fact.predicate, fact.args.len()))
+                .or_default() += 1;
+        }
+
+        counts
+            .into_iter()
+            .map(|((name, arity), count)| (name, arity, count))
+            .collect()
+    }
+
+    pub fn facts_with_predicate(&self, predicate: &str, arity: usize) -> Vec<&Fact> {
+        self.candidate_facts(predicate, arity, None)
+            .iter()
+            .map(|&idx| &self.facts[idx])
+            .collect()
+    }
+
+    /// Every stored fact mentioning `entity`, either as an argument or as the
+    /// predicate (`bear(animal)` mentions both), grouped by predicate in the
+    /// order the predicates were first seen.
+    pub fn facts_mentioning(&self, entity: &str) -> Vec<(&str, Vec<(&Fact, &FactOccurrences)>)> {
+        let mut groups: Vec<(&str, Vec<(&Fact, &FactOccurrences)>)> = Vec::new();
+
+        for (fact, occurrences) in self.facts.iter().zip(&self.occurrences) {
+            if fact.predicate != entity && !fact.args.iter().any(|arg| arg.mentions_atom(entity)) {
+                continue;
+            }
+
+            match groups
+                .iter_mut()
+                .find(|(predicate, _)| *predicate == fact.predicate)
+            {
+                Some((_, facts)) => facts.push((fact, occurrences)),
+                None => groups.push((&fact.predicate, vec![(fact, occurrences)])),
+            }
+        }
+
+        groups
+    }
+
+    /// Every ground fact that holds while its `not_` negation holds too,
+    /// whether each side is stored or derived by rules. Sorted by fact.
+    pub fn contradictions(&self) -> Vec<Contradiction> {
+        let negated: BTreeSet<(&str, usize)> = self
+            .facts
+            .iter()
+            .chain(self.rules.iter().map(|rule| &rule.head))
+            .filter(|fact| fact.predicate.len() > NEGATION_PREFIX.len())
+            .filter(|fact| fact.predicate.starts_with(NEGATION_PREFIX))
+            .map(|fact| (fact.predicate.as_str(), fact.args.len()))
+            .collect();
+
+        self.start_query();
+        let mut found: BTreeMap<String, Contradiction> = BTreeMap::new();
+        for (predicate, arity) in negated {
+            let goal = Fact {
+                predicate: predicate.to_string(),
+                args: (0..arity).map(|i| Term::Variable(format!("_N{}", i))).collect(),
+            };
+            let goals = [Goal::Call(goal.clone())];
+            for (bindings, _) in self.solve_conjunction(&goals, Bindings::new(), 0).0 {
+                let args: Vec<Term> = goal
+                    .args
+                    .iter()
+                    .map(|arg| self.substitute(arg, &bindings))
+                    .collect();
+                let mut variables = Vec::new();
+                args.iter().for_each(|arg| arg.collect_variables(&mut variables));
+                if !variables.is_empty() {
+                    continue;
+                }
+
+                let negative = Fact {
+                    predicate: predicate.to_string(),
+                    args: args.clone(),
+                };
+                let positive = Fact {
+                    predicate: predicate[NEGATION_PREFIX.len()..].to_string(),
+                    args,
+                };
+                let key = positive.to_string();
+                if found.contains_key(&key)
+                    || self.solve_goal(&positive, &Bindings::new(), 0).is_empty()
+                {
+                    continue;
+                }
+                found.insert(
+                    key.clone(),
+                    Contradiction {
+                        positive_stored: self.fact_lookup.contains_key(&key),
+                        negative_stored: self.fact_lookup.contains_key(&negative.to_string()),
+                        fact: positive,
+                    },
+                );
+            }
+        }
+        found.into_values().collect()
+    }
+
+    pub fn add_rule(&mut self, rule_str: &str) -> Result<(), String> {
+        let parts: Vec<&str> = rule_str.split(":-").collect();
+        if parts.len() != 2 {
+            return Err("Rule must have format: head :- body".to_string());
+        }
+
+        let head = self
+            .parse_fact(parts[0].trim())
+            .ok_or("Invalid head in rule")?;
+
+        let body = self.parse_body(parts[1].trim().trim_end_matches('.'))?;
+
+        self.push_rule(Rule { head, body });
+        Ok(())
+    }
+
+    fn push_rule(&mut self, rule: Rule) {
+        self.invalidate_derived();
+        self.rule_map
+            .entry((rule.head.predicate.clone(), rule.head.args.len()))
+            .or_default()
+            .push(self.rules.len());
+        self.rules.push(rule);
+    }
+
+    /// Parses a rule body into a conjunction of goals. `,` binds tighter than
+    /// `;`, and parentheses group either.
+    fn parse_body(&self, text: &str) -> Result<Vec<Goal>, String> {
+        let alternatives = split_top_level_on(text, ';');
+        if alternatives.len() > 1 {
+            let alternatives = alternatives
+                .iter()
+                .map(|alternative| self.parse_body(alternative))
+                .collect::<Result<_, _>>()?;
+            return Ok(vec![Goal::Or(alternatives)]);
+        }
+
+        let mut goals = Vec::new();
+        for part in self.split_by_top_level_comma(text) {
+            if part == "!" {
+                goals.push(Goal::Cut);
+            } else if let Some(group) = part.strip_prefix('(').and_then(|p| p.strip_suffix(')')) {
+                goals.extend(self.parse_body(group)?);
+            } else {
+                let fact = self
+                    .parse_goal(&part)
+                    .ok_or(format!("Invalid body fact: {}", part))?;
+                goals.push(Goal::Call(fact));
+            }
+        }
+
+        if goals.is_empty() {
+            return Err(format!("Empty goal in rule body: {}", text));
+        }
+        Ok(goals)
+    }
+
+    pub fn add_pattern(&mut self, pattern_str: &str) -> Result<(), String> {
+        let parts: Vec<&str> = pattern_str.split("-->").collect();
+        if parts.len() != 2 {
+            return Err("Pattern must have format: name --> components".to_string());
+        }
+
+        let head = self.parse_component(parts[0]);
+        let components = self
+            .split_by_top_level_comma(parts[1])
+            .iter()
+            .map(|component| self.parse_phrase_component(component))
+            .collect::<Result<_, _>>()?;
+
+        self.patterns.push(Pattern { head, components });
+        Ok(())
+    }
+
+    /// Parses one component of a pattern body: `[words]`, `{Goal}` or a
+    /// non-terminal.
+    fn parse_phrase_component(&self, text: &str) -> Result<PhraseComponent, String> {
+        let text = text.trim();
+        if text.starts_with('[') {
+            return match Term::parse(text) {
+                Term::List(words, None) => Ok(PhraseComponent::Terminal(words)),
+                _ => Err(format!("Invalid terminal: {}", text)),
+            };
+        }
+        if let Some(goals) = text.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+            return self.parse_body(goals).map(PhraseComponent::Goals);
+        }
+        Ok(PhraseComponent::NonTerminal(self.parse_component(text)))
+    }
+
+    /// Parses a pattern head or component, where `np` is shorthand for `np()`.
+    fn parse_component(&self, text: &str) -> Fact {
+        let text = text.trim();
+        self.parse_fact(text).unwrap_or_else(|| Fact {
+            predicate: text.to_string(),
+            args: vec![],
+        })
+    }
+
+    /// Execute a query and return one display line per answer, followed by
+    /// notes on any limit that cut the search short.
+    /// Supports:
+    /// - Simple queries: "animal(X)"
+    /// - Conjunction queries: "animal(X), action(Y)"
+    /// - Phrase queries: "phrase(sentence, X)" to generate patterns
+    /// - Count queries: "fact_count(is_a(cat, mammal), N)"
+    pub fn query(&self, query_str: &str) -> Result<Vec<String>, String> {
+        let (solutions, notes) = self.run_query(query_str)?;
+        Ok(solutions
+            .iter()
+            .map(|solution| solution.to_string())
+            .chain(notes)
+            .collect())
+    }
+
+    /// Like [`query`](Self::query), but returns each answer's bindings by
+    /// variable name for callers that work with the values rather than the
+    /// display text. Limit notes are dropped.
+    pub fn query_bindings(&self, query_str: &str) -> Result<Vec<BTreeMap<String, Term>>, String> {
+        let (solutions, _) = self.run_query(query_str)?;
+        Ok(solutions.into_iter().map(Solution::into_map).collect())
+    }
+
+    /// Runs any kind of query, returning its answers and notes on limits hit.
+    fn run_query(&self, query_str: &str) -> Result<(Vec<Solution>, Vec<String>), String> {
+        let query_str = query_str.trim_end_matches('.').trim();
+
+        let mut notes = Vec::new();
+        let mut solutions = if query_str.starts_with("phrase(") {
+            self.start_query();
+            self.query_phrase(query_str, &mut notes)?
+        } else if query_str.starts_with("fact_count(") {
+            self.start_query();
+            self.query_fact_count(query_str)?
+        } else {
+            // One answer past the limit tells whether any were left out.
+            let limit = self.max_solutions.map_or(usize::MAX, |max| max.saturating_add(1));
+            self.query_iter(query_str)?.take(limit).collect()
+        };
+
+        if let Some(max) = self.max_solutions
+            && solutions.len() > max
+        {
+            solutions.truncate(max);
+            notes.push(format!("// Showing the first {} solutions", max));
+        }
+
+        if self.depth_limit_reached.get() {
+            notes.push(format!(
+                "// Rule depth limit of {} reached; results may be incomplete",
+                self.max_rule_depth
+            ));
+        }
+
+        match self.search_limit_reached.get() {
+            Some(SearchLimit::Steps) => notes.push(format!(
+                "// Step limit of {} reached; results may be incomplete",
+                self.max_steps.unwrap_or_default()
+            )),
+            Some(SearchLimit::Timeout) => notes.push(format!(
+                "// Query timed out after {} ms; results may be incomplete",
+                self.timeout.unwrap_or_default().as_millis()
+            )),
+            None => {}
+        }
+
+        Ok((solutions, notes))
+    }
+
+    /// Streams the solutions of a fact, rule or conjunction query as they are
+    /// found instead of collecting them first. The step and time limits apply;
+    /// `max_solutions` is left to the caller, who can simply stop iterating.
+    /// phrase/2 and fact_count/2 queries are only available through
+    /// [`query`](Self::query).
+    pub fn query_iter(&self, query_str: &str) -> Result<Solutions<'_>, String> {
+        let query_str = query_str.trim_end_matches('.').trim();
+        if query_str.starts_with("phrase(") || query_str.starts_with("fact_count(") {
+            return Err("phrase/fact_count queries cannot be streamed; use query".to_string());
+        }
+
+        self.start_query();
+        self.solutions(query_str)
+    }
+
+    fn solutions(&self, query_str: &str) -> Result<Solutions<'_>, String> {
+        let goals = if self.is_conjunction(query_str) {
+            self.split_by_top_level_comma(query_str)
+                .iter()
+                .map(|pred_str| {
+                    self.parse_goal(pred_str)
+                        .ok_or(format!("Invalid predicate: {}", pred_str))
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            vec![self.parse_goal(query_str).ok_or("Invalid query format")?]
+        };
+
+        let variables = self.goal_variables(&goals);
+        let goals = goals.into_iter().map(Goal::Call).collect();
+        Ok(Solutions {
+            engine: self,
+            variables,
+            pending: Some((
+                push_goals(goals, 0, 0, Rc::new(Continuation::Done)),
+                Bindings::new(),
+            )),
+            choices: Vec::new(),
+            seen: HashSet::new(),
+        })
+    }
+
+    /// Resets the per-query limit bookkeeping and starts the timeout clock.
+    fn start_query(&self) {
+        self.tables.borrow_mut().clear();
+        self.table_stack.borrow_mut().clear();
+        self.table_answers_added.set(0);
+        self.depth_limit_reached.set(false);
+        self.search_limit_reached.set(None);
+        self.steps_taken.set(0);
+        self.deadline
+            .set(self.timeout.map(|timeout| Instant::now() + timeout));
+
+        if self.bottom_up && self.derived.borrow().is_none() {
+            let derived = self.materialize();
+            // A derivation cut short by the budget would answer incompletely
+            // forever; derive again on the next query instead.
+            if self.search_limit_reached.get().is_none() {
+                *self.derived.borrow_mut() = Some(derived);
+            }
+        }
+    }
+
+    /// Predicates that can be derived bottom-up: every rule for them is a
+    /// plain conjunction of calls (no cut, disjunction or findall/3 family)
+    /// whose rule-defined callees can be derived bottom-up too.
+    fn bottom_up_predicates(&self) -> HashSet<(String, usize)> {
+        let mut predicates: HashSet<(String, usize)> = self
+            .rule_map
+            .iter()
+            .filter(|(_, rule_indices)| {
+                rule_indices.iter().all(|&idx| {
+                    self.rules[idx].body.iter().all(|goal| {
+                        matches!(goal, Goal::Call(call)
+                            if !AGGREGATE_PREDICATES.contains(&call.predicate.as_str()))
+                    })
+                })
+            })
+            .map(|(predicate, _)| predicate.clone())
+            .collect();
+
+        loop {
+            let blocked: Vec<(String, usize)> = predicates
+                .iter()
+                .filter(|predicate| {
+                    self.rule_map[*predicate].iter().any(|&idx| {
+                        self.rules[idx].body.iter().any(|goal| {
+                            let Goal::Call(call) = goal else {
+                                return true;
+                            };
+                            let callee = (call.predicate.clone(), call.args.len());
+                            self.rule_map.contains_key(&callee) && !predicates.contains(&callee)
+                        })
+                    })
+                })
+                .cloned()
+                .collect();
+            if blocked.is_empty() {
+                return predicates;
+            }
+            for predicate in blocked {
+                predicates.remove(&predicate);
+            }
+        }
+    }
+
+    /// Derives every fact of the bottom-up predicates by semi-naive
+    /// iteration: after a first round over all facts, each round joins only
+    /// against the facts the previous round derived, until none are new.
+    fn materialize(&self) -> DerivedFacts {
+        let mut derived = DerivedFacts {
+            predicates: self.bottom_up_predicates(),
+            ..Default::default()
+        };
+        let rules: Vec<Rule> = self
+            .rules
+            .iter()
+            .filter(|rule| {
+                derived
+                    .predicates
+                    .contains(&(rule.head.predicate.clone(), rule.head.args.len()))
+            })
+            .map(|rule| self.rename_apart(rule))
+            .collect();
+
+        let mut new_facts = Vec::new();
+        for rule in &rules {
+            self.derive_with_rule(rule, None, &derived, &mut new_facts);
+        }
+
+        loop {
+            let delta_start = derived.facts.len();
+            for fact in new_facts.drain(..) {
+                derived.insert(fact);
+            }
+            if derived.facts.len() == delta_start || self.search_limit_reached.get().is_some() {
+                return derived;
+            }
+
+            let delta = delta_start..derived.facts.len();
+            for rule in &rules {
+                for (position, goal) in rule.body.iter().enumerate() {
+                    let Goal::Call(call) = goal else {
+                        continue;
+                    };
+                    if derived
+                        .predicates
+                        .contains(&(call.predicate.clone(), call.args.len()))
+                    {
+                        let delta = Some((position, delta.clone()));
+                        self.derive_with_rule(rule, delta, &derived, &mut new_facts);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds the head of `rule` for every way its body holds. With `delta`, the
+    /// body goal at that position only matches derived facts in that range.
+    fn derive_with_rule(
+        &self,
+        rule: &Rule,
+        delta: Option<(usize, std::ops::Range<usize>)>,
+        derived: &DerivedFacts,
+        out: &mut Vec<Fact>,
+    ) {
+        let mut partial = vec![Bindings::new()];
+
+        for (position, goal) in rule.body.iter().enumerate() {
+            let Goal::Call(call) = goal else {
+                return;
+            };
+            let delta_range = delta
+                .as_ref()
+                .filter(|(delta_position, _)| *delta_position == position)
+                .map(|(_, range)| range.clone());
+
+            let mut next = Vec::new();
+            for bindings in &partial {
+                if !self.take_step() {
+                    return;
+                }
+                next.extend(self.match_bottom_up(call, bindings, delta_range.clone(), derived));
+            }
+            partial = next;
+            if partial.is_empty() {
+                return;
+            }
+        }
+
+        for bindings in &partial {
+            out.push(Fact {
+                predicate: rule.head.predicate.clone(),
+                args: rule
+                    .head
+                    .args
+                    .iter()
+                    .map(|arg| self.substitute(arg, bindings))
+                    .collect(),
+            });
+        }
+    }
+
+    /// Solutions of one body goal against built-ins, stored facts (forward and
+    /// reversed, as top-down calls read them) and derived facts.
+    fn match_bottom_up(
+        &self,
+        goal: &Fact,
+        bindings: &Bindings,
+        delta: Option<std::ops::Range<usize>>,
+        derived: &DerivedFacts,
+    ) -> Vec<Bindings> {
+        let derived_matches = |indices: &[usize]| -> Vec<Bindings> {
+            indices
+                .iter()
+                .filter(|&&idx| delta.as_ref().is_none_or(|range| range.contains(&idx)))
+                .filter_map(|&idx| {
+                    self.unify_with(&goal.args, &derived.facts[idx].args, bindings.clone())
+                })
+                .collect()
+        };
+        let derived_indices = derived.with_predicate(&goal.predicate, goal.args.len());
+        if delta.is_some() {
+            return derived_matches(derived_indices);
+        }
+
+        if let Some(solutions) = self
+            .eval_builtin(goal, bindings)
+            .or_else(|| self.eval_atom_builtin(goal, bindings))
+        {
+            return solutions;
+        }
+
+        let substituted_args: Vec<Term> = goal
+            .args
+            .iter()
+            .map(|arg| self.substitute(arg, bindings))
+            .collect();
+        let mut solutions: Vec<Bindings> = self
+            .candidate_facts(&goal.predicate, goal.args.len(), substituted_args.first())
+            .iter()
+            .filter_map(|&idx| {
+                self.unify_with(&substituted_args, &self.facts[idx].args, bindings.clone())
+            })
+            .collect();
+        for &(idx, arg_idx) in self
+            .atom_args
+            .get(&goal.predicate)
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+        {
+            solutions.extend(self.unify_reversed(&substituted_args, idx, arg_idx, bindings.clone()));
+        }
+        solutions.extend(derived_matches(derived_indices));
+        solutions
+    }
+
+    /// Counts one goal resolution against the step and time budget. Returns
+    /// false once either is exhausted; the search should then give up.
+    fn take_step(&self) -> bool {
+        if self.search_limit_reached.get().is_some() {
+            return false;
+        }
+
+        let steps = self.steps_taken.get() + 1;
+        self.steps_taken.set(steps);
+
+        let limit = if self.max_steps.is_some_and(|max| steps > max) {
+            Some(SearchLimit::Steps)
+        } else if self
+            .deadline
+            .get()
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            Some(SearchLimit::Timeout)
+        } else {
+            None
+        };
+        self.search_limit_reached.set(limit);
+        limit.is_none()
+    }
+
+    /// Like [`query`](Self::query), but explains each answer with the facts,
+    /// built-ins and rules it was derived from. Conjunctions and rules are
+    /// supported; phrase/2 and fact_count/2 queries are not.
+    pub fn query_with_trace(&self, query_str: &str) -> Result<Vec<Explanation>, String> {
+        let query_str = query_str.trim_end_matches('.').trim();
+        if query_str.starts_with("phrase(") || query_str.starts_with("fact_count(") {
+            return Err("Explanations are not available for phrase/fact_count queries".to_string());
+        }
+
+        let goals = self.parse_body(query_str)?;
+        let mut variables = Vec::new();
+        collect_goal_variables(&goals, &mut variables);
+        self.start_query();
+
+        let mut explanations = Vec::new();
+        let mut seen = HashSet::new();
+        for (bindings, proof) in self.solve_conjunction(&goals, Bindings::new(), 0).0 {
+            let answer = self.format_answer(&variables, &bindings);
+            if !seen.insert(answer.clone()) {
+                continue;
+            }
+            let mut steps = Vec::new();
+            self.describe_proof(&proof, &bindings, 0, &mut steps);
+            explanations.push(Explanation { answer, steps });
+            if self.max_solutions == Some(explanations.len()) {
+                break;
+            }
+        }
+
+        Ok(explanations)
+    }
+
+    fn is_conjunction(&self, query_str: &str) -> bool {
+        self.find_top_level(query_str, ",").is_some()
+    }
+
+    fn split_by_top_level_comma(&self, s: &str) -> Vec<String> {
+        split_top_level(s)
+    }
+
+    fn query_fact_count(&self, query_str: &str) -> Result<Vec<Solution>, String> {
+        let inner = query_str
+            .strip_prefix("fact_count(")
+            .and_then(|s| s.strip_suffix(')'))
+            .ok_or("Invalid fact_count query format")?;
+
+        let args = self.split_by_top_level_comma(inner);
+        if args.len() != 2 {
+            return Err("fact_count/2 expects 2 arguments: fact_count(Fact, Count)".to_string());
+        }
+
+        let goal = self
+            .parse_fact(&args[0])
+            .ok_or(format!("Invalid fact: {}", args[0]))?;
+        let count_arg = Term::parse(&args[1]);
+
+        let mut variables = self.goal_variables(std::slice::from_ref(&goal));
+        count_arg.collect_variables(&mut variables);
+
+        let mut results = Vec::new();
+
+        let candidates = self.candidate_facts(&goal.predicate, goal.args.len(), goal.args.first());
+        for &idx in candidates {
+            let Some(mut bindings) = self.unify(&goal.args, &self.facts[idx].args) else {
+                continue;
+            };
+
+            let count = Term::Integer(self.occurrences[idx].count as i64);
+            match self.substitute(&count_arg, &bindings) {
+                Term::Variable(name) => {
+                    bindings.insert(name, count);
+                }
+                bound if bound != count => continue,
+                _ => {}
+            }
+
+            results.push(self.solution(&variables, &bindings));
+        }
+
+        Ok(results)
+    }
+
+    /// Handles `phrase(Pattern, X)` and `phrase(Pattern, X, Options)` where
+    /// Options is a list of `max(N)`, `sample(N)` and `seed(N)`. A note is
+    /// added to `notes` when the combinations were cut off at the limit.
+    fn query_phrase(
+        &self,
+        query_str: &str,
+        notes: &mut Vec<String>,
+    ) -> Result<Vec<Solution>, String> {
+        let inner = query_str
+            .strip_prefix("phrase(")
+            .and_then(|s| s.strip_suffix(')'))
+            .ok_or("Invalid phrase query format")?;
+
+        let args = self.split_by_top_level_comma(inner);
+        if args.len() != 2 && args.len() != 3 {
+            return Err(
+                "phrase expects phrase(pattern, Variable) or phrase(pattern, Variable, Options)"
+                    .to_string(),
+            );
+        }
+
+        let mut max = self.phrase_limit;
+        let mut sample = None;
+        let mut seed = None;
+
+        if let Some(options) = args.get(2) {
+            let options = options
+                .strip_prefix('[')
+                .and_then(|s| s.strip_suffix(']'))
+                .ok_or("phrase options must be a list, e.g. [max(10)]")?;
+
+            for option in self.split_by_top_level_comma(options) {
+                match Term::parse(&option) {
+                    Term::Compound(name, values) if values.len() == 1 => {
+                        let Term::Integer(value) = values[0] else {
+                            return Err(format!("Option {} expects an integer", option));
+                        };
+                        let value = value.max(0) as usize;
+                        match name.as_str() {
+                            "max" => max = Some(value),
+                            "sample" => sample = Some(value),
+                            "seed" => seed = Some(value as u64),
+                            _ => return Err(format!("Unknown phrase option: {}", option)),
+                        }
+                    }
+                    _ => return Err(format!("Unknown phrase option: {}", option)),
+                }
+            }
+        }
+
+        let target = self.parse_component(&args[0]);
+        let var_name = &args[1];
+
+        let patterns: Vec<&Pattern> = self.patterns_for(&target).collect();
+        if patterns.is_empty() {
+            return Err(format!("Pattern '{}' not defined", args[0]));
+        }
+
+        if let Some(missing) = patterns
+            .iter()
+            .flat_map(|pattern| &pattern.components)
+            .find_map(|component| match component {
+                PhraseComponent::NonTerminal(c) if !self.defines_component(c) => Some(c),
+                _ => None,
+            })
+        {
+            return Err(format!(
+                "No facts found for component '{}'",
+                missing.predicate
+            ));
+        }
+
+        let root = PhraseComponent::NonTerminal(target.clone());
+        let bindings = Bindings::new();
+
+        // The generated words form a real list, so the second argument may be
+        // a pattern such as [the, X] or [First|_].
+        let output = Term::parse(var_name);
+        let mut variables = self.goal_variables(std::slice::from_ref(&target));
+        output.collect_variables(&mut variables);
+        let format = |(combination, bindings): (Vec<Term>, Bindings)| {
+            let mut bindings = bindings;
+            let list = Term::List(combination, None);
+            self.unify_terms(&output, &list, &mut bindings)
+                .then(|| self.solution(&variables, &bindings))
+        };
+
+        if let Some(count) = sample {
+            let seed = seed.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(0)
+            });
+            let samples = self.sample_combinations(root, bindings, count, seed);
+            return Ok(samples.into_iter().filter_map(format).collect());
+        }
+
+        // A partial pattern such as [First|_] can yield the same answer for
+        // many combinations; report each distinct answer once.
+        let mut seen = HashSet::new();
+        let mut answers = self
+            .phrase_combinations(vec![root], bindings)
+            .filter_map(format)
+            .filter(|answer| seen.insert(answer.to_string()));
+        let solutions: Vec<Solution> = match max {
+            Some(limit) => answers.by_ref().take(limit).collect(),
+            None => answers.by_ref().collect(),
+        };
+
+        if let Some(limit) = max
+            && answers.next().is_some()
+        {
+            notes.push(format!("// Stopped after {} combinations", limit));
+        }
+
+        Ok(solutions)
+    }
+
+    /// Lazily enumerates pattern components left to right. A component
+    /// `c(A1, ..., An)` is satisfied by a fact `c(Word, A1, ..., An)`, or by a
+    /// rule deriving one: `Word` is emitted and the remaining arguments are
+    /// unified, so bindings such as a tense or number flow into later components.
+    /// A component naming a pattern `c/n` is replaced by each such pattern's
+    /// body in turn, as DCG non-terminals are.
+    pub fn phrase_combinations(
+        &self,
+        components: Vec<PhraseComponent>,
+        bindings: Bindings,
+    ) -> PhraseCombinations<'_> {
+        let goals = components.into_iter().map(|component| (component, 0)).collect();
+        PhraseCombinations {
+            engine: self,
+            frames: vec![PhraseFrame::new(goals, bindings, 0)],
+            words: Vec::new(),
+        }
+    }
+
+    /// The patterns whose head `component` names, in the order they were added.
+    fn patterns_for<'a>(&'a self, component: &'a Fact) -> impl Iterator<Item = &'a Pattern> {
+        self.patterns.iter().filter(move |pattern| {
+            pattern.head.predicate == component.predicate
+                && pattern.head.args.len() == component.args.len()
+        })
+    }
+
+    /// Whether a pattern, fact or rule can satisfy the non-terminal `component`.
+    fn defines_component(&self, component: &Fact) -> bool {
+        let arity = component.args.len() + 1;
+        self.patterns_for(component).next().is_some()
+            || !self
+                .candidate_facts(&component.predicate, arity, None)
+                .is_empty()
+            || self
+                .rule_map
+                .contains_key(&(component.predicate.clone(), arity))
+    }
+
+    /// Steps for `component` from stored facts `c(Word, A1, ..., An)`.
+    fn phrase_fact_steps(&self, component: &PhraseComponent, bindings: &Bindings) -> Vec<PhraseStep> {
+        let PhraseComponent::NonTerminal(component) = component else {
+            return Vec::new();
+        };
+        self.candidate_facts(&component.predicate, component.args.len() + 1, None)
+            .iter()
+            .map(|&idx| &self.facts[idx])
+            .filter_map(|fact| {
+                let bindings = self.unify_with(&component.args, &fact.args[1..], bindings.clone())?;
+                Some(PhraseStep {
+                    words: vec![fact.args[0].clone()],
+                    expansion: Vec::new(),
+                    bindings,
+                })
+            })
+            .collect()
+    }
+
+    /// Steps for `component` other than stored facts: literal words, each
+    /// solution of `{Goal}`, the body of each pattern it names and words
+    /// derived by rules. `depth` is how many patterns it is nested in;
+    /// expansions stop at `max_rule_depth` so recursive patterns terminate.
+    fn phrase_expansions(
+        &self,
+        component: &PhraseComponent,
+        bindings: &Bindings,
+        depth: usize,
+    ) -> Vec<PhraseStep> {
+        let step = |words, expansion, bindings| PhraseStep {
+            words,
+            expansion,
+            bindings,
+        };
+
+        match component {
+            PhraseComponent::Terminal(words) => {
+                vec![step(words.clone(), Vec::new(), bindings.clone())]
+            }
+            PhraseComponent::Goals(goals) => self
+                .solve_conjunction(goals, bindings.clone(), 0)
+                .0
+                .into_iter()
+                .map(|(bindings, _)| step(Vec::new(), Vec::new(), bindings))
+                .collect(),
+            PhraseComponent::NonTerminal(component) => {
+                let mut steps = Vec::new();
+                for pattern in self.patterns_for(component) {
+                    if depth >= self.max_rule_depth {
+                        self.depth_limit_reached.set(true);
+                        break;
+                    }
+                    let id = self.fresh_rename_id();
+                    let head = self.rename_fact(&pattern.head, id);
+                    if let Some(bindings) =
+                        self.unify_with(&component.args, &head.args, bindings.clone())
+                    {
+                        let body = pattern
+                            .components
+                            .iter()
+                            .map(|c| self.rename_component(c, id))
+                            .collect();
+                        steps.push(step(Vec::new(), body, bindings));
+                    }
+                }
+                steps.extend(
+                    self.rule_component_words(component, bindings)
+                        .into_iter()
+                        .map(|(word, bindings)| step(vec![word], Vec::new(), bindings)),
+                );
+                steps
+            }
+        }
+    }
+
+    /// Words a component gets from rules: each solution of `c(Word, A1, ..., An)`
+    /// derived by a rule for `c/n+1`, with its bindings.
+    fn rule_component_words(&self, component: &Fact, bindings: &Bindings) -> Vec<(Term, Bindings)> {
+        if !self
+            .rule_map
+            .contains_key(&(component.predicate.clone(), component.args.len() + 1))
+        {
+            return Vec::new();
+        }
+
+        // `#` keeps the word variable apart from any written one.
+        let word = Term::Variable(format!("Word#{}", self.fresh_rename_id()));
+        let goal = Fact {
+            predicate: component.predicate.clone(),
+            args: std::iter::once(word.clone())
+                .chain(component.args.iter().cloned())
+                .collect(),
+        };
+
+        self.solve_with_rules(&goal, bindings, 0)
+            .into_iter()
+            .map(|(bindings, _)| (self.substitute(&word, &bindings), bindings))
+            .collect()
+    }
+
+    /// Draws up to `count` distinct random combinations of `root` by choosing a
+    /// random step for each component in turn, never expanding the full
+    /// cross product. Gives up after a bounded number of dead ends.
+    fn sample_combinations(
+        &self,
+        root: PhraseComponent,
+        bindings: Bindings,
+        count: usize,
+        seed: u64,
+    ) -> Vec<(Vec<Term>, Bindings)> {
+        let mut rng = SplitMix64(seed);
+        let mut samples: Vec<(Vec<Term>, Bindings)> = Vec::new();
+        let mut seen = HashSet::new();
+        let max_attempts = count.saturating_mul(20).max(100);
+
+        for _ in 0..max_attempts {
+            if samples.len() >= count {
+                break;
+            }
+
+            let mut words = Vec::new();
+            let mut current = bindings.clone();
+            let mut complete = true;
+            // Components still to expand, the next one last.
+            let mut goals = vec![(root.clone(), 0)];
+
+            while let Some((component, depth)) = goals.pop() {
+                let mut candidates = self.phrase_fact_steps(&component, &current);
+                candidates.extend(self.phrase_expansions(&component, &current, depth));
+
+                if candidates.is_empty() {
+                    complete = false;
+                    break;
+                }
+
+                let step = candidates.swap_remove(rng.below(candidates.len()));
+                words.extend(step.words);
+                goals.extend(step.expansion.into_iter().rev().map(|c| (c, depth + 1)));
+                current = step.bindings;
+            }
+
+            if complete && seen.insert(Term::List(words.clone(), None).to_string()) {
+                samples.push((words, current));
+            }
+        }
+
+        samples
+    }
+
+    /// Unifies `args` with the fact at `fact_idx` read in reverse around the
+    /// atom at `arg_idx`: `bear(animal)` read around `animal` is `animal(bear)`.
+    fn unify_reversed(
+        &self,
+        args: &[Term],
+        fact_idx: usize,
+        arg_idx: usize,
+        bindings: Bindings,
+    ) -> Option<Bindings> {
+        let fact = &self.facts[fact_idx];
+        if fact.args.len() != args.len() {
+            return None;
+        }
+
+        let mut reversed_args = vec![Term::Atom(fact.predicate.clone())];
+        for (i, other_arg) in fact.args.iter().enumerate() {
+            if i != arg_idx {
+                reversed_args.push(other_arg.clone());
+            }
+        }
+        self.unify_with(args, &reversed_args, bindings)
+    }
+
+    fn unify(&self, query_args: &[Term], fact_args: &[Term]) -> Option<Bindings> {
+        self.unify_with(query_args, fact_args, Bindings::new())
+    }
+
+    fn unify_with(
+        &self,
+        left: &[Term],
+        right: &[Term],
+        mut bindings: Bindings,
+    ) -> Option<Bindings> {
+        if left.len() != right.len() {
+            return None;
+        }
+
+        for (l_arg, r_arg) in left.iter().zip(right.iter()) {
+            if !self.unify_terms(l_arg, r_arg, &mut bindings) {
+                return None;
+            }
+        }
+
+        Some(bindings)
+    }
+
+    fn unify_terms(&self, left: &Term, right: &Term, bindings: &mut Bindings) -> bool {
+        if left.is_anonymous() || right.is_anonymous() {
+            return true;
+        }
+
+        if let (Term::Variable(l), Term::Variable(r)) = (left, right)
+            && l == r
+        {
+            return true;
+        }
+
+        let left = self.walk(left, bindings);
+        let right = self.walk(right, bindings);
+
+        match (&left, &right) {
+            (Term::Variable(l), Term::Variable(r)) if l == r => true,
+            (Term::Variable(name), other) | (other, Term::Variable(name)) => {
+                if self.occurs_check && self.occurs(name, other, bindings) {
+                    return false;
+                }
+                bindings.insert(name.clone(), other.clone());
+                true
+            }
+            (Term::Compound(l_name, l_args), Term::Compound(r_name, r_args)) => {
+                l_name == r_name
+                    && l_args.len() == r_args.len()
+                    && l_args
+                        .iter()
+                        .zip(r_args.iter())
+                        .all(|(l, r)| self.unify_terms(l, r, bindings))
+            }
+            (Term::List(l_items, l_tail), Term::List(r_items, r_tail)) => self.unify_lists(
+                l_items,
+                l_tail.as_deref(),
+                r_items,
+                r_tail.as_deref(),
+                bindings,
+            ),
+            (l, r) => self.constants_equal(l, r),
+        }
+    }
+
+    /// Unifies the common prefix item by item; whatever one side has left over
+    /// must then unify with the other side's tail.
+    fn unify_lists(
+        &self,
+        l_items: &[Term],
+        l_tail: Option<&Term>,
+        r_items: &[Term],
+        r_tail: Option<&Term>,
+        bindings: &mut Bindings,
+    ) -> bool {
+        let common = l_items.len().min(r_items.len());
+        if !l_items[..common]
+            .iter()
+            .zip(&r_items[..common])
+            .all(|(l, r)| self.unify_terms(l, r, bindings))
+        {
+            return false;
+        }
+
+        let l_rest = &l_items[common..];
+        let r_rest = &r_items[common..];
+
+        match (l_rest.is_empty(), r_rest.is_empty()) {
+            (true, true) => match (l_tail, r_tail) {
+                (None, None) => true,
+                (Some(l), Some(r)) => self.unify_terms(l, r, bindings),
+                (Some(tail), None) | (None, Some(tail)) => {
+                    self.unify_terms(tail, &Term::List(Vec::new(), None), bindings)
+                }
+            },
+            (true, false) => l_tail.is_some_and(|tail| {
+                let rest = Term::list(r_rest.to_vec(), r_tail.cloned());
+                self.unify_terms(tail, &rest, bindings)
+            }),
+            _ => r_tail.is_some_and(|tail| {
+                let rest = Term::list(l_rest.to_vec(), l_tail.cloned());
+                self.unify_terms(&rest, tail, bindings)
+            }),
+        }
+    }
+
+    /// Follows variable bindings until reaching an unbound variable or a non-variable term.
+    fn walk(&self, term: &Term, bindings: &Bindings) -> Term {
+        let mut current = term.clone();
+        while let Term::Variable(name) = &current {
+            match bindings.get(name) {
+                Some(value) => current = value.clone(),
+                None => break,
+            }
+        }
+        current
+    }
+
+    fn occurs(&self, name: &str, term: &Term, bindings: &Bindings) -> bool {
+        self.substitute(term, bindings).contains_variable(name)
+    }
+
+    /// Numbers unify by value so `age(X, 25)` matches a stored `age(john, 25.0)`;
+    /// every other constant must match exactly, including its type.
+    fn constants_equal(&self, a: &Term, b: &Term) -> bool {
+        match (a.as_number(), b.as_number()) {
+            (Some(x), Some(y)) => x == y,
+            _ => a == b,
+        }
+    }
+
+    /// Returns a copy of `rule` whose variables carry fresh names, so they can
+    /// never collide with variables of the query or of another invocation.
+    fn rename_apart(&self, rule: &Rule) -> Rule {
+        let id = self.fresh_rename_id();
+
+        Rule {
+            head: self.rename_fact(&rule.head, id),
+            body: self.rename_goals(&rule.body, id),
+        }
+    }
+
+    fn rename_goals(&self, goals: &[Goal], id: usize) -> Vec<Goal> {
+        goals
+            .iter()
+            .map(|goal| match goal {
+                Goal::Call(fact) => Goal::Call(self.rename_fact(fact, id)),
+                Goal::Cut => Goal::Cut,
+                Goal::Or(alternatives) => Goal::Or(
+                    alternatives
+                        .iter()
+                        .map(|alternative| self.rename_goals(alternative, id))
+                        .collect(),
+                ),
+            })
+            .collect()
+    }
+
+    fn fresh_rename_id(&self) -> usize {
+        let id = self.next_rename_id.get();
+        self.next_rename_id.set(id + 1);
+        id
+    }
+
+    fn rename_component(&self, component: &PhraseComponent, id: usize) -> PhraseComponent {
+        match component {
+            PhraseComponent::NonTerminal(fact) => {
+                PhraseComponent::NonTerminal(self.rename_fact(fact, id))
+            }
+            PhraseComponent::Terminal(words) => {
+                let rename = |name: &str| format!("{}#{}", name, id);
+                PhraseComponent::Terminal(
+                    words.iter().map(|word| word.rename_variables(&rename)).collect(),
+                )
+            }
+            PhraseComponent::Goals(goals) => PhraseComponent::Goals(self.rename_goals(goals, id)),
+        }
+    }
+
+    fn rename_fact(&self, fact: &Fact, id: usize) -> Fact {
+        // `#` cannot appear in a parsed variable name, so renamed variables are
+        // guaranteed not to clash with user-written ones.
+        let rename = |name: &str| format!("{}#{}", name, id);
+
+        Fact {
+            predicate: fact.predicate.clone(),
+            args: fact
+                .args
+                .iter()
+                .map(|arg| arg.rename_variables(&rename))
+                .collect(),
+        }
+    }
+
+    /// Whether calls to `goal`'s predicate are answered from answer tables or
+    /// the bottom-up derivation instead of by running its rules.
+    fn answers_from_tables(&self, goal: &Fact) -> bool {
+        let key = (goal.predicate.clone(), goal.args.len());
+        let derived = self.bottom_up
+            && self
+                .derived
+                .borrow()
+                .as_ref()
+                .is_some_and(|derived| derived.predicates.contains(&key));
+        derived || self.tabled.contains(&key)
+    }
+
+    /// Solves `goal` against rules whose head matches it. Each rule expansion
+    /// goes one level deeper; expansions stop at `max_rule_depth` so left- or
+    /// mutually-recursive rules cannot loop forever.
+    fn solve_with_rules(
+        &self,
+        goal: &Fact,
+        bindings: &Bindings,
+        depth: usize,
+    ) -> Vec<(Bindings, ProofStep)> {
+        if self.bottom_up {
+            let derived = self.derived.borrow();
+            if let Some(derived) = derived.as_ref()
+                && derived
+                    .predicates
+                    .contains(&(goal.predicate.clone(), goal.args.len()))
+            {
+                return derived
+                    .with_predicate(&goal.predicate, goal.args.len())
+                    .iter()
+                    .filter_map(|&idx| {
+                        let fact = &derived.facts[idx];
+                        self.unify_with(&goal.args, &fact.args, bindings.clone())
+                            .map(|bindings| (bindings, ProofStep::Derived(fact.clone())))
+                    })
+                    .collect();
+            }
+        }
+
+        if !self.tabled.is_empty()
+            && self
+                .tabled
+                .contains(&(goal.predicate.clone(), goal.args.len()))
+        {
+            return self.solve_tabled(goal, bindings, depth);
+        }
+        self.resolve_rules(goal, bindings, depth)
+    }
+
+    /// Answers a call to a tabled predicate from its table, evaluating the
+    /// table first unless it is complete. A recursive call made while its own
+    /// table is being evaluated reads the answers found so far instead of
+    /// recursing again; the evaluation repeats until a round adds no answer.
+    fn solve_tabled(
+        &self,
+        goal: &Fact,
+        bindings: &Bindings,
+        depth: usize,
+    ) -> Vec<(Bindings, ProofStep)> {
+        let call = Fact {
+            predicate: goal.predicate.clone(),
+            args: goal
+                .args
+                .iter()
+                .map(|arg| self.substitute(arg, bindings))
+                .collect(),
+        };
+        let key = variant_key(&call);
+
+        let state = self.tables.borrow().get(&key).map(|table| table.state);
+        match state {
+            Some(TableState::Complete) => {}
+            Some(TableState::Evaluating) => {
+                // Calls entered since this one saw unfinished answers, so they
+                // may not complete before it does.
+                let mut stack = self.table_stack.borrow_mut();
+                if let Some(pos) = stack.iter().position(|(k, _)| *k == key) {
+                    for frame in &mut stack[pos + 1..] {
+                        frame.1 = true;
+                    }
+                }
+            }
+            Some(TableState::Incomplete) | None => self.evaluate_table(&key, &call, depth),
+        }
+
+        let answers = self
+            .tables
+            .borrow()
+            .get(&key)
+            .map(|table| table.answers.clone())
+            .unwrap_or_default();
+        answers
+            .iter()
+            .filter_map(|answer| {
+                let id = self.fresh_rename_id();
+                let rename = |name: &str| format!("{}#{}", name, id);
+                let answer: Vec<Term> = answer.iter().map(|t| t.rename_variables(&rename)).collect();
+                self.unify_with(&goal.args, &answer, bindings.clone())
+            })
+            .map(|bindings| (bindings, ProofStep::Tabled(goal.clone())))
+            .collect()
+    }
+
+    /// Runs the rules for `call` until a round adds no answer to any table.
+    fn evaluate_table(&self, key: &str, call: &Fact, depth: usize) {
+        self.tables
+            .borrow_mut()
+            .entry(key.to_string())
+            .or_default()
+            .state = TableState::Evaluating;
+        self.table_stack.borrow_mut().push((key.to_string(), false));
+
+        loop {
+            let added_before = self.table_answers_added.get();
+            let solutions = self.resolve_rules(call, &Bindings::new(), depth);
+
+            let mut tables = self.tables.borrow_mut();
+            let table = tables.entry(key.to_string()).or_default();
+            for (solution, _) in solutions {
+                let answer: Vec<Term> = call
+                    .args
+                    .iter()
+                    .map(|arg| self.substitute(arg, &solution))
+                    .collect();
+                let answer_key = variant_key(&Fact {
+                    predicate: call.predicate.clone(),
+                    args: answer.clone(),
+                });
+                if table.seen.insert(answer_key) {
+                    table.answers.push(answer);
+                    self.table_answers_added
+                        .set(self.table_answers_added.get() + 1);
+                }
+            }
+
+            if self.table_answers_added.get() == added_before
+                || self.search_limit_reached.get().is_some()
+            {
+                break;
+            }
+        }
+
+        let dependent = self
+            .table_stack
+            .borrow_mut()
+            .pop()
+            .is_some_and(|(_, dependent)| dependent);
+        if let Some(table) = self.tables.borrow_mut().get_mut(key) {
+            table.state = if dependent {
+                TableState::Incomplete
+            } else {
+                TableState::Complete
+            };
+        }
+    }
+
+    /// Solves `goal` against its rules, without consulting answer tables.
+    fn resolve_rules(
+        &self,
+        goal: &Fact,
+        bindings: &Bindings,
+        depth: usize,
+    ) -> Vec<(Bindings, ProofStep)> {
+        let mut solutions = Vec::new();
+        let rule_indices = self
+            .rule_map
+            .get(&(goal.predicate.clone(), goal.args.len()))
+            .map(|v| v.as_slice())
+            .unwrap_or(&[]);
+
+        for &rule_idx in rule_indices {
+            let rule = &self.rules[rule_idx];
+            if depth >= self.max_rule_depth {
+                self.depth_limit_reached.set(true);
+                break;
+            }
+            let (rule_solutions, cut) = self.evaluate_rule(rule, &goal.args, bindings, depth);
+            solutions.extend(rule_solutions.into_iter().map(|(bindings, body)| {
+                let step = ProofStep::Rule {
+                    goal: goal.clone(),
+                    rule: rule_idx,
+                    body,
+                };
+                (bindings, step)
+            }));
+            if cut {
+                break;
+            }
+        }
+
+        solutions
+    }
+
+    /// Solves one rule for the query arguments. Also reports whether the body
+    /// executed a cut, in which case no further rules may be tried.
+    fn evaluate_rule(
+        &self,
+        rule: &Rule,
+        query_args: &[Term],
+        bindings: &Bindings,
+        depth: usize,
+    ) -> (Vec<Derivation>, bool) {
+        let rule = &self.rename_apart(rule);
+        let Some(head_bindings) = self.unify_with(query_args, &rule.head.args, bindings.clone())
+        else {
+            return (Vec::new(), false);
+        };
+
+        self.solve_conjunction(&rule.body, head_bindings, depth + 1)
+    }
+
+    /// Every way all of `goals` hold together, solved depth first so a cut can
+    /// discard the alternatives left behind it. The flag is set when a cut ran.
+    fn solve_conjunction(
+        &self,
+        goals: &[Goal],
+        bindings: Bindings,
+        depth: usize,
+    ) -> (Vec<Derivation>, bool) {
+        let Some((goal, rest)) = goals.split_first() else {
+            return (vec![(bindings, Vec::new())], false);
+        };
+
+        // Solves the remaining goals for each candidate, stopping at a cut.
+        let continue_with = |solutions: &mut Vec<Derivation>, candidates: Vec<Derivation>| {
+            for (candidate, proof) in candidates {
+                let (rest_solutions, cut) = self.solve_conjunction(rest, candidate, depth);
+                solutions.extend(rest_solutions.into_iter().map(|(bindings, rest_proof)| {
+                    let mut full_proof = proof.clone();
+                    full_proof.extend(rest_proof);
+                    (bindings, full_proof)
+                }));
+                if cut {
+                    return true;
+                }
+            }
+            false
+        };
+
+        let mut solutions = Vec::new();
+        match goal {
+            Goal::Cut => (self.solve_conjunction(rest, bindings, depth).0, true),
+            Goal::Call(fact) => {
+                let candidates = self
+                    .solve_goal(fact, &bindings, depth)
+                    .into_iter()
+                    .map(|(bindings, step)| (bindings, vec![step]))
+                    .collect();
+                let cut = continue_with(&mut solutions, candidates);
+                (solutions, cut)
+            }
+            Goal::Or(alternatives) => {
+                for alternative in alternatives {
+                    let (candidates, cut) =
+                        self.solve_conjunction(alternative, bindings.clone(), depth);
+                    // A cut inside a disjunction cuts the whole rule.
+                    if continue_with(&mut solutions, candidates) || cut {
+                        return (solutions, true);
+                    }
+                }
+                (solutions, false)
+            }
+        }
+    }
+
+    /// Every way `goal` can hold under `bindings`: built-ins, stored facts (also
+    /// read in reverse, so `animal(X)` finds `bear(animal)`) and rules.
+    fn solve_goal(
+        &self,
+        goal: &Fact,
+        bindings: &Bindings,
+        depth: usize,
+    ) -> Vec<(Bindings, ProofStep)> {
+        if !self.take_step() {
+            return Vec::new();
+        }
+
+        if let Some(solutions) = self
+            .eval_aggregate(goal, bindings, depth)
+            .or_else(|| self.eval_builtin(goal, bindings))
+            .or_else(|| self.eval_atom_builtin(goal, bindings))
+        {
+            return solutions
+                .into_iter()
+                .map(|bindings| (bindings, ProofStep::Builtin(goal.clone())))
+                .collect();
+        }
+
+        let substituted_args: Vec<Term> = goal
+            .args
+            .iter()
+            .map(|arg| self.substitute(arg, bindings))
+            .collect();
+
+        let mut solutions = Vec::new();
+
+        // Forward matching: goal predicate matches fact predicate
+        let candidates =
+            self.candidate_facts(&goal.predicate, goal.args.len(), substituted_args.first());
+        for &idx in candidates {
+            let fact = &self.facts[idx];
+            if let Some(combined) = self.unify_with(&substituted_args, &fact.args, bindings.clone())
+            {
+                solutions.push((combined, ProofStep::Fact(idx)));
+            }
+        }
+
+        // Bidirectional matching: check if goal predicate appears in fact arguments
+        let positions = self
+            .atom_args
+            .get(&goal.predicate)
+            .map(|v| v.as_slice())
+            .unwrap_or(&[]);
+        for &(idx, arg_idx) in positions {
+            if let Some(combined) =
+                self.unify_reversed(&substituted_args, idx, arg_idx, bindings.clone())
+            {
+                solutions.push((combined, ProofStep::Fact(idx)));
+            }
+        }
+
+        solutions.extend(self.solve_with_rules(goal, bindings, depth));
+        solutions
+    }
+
+    /// Appends one line per step of `proof`, with each goal shown as proven
+    /// under the solution's `bindings`.
+    fn describe_proof(
+        &self,
+        proof: &[ProofStep],
+        bindings: &Bindings,
+        indent: usize,
+        lines: &mut Vec<String>,
+    ) {
+        let resolved = |goal: &Fact| Fact {
+            predicate: goal.predicate.clone(),
+            args: goal
+                .args
+                .iter()
+                .map(|arg| self.substitute(arg, bindings))
+                .collect(),
+        };
+        let pad = " ".repeat(indent);
+
+        for step in proof {
+            match step {
+                ProofStep::Fact(idx) => {
+                    let fact = &self.facts[*idx];
+                    lines.push(format!("{}{}.  [fact {}]", pad, fact, fact.stable_id()));
+                }
+                ProofStep::Builtin(goal) => {
+                    lines.push(format!("{}{}  [built-in]", pad, resolved(goal)));
+                }
+                ProofStep::Rule { goal, rule, body } => {
+                    lines.push(format!(
+                        "{}{}  [rule: {}]",
+                        pad,
+                        resolved(goal),
+                        self.rules[*rule]
+                    ));
+                    self.describe_proof(body, bindings, indent + 2, lines);
+                }
+                ProofStep::Tabled(goal) => {
+                    lines.push(format!("{}{}  [tabled]", pad, resolved(goal)));
+                }
+                ProofStep::Derived(fact) => {
+                    lines.push(format!("{}{}.  [derived bottom-up]", pad, fact));
+                }
+            }
+        }
+    }
+
+    /// Variables of `goals` in the order they are first written.
+    fn goal_variables(&self, goals: &[Fact]) -> Vec<String> {
+        let mut variables = Vec::new();
+        for goal in goals {
+            for arg in &goal.args {
+                arg.collect_variables(&mut variables);
+            }
+        }
+        variables
+    }
+
+    /// Formats one answer, showing only the query's own variables in the order
+    /// they were written. Internal rule variables never leak into the output.
+    fn format_answer(&self, variables: &[String], bindings: &Bindings) -> String {
+        self.solution(variables, bindings).to_string()
+    }
+
+    /// The value of every bound variable in `variables`.
+    fn solution(&self, variables: &[String], bindings: &Bindings) -> Solution {
+        let bindings = variables
+            .iter()
+            .filter_map(|var| {
+                let value = self.substitute(&Term::Variable(var.clone()), bindings);
+                match &value {
+                    Term::Variable(name) if name == var => None,
+                    _ => Some((var.clone(), value)),
+                }
+            })
+            .collect();
+        Solution { bindings }
+    }
+}
+
+/// One clause in SWI-Prolog syntax, ending in a full stop. Variables that
+/// occur once are prefixed with `_` so they raise no singleton warning.
+fn swi_clause(head: &Fact, body: &[Goal]) -> String {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    head.args
+        .iter()
+        .for_each(|arg| count_variables(arg, &mut counts));
+    count_goal_variables(body, &mut counts);
+    let rename = |name: &str| {
+        if counts[name] == 1 && !name.starts_with('_') {
+            format!("_{}", name)
+        } else {
+            name.to_string()
+        }
+    };
+
+    let head = swi_goal_text(head, &rename);
+    if body.is_empty() {
+        return format!("{}.", head);
+    }
+    format!("{} :-\n    {}.", head, swi_goals(body, &rename).join(",\n    "))
+}
+
+fn swi_goals(goals: &[Goal], rename: &impl Fn(&str) -> String) -> Vec<String> {
+    goals
+        .iter()
+        .map(|goal| match goal {
+            Goal::Call(fact) => swi_goal_text(fact, rename),
+            Goal::Cut => "!".to_string(),
+            Goal::Or(alternatives) => {
+                let alternatives: Vec<String> = alternatives
+                    .iter()
+                    .map(|alternative| swi_goals(alternative, rename).join(", "))
+                    .collect();
+                format!("( {} )", alternatives.join(" ; "))
+            }
+        })
+        .collect()
+}
+
+/// A fact or goal with its predicate quoted when needed and no parentheses
+/// for arity 0, which SWI-Prolog rejects.
+fn swi_goal_text(fact: &Fact, rename: &impl Fn(&str) -> String) -> String {
+    let renamed = Fact {
+        predicate: fact.predicate.clone(),
+        args: fact
+            .args
+            .iter()
+            .map(|arg| arg.rename_variables(rename))
+            .collect(),
+    };
+    if COMPARISON_OPERATORS.contains(&renamed.predicate.as_str()) && renamed.args.len() == 2 {
+        return renamed.to_string();
+    }
+
+    let name = Term::Atom(renamed.predicate).to_string();
+    if renamed.args.is_empty() {
+        return name;
+    }
+    let args: Vec<String> = renamed.args.iter().map(|arg| arg.to_string()).collect();
+    format!("{}({})", name, args.join(", "))
+}
+
+fn count_variables(term: &Term, counts: &mut HashMap<String, usize>) {
+    match term {
+        Term::Variable(name) if name != "_" => *counts.entry(name.clone()).or_default() += 1,
+        Term::Compound(_, args) => args.iter().for_each(|arg| count_variables(arg, counts)),
+        Term::List(items, tail) => items
+            .iter()
+            .chain(tail.as_deref())
+            .for_each(|item| count_variables(item, counts)),
+        _ => {}
+    }
+}
+
+fn count_goal_variables(goals: &[Goal], counts: &mut HashMap<String, usize>) {
+    for goal in goals {
+        match goal {
+            Goal::Call(fact) => fact.args.iter().for_each(|arg| count_variables(arg, counts)),
+            Goal::Cut => {}
+            Goal::Or(alternatives) => alternatives
+                .iter()
+                .for_each(|alternative| count_goal_variables(alternative, counts)),
+        }
+    }
+}
+
+/// Appends the variables of a rule body or query, in order of first appearance.
+fn collect_goal_variables(goals: &[Goal], out: &mut Vec<String>) {
+    for goal in goals {
+        match goal {
+            Goal::Call(fact) => fact.args.iter().for_each(|arg| arg.collect_variables(out)),
+            Goal::Cut => {}
+            Goal::Or(alternatives) => alternatives
+                .iter()
+                .for_each(|alternative| collect_goal_variables(alternative, out)),
+        }
+    }
+}
+
+/// Sum of numeric terms, kept an integer unless one of them is a float.
+fn sum_numbers(values: &[Term]) -> Term {
+    let integers: Option<Vec<i64>> = values
+        .iter()
+        .map(|value| match value {
+            Term::Integer(value) => Some(*value),
+            _ => None,
+        })
+        .collect();
+    match integers {
+        Some(integers) => Term::Integer(integers.iter().sum()),
+        None => Term::Float(values.iter().filter_map(Term::as_number).sum()),
+    }
+}
+
+/// Standard order of terms: variables, numbers, atoms, strings, then compound
+/// terms and lists.
+fn standard_order(a: &Term, b: &Term) -> Ordering {
+    fn rank(term: &Term) -> u8 {
+        match term {
+            Term::Variable(_) => 0,
+            Term::Integer(_) | Term::Float(_) => 1,
+            Term::Atom(_) => 2,
+            Term::Str(_) => 3,
+            Term::Compound(..) | Term::List(..) => 4,
+        }
+    }
+
+    match (a.as_number(), b.as_number()) {
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        _ => rank(a)
+            .cmp(&rank(b))
+            .then_with(|| a.to_string().cmp(&b.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simple_query() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("animal(bear).\nanimal(deer).\nanimal(owl).");
+
+        let results = engine.query("animal(X)").unwrap();
+        assert_eq!(results.len(), 3);
+    }
+
+    #[test]
+    fn test_pattern_generation() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("animal(bear).\nanimal(deer).\naction(chase).\naction(run).");
+        engine.add_pattern("sentence --> animal, action").unwrap();
+
+        let results = engine.query("phrase(sentence, X)").unwrap();
+        assert_eq!(results.len(), 4);
+    }
+
+    #[test]
+    fn test_bounded_and_sampled_phrase_generation() {
+        let mut engine = QueryEngine::new();
+        let facts: Vec<String> = (0..50)
+            .map(|i| format!("adj(a{}).\nnoun(n{}).\nverb(v{}).", i, i, i))
+            .collect();
+        engine.load_facts_from_output(&facts.join("\n"));
+        engine.add_pattern("sentence --> adj, noun, verb").unwrap();
+
+        let results = engine.query("phrase(sentence, X, [max(3)])").unwrap();
+        assert_eq!(
+            results,
+            vec![
+                "X = [a0, n0, v0]",
+                "X = [a0, n0, v1]",
+                "X = [a0, n0, v2]",
+                "// Stopped after 3 combinations"
+            ]
+        );
+
+        engine.set_phrase_limit(Some(10));
+        assert_eq!(engine.query("phrase(sentence, X)").unwrap().len(), 11);
+
+        let first = engine
+            .query("phrase(sentence, X, [sample(5), seed(7)])")
+            .unwrap();
+        let second = engine
+            .query("phrase(sentence, X, [sample(5), seed(7)])")
+            .unwrap();
+        assert_eq!(first.len(), 5);
+        assert_eq!(first, second);
+        assert_eq!(first.iter().collect::<HashSet<_>>().len(), 5);
+
+        assert!(engine.query("phrase(sentence, X, [bogus(1)])").is_err());
+    }
+
+    #[test]
+    fn test_pattern_component_arguments() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "np(john).\nnp(mary).\nvp(runs, present).\nvp(ran, past).\nvp(will_run, future).",
+        );
+        engine
+            .add_pattern("sentence(Tense) --> np, vp(Tense)")
+            .unwrap();
+
+        let results = engine.query("phrase(sentence(past), X)").unwrap();
+        assert_eq!(results, vec!["X = [john, ran]", "X = [mary, ran]"]);
+
+        let results = engine.query("phrase(sentence(T), X)").unwrap();
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[0], "T = present, X = [john, runs]");
+
+        // Shared variables force agreement between components.
+        engine.load_facts_from_output(
+            "det(a, singular).\ndet(some, plural).\nnoun(cat, singular).\nnoun(cats, plural).",
+        );
+        engine
+            .add_pattern("noun_phrase(N) --> det(N), noun(N)")
+            .unwrap();
+        let results = engine.query("phrase(noun_phrase(N), X)").unwrap();
+        assert_eq!(
+            results,
+            vec!["N = singular, X = [a, cat]", "N = plural, X = [some, cats]"]
+        );
+    }
+
+    #[test]
+    fn test_phrase_grammar_nesting() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "det(the).\nnoun(cat).\nnoun(dog).\nname(rex).\nverb(sees).\nowns(rex, dog).",
+        );
+        engine.add_pattern("np --> det, noun").unwrap();
+        engine.add_pattern("np --> name").unwrap();
+        engine.add_pattern("sentence --> np, verb, np").unwrap();
+
+        let results = engine.query("phrase(sentence, X)").unwrap();
+        assert_eq!(results.len(), 9);
+        assert_eq!(results[0], "X = [the, cat, sees, the, cat]");
+        assert_eq!(results[8], "X = [rex, sees, rex]");
+
+        // Goals bind variables through facts of any arity; terminals emit them.
+        engine
+            .add_pattern("claim --> {owns(O, T)}, [O, owns, the, T]")
+            .unwrap();
+        assert_eq!(
+            engine.query("phrase(claim, X)").unwrap(),
+            vec!["X = [rex, owns, the, dog]"]
+        );
+
+        // Recursive patterns generate lazily.
+        engine.add_pattern("adjs --> []").unwrap();
+        engine.add_pattern("adjs --> [big], adjs").unwrap();
+        assert_eq!(
+            engine.query("phrase(adjs, X, [max(3)])").unwrap(),
+            vec![
+                "X = []",
+                "X = [big]",
+                "X = [big, big]",
+                "// Stopped after 3 combinations"
+            ]
+        );
+        assert!(engine.add_pattern("bad --> [a|b").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_facts_are_consolidated() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "// FROM: cat is a mammal.\nis_a(cat, mammal).\n\n// FROM: a cat is a mammal.\nis_a(cat, mammal).\nis_a(dog, mammal).",
+        );
+
+        let results = engine.query("is_a(X, mammal)").unwrap();
+        assert_eq!(results.len(), 2);
+
+        let idx = engine.fact_lookup["is_a(cat, mammal)"];
+        let occurrences = &engine.occurrences[idx];
+        assert_eq!(occurrences.count, 2);
+        assert_eq!(
+            occurrences.sources,
+            vec!["cat is a mammal.", "a cat is a mammal."]
+        );
+
+        let results = engine.query("fact_count(is_a(cat, mammal), N)").unwrap();
+        assert_eq!(results, vec!["N = 2"]);
+
+        let results = engine.query("fact_count(is_a(X, mammal), 1)").unwrap();
+        assert_eq!(results, vec!["X = dog"]);
+    }
+
+    #[test]
+    fn test_stable_fact_ids() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("is_a(cat, mammal).\nis_a(dog, mammal).");
+        let first: Vec<String> = engine.facts().iter().map(Fact::stable_id).collect();
+
+        // Same facts in a different order and spelling keep their IDs.
+        engine.load_facts_from_output("is_a(dog,mammal).\nis_a( cat , mammal ).");
+        let second: Vec<String> = engine.facts().iter().map(Fact::stable_id).collect();
+
+        assert_eq!(first[0], second[1]);
+        assert_eq!(first[1], second[0]);
+        assert_ne!(first[0], first[1]);
+        assert_eq!(first[0].len(), 13);
+    }
+
+    #[test]
+    fn test_typed_constants() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "age(john, 25).\nage(mary, 17.5).\nage(bob, 030).\nname(john, \"John Smith\").",
+        );
+
+        let fact = engine
+            .parse_fact_public("name(john, \"John Smith\")")
+            .unwrap();
+        assert_eq!(fact.args[1], Term::Str("John Smith".to_string()));
+        assert_eq!(
+            engine.parse_fact_public("age(john, 25)").unwrap().args[1],
+            Term::Integer(25)
+        );
+
+        let results = engine.query("age(X, A), A >= 18").unwrap();
+        assert_eq!(results, vec!["X = john, A = 25", "X = bob, A = 30"]);
+
+        let results = engine.query("age(X, 30.0)").unwrap();
+        assert_eq!(results, vec!["X = bob"]);
+
+        let results = engine.query("name(X, \"John Smith\")").unwrap();
+        assert_eq!(results, vec!["X = john"]);
+
+        engine.add_rule("adult(X) :- age(X, A), A >= 18").unwrap();
+        let results = engine.query("adult(X)").unwrap();
+        assert_eq!(results, vec!["X = john", "X = bob"]);
+    }
+
+    #[test]
+    fn test_rule_variables_are_renamed_apart() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("parent(tom, bob).\nparent(bob, ann).\nparent(bob, liz).");
+        engine
+            .add_rule("grandparent(X, Z) :- parent(X, Y), parent(Y, Z)")
+            .unwrap();
+
+        // The query's Y is unrelated to the rule's intermediate Y.
+        let results = engine.query("grandparent(X, Y)").unwrap();
+        assert_eq!(results, vec!["X = tom, Y = ann", "X = tom, Y = liz"]);
+
+        let results = engine.query("grandparent(Z, X)").unwrap();
+        assert_eq!(results, vec!["Z = tom, X = ann", "Z = tom, X = liz"]);
+    }
+
+    #[test]
+    fn test_findall_bagof_setof() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "age(tom, 30).\nage(ann, 12).\nage(bob, 45).\nlikes(tom, fish).\nlikes(bob, fish).",
+        );
+        engine
+            .add_rule("adults(L) :- findall(P, (age(P, A), A >= 18), L)")
+            .unwrap();
+
+        assert_eq!(
+            engine.query("findall(X, age(X, _), L)").unwrap(),
+            vec!["L = [tom, ann, bob]"]
+        );
+        assert_eq!(engine.query("adults(L)").unwrap(), vec!["L = [tom, bob]"]);
+        assert_eq!(
+            engine.query("setof(A, age(_, A), L)").unwrap(),
+            vec!["L = [12, 30, 45]"]
+        );
+        assert_eq!(
+            engine.query("setof(X, likes(X, fish), L)").unwrap(),
+            vec!["L = [bob, tom]"]
+        );
+        assert_eq!(
+            engine.query("findall(X, likes(X, meat), L)").unwrap(),
+            vec!["L = []"]
+        );
+        assert!(
+            engine
+                .query("bagof(X, likes(X, meat), L)")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_aggregate_all() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "animal(bear).\nanimal(deer).\nanimal(owl).\nage(tom, 30).\nage(ann, 12).\nweight(owl, 1.5).\nweight(deer, 80).",
+        );
+        engine
+            .add_rule("total_age(S) :- aggregate_all(sum(A), age(_, A), S)")
+            .unwrap();
+
+        assert_eq!(
+            engine.query("aggregate_all(count, animal(X), N)").unwrap(),
+            vec!["N = 3"]
+        );
+        assert_eq!(
+            engine.query("aggregate_all(count, animal(rock), N)").unwrap(),
+            vec!["N = 0"]
+        );
+        assert_eq!(engine.query("total_age(S)").unwrap(), vec!["S = 42"]);
+        assert_eq!(
+            engine.query("aggregate_all(sum(W), weight(_, W), S)").unwrap(),
+            vec!["S = 81.5"]
+        );
+        assert_eq!(
+            engine.query("aggregate_all(sum(X), likes(X, fish), S)").unwrap(),
+            vec!["S = 0"]
+        );
+        assert!(
+            engine
+                .query("aggregate_all(sum(X), animal(X), S)")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_negative_facts_and_contradictions() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "animal(bear).\nanimal(rock).\n-animal(rock).\nnot_animal(tree).\nbird(penguin).\nflies(eagle).",
+        );
+        engine.add_rule("flies(X) :- bird(X)").unwrap();
+        engine.add_rule("-flies(X) :- bird(X), X = penguin").unwrap();
+
+        assert_eq!(
+            engine.query("not_animal(X)").unwrap(),
+            vec!["X = rock", "X = tree"]
+        );
+        assert_eq!(engine.query("-animal(X)").unwrap(), vec!["X = rock", "X = tree"]);
+
+        let contradictions: Vec<String> = engine
+            .contradictions()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(
+            contradictions,
+            vec![
+                "animal(rock) (stored) contradicts not_animal(rock) (stored)",
+                "flies(penguin) (derived) contradicts not_flies(penguin) (derived)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assert_and_retract() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("animal(bear).\nanimal(deer).");
+
+        assert_eq!(
+            engine.update("assert(animal(owl)).").unwrap().unwrap(),
+            "Fact asserted: animal(owl)."
+        );
+        engine.update("asserta(animal(cat))").unwrap().unwrap();
+        assert_eq!(
+            engine.query("animal(X)").unwrap(),
+            vec!["X = cat", "X = bear", "X = deer", "X = owl"]
+        );
+
+        assert_eq!(
+            engine.update("retract(animal(b))").unwrap().unwrap(),
+            "No fact to retract: animal(b)."
+        );
+        assert_eq!(
+            engine.update("retract(animal(X))").unwrap().unwrap(),
+            "Fact retracted: animal(cat)."
+        );
+        assert_eq!(
+            engine.query("animal(X)").unwrap(),
+            vec!["X = bear", "X = deer", "X = owl"]
+        );
+
+        assert!(engine.update("animal(X)").is_none());
+        assert!(engine.update("assert(X)").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_assert_and_retract_survive_reloads() {
+        let mut engine = QueryEngine::new();
+        engine.consult_source("animal(fox).").unwrap();
+        engine.load_facts_from_output("animal(bear).\nanimal(deer).");
+
+        engine.update("asserta(animal(owl))").unwrap().unwrap();
+        engine.update("retract(animal(fox))").unwrap().unwrap();
+        engine.update("retract(animal(deer))").unwrap().unwrap();
+        assert_eq!(engine.query("animal(X)").unwrap(), vec!["X = owl", "X = bear"]);
+
+        // Neither the consulted fact nor the output fact comes back, and the
+        // asserted fact is kept in front
+        engine.load_facts_from_output("animal(bear).\nanimal(deer).\nanimal(cat).");
+        assert_eq!(
+            engine.query("animal(X)").unwrap(),
+            vec!["X = owl", "X = bear", "X = cat"]
+        );
+    }
+
+    #[test]
+    fn test_cut_prunes_backtracking() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "has_feathers(tweety).\nhas_feathers(polly).\nscore(tom, 70).\nscore(ann, 30).",
+        );
+        engine
+            .add_rule("first_bird(X) :- has_feathers(X), !.")
+            .unwrap();
+        engine
+            .add_rule("grade(S, pass) :- score(S, N), N >= 50, !")
+            .unwrap();
+        engine.add_rule("grade(S, fail) :- score(S, _)").unwrap();
+
+        assert_eq!(engine.query("first_bird(X)").unwrap(), vec!["X = tweety"]);
+        assert_eq!(engine.query("grade(tom, G)").unwrap(), vec!["G = pass"]);
+        assert_eq!(engine.query("grade(ann, G)").unwrap(), vec!["G = fail"]);
+        assert_eq!(
+            engine.query("findall(X, first_bird(X), L)").unwrap(),
+            vec!["L = [tweety]"]
+        );
+    }
+
+    #[test]
+    fn test_query_with_trace() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("parent(tom, bob).\nparent(bob, ann).\nage(ann, 12).");
+        engine.add_rule("ancestor(X, Y) :- parent(X, Y)").unwrap();
+        engine
+            .add_rule("ancestor(X, Y) :- parent(X, Z), ancestor(Z, Y)")
+            .unwrap();
+
+        let explanations = engine.query_with_trace("ancestor(tom, ann)").unwrap();
+        assert_eq!(explanations.len(), 1);
+        assert_eq!(explanations[0].answer, "true.");
+        let parent_id = engine.facts()[0].stable_id();
+        let steps: Vec<&str> = explanations[0].steps.iter().map(String::as_str).collect();
+        assert_eq!(
+            steps[0],
+            "ancestor(tom, ann)  [rule: ancestor(X, Y) :- parent(X, Z), ancestor(Z, Y)]"
+        );
+        assert_eq!(
+            steps[1],
+            format!("  parent(tom, bob).  [fact {}]", parent_id)
+        );
+        assert_eq!(
+            steps[2],
+            "  ancestor(bob, ann)  [rule: ancestor(X, Y) :- parent(X, Y)]"
+        );
+        assert!(steps[3].starts_with("    parent(bob, ann).  [fact "));
+
+        let explanations = engine.query_with_trace("age(P, A), A < 18").unwrap();
+        assert_eq!(explanations[0].answer, "P = ann, A = 12");
+        assert_eq!(explanations[0].steps[1], "12 < 18  [built-in]");
+
+        assert!(engine.query_with_trace("phrase(s, X)").is_err());
+    }
+
+    #[test]
+    fn test_disjunction_in_rule_bodies() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("cat(tom).\ndog(rex).\nfish(nemo).\ntame(tom).\ntame(nemo).");
+        engine.add_rule("pet(X) :- cat(X) ; dog(X)").unwrap();
+        engine
+            .add_rule("house_pet(X) :- (cat(X) ; fish(X)), tame(X)")
+            .unwrap();
+
+        assert_eq!(engine.query("pet(X)").unwrap(), vec!["X = tom", "X = rex"]);
+        assert_eq!(
+            engine.query("house_pet(X)").unwrap(),
+            vec!["X = tom", "X = nemo"]
+        );
+        assert!(engine.add_rule("bad(X) :- ; cat(X)").is_err());
+    }
+
+    #[test]
+    fn test_recursive_rules() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("parent(tom, bob).\nparent(bob, ann).\nparent(ann, joe).");
+        engine.add_rule("ancestor(X, Y) :- parent(X, Y)").unwrap();
+        engine
+            .add_rule("ancestor(X, Y) :- parent(X, Z), ancestor(Z, Y)")
+            .unwrap();
+
+        let results = engine.query("ancestor(tom, Y)").unwrap();
+        assert_eq!(results, vec!["Y = bob", "Y = ann", "Y = joe"]);
+
+        // Left recursion terminates at the depth limit and says so.
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("edge(a, b).\nedge(b, c).");
+        engine.set_max_rule_depth(5);
+        engine
+            .add_rule("path(X, Y) :- path(X, Z), edge(Z, Y)")
+            .unwrap();
+        engine.add_rule("path(X, Y) :- edge(X, Y)").unwrap();
+
+        let results = engine.query("path(a, Y)").unwrap();
+        assert!(results.contains(&"Y = b".to_string()));
+        assert!(results.contains(&"Y = c".to_string()));
+        assert!(
+            results
+                .last()
+                .unwrap()
+                .starts_with("// Rule depth limit of 5 reached")
+        );
+    }
+
+    #[test]
+    fn test_answers_show_only_query_variables_in_written_order() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "likes(A, icecream).\nparent(tom, bob).\nage(bob, 7).\nowns(bob, pet(rex, dog)).",
+        );
+
+        assert_eq!(engine.query("likes(bob, W)").unwrap(), vec!["W = icecream"]);
+        assert_eq!(
+            engine.query("parent(Z, C), age(C, A)").unwrap(),
+            vec!["Z = tom, C = bob, A = 7"]
+        );
+        assert_eq!(
+            engine.query("owns(bob, P)").unwrap(),
+            vec!["P = pet(rex, dog)"]
+        );
+        assert_eq!(engine.query("parent(tom, bob)").unwrap(), vec!["true."]);
+
+        engine.add_rule("older(X) :- age(X, A), A > 5").unwrap();
+        assert_eq!(engine.query("older(Who)").unwrap(), vec!["Who = bob"]);
+    }
+
+    #[test]
+    fn test_compound_terms() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("likes(john, food(pizza)).\nlikes(mary, drink(tea)).");
+
+        let results = engine.query("likes(X, food(Y))").unwrap();
+        assert_eq!(results, vec!["X = john, Y = pizza"]);
+    }
+
+    #[test]
+    fn test_nested_terms_and_lists() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "likes(john, food(pizza)).\nroute(a, [b, c, d]).\nroute(x, []).\ntags(post, [t(a), t(\"b, c\")]).",
+        );
+
+        assert_eq!(
+            engine.query("likes(john, food(X))").unwrap(),
+            vec!["X = pizza"]
+        );
+        assert_eq!(engine.query("route(a, [b, X, d])").unwrap(), vec!["X = c"]);
+        assert_eq!(engine.query("route(X, [])").unwrap(), vec!["X = x"]);
+        assert!(engine.query("route(a, [b, c])").unwrap().is_empty());
+        assert_eq!(
+            engine.query("tags(post, [A, t(B)])").unwrap(),
+            vec!["A = t(a), B = \"b, c\""]
+        );
+        assert_eq!(engine.query("route(a, L)").unwrap(), vec!["L = [b, c, d]"]);
+    }
+
+    #[test]
+    fn test_list_destructuring() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("route(a, [b, c, d]).\ndet(the).\nnoun(cat).\nnoun(dog).");
+        engine
+            .add_rule("first_stop(R, S) :- route(R, [S|_])")
+            .unwrap();
+        engine.add_rule("last_of(L, X) :- L = [X]").unwrap();
+        engine
+            .add_rule("last_of(L, X) :- L = [_|T], last_of(T, X)")
+            .unwrap();
+        engine.add_pattern("np --> det, noun").unwrap();
+
+        assert_eq!(
+            engine.query("route(a, [H|T])").unwrap(),
+            vec!["H = b, T = [c, d]"]
+        );
+        assert_eq!(
+            engine.query("route(a, [_, _, _|T])").unwrap(),
+            vec!["T = []"]
+        );
+        assert!(engine.query("route(a, [_, _, _, _|_])").unwrap().is_empty());
+        assert_eq!(engine.query("first_stop(a, S)").unwrap(), vec!["S = b"]);
+        assert_eq!(
+            engine.query("last_of([x, y, z], X)").unwrap(),
+            vec!["X = z"]
+        );
+        assert_eq!(
+            engine.query("[a|T] = [a, b, c]").unwrap(),
+            vec!["T = [b, c]"]
+        );
+
+        assert_eq!(
+            engine.query("phrase(np, [the, N])").unwrap(),
+            vec!["N = cat", "N = dog"]
+        );
+        assert_eq!(
+            engine.query("phrase(np, [First|_])").unwrap(),
+            vec!["First = the"]
+        );
+    }
+
+    #[test]
+    fn test_occurs_check() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("same(A, A).");
+
+        // Without the occurs check both queries build the cyclic term X = f(X).
+        assert_eq!(engine.query("X = f(X)").unwrap(), vec!["X = f(X)"]);
+        assert_eq!(engine.query("same(X, f(X))").unwrap().len(), 1);
+        assert_eq!(engine.query("f(X, Y) = f(Y, g(X))").unwrap().len(), 1);
+
+        engine.set_occurs_check(true);
+        assert!(engine.query("X = f(X)").unwrap().is_empty());
+        assert!(engine.query("same(X, f(X))").unwrap().is_empty());
+        assert!(engine.query("f(X, Y) = f(Y, g(X))").unwrap().is_empty());
+
+        // Non-cyclic bindings are unaffected.
+        assert_eq!(engine.query("X = f(Y)").unwrap(), vec!["X = f(Y)"]);
+        assert_eq!(engine.query("same(X, f(a))").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_query_limits() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("n(a).\nn(b).\nn(c).");
+
+        engine.set_max_solutions(Some(2));
+        assert_eq!(
+            engine.query("n(X), n(Y)").unwrap()[2..],
+            ["// Showing the first 2 solutions"]
+        );
+        engine.set_max_solutions(None);
+
+        // A rule that keeps generating new goals is stopped by the step budget.
+        engine.set_max_rule_depth(10_000);
+        engine.add_rule("loop(X) :- n(X), loop(X)").unwrap();
+        engine.set_max_steps(Some(100));
+        let results = engine.query("loop(X)").unwrap();
+        assert_eq!(
+            results,
+            vec!["// Step limit of 100 reached; results may be incomplete"]
+        );
+
+        // The budget is per query, not per engine.
+        assert_eq!(
+            engine.query("n(X)").unwrap(),
+            vec!["X = a", "X = b", "X = c"]
+        );
+
+        engine.set_max_steps(None);
+        engine.set_timeout(Some(Duration::ZERO));
+        assert_eq!(
+            engine.query("loop(X)").unwrap(),
+            vec!["// Query timed out after 0 ms; results may be incomplete"]
+        );
+    }
+
+    #[test]
+    fn test_query_iter_streams_solutions() {
+        let mut engine = QueryEngine::new();
+        let facts: Vec<String> = (0..10_000).map(|i| format!("n(v{}).", i)).collect();
+        engine.load_facts_from_output(&facts.join("\n"));
+
+        let first: Vec<Solution> = engine.query_iter("n(X)").unwrap().take(2).collect();
+        assert_eq!(
+            first,
+            vec![
+                Solution {
+                    bindings: vec![("X".to_string(), Term::Atom("v0".to_string()))]
+                },
+                Solution {
+                    bindings: vec![("X".to_string(), Term::Atom("v1".to_string()))]
+                },
+            ]
+        );
+
+        let mut pairs = engine.query_iter("n(X), n(X)").unwrap();
+        assert_eq!(pairs.next().unwrap().to_string(), "X = v0");
+        assert_eq!(engine.query_iter("n(v3)").unwrap().next().unwrap().to_string(), "true.");
+        assert!(engine.query_iter("phrase(s, X)").is_err());
+    }
+
+    #[test]
+    fn test_query_iter_streams_rule_answers() {
+        let mut engine = QueryEngine::new();
+        let edges: Vec<String> = (0..40)
+            .map(|i| format!("edge(n{}, n{}).", i, i + 1))
+            .collect();
+        engine.load_facts_from_output(&edges.join("\n"));
+        engine.add_rule("path(X, Y) :- edge(X, Y)").unwrap();
+        engine
+            .add_rule("path(X, Z) :- edge(X, Y), path(Y, Z)")
+            .unwrap();
+
+        let first = engine.query_iter("path(n0, X)").unwrap().next().unwrap();
+        assert_eq!(first.to_string(), "X = n1");
+        let first_steps = engine.steps_taken.get();
+        assert!(first_steps < 5, "first answer took {} steps", first_steps);
+
+        assert_eq!(engine.query_iter("path(n0, X)").unwrap().count(), 40);
+        assert!(engine.steps_taken.get() > 10 * first_steps);
+    }
+
+    #[test]
+    fn test_query_bindings() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("age(ann, 31).\nage(bob, 17).\nanimal(bear).");
+        engine.add_pattern("greeting --> animal").unwrap();
+
+        let adults = engine.query_bindings("age(X, A), A >= 18").unwrap();
+        assert_eq!(adults.len(), 1);
+        assert_eq!(adults[0]["X"], Term::Atom("ann".to_string()));
+        assert_eq!(adults[0]["A"], Term::Integer(31));
+
+        let phrases = engine.query_bindings("phrase(greeting, P)").unwrap();
+        assert_eq!(
+            phrases[0]["P"],
+            Term::List(vec![Term::Atom("bear".to_string())], None)
+        );
+
+        assert_eq!(engine.query_bindings("animal(bear)").unwrap(), vec![BTreeMap::new()]);
+    }
+
+    #[test]
+    fn test_fact_indexing() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "score(1, low).\nscore(2, high).\nscore(ann).\nbear(animal).\nscore(_, any).",
+        );
+
+        // `1.0` unifies with `1`, and `score(_, any)` matches every first argument.
+        assert_eq!(engine.query("score(1.0, S)").unwrap(), vec!["S = low", "S = any"]);
+        assert_eq!(engine.query("score(3, S)").unwrap(), vec!["S = any"]);
+        assert_eq!(engine.query("score(X)").unwrap(), vec!["X = ann"]);
+        assert_eq!(engine.query("animal(X)").unwrap(), vec!["X = bear"]);
+
+        engine.add_rule("big(X) :- score(X, high)").unwrap();
+        engine.add_rule("big(X, Y) :- score(X, Y)").unwrap();
+        assert_eq!(engine.query("big(X)").unwrap(), vec!["X = 2"]);
+    }
+
+    #[test]
+    fn test_output_directives_replace_previous_ones() {
+        let mut engine = QueryEngine::new();
+        engine.add_rule("pet(X) :- cat(X)").unwrap();
+
+        let output = "// DIRECTIVE\nmortal(X) :- cat(X).\n// DIRECTIVE\ngreeting --> cat\ncat(tom).";
+        engine.load_facts_from_output(output);
+        engine.load_facts_from_output(output);
+        assert_eq!(engine.query("mortal(X)").unwrap(), vec!["X = tom"]);
+        assert_eq!(engine.query("phrase(greeting, P)").unwrap(), vec!["P = [tom]"]);
+
+        engine.load_facts_from_output("cat(tom).");
+        assert!(engine.query("mortal(X)").unwrap().is_empty());
+        assert_eq!(engine.query("pet(X)").unwrap(), vec!["X = tom"]);
+    }
+
+    #[test]
+    fn test_consult_merges_prolog_source() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("// DIRECTIVE\nmortal(X) :- human(X).\nhuman(socrates).");
+
+        let source = "% family library\n\
+                      :- dynamic parent/2.\n\
+                      :- table ancestor/2.\n\
+                      parent(tom, bob). parent(bob, 'Ann Lee').\n\
+                      /* transitive\n   closure */\n\
+                      ancestor(X, Y) :-\n    parent(X, Y).\n\
+                      ancestor(X, Y) :-\n    parent(X, Z),\n    ancestor(Z, Y).\n";
+        assert_eq!(engine.consult_source(source), Ok(5));
+        assert_eq!(
+            engine.query("ancestor(tom, X)").unwrap(),
+            vec!["X = bob", "X = 'Ann Lee'"]
+        );
+
+        // Reloading the output keeps the consulted clauses and replaces its own.
+        engine.load_facts_from_output("human(plato).");
+        assert!(engine.query("mortal(X)").unwrap().is_empty());
+        assert_eq!(engine.query("parent(X, bob)").unwrap(), vec!["X = tom"]);
+        assert_eq!(engine.query("ancestor(bob, X)").unwrap(), vec!["X = 'Ann Lee'"]);
+
+        assert_eq!(
+            engine.consult_source("likes(a, b).\nbroken.\n"),
+            Err("Line 2: Invalid fact: broken".to_string())
+        );
+    }
+
+    #[test]
+    fn test_output_directives() {
+        let mut engine = QueryEngine::new();
+        let errors = engine.load_facts_from_output(
+            "// DIRECTIVE\n:- dynamic cat/1.\n// DIRECTIVE\n:- discontiguous cat/1.\n:- ensure_loaded(lib).\ncat(tom).",
+        );
+        assert_eq!(errors, vec!["Unsupported directive: :- ensure_loaded(lib)."]);
+        assert_eq!(engine.query("cat(X)").unwrap(), vec!["X = tom"]);
+    }
+
+    #[test]
+    fn test_export_swi_prolog() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("likes(john, 'New York').\nis_a(cat, animal).\nlikes(mary, tea).");
+        engine.add_rule("happy(X) :- likes(X, Y), !").unwrap();
+        engine.add_rule("path(X, Y) :- is_a(X, Y) ; (is_a(X, Z), path(Z, Y))").unwrap();
+        engine.table_predicate("path", 2);
+
+        assert_eq!(
+            engine.export_swi_prolog(),
+            "% Exported from Simple Prolog\n\n\
+             :- dynamic likes/2.\n\
+             :- discontiguous likes/2.\n\
+             :- dynamic is_a/2.\n\
+             :- discontiguous is_a/2.\n\
+             :- dynamic happy/1.\n\
+             :- discontiguous happy/1.\n\
+             :- table path/2.\n\
+             :- discontiguous path/2.\n\
+             \n\
+             likes(john, 'New York').\n\
+             likes(mary, tea).\n\
+             \n\
+             is_a(cat, animal).\n\
+             \n\
+             happy(X) :-\n    likes(X, _Y),\n    !.\n\
+             \n\
+             path(X, Y) :-\n    ( is_a(X, Y) ; is_a(X, Z), path(Z, Y) ).\n"
+        );
+    }
+
+    #[test]
+    fn test_conjunctions_consult_rules() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("mammal(bear).\nmammal(whale).\nlives_in(bear, forest).");
+        engine.add_rule("animal(X) :- mammal(X)").unwrap();
+        engine.add_rule("land_animal(X) :- lives_in(X, forest)").unwrap();
+
+        assert_eq!(
+            engine.query("mammal(X), animal(X)").unwrap(),
+            vec!["X = bear", "X = whale"]
+        );
+        assert_eq!(
+            engine.query("animal(X), land_animal(X)").unwrap(),
+            vec!["X = bear"]
+        );
+        // Facts read in reverse count too: `bear(mammal)` answers `mammal(X)`.
+        engine.load_facts_from_output("bear(mammal).\nlives_in(bear, forest).");
+        assert_eq!(
+            engine.query("mammal(X), land_animal(X)").unwrap(),
+            vec!["X = bear"]
+        );
+    }
+
+    #[test]
+    fn test_atom_builtins() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("name(big_cat).\nname(dog).");
+        engine
+            .add_rule("short_name(N) :- name(N), atom_length(N, L), L < 4")
+            .unwrap();
+
+        assert_eq!(engine.query("short_name(N)").unwrap(), vec!["N = dog"]);
+        assert_eq!(
+            engine.query("name(N), atom_concat(big_, R, N)").unwrap(),
+            vec!["N = big_cat, R = cat"]
+        );
+        assert_eq!(
+            engine.query("atom_concat(X, Y, ab)").unwrap(),
+            vec!["X = '', Y = ab", "X = a, Y = b", "X = ab, Y = ''"]
+        );
+        assert_eq!(
+            engine.query("sub_atom(big_cat, B, 3, 0, S)").unwrap(),
+            vec!["B = 4, S = cat"]
+        );
+        assert_eq!(
+            engine.query("sub_atom(banana, B, _, _, ana)").unwrap(),
+            vec!["B = 1", "B = 3"]
+        );
+        assert_eq!(engine.query("upcase_atom(dog, U)").unwrap(), vec!["U = 'DOG'"]);
+        assert_eq!(engine.query("downcase_atom('Dog', D)").unwrap(), vec!["D = dog"]);
+        assert!(engine.query("atom_length(X, L)").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_phrase_uses_rules() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("animal(bear).\nmammal(cat).\naction(runs).");
+        engine.add_rule("animal(X) :- mammal(X)").unwrap();
+        engine.add_rule("creature(X) :- animal(X)").unwrap();
+        engine.add_pattern("sentence --> animal, action").unwrap();
+        engine.add_pattern("only_rules --> creature").unwrap();
+
+        assert_eq!(
+            engine.query("phrase(sentence, S)").unwrap(),
+            vec!["S = [bear, runs]", "S = [cat, runs]"]
+        );
+        assert_eq!(
+            engine.query("phrase(only_rules, S)").unwrap(),
+            vec!["S = [bear]", "S = [cat]"]
+        );
+        assert_eq!(
+            engine
+                .query("phrase(only_rules, S, [sample(5), seed(3)])")
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_tabled_transitive_closure() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output("edge(a, b).\nedge(b, c).\nedge(c, a).\nedge(c, d).");
+        engine
+            .load_config(
+                ":- table path/2.\npath(X, Y) :- path(X, Z), edge(Z, Y)\npath(X, Y) :- edge(X, Y)",
+            )
+            .unwrap();
+
+        let mut reachable = engine.query("path(a, Y)").unwrap();
+        reachable.sort();
+        assert_eq!(reachable, vec!["Y = a", "Y = b", "Y = c", "Y = d"]);
+        assert_eq!(engine.query("path(d, Y)").unwrap(), Vec::<String>::new());
+        assert_eq!(engine.query("path(X, d)").unwrap().len(), 3);
+
+        let trace = engine.query_with_trace("path(b, a)").unwrap();
+        assert_eq!(trace[0].steps[0], "path(b, a)  [tabled]");
+
+        assert!(engine.load_config(":- dynamic path/2.").is_err());
+    }
+
+    #[test]
+    fn test_bottom_up_evaluation() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "edge(a, b).\nedge(b, c).\nedge(c, a).\nbear(mammal).\nage(bob, 7).\nage(ann, 31).",
+        );
+        engine.add_rule("path(X, Y) :- edge(X, Y)").unwrap();
+        engine.add_rule("path(X, Y) :- path(X, Z), edge(Z, Y)").unwrap();
+        engine.add_rule("animal(X) :- mammal(X)").unwrap();
+        engine.add_rule("adult(X) :- age(X, A), A >= 18").unwrap();
+        engine.add_rule("first_edge(X) :- edge(X, _), !").unwrap();
+        engine.set_bottom_up(true);
+
+        // Left recursion terminates: every path is derived once up front.
+        assert_eq!(engine.query("path(a, Y)").unwrap(), vec!["Y = b", "Y = c", "Y = a"]);
+        assert_eq!(engine.query("animal(X)").unwrap(), vec!["X = bear"]);
+        assert_eq!(engine.query("adult(X)").unwrap(), vec!["X = ann"]);
+        // Rules with a cut still run top-down.
+        assert_eq!(engine.query("first_edge(X)").unwrap(), vec!["X = a"]);
+
+        engine.load_facts_from_output("edge(x, y).");
+        assert_eq!(engine.query("path(x, Y)").unwrap(), vec!["Y = y"]);
+    }
+}
@@ -0,0 +1,113 @@
+//! Export of the binary facts as a GraphViz knowledge graph: each argument is
+//! a node, and `likes(john, mary)` is an edge from `john` to `mary` labelled
+//! `likes`. Render with `dot -Tpng knowledge_graph.dot -o knowledge_graph.png`.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use super::{Fact, QueryEngine, Term};
+
+/// Writes the graph to `path`. Returns how many edges were written.
+pub fn export_dot(engine: &QueryEngine, path: &Path) -> std::io::Result<usize> {
+    let (graph, edges) = dot_graph(engine.facts());
+    std::fs::write(path, graph)?;
+    Ok(edges)
+}
+
+/// Renders the DOT file at `dot_path` to a PNG beside it with GraphViz's
+/// `dot`, which has to be on the PATH. Returns where the PNG was written.
+pub fn render_png(dot_path: &Path) -> std::io::Result<PathBuf> {
+    let png_path = dot_path.with_extension("png");
+    let output = Command::new("dot")
+        .arg("-Tpng")
+        .arg(dot_path)
+        .arg("-o")
+        .arg(&png_path)
+        .output()?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(std::io::Error::other(error.trim().to_string()));
+    }
+    Ok(png_path)
+}
+
+/// The graph in DOT syntax, and how many edges it has. Facts with any other
+/// number of arguments are not relations between two entities and are left out.
+pub fn dot_graph(facts: &[Fact]) -> (String, usize) {
+    let mut nodes = Vec::new();
+    let mut seen = HashSet::new();
+    let mut edges = Vec::new();
+    for fact in facts {
+        let [from, to] = fact.args.as_slice() else {
+            continue;
+        };
+        let (from, to) = (node_name(from), node_name(to));
+        for node in [&from, &to] {
+            if seen.insert(node.clone()) {
+                nodes.push(node.clone());
+            }
+        }
+        edges.push(format!(
+            "    {} -> {} [label={}];\n",
+            quoted(&from),
+            quoted(&to),
+            quoted(&fact.predicate)
+        ));
+    }
+
+    let mut graph =
+        String::from("digraph knowledge_base {\n    rankdir=LR;\n    node [shape=box];\n\n");
+    for node in &nodes {
+        graph.push_str(&format!("    {};\n", quoted(node)));
+    }
+    if !nodes.is_empty() {
+        graph.push('\n');
+    }
+    for edge in &edges {
+        graph.push_str(edge);
+    }
+    graph.push_str("}\n");
+    (graph, edges.len())
+}
+
+/// Atoms and strings are shown without Prolog quotes.
+fn node_name(term: &Term) -> String {
+    match term {
+        Term::Atom(name) => name.clone(),
+        Term::Str(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// A DOT string literal.
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_facts_become_labelled_edges() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "likes(john, mary).\nlikes(mary, 'New York').\nanimal(bear).\nage(john, 42).",
+        );
+
+        let (graph, edges) = dot_graph(engine.facts());
+        assert_eq!(edges, 3);
+        assert_eq!(
+            graph,
+            "digraph knowledge_base {\n    rankdir=LR;\n    node [shape=box];\n\n\
+             \x20   \"john\";\n    \"mary\";\n    \"New York\";\n    \"42\";\n\n\
+             \x20   \"john\" -> \"mary\" [label=\"likes\"];\n\
+             \x20   \"mary\" -> \"New York\" [label=\"likes\"];\n\
+             \x20   \"john\" -> \"42\" [label=\"age\"];\n\
+             }\n"
+        );
+    }
+}
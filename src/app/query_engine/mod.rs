@@ -0,0 +1,7 @@
+mod engine;
+pub mod graphviz;
+pub mod souffle;
+mod term;
+
+pub use engine::{Fact, QueryEngine};
+pub use term::Term;
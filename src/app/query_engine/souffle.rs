@@ -0,0 +1,158 @@
+//! Export of the fact base for the Soufflé Datalog engine: one tab-separated
+//! `<relation>.facts` file per predicate, and a `.dl` schema declaring each
+//! relation as an input. Run with `souffle -F <dir> <dir>/knowledge_base.dl`.
+
+use std::{collections::HashMap, path::Path};
+
+use super::{Fact, QueryEngine, Term};
+
+const SCHEMA_FILE: &str = "knowledge_base.dl";
+
+/// Writes the schema and facts files into `dir`, creating it if needed.
+/// Returns how many relations were written.
+pub fn export_souffle(engine: &QueryEngine, dir: &Path) -> std::io::Result<usize> {
+    std::fs::create_dir_all(dir)?;
+    let files = souffle_files(engine.facts());
+    for (name, contents) in &files {
+        std::fs::write(dir.join(name), contents)?;
+    }
+    Ok(files.len() - 1)
+}
+
+/// The schema file followed by one `.facts` file per relation, as
+/// `(file name, contents)`. Facts with variables have no Datalog counterpart
+/// and are left out.
+pub fn souffle_files(facts: &[Fact]) -> Vec<(String, String)> {
+    let mut relations: Vec<(String, usize)> = Vec::new();
+    let mut rows: HashMap<(String, usize), Vec<&Fact>> = HashMap::new();
+    let mut skipped = 0;
+    for fact in facts {
+        if !fact.args.iter().all(is_ground) || fact.args.is_empty() {
+            skipped += 1;
+            continue;
+        }
+        let key = (fact.predicate.clone(), fact.args.len());
+        if !rows.contains_key(&key) {
+            relations.push(key.clone());
+        }
+        rows.entry(key).or_default().push(fact);
+    }
+
+    // Soufflé relations cannot share a name, so overloaded predicates get
+    // their arity appended.
+    let mut arities: HashMap<&str, usize> = HashMap::new();
+    for (predicate, _) in &relations {
+        *arities.entry(predicate).or_default() += 1;
+    }
+    let relation_name = |predicate: &str, arity: usize| {
+        let name = identifier(predicate);
+        if arities[predicate] > 1 {
+            format!("{}_{}", name, arity)
+        } else {
+            name
+        }
+    };
+
+    let mut schema = String::from("// Exported from Simple Prolog\n");
+    if skipped > 0 {
+        schema.push_str(&format!(
+            "// {} facts with variables or no arguments were left out\n",
+            skipped
+        ));
+    }
+    let mut files = Vec::new();
+    for key in &relations {
+        let (predicate, arity) = key;
+        let name = relation_name(predicate, *arity);
+        let facts = &rows[key];
+
+        let attributes: Vec<String> = (0..*arity)
+            .map(|position| {
+                let column = facts.iter().map(|fact| &fact.args[position]);
+                format!("a{}:{}", position + 1, column_type(column))
+            })
+            .collect();
+        schema.push_str(&format!("\n.decl {}({})\n.input {}\n", name, attributes.join(", "), name));
+
+        let mut contents = String::new();
+        for fact in facts {
+            let values: Vec<String> = fact.args.iter().map(field).collect();
+            contents.push_str(&values.join("\t"));
+            contents.push('\n');
+        }
+        files.push((format!("{}.facts", name), contents));
+    }
+
+    files.insert(0, (SCHEMA_FILE.to_string(), schema));
+    files
+}
+
+fn is_ground(term: &Term) -> bool {
+    match term {
+        Term::Variable(_) => false,
+        Term::Compound(_, args) => args.iter().all(is_ground),
+        Term::List(items, tail) => items.iter().chain(tail.as_deref()).all(is_ground),
+        _ => true,
+    }
+}
+
+/// `number` or `float` when every value in the column is one, else `symbol`.
+fn column_type<'a>(mut column: impl Iterator<Item = &'a Term> + Clone) -> &'static str {
+    if column.clone().all(|term| matches!(term, Term::Integer(_))) {
+        "number"
+    } else if column.all(|term| matches!(term, Term::Integer(_) | Term::Float(_))) {
+        "float"
+    } else {
+        "symbol"
+    }
+}
+
+/// A value as a facts-file field. Symbols are written unquoted; tabs and
+/// line breaks would split the row, so they become spaces.
+fn field(term: &Term) -> String {
+    let text = match term {
+        Term::Atom(name) => name.clone(),
+        Term::Str(text) => text.clone(),
+        other => other.to_string(),
+    };
+    text.replace(['\t', '\n', '\r'], " ")
+}
+
+/// A Soufflé identifier: letters, digits, `_` and `?`, not starting with a digit.
+fn identifier(predicate: &str) -> String {
+    let mut name: String = predicate
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '?' { c } else { '_' })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_facts_become_relations_with_typed_columns() {
+        let mut engine = QueryEngine::new();
+        engine.load_facts_from_output(
+            "age(john, 42).\nage(mary, 37).\nlikes(john, 'New York').\nlikes(john).\nheight(X, 2.5).",
+        );
+
+        let files = souffle_files(engine.facts());
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec![SCHEMA_FILE, "age.facts", "likes_2.facts", "likes_1.facts"]);
+        assert_eq!(
+            files[0].1,
+            "// Exported from Simple Prolog\n\
+             // 1 facts with variables or no arguments were left out\n\
+             \n.decl age(a1:symbol, a2:number)\n.input age\n\
+             \n.decl likes_2(a1:symbol, a2:symbol)\n.input likes_2\n\
+             \n.decl likes_1(a1:symbol)\n.input likes_1\n"
+        );
+        assert_eq!(files[1].1, "john\t42\nmary\t37\n");
+        assert_eq!(files[2].1, "john\tNew York\n");
+    }
+}
@@ -0,0 +1,461 @@
+use std::fmt;
+
+/// Infix built-in operators, longest first so `=<` is not read as `<` or `=`.
+pub const COMPARISON_OPERATORS: [&str; 8] = ["=:=", "=\\=", "\\=", ">=", "=<", "=", ">", "<"];
+
+/// A single argument of a fact, rule or query.
+///
+/// Constants keep their type so `age(john, 25)` can be compared numerically
+/// while `name(john, "John Smith")` stays textual.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Atom(String),
+    Integer(i64),
+    Float(f64),
+    Str(String),
+    Variable(String),
+    Compound(String, Vec<Term>),
+    /// `[a, b]` has no tail; `[H|T]` keeps `T` as the tail.
+    List(Vec<Term>, Option<Box<Term>>),
+}
+
+impl Term {
+    /// Parses a single argument as written in Prolog source or parser output.
+    pub fn parse(text: &str) -> Term {
+        let text = text.trim();
+
+        if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+            return Term::Str(unescape(&text[1..text.len() - 1]));
+        }
+
+        if text.len() >= 2 && text.starts_with('\'') && text.ends_with('\'') {
+            return Term::Atom(unescape(&text[1..text.len() - 1]));
+        }
+
+        if let Ok(value) = text.parse::<i64>() {
+            return Term::Integer(value);
+        }
+
+        if text.contains('.')
+            && text
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_digit() || c == '-')
+            && let Ok(value) = text.parse::<f64>()
+        {
+            return Term::Float(value);
+        }
+
+        // Goal arguments such as `findall(X, (age(X, A), A > 18), L)` keep
+        // their structure: infix comparisons and `(a, b)` / `(a ; b)` groups
+        // become compounds named after the operator.
+        for op in COMPARISON_OPERATORS {
+            if let Some(pos) = find_top_level(text, op) {
+                let (lhs, rhs) = (text[..pos].trim(), text[pos + op.len()..].trim());
+                if !lhs.is_empty() && !rhs.is_empty() {
+                    return Term::Compound(
+                        op.to_string(),
+                        vec![Term::parse(lhs), Term::parse(rhs)],
+                    );
+                }
+            }
+        }
+
+        if let Some(inner) = text.strip_prefix('(').and_then(|t| t.strip_suffix(')'))
+            && is_single_group(text)
+        {
+            let alternatives = split_top_level_on(inner, ';');
+            if alternatives.len() > 1 {
+                return Term::Compound(
+                    ";".to_string(),
+                    alternatives.iter().map(|alt| Term::parse(alt)).collect(),
+                );
+            }
+            let goals = split_top_level(inner);
+            if goals.len() > 1 {
+                return Term::Compound(
+                    ",".to_string(),
+                    goals.iter().map(|goal| Term::parse(goal)).collect(),
+                );
+            }
+            return Term::parse(inner);
+        }
+
+        if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            let (items, tail) = match find_list_bar(inner) {
+                Some(bar) => (
+                    &inner[..bar],
+                    Some(Box::new(Term::parse(&inner[bar + 1..]))),
+                ),
+                None => (inner, None),
+            };
+            let items = if items.trim().is_empty() {
+                Vec::new()
+            } else {
+                split_top_level(items)
+                    .iter()
+                    .map(|item| Term::parse(item))
+                    .collect()
+            };
+            return Term::list(items, tail.map(|t| *t));
+        }
+
+        if let Some(open_paren) = text.find('(')
+            && text.ends_with(')')
+            && open_paren > 0
+        {
+            let name = Term::parse(&text[..open_paren]);
+            let args = split_top_level(&text[open_paren + 1..text.len() - 1])
+                .iter()
+                .map(|arg| Term::parse(arg))
+                .collect();
+
+            if let Term::Atom(name) = name {
+                return Term::Compound(name, args);
+            }
+        }
+
+        if text
+            .chars()
+            .next()
+            .map(|c| c.is_uppercase() || c == '_')
+            .unwrap_or(false)
+        {
+            return Term::Variable(text.to_string());
+        }
+
+        Term::Atom(text.to_string())
+    }
+
+    /// Builds a list, folding a tail that is itself a list into the items so
+    /// `[a|[b, c]]` and `[a, b, c]` are the same term.
+    pub fn list(mut items: Vec<Term>, tail: Option<Term>) -> Term {
+        match tail {
+            Some(Term::List(rest, rest_tail)) => {
+                items.extend(rest);
+                Term::List(items, rest_tail)
+            }
+            Some(tail) => Term::List(items, Some(Box::new(tail))),
+            None => Term::List(items, None),
+        }
+    }
+
+    /// `_` unifies with anything and never binds.
+    pub fn is_anonymous(&self) -> bool {
+        matches!(self, Term::Variable(name) if name == "_")
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Term::Integer(value) => Some(*value as f64),
+            Term::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Text of an atomic term, as read by atom built-ins such as
+    /// `atom_length/2`: atoms and strings unquoted, numbers as written.
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            Term::Atom(text) | Term::Str(text) => Some(text.clone()),
+            Term::Integer(_) | Term::Float(_) => Some(self.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Appends the names of all named variables in this term, in order of
+    /// first appearance.
+    pub fn collect_variables(&self, out: &mut Vec<String>) {
+        match self {
+            Term::Variable(name) if name != "_" && !out.contains(name) => out.push(name.clone()),
+            Term::Compound(_, args) => args.iter().for_each(|arg| arg.collect_variables(out)),
+            Term::List(items, tail) => items
+                .iter()
+                .chain(tail.as_deref())
+                .for_each(|item| item.collect_variables(out)),
+            _ => {}
+        }
+    }
+
+    /// Applies `rename` to every named variable in this term.
+    pub fn rename_variables(&self, rename: &impl Fn(&str) -> String) -> Term {
+        match self {
+            Term::Variable(name) if name != "_" => Term::Variable(rename(name)),
+            Term::Compound(name, args) => Term::Compound(
+                name.clone(),
+                args.iter()
+                    .map(|arg| arg.rename_variables(rename))
+                    .collect(),
+            ),
+            Term::List(items, tail) => Term::List(
+                items
+                    .iter()
+                    .map(|item| item.rename_variables(rename))
+                    .collect(),
+                tail.as_ref()
+                    .map(|tail| Box::new(tail.rename_variables(rename))),
+            ),
+            _ => self.clone(),
+        }
+    }
+
+    /// True if the atom `name` appears anywhere inside this term, including as
+    /// the functor of a compound.
+    pub fn mentions_atom(&self, name: &str) -> bool {
+        match self {
+            Term::Atom(atom) => atom == name,
+            Term::Compound(functor, args) => {
+                functor == name || args.iter().any(|arg| arg.mentions_atom(name))
+            }
+            Term::List(items, tail) => items
+                .iter()
+                .chain(tail.as_deref())
+                .any(|item| item.mentions_atom(name)),
+            _ => false,
+        }
+    }
+
+    /// Replaces every occurrence of the atom `from` (including compound functors) with `to`.
+    pub fn rename_atom(&self, from: &str, to: &str) -> Term {
+        match self {
+            Term::Atom(name) if name == from => Term::Atom(to.to_string()),
+            Term::Compound(name, args) => Term::Compound(
+                if name == from {
+                    to.to_string()
+                } else {
+                    name.clone()
+                },
+                args.iter().map(|arg| arg.rename_atom(from, to)).collect(),
+            ),
+            Term::List(items, tail) => Term::List(
+                items
+                    .iter()
+                    .map(|item| item.rename_atom(from, to))
+                    .collect(),
+                tail.as_ref()
+                    .map(|tail| Box::new(tail.rename_atom(from, to))),
+            ),
+            _ => self.clone(),
+        }
+    }
+
+    /// True if the variable `name` appears anywhere inside this term.
+    pub fn contains_variable(&self, name: &str) -> bool {
+        match self {
+            Term::Variable(var) => var == name,
+            Term::Compound(_, args) => args.iter().any(|arg| arg.contains_variable(name)),
+            Term::List(items, tail) => items
+                .iter()
+                .chain(tail.as_deref())
+                .any(|item| item.contains_variable(name)),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Atom(name) => {
+                if is_plain_atom(name) {
+                    write!(f, "{}", name)
+                } else {
+                    write!(f, "'{}'", name.replace('\\', "\\\\").replace('\'', "\\'"))
+                }
+            }
+            Term::Integer(value) => write!(f, "{}", value),
+            Term::Float(value) => {
+                if value.fract() == 0.0 && value.is_finite() {
+                    write!(f, "{:.1}", value)
+                } else {
+                    write!(f, "{}", value)
+                }
+            }
+            Term::Str(text) => write!(f, "\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"")),
+            Term::Variable(name) => write!(f, "{}", name),
+            Term::Compound(op, args)
+                if COMPARISON_OPERATORS.contains(&op.as_str()) && args.len() == 2 =>
+            {
+                write!(f, "{} {} {}", args[0], op, args[1])
+            }
+            Term::Compound(op, args) if op == "," || op == ";" => {
+                let separator = if op == "," { ", " } else { " ; " };
+                let parts: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "({})", parts.join(separator))
+            }
+            Term::Compound(name, args) => {
+                write!(f, "{}(", Term::Atom(name.clone()))?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Term::List(items, tail) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                if let Some(tail) = tail {
+                    write!(f, "|{}", tail)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// Position of the `|` separating list items from the tail, ignoring any
+/// nested inside parentheses, brackets or quotes.
+fn find_list_bar(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+
+    for (i, ch) in text.char_indices() {
+        if let Some(q) = quote {
+            if ch == q {
+                quote = None;
+            }
+            continue;
+        }
+
+        match ch {
+            '\'' | '"' => quote = Some(ch),
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '|' if depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Byte position of `needle` outside any parentheses, brackets or quotes.
+fn find_top_level(text: &str, needle: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+
+    for (i, ch) in text.char_indices() {
+        match (quote, ch) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(ch),
+            (None, _) if depth == 0 && text[i..].starts_with(needle) => return Some(i),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth -= 1,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// True if the parenthesis opening `text` is the one closing it, so `(a, b)`
+/// is one group but `(a), (b)` is not.
+fn is_single_group(text: &str) -> bool {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+
+    for (i, ch) in text.char_indices() {
+        match (quote, ch) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(ch),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => {
+                depth -= 1;
+                if depth == 0 {
+                    return i == text.len() - 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    false
+}
+
+/// Splits a comma separated argument list, ignoring commas nested inside
+/// parentheses, brackets or quotes.
+pub fn split_top_level(text: &str) -> Vec<String> {
+    split_top_level_on(text, ',')
+}
+
+/// Like [`split_top_level`], but splits on `separator` (e.g. `;` in rule bodies).
+pub fn split_top_level_on(text: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut paren_depth = 0;
+    let mut quote: Option<char> = None;
+
+    for ch in text.chars() {
+        if let Some(q) = quote {
+            if ch == q {
+                quote = None;
+            }
+            current.push(ch);
+            continue;
+        }
+
+        match ch {
+            '\'' | '"' => {
+                quote = Some(ch);
+                current.push(ch);
+            }
+            '(' | '[' | '{' => {
+                paren_depth += 1;
+                current.push(ch);
+            }
+            ')' | ']' | '}' => {
+                paren_depth -= 1;
+                current.push(ch);
+            }
+            c if c == separator && paren_depth == 0 => {
+                parts.push(current.trim().to_string());
+                current.clear();
+            }
+            _ => {
+                current.push(ch);
+            }
+        }
+    }
+
+    if !current.is_empty() {
+        parts.push(current.trim().to_string());
+    }
+
+    parts
+}
+
+/// Atoms that can be written without quotes: a lowercase letter followed by
+/// letters, digits and underscores.
+fn is_plain_atom(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_lowercase()) && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(other) => result.push(other),
+                None => result.push('\\'),
+            }
+        } else {
+            result.push(ch);
+        }
+    }
+
+    result
+}
@@ -0,0 +1,473 @@
+use std::time::Duration;
+
+use crate::app::{
+    data_dir,
+    parser::{
+        pattern_matcher::WordFallback,
+        pronoun_resolver,
+        subordinate::{ClauseLink, SUBORDINATORS},
+    },
+    query_engine::QueryEngine,
+    updates::UpdateChecker,
+};
+
+pub struct Settings {
+    pub occurs_check: bool,
+    /// Derive the facts of plain rules bottom-up before answering queries.
+    pub bottom_up: bool,
+    /// How `<Type>` pattern tokens treat words missing from the database,
+    /// unless the token sets its own policy.
+    pub word_fallback: WordFallback,
+    /// Add the tense of the matched verb to every fact as a last argument.
+    pub tag_tense: bool,
+    /// How the clauses around each of the [`SUBORDINATORS`] are joined.
+    pub clause_links: [ClauseLink; SUBORDINATORS.len()],
+    /// Who "I", "me" and "my" stand for; left as written when empty. A
+    /// document's `:- speaker(name).` wins over it.
+    pub speaker: String,
+    /// Who "you" and "your" stand for, likewise; `:- addressee(name).`
+    /// sets it per document.
+    pub addressee: String,
+    /// How many sentences back a pronoun may find its antecedent.
+    pub pronoun_window: usize,
+    pub phrase_limit: usize,
+    pub max_rule_depth: usize,
+    pub max_solutions: usize,
+    pub max_steps: usize,
+    pub timeout_ms: u64,
+    pub report_queries_path: String,
+    pub report_output_path: String,
+    /// Where Export .pl writes the knowledge base for SWI-Prolog.
+    pub export_path: String,
+    /// Folder Export Soufflé writes the `.dl` schema and `.facts` files to.
+    pub souffle_dir: String,
+    /// Where Export graph writes the GraphViz knowledge graph.
+    pub graph_path: String,
+    /// Also render the graph to a PNG with GraphViz's `dot`.
+    pub render_graph_png: bool,
+    /// Where Export training data writes the curated matches as JSON Lines.
+    pub training_data_path: String,
+    /// Black background, white text and a yellow outline on the hovered and
+    /// focused widget, for low-vision users.
+    pub high_contrast: bool,
+    update_checker: UpdateChecker,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            occurs_check: false,
+            bottom_up: false,
+            word_fallback: WordFallback::Noun,
+            tag_tense: false,
+            clause_links: SUBORDINATORS.map(|subordinator| subordinator.default_link),
+            speaker: String::new(),
+            addressee: String::new(),
+            pronoun_window: pronoun_resolver::DEFAULT_WINDOW,
+            phrase_limit: 500,
+            max_rule_depth: 50,
+            max_solutions: 1000,
+            max_steps: 1_000_000,
+            timeout_ms: 2000,
+            report_queries_path: data_dir_path("report_queries.txt"),
+            report_output_path: data_dir_path("batch_report.txt"),
+            export_path: data_dir_path("knowledge_base.pl"),
+            souffle_dir: data_dir_path("souffle"),
+            graph_path: data_dir_path("knowledge_graph.dot"),
+            render_graph_png: false,
+            training_data_path: data_dir_path("training_data.jsonl"),
+            high_contrast: false,
+            update_checker: UpdateChecker::new(),
+        }
+    }
+
+    /// The theme the app is drawn with.
+    pub fn visuals(&self) -> egui::Visuals {
+        let mut visuals = egui::Visuals::dark();
+        if !self.high_contrast {
+            return visuals;
+        }
+
+        let outline = egui::Stroke::new(1.0, egui::Color32::WHITE);
+        let highlight = egui::Stroke::new(2.0, egui::Color32::YELLOW);
+
+        visuals.override_text_color = Some(egui::Color32::WHITE);
+        visuals.panel_fill = egui::Color32::BLACK;
+        visuals.window_fill = egui::Color32::BLACK;
+        visuals.window_stroke = outline;
+        visuals.extreme_bg_color = egui::Color32::BLACK;
+        visuals.faint_bg_color = egui::Color32::from_gray(24);
+        visuals.hyperlink_color = egui::Color32::from_rgb(0, 255, 255);
+        visuals.selection.bg_fill = egui::Color32::from_rgb(0, 70, 200);
+        visuals.selection.stroke = highlight;
+
+        let widgets = &mut visuals.widgets;
+        for widget in [
+            &mut widgets.noninteractive,
+            &mut widgets.inactive,
+            &mut widgets.hovered,
+            &mut widgets.active,
+            &mut widgets.open,
+        ] {
+            widget.bg_fill = egui::Color32::BLACK;
+            widget.weak_bg_fill = egui::Color32::BLACK;
+            widget.bg_stroke = outline;
+            widget.fg_stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+        }
+        widgets.hovered.bg_stroke = highlight;
+        widgets.active.bg_stroke = highlight;
+        widgets.open.bg_stroke = highlight;
+
+        visuals
+    }
+
+    pub fn apply_to_engine(&self, query_engine: &mut QueryEngine) {
+        query_engine.set_occurs_check(self.occurs_check);
+        query_engine.set_bottom_up(self.bottom_up);
+        query_engine.set_phrase_limit(Some(self.phrase_limit));
+        query_engine.set_max_rule_depth(self.max_rule_depth);
+        query_engine.set_max_solutions(Some(self.max_solutions));
+        query_engine.set_max_steps(Some(self.max_steps));
+        query_engine.set_timeout(Some(Duration::from_millis(self.timeout_ms)));
+    }
+
+    /// The settings that change how the input is parsed, to tell when it
+    /// has to be parsed again.
+    pub fn parsing(
+        &self,
+    ) -> (WordFallback, bool, [ClauseLink; SUBORDINATORS.len()], String, String, usize) {
+        (
+            self.word_fallback,
+            self.tag_tense,
+            self.clause_links,
+            self.speaker.clone(),
+            self.addressee.clone(),
+            self.pronoun_window,
+        )
+    }
+
+    /// Draws the settings tab. Returns true if any setting changed this frame.
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut changed = false;
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Settings");
+            ui.separator();
+
+            ui.group(|ui| {
+                ui.label(egui::RichText::new("Query Engine").strong());
+                ui.add_space(5.0);
+
+                changed |= ui
+                    .checkbox(&mut self.occurs_check, "Occurs check in unification")
+                    .changed();
+                ui.label(
+                    egui::RichText::new(
+                        "Rejects cyclic bindings such as X = f(X). Slower, but matches logic textbooks.",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+
+                ui.add_space(5.0);
+                changed |= ui
+                    .checkbox(&mut self.bottom_up, "Bottom-up (Datalog) evaluation")
+                    .changed();
+                ui.label(
+                    egui::RichText::new(
+                        "Derives every fact of rules without cuts or findall once, then answers from them. \
+                         Faster for rule-heavy knowledge bases, and left-recursive rules terminate.",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    let label = ui.label("Max phrase combinations:");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut self.phrase_limit).range(1..=100_000))
+                        .labelled_by(label.id)
+                        .changed();
+                });
+                ui.label(
+                    egui::RichText::new(
+                        "Default cap for phrase/2; override per query with phrase(P, X, [max(N)]).",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    let label = ui.label("Max rule depth:");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut self.max_rule_depth).range(1..=1000))
+                        .labelled_by(label.id)
+                        .changed();
+                });
+                ui.label(
+                    egui::RichText::new(
+                        "How deeply rules may call other rules (or themselves) before giving up.",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    let label = ui.label("Max solutions:");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut self.max_solutions).range(1..=1_000_000))
+                        .labelled_by(label.id)
+                        .changed();
+                    let label = ui.label("Max steps:");
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut self.max_steps)
+                                .range(1_000..=100_000_000)
+                                .speed(1000),
+                        )
+                        .labelled_by(label.id)
+                        .changed();
+                    let label = ui.label("Timeout (ms):");
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut self.timeout_ms)
+                                .range(10..=60_000)
+                                .speed(10),
+                        )
+                        .labelled_by(label.id)
+                        .changed();
+                });
+                ui.label(
+                    egui::RichText::new(
+                        "A query stops at whichever limit it reaches first and shows the answers found so far.",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+            });
+
+            ui.add_space(10.0);
+            ui.group(|ui| {
+                ui.label(egui::RichText::new("Parser").strong());
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.label("Unknown word fallback:");
+                    egui::ComboBox::from_id_source("word_fallback")
+                        .selected_text(self.word_fallback.name())
+                        .show_ui(ui, |ui| {
+                            for fallback in WordFallback::ALL {
+                                changed |= ui
+                                    .selectable_value(
+                                        &mut self.word_fallback,
+                                        fallback,
+                                        fallback.name(),
+                                    )
+                                    .changed();
+                            }
+                        });
+                });
+                ui.label(
+                    egui::RichText::new(
+                        "strict: unknown words never match <Type> tokens. noun: they count as nouns. \
+                         guess: their type is guessed from the ending (-ly, -ing, -ous, ...). \
+                         Override per token with <Noun:strict>.",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+
+                ui.add_space(5.0);
+                changed |= ui
+                    .checkbox(&mut self.tag_tense, "Tag facts with tense")
+                    .changed();
+                ui.label(
+                    egui::RichText::new(
+                        "Adds past, present or future to every fact, from the matched verb form \
+                         and auxiliaries like will or was: chase(cat, mouse, past).",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+
+                ui.add_space(5.0);
+                ui.horizontal_wrapped(|ui| {
+                    for (subordinator, link) in SUBORDINATORS.iter().zip(&mut self.clause_links) {
+                        let name = subordinator.name();
+                        ui.label(format!("\"{}\" clauses:", name));
+                        egui::ComboBox::from_id_source(("clause_link", name))
+                            .selected_text(link.name())
+                            .show_ui(ui, |ui| {
+                                for option in ClauseLink::ALL {
+                                    changed |=
+                                        ui.selectable_value(link, option, option.name()).changed();
+                                }
+                            });
+                    }
+                });
+                ui.label(
+                    egui::RichText::new(
+                        "Sentences split at because, when, unless and so that. A rule writes \
+                         sad(john) :- lose(john, key); a causes/2 fact writes \
+                         causes(lose(john, key), sad(john)) along with the facts the sentence states.",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    let label = ui.label("Speaker:");
+                    changed |= ui
+                        .add(
+                            egui::TextEdit::singleline(&mut self.speaker)
+                                .hint_text("e.g. alice")
+                                .desired_width(120.0),
+                        )
+                        .labelled_by(label.id)
+                        .changed();
+                    let label = ui.label("Addressee:");
+                    changed |= ui
+                        .add(
+                            egui::TextEdit::singleline(&mut self.addressee)
+                                .hint_text("e.g. bob")
+                                .desired_width(120.0),
+                        )
+                        .labelled_by(label.id)
+                        .changed();
+                });
+                ui.label(
+                    egui::RichText::new(
+                        "\"I like pizza\" becomes like(alice, pizza) and \"You are a student\" \
+                         student(bob). Left empty, I and you stay as written. A document can set \
+                         its own with :- speaker(alice). and :- addressee(bob).",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    let label = ui.label("Pronoun window (sentences):");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut self.pronoun_window).range(0..=50))
+                        .labelled_by(label.id)
+                        .changed();
+                });
+                ui.label(
+                    egui::RichText::new(
+                        "How far back he, she, it and they look for what they stand for. Recent \
+                         nouns and sentence subjects are preferred; older ones are forgotten.",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+            });
+
+            ui.add_space(10.0);
+            ui.group(|ui| {
+                ui.label(egui::RichText::new("Appearance").strong());
+                ui.add_space(5.0);
+
+                changed |= ui
+                    .checkbox(&mut self.high_contrast, "High-contrast theme")
+                    .changed();
+                ui.label(
+                    egui::RichText::new(
+                        "Black background, white text and yellow outlines. \
+                         F6 moves focus between the input, query and results fields.",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+            });
+
+            ui.add_space(10.0);
+            ui.group(|ui| {
+                ui.label(egui::RichText::new("Batch Report").strong());
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    let label = ui.label("Saved queries file:");
+                    ui.text_edit_singleline(&mut self.report_queries_path)
+                        .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Report output file:");
+                    ui.text_edit_singleline(&mut self.report_output_path)
+                        .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("SWI-Prolog export file:");
+                    ui.text_edit_singleline(&mut self.export_path)
+                        .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Soufflé export folder:");
+                    ui.text_edit_singleline(&mut self.souffle_dir)
+                        .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Graph export file:");
+                    ui.text_edit_singleline(&mut self.graph_path)
+                        .labelled_by(label.id);
+                    ui.checkbox(&mut self.render_graph_png, "Render PNG (needs GraphViz)");
+                });
+                ui.horizontal(|ui| {
+                    let label = ui.label("Training data file:");
+                    ui.text_edit_singleline(&mut self.training_data_path)
+                        .labelled_by(label.id);
+                });
+                ui.label(
+                    egui::RichText::new(
+                        "Run Report appends result counts and new results since the last run. \
+                         For document folders use: batch_report <queries> <report> <files...>",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+            });
+
+            ui.add_space(10.0);
+            ui.group(|ui| {
+                ui.label(egui::RichText::new("Files & Updates").strong());
+                ui.add_space(5.0);
+
+                ui.label(format!("Data folder: {}", data_dir::describe()));
+                self.update_checker.show(ui);
+                ui.label(
+                    egui::RichText::new(
+                        "The database, query config, snapshots and crash reports are kept in the \
+                         data folder. Start with --portable to keep them next to the app instead. \
+                         Updates are only checked when you ask.",
+                    )
+                    .italics()
+                    .color(egui::Color32::from_rgb(100, 100, 100))
+                    .size(11.0),
+                );
+            });
+        });
+
+        changed
+    }
+}
+
+/// The default path of an export file, in the data folder.
+fn data_dir_path(file: &str) -> String {
+    data_dir::path(file).display().to_string()
+}
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::data_dir;
+
+const SNAPSHOTS_PATH: &str = "snapshots.json";
+
+/// A named copy of the knowledge base: the parsed facts and the rules typed into
+/// the query executor.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub name: String,
+    pub parsed_output: String,
+    pub rules: Vec<String>,
+}
+
+impl Snapshot {
+    fn fact_lines(&self) -> Vec<&str> {
+        self.parsed_output
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with("//"))
+            .collect()
+    }
+}
+
+fn rule_lines(query_text: &str) -> Vec<String> {
+    query_text
+        .lines()
+        .map(str::trim)
+        .filter(|l| l.contains(":-"))
+        .map(str::to_string)
+        .collect()
+}
+
+pub struct SnapshotStore {
+    snapshots: Vec<Snapshot>,
+    active: Option<usize>,
+    new_name: String,
+    diff_left: usize,
+    diff_right: usize,
+    diff_output: String,
+    status_message: String,
+}
+
+impl SnapshotStore {
+    pub fn new() -> Self {
+        let snapshots = std::fs::read_to_string(data_dir::path(SNAPSHOTS_PATH))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Self {
+            snapshots,
+            active: None,
+            new_name: String::new(),
+            diff_left: 0,
+            diff_right: 0,
+            diff_output: String::new(),
+            status_message: String::new(),
+        }
+    }
+
+    fn save_to_disk(&mut self) {
+        let result = serde_json::to_string_pretty(&self.snapshots)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                std::fs::write(data_dir::path(SNAPSHOTS_PATH), json).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            self.status_message = format!("Error saving snapshots: {}", e);
+        }
+    }
+
+    /// Draws the snapshots tab. Returns the snapshot to switch to, if one was picked.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        parsed_output: &str,
+        query_text: &str,
+    ) -> Option<Snapshot> {
+        let mut switch_to = None;
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Knowledge Base Snapshots");
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut self.new_name);
+                if ui.button("📸 Save Snapshot").clicked() {
+                    let name = self.new_name.trim().to_string();
+                    if name.is_empty() {
+                        self.status_message = "Error: snapshot name is required".to_string();
+                    } else {
+                        let snapshot = Snapshot {
+                            name: name.clone(),
+                            parsed_output: parsed_output.to_string(),
+                            rules: rule_lines(query_text),
+                        };
+                        if let Some(idx) = self.snapshots.iter().position(|s| s.name == name) {
+                            self.snapshots[idx] = snapshot;
+                            self.active = Some(idx);
+                        } else {
+                            self.snapshots.push(snapshot);
+                            self.active = Some(self.snapshots.len() - 1);
+                        }
+                        self.new_name.clear();
+                        self.status_message = format!("✓ Saved snapshot '{}'", name);
+                        self.save_to_disk();
+                    }
+                }
+            });
+
+            if !self.status_message.is_empty() {
+                ui.label(&self.status_message);
+            }
+
+            ui.add_space(10.0);
+            ui.group(|ui| {
+                ui.label(egui::RichText::new("Saved Snapshots").strong());
+                ui.add_space(5.0);
+
+                if self.snapshots.is_empty() {
+                    ui.label(
+                        egui::RichText::new(
+                            "No snapshots yet. Parse some text and save one above.",
+                        )
+                        .italics()
+                        .color(egui::Color32::from_rgb(100, 100, 100)),
+                    );
+                }
+
+                let mut delete_idx = None;
+                for (idx, snapshot) in self.snapshots.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let name = if self.active == Some(idx) {
+                            egui::RichText::new(format!("● {}", snapshot.name))
+                                .strong()
+                                .color(egui::Color32::from_rgb(100, 200, 100))
+                        } else {
+                            egui::RichText::new(&snapshot.name)
+                        };
+                        ui.label(name);
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "{} facts, {} rules",
+                                snapshot.fact_lines().len(),
+                                snapshot.rules.len()
+                            ))
+                            .color(egui::Color32::from_rgb(150, 150, 150)),
+                        );
+                        if ui.button("Switch").clicked() {
+                            switch_to = Some(snapshot.clone());
+                            self.active = Some(idx);
+                        }
+                        if ui.button("🗑").clicked() {
+                            delete_idx = Some(idx);
+                        }
+                    });
+                }
+
+                if let Some(idx) = delete_idx {
+                    let removed = self.snapshots.remove(idx);
+                    self.active = match self.active {
+                        Some(active) if active == idx => None,
+                        Some(active) if active > idx => Some(active - 1),
+                        other => other,
+                    };
+                    self.status_message = format!("Deleted snapshot '{}'", removed.name);
+                    self.save_to_disk();
+                }
+            });
+
+            if self.snapshots.len() >= 2 {
+                ui.add_space(10.0);
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("Diff").strong());
+                    ui.add_space(5.0);
+
+                    ui.horizontal(|ui| {
+                        self.diff_left = self.diff_left.min(self.snapshots.len() - 1);
+                        self.diff_right = self.diff_right.min(self.snapshots.len() - 1);
+
+                        egui::ComboBox::from_id_source("snapshot_diff_left")
+                            .selected_text(&self.snapshots[self.diff_left].name)
+                            .show_ui(ui, |ui| {
+                                for (idx, snapshot) in self.snapshots.iter().enumerate() {
+                                    ui.selectable_value(&mut self.diff_left, idx, &snapshot.name);
+                                }
+                            });
+                        ui.label("→");
+                        egui::ComboBox::from_id_source("snapshot_diff_right")
+                            .selected_text(&self.snapshots[self.diff_right].name)
+                            .show_ui(ui, |ui| {
+                                for (idx, snapshot) in self.snapshots.iter().enumerate() {
+                                    ui.selectable_value(&mut self.diff_right, idx, &snapshot.name);
+                                }
+                            });
+
+                        if ui.button("Compare").clicked() {
+                            self.diff_output = diff_snapshots(
+                                &self.snapshots[self.diff_left],
+                                &self.snapshots[self.diff_right],
+                            );
+                        }
+                    });
+
+                    if !self.diff_output.is_empty() {
+                        ui.add_space(5.0);
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            ui.add(
+                                egui::TextEdit::multiline(&mut self.diff_output)
+                                    .code_editor()
+                                    .desired_width(f32::INFINITY),
+                            );
+                        });
+                    }
+                });
+            }
+        });
+
+        switch_to
+    }
+}
+
+/// Lists facts and rules removed (`-`) and added (`+`) going from `left` to `right`.
+fn diff_snapshots(left: &Snapshot, right: &Snapshot) -> String {
+    fn diff_lines(output: &mut Vec<String>, label: &str, left: &[&str], right: &[&str]) {
+        let left_set: HashSet<&str> = left.iter().copied().collect();
+        let right_set: HashSet<&str> = right.iter().copied().collect();
+
+        output.push(format!("// {}", label));
+        let mut changed = false;
+        for line in left.iter().filter(|l| !right_set.contains(*l)) {
+            output.push(format!("- {}", line));
+            changed = true;
+        }
+        for line in right.iter().filter(|l| !left_set.contains(*l)) {
+            output.push(format!("+ {}", line));
+            changed = true;
+        }
+        if !changed {
+            output.push("// (no differences)".to_string());
+        }
+    }
+
+    let mut output = vec![format!("// {} → {}", left.name, right.name)];
+
+    diff_lines(
+        &mut output,
+        "Facts",
+        &left.fact_lines(),
+        &right.fact_lines(),
+    );
+
+    let left_rules: Vec<&str> = left.rules.iter().map(String::as_str).collect();
+    let right_rules: Vec<&str> = right.rules.iter().map(String::as_str).collect();
+    diff_lines(&mut output, "Rules", &left_rules, &right_rules);
+
+    output.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_reports_added_and_removed_facts_and_rules() {
+        let left = Snapshot {
+            name: "a".to_string(),
+            parsed_output: "// FROM: Bear is an animal.\nanimal(bear).\nis_a(cat, pet).\n"
+                .to_string(),
+            rules: rule_lines("mammal(X) :- animal(X).\nanimal(X)."),
+        };
+        let right = Snapshot {
+            name: "b".to_string(),
+            parsed_output: "animal(bear).\nis_a(cat, mammal).\n".to_string(),
+            rules: rule_lines("mammal(X) :- animal(X)."),
+        };
+
+        let diff = diff_snapshots(&left, &right);
+        assert_eq!(
+            diff,
+            "// a → b\n// Facts\n- is_a(cat, pet).\n+ is_a(cat, mammal).\n// Rules\n// (no differences)"
+        );
+    }
+}
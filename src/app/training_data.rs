@@ -0,0 +1,164 @@
+//! Export of the hand-curated matches as annotated training data. Each
+//! locked or corrected match becomes one JSON record per line: the sentence
+//! tokens, the captured spans, a BIO label per token and the facts the match
+//! stands for, ready for training an external sequence-labeling model.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::app::interactive_parser::{InteractiveParser, SentenceMatch};
+
+/// Label of the tokens outside every capture.
+const OUTSIDE_LABEL: &str = "O";
+
+#[derive(Debug, Serialize)]
+pub struct AnnotationRecord {
+    pub sentence: String,
+    pub tokens: Vec<String>,
+    pub spans: Vec<AnnotatedSpan>,
+    /// One label per token: `B-ARG1` starts capture `$1`, `I-ARG1` continues
+    /// it, and `O` is outside every capture.
+    pub labels: Vec<String>,
+    pub pattern: String,
+    /// The facts the match generates, which the labels should lead to.
+    pub facts: Vec<String>,
+    /// Whether the parse was locked, rather than only having captures corrected.
+    pub locked: bool,
+}
+
+/// One capture: `tokens[start..end]`, filling `$argument` of the template.
+#[derive(Debug, Serialize)]
+pub struct AnnotatedSpan {
+    pub start: usize,
+    pub end: usize,
+    pub argument: usize,
+    /// The word type the capture was read as, or `Greedy`.
+    pub word_type: String,
+    /// The value the capture fills in, as corrected.
+    pub value: String,
+}
+
+/// The record of one curated match.
+pub fn annotation_record(sentence_match: &SentenceMatch, locked: bool) -> AnnotationRecord {
+    let mut labels = vec![OUTSIDE_LABEL.to_string(); sentence_match.words.len()];
+    let mut spans = Vec::new();
+    for highlight in &sentence_match.highlights {
+        let end = highlight.word_end.min(labels.len());
+        let start = highlight.word_index.min(end);
+        for (offset, label) in labels[start..end].iter_mut().enumerate() {
+            let prefix = if offset == 0 { "B" } else { "I" };
+            *label = format!("{}-ARG{}", prefix, highlight.capture_index);
+        }
+        spans.push(AnnotatedSpan {
+            start,
+            end,
+            argument: highlight.capture_index,
+            word_type: match highlight.token_type.word_type() {
+                Some(word_type) => word_type.to_string(),
+                None => "Greedy".to_string(),
+            },
+            value: highlight.word.clone(),
+        });
+    }
+    spans.sort_by_key(|span| span.start);
+
+    AnnotationRecord {
+        sentence: sentence_match.sentence.clone(),
+        tokens: sentence_match.words.clone(),
+        spans,
+        labels,
+        pattern: sentence_match.pattern_name.clone(),
+        facts: sentence_match
+            .generated_output
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("//"))
+            .map(str::to_string)
+            .collect(),
+        locked,
+    }
+}
+
+/// Records for every locked and corrected match, sorted by sentence so
+/// repeated exports of the same corrections are identical.
+pub fn curated_records(parser: &InteractiveParser) -> Vec<AnnotationRecord> {
+    let mut curated: Vec<(&String, &Vec<SentenceMatch>, bool)> = parser
+        .locked
+        .iter()
+        .map(|(sentence, locked)| (sentence, &locked.matches, true))
+        .chain(
+            parser
+                .corrected
+                .iter()
+                .map(|(sentence, matches)| (sentence, matches, false)),
+        )
+        .collect();
+    curated.sort_by(|a, b| a.0.cmp(b.0));
+
+    curated
+        .into_iter()
+        .flat_map(|(_, matches, locked)| {
+            matches
+                .iter()
+                .map(move |sentence_match| annotation_record(sentence_match, locked))
+        })
+        .collect()
+}
+
+/// Writes the curated matches to `path` as JSON Lines. Returns how many
+/// records were written.
+pub fn export_jsonl(parser: &InteractiveParser, path: &Path) -> Result<usize, String> {
+    let records = curated_records(parser);
+    let mut lines = String::new();
+    for record in &records {
+        lines.push_str(&serde_json::to_string(record).map_err(|e| e.to_string())?);
+        lines.push('\n');
+    }
+    std::fs::write(path, lines).map_err(|e| e.to_string())?;
+    Ok(records.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::interactive_parser::{TokenHighlight, TokenType};
+
+    #[test]
+    fn test_captures_become_spans_and_bio_labels() {
+        let highlight = |word: &str, word_index, word_end, capture_index, token_type| {
+            TokenHighlight {
+                word: word.to_string(),
+                word_index,
+                word_end,
+                capture_index,
+                token_type,
+            }
+        };
+        let sentence_match = SentenceMatch {
+            sentence: "johannes gutenberg was a german inventor.".to_string(),
+            words: ["johannes", "gutenberg", "was", "a", "german", "inventor"]
+                .iter()
+                .map(|word| word.to_string())
+                .collect(),
+            pattern_name: "was_a".to_string(),
+            template: "$2($1)".to_string(),
+            highlights: vec![
+                highlight("german_inventor", 4, 6, 2, TokenType::Greedy),
+                highlight("johannes_gutenberg", 0, 2, 1, TokenType::Greedy),
+            ],
+            generated_output: "// corrected\ngerman_inventor(johannes_gutenberg)".to_string(),
+            confidence: 1.0,
+            alternatives: Vec::new(),
+        };
+
+        let record = annotation_record(&sentence_match, true);
+        assert_eq!(
+            record.labels,
+            vec!["B-ARG1", "I-ARG1", "O", "O", "B-ARG2", "I-ARG2"]
+        );
+        assert_eq!(record.spans[0].value, "johannes_gutenberg");
+        assert_eq!((record.spans[1].start, record.spans[1].end), (4, 6));
+        assert_eq!(record.facts, vec!["german_inventor(johannes_gutenberg)"]);
+    }
+}
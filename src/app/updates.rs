@@ -0,0 +1,161 @@
+//! Checking GitHub for a newer release. Nothing is fetched unless the user
+//! asks from the settings tab; the request is made with `curl`, which ships
+//! with Windows 10, macOS and most Linux distributions.
+
+use std::process::Command;
+use std::sync::mpsc::{Receiver, channel};
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/OtadTOAD/simple-prolog/releases/latest";
+
+/// The newest published release.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    pub version: String,
+    pub url: String,
+}
+
+/// Fetches the latest release from GitHub.
+fn fetch_latest_release() -> Result<Release, String> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location", "--max-time", "10"])
+        .args(["--header", "Accept: application/vnd.github+json"])
+        .args(["--user-agent", "simple-prolog"])
+        .arg(LATEST_RELEASE_URL)
+        .output()
+        .map_err(|e| format!("Could not run curl: {}", e))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(error.trim().to_string());
+    }
+    parse_release(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// The version and page of a release from GitHub's JSON description of it.
+fn parse_release(json: &str) -> Result<Release, String> {
+    let release: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let field = |name: &str| {
+        release[name]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("Release has no {}", name))
+    };
+    Ok(Release {
+        version: field("tag_name")?,
+        url: field("html_url")?,
+    })
+}
+
+/// Whether the release `tag`, e.g. `v0.2.0`, is newer than `current`.
+/// Missing version fields count as 0, and pre-release suffixes are ignored.
+pub fn is_newer(tag: &str, current: &str) -> bool {
+    let numbers = |version: &str| -> Vec<u64> {
+        version
+            .trim_start_matches(['v', 'V'])
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|field| field.parse().unwrap_or(0))
+            .collect()
+    };
+    let (mut tag, mut current) = (numbers(tag), numbers(current));
+    let len = tag.len().max(current.len());
+    tag.resize(len, 0);
+    current.resize(len, 0);
+    tag > current
+}
+
+/// The update check drawn in the settings tab.
+pub struct UpdateChecker {
+    receiver: Option<Receiver<Result<Release, String>>>,
+    result: Option<Result<Release, String>>,
+}
+
+impl UpdateChecker {
+    pub fn new() -> Self {
+        Self {
+            receiver: None,
+            result: None,
+        }
+    }
+
+    /// Draws the check button and what the last check found.
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        if let Some(receiver) = &self.receiver
+            && let Ok(result) = receiver.try_recv()
+        {
+            self.result = Some(result);
+            self.receiver = None;
+        }
+
+        ui.horizontal(|ui| {
+            let checking = self.receiver.is_some();
+            if ui
+                .add_enabled(!checking, egui::Button::new("🔄 Check for updates"))
+                .clicked()
+            {
+                let (sender, receiver) = channel();
+                let ctx = ui.ctx().clone();
+                std::thread::spawn(move || {
+                    let _ = sender.send(fetch_latest_release());
+                    ctx.request_repaint();
+                });
+                self.receiver = Some(receiver);
+                self.result = None;
+            }
+
+            if checking {
+                ui.spinner();
+                ui.label("Checking...");
+                return;
+            }
+            match &self.result {
+                Some(Ok(release)) if is_newer(&release.version, env!("CARGO_PKG_VERSION")) => {
+                    ui.label(format!("⬆ {} is available", release.version));
+                    ui.hyperlink_to("Release page", &release.url);
+                }
+                Some(Ok(_)) => {
+                    ui.label("✅ Up to date");
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(200, 60, 60),
+                        format!("Update check failed: {}", e),
+                    );
+                }
+                None => {
+                    ui.label(format!("Version {}", env!("CARGO_PKG_VERSION")));
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_tags_are_compared_as_versions() {
+        assert!(is_newer("v0.2.0", "0.1.0"));
+        assert!(is_newer("0.10.0", "0.9.3"));
+        assert!(is_newer("v1.0", "0.1.0"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("v0.1", "0.1.0"));
+        assert!(!is_newer("v0.1.0-beta", "0.1.0"));
+        assert!(!is_newer("v0.0.9", "0.1.0"));
+
+        let release = parse_release(
+            r#"{"tag_name": "v0.2.0", "html_url": "https://example.com/v0.2.0", "draft": false}"#,
+        );
+        assert_eq!(
+            release,
+            Ok(Release {
+                version: "v0.2.0".to_string(),
+                url: "https://example.com/v0.2.0".to_string(),
+            })
+        );
+        assert!(parse_release("{}").is_err());
+    }
+}
@@ -0,0 +1,37 @@
+// Batch Query Report
+// Parses a batch of documents, runs the saved report queries against the
+// resulting knowledge base and appends a summary (result counts, new results
+// since the previous run) to the report file.
+// Run with: cargo run --release --bin batch_report -- <queries.txt> <report.txt> <documents...>
+
+use simple_prolog::app::PrologApp;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.len() < 3 {
+        eprintln!("Usage: batch_report <queries.txt> <report.txt> <documents...>");
+        std::process::exit(1);
+    }
+
+    let queries_path = &args[0];
+    let output_path = &args[1];
+    let documents = &args[2..];
+
+    let mut text = String::new();
+    for document in documents {
+        text.push_str(&std::fs::read_to_string(document)?);
+        text.push('\n');
+    }
+
+    println!("Parsing {} documents...", documents.len());
+    let app = PrologApp::with_text(text, None);
+
+    let label = format!("{} documents", documents.len());
+    let summary = app.run_batch_report(queries_path, output_path, &label)?;
+
+    println!("{}", summary);
+    println!("✓ Report appended to {}", output_path);
+
+    Ok(())
+}
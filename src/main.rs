@@ -3,11 +3,53 @@
 mod app;
 
 use eframe::egui;
-use std::fs;
+use std::{fs, path::PathBuf};
 
 use crate::app::PrologApp;
 
+const HELP: &str = "\
+Usage: simple_prolog [--portable] [--script <file> [--output <file>]]
+
+  --portable        Keep the database and other files in the working directory
+  --script <file>   Run command palette commands from <file> instead of opening
+                    the window, printing the result of each
+  --output <file>   Write the script results, or its error, to <file> instead.
+                    The Windows build has no console, so use this there
+  --help            Show this text";
+
 fn main() -> Result<(), eframe::Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let flag_value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .map(|idx| args.get(idx + 1).map(String::as_str))
+    };
+
+    if args.iter().any(|arg| arg == "--help") {
+        println!("{}", HELP);
+        return Ok(());
+    }
+
+    // --portable keeps the database and other files in the working directory
+    let portable = args.iter().any(|arg| arg == "--portable");
+    app::data_dir::init(portable);
+    app::crash_report::install_panic_hook();
+
+    if let Some(script_path) = flag_value("--script") {
+        match (script_path, flag_value("--output")) {
+            (Some(script_path), None) => run_script_file(script_path, None),
+            (Some(script_path), Some(Some(output_path))) => {
+                run_script_file(script_path, Some(output_path))
+            }
+            // --script or --output without its file
+            _ => {
+                eprintln!("{}", HELP);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
     let icon_data = load_icon();
 
     let mut viewport_builder = egui::ViewportBuilder::default()
@@ -23,29 +65,55 @@ fn main() -> Result<(), eframe::Error> {
         ..Default::default()
     };
 
-    let default_text = load_default_test_file();
+    let (default_path, default_text) = load_default_test_file();
 
     eframe::run_native(
         "Daviti's Prolog Parser",
         options,
-        Box::new(move |_cc| Ok(Box::new(PrologApp::with_text(default_text)))),
+        Box::new(move |_cc| Ok(Box::new(PrologApp::with_text(default_text, default_path)))),
     )
 }
 
-fn load_default_test_file() -> String {
-    if let Ok(content) = fs::read_to_string("assets/base.txt") {
-        println!("Loaded assets/base.txt");
-        content
-    } else if let Ok(content) = fs::read_to_string("assets/simple.txt") {
-        println!("Loaded assets/simple.txt");
-        content
-    } else if let Ok(content) = fs::read_to_string("assets/complex.txt") {
-        println!("Loaded assets/complex.txt");
-        content
-    } else {
-        println!("Could not load asset files, using default text");
-        "Bear is an animal.\nCat has fur.\nJohn likes pizza.\nAll mammals are animals.".to_string()
+/// Runs the command palette commands of `script_path` and writes their
+/// results to `output_path`, or prints them. A failing script writes its
+/// error the same way and exits with status 1.
+fn run_script_file(script_path: &str, output_path: Option<&str>) {
+    let result = fs::read_to_string(script_path)
+        .map_err(|e| format!("Failed to read {}: {}", script_path, e))
+        .and_then(|script| PrologApp::with_text(String::new(), None).run_script(&script));
+    let (text, failed) = match result {
+        Ok(outputs) => (outputs.join("\n"), false),
+        Err(e) => (format!("Error: {}", e), true),
+    };
+
+    match output_path {
+        Some(output_path) => {
+            if let Err(e) = fs::write(output_path, format!("{}\n", text)) {
+                eprintln!("Failed to write {}: {}", output_path, e);
+                std::process::exit(1);
+            }
+        }
+        None if failed => eprintln!("{}", text),
+        None => println!("{}", text),
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn load_default_test_file() -> (Option<PathBuf>, String) {
+    for path in ["assets/base.txt", "assets/simple.txt", "assets/complex.txt"] {
+        if let Ok(content) = fs::read_to_string(path) {
+            println!("Loaded {}", path);
+            return (Some(PathBuf::from(path)), content);
+        }
     }
+
+    println!("Could not load asset files, using default text");
+    (
+        None,
+        "Bear is an animal.\nCat has fur.\nJohn likes pizza.\nAll mammals are animals.".to_string(),
+    )
 }
 
 fn load_icon() -> Option<egui::IconData> {